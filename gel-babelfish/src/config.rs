@@ -98,6 +98,13 @@ pub trait ListenerConfig: std::fmt::Debug + Send + Sync + 'static {
     ) -> bool;
 
     fn version(&self) -> &str;
+
+    /// Returns the admission control limits applied to the connections accepted
+    /// on each listen address.
+    /// Rejected connections receive the go-away message for their stream type.
+    fn admission_control(&self) -> Option<gel_stream::AdmissionControl> {
+        None
+    }
 }
 
 impl<C: ListenerConfig> ListenerConfig for Arc<C> {
@@ -128,17 +135,25 @@ impl<C: ListenerConfig> ListenerConfig for Arc<C> {
     fn version(&self) -> &str {
         self.as_ref().version()
     }
+
+    fn admission_control(&self) -> Option<gel_stream::AdmissionControl> {
+        self.as_ref().admission_control()
+    }
 }
 
 #[derive(Debug)]
 pub struct TestListenerConfig {
     pub addrs: Vec<SocketAddr>,
+    pub admission_control: Option<gel_stream::AdmissionControl>,
 }
 
 impl TestListenerConfig {
     pub fn new(s: impl ToSocketAddrs) -> Self {
         let addrs = s.to_socket_addrs().unwrap().collect();
-        Self { addrs }
+        Self {
+            addrs,
+            admission_control: None,
+        }
     }
 }
 
@@ -193,6 +208,10 @@ impl ListenerConfig for TestListenerConfig {
     fn version(&self) -> &str {
         "(development)"
     }
+
+    fn admission_control(&self) -> Option<gel_stream::AdmissionControl> {
+        self.admission_control.clone()
+    }
 }
 
 fn default_tls_config() -> TlsServerParameterProvider {
@@ -6,8 +6,8 @@ use crate::stream_type::{
 };
 use futures::StreamExt;
 use gel_stream::{
-    Acceptor, LocalAddress, PeerCred, PreviewConfiguration, RemoteAddress, ResolvedTarget, TlsAlpn,
-    TlsServerParameterProvider, Transport,
+    Acceptor, AdmissionControl, LocalAddress, PeerCred, PreviewConfiguration, RemoteAddress,
+    ResolvedTarget, TlsAlpn, TlsServerParameterProvider, Transport,
 };
 use scopeguard::defer;
 use std::{
@@ -26,6 +26,9 @@ mod gel;
 mod http;
 mod postgres;
 
+/// The default limit on rejected connections awaiting a go-away message.
+const MAX_RETURNED_REJECTIONS: usize = 64;
+
 /// Handles a connection from the listener. This method will not return until the connection is closed.
 pub async fn handle_connection_inner(
     state: StreamState,
@@ -47,6 +50,15 @@ pub async fn handle_connection_inner(
         }
     };
 
+    if let Some(rejection) = socket.props().admission_rejection {
+        warn!("{stream_type:?} connection rejected: {rejection}");
+        handle_stream_shutdown(stream_type.go_away_message(), socket).await?;
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("Connection rejected: {rejection}"),
+        ));
+    }
+
     let transport = socket.transport_type();
     if !bound_config.config().is_supported_final(
         stream_type,
@@ -258,6 +270,7 @@ fn bind_task<C: ListenerConfig>(
                 addresses = entry.addresses
             );
             let tls_lookup = entry.tls_lookup();
+            let admission_control = config.admission_control();
             info!("TLS lookup: {tls_lookup:?}");
             let mut new_listeners = HashSet::<_>::from_iter(entry.addresses);
             listeners.lock().unwrap().retain(|k, (_, v)| {
@@ -277,14 +290,20 @@ fn bind_task<C: ListenerConfig>(
                     continue;
                 }
 
-                let (listen_addr, task) =
-                    match bind(addr.clone(), tls_lookup.clone(), callback.clone()).await {
-                        Ok(task) => task,
-                        Err(e) => {
-                            error!("Failed to bind {addr:?}: {e:?}");
-                            continue;
-                        }
-                    };
+                let (listen_addr, task) = match bind(
+                    addr.clone(),
+                    tls_lookup.clone(),
+                    admission_control.clone(),
+                    callback.clone(),
+                )
+                .await
+                {
+                    Ok(task) => task,
+                    Err(e) => {
+                        error!("Failed to bind {addr:?}: {e:?}");
+                        continue;
+                    }
+                };
 
                 listeners
                     .lock()
@@ -306,6 +325,7 @@ fn bind_task<C: ListenerConfig>(
 async fn bind(
     addr: ResolvedTarget,
     tls_lookup: Option<TlsServerParameterProvider>,
+    admission_control: Option<AdmissionControl>,
     callback: Arc<Mutex<impl FnMut(ListenerStream) + Send + Sync + 'static>>,
 ) -> Result<(ResolvedTarget, JoinHandle<std::io::Result<()>>), std::io::Error> {
     let acceptor = if let Some(tls_lookup) = tls_lookup {
@@ -315,6 +335,19 @@ async fn bind(
     }
     .with_reuse_addr()
     .with_reuse_port();
+    // Rejected connections are returned so that we can reply with a
+    // protocol-appropriate error once the stream type is known. Their number
+    // is bounded since each one is still previewed (and TLS-accepted).
+    let acceptor = match admission_control {
+        Some(admission_control) => acceptor.with_admission_control(AdmissionControl {
+            return_rejected: true,
+            max_returned_rejections: admission_control
+                .max_returned_rejections
+                .or(Some(MAX_RETURNED_REJECTIONS)),
+            ..admission_control
+        }),
+        None => acceptor,
+    };
 
     let mut acceptor = acceptor.bind().await?;
     let local_addr = acceptor.local_address()?;
@@ -505,9 +538,19 @@ mod tests {
     fn run_test_service<F: Future<Output = Result<(), std::io::Error>> + Send + 'static>(
         mode: TestMode,
         f: impl Fn(RawStream) -> F + Send + Sync + 'static,
+    ) {
+        run_test_service_with_config(TestListenerConfig::new("localhost:0"), mode, f)
+    }
+
+    /// Run a test server with the given config and connect to it.
+    fn run_test_service_with_config<
+        F: Future<Output = Result<(), std::io::Error>> + Send + 'static,
+    >(
+        config: TestListenerConfig,
+        mode: TestMode,
+        f: impl Fn(RawStream) -> F + Send + Sync + 'static,
     ) {
         let svc = TestService::default();
-        let config = TestListenerConfig::new("localhost:0");
 
         tokio::runtime::Runtime::new()
             .unwrap()
@@ -627,6 +670,38 @@ mod tests {
         });
     }
 
+    /// Rejected connections receive the go-away message for their protocol.
+    #[rstest]
+    #[test_log::test]
+    #[timeout(Duration::from_secs(30))]
+    fn test_admission_rejected(#[values(TestMode::Tcp, TestMode::Ssl)] mode: TestMode) {
+        let config = TestListenerConfig {
+            admission_control: Some(AdmissionControl {
+                accept_rate: Some(gel_stream::AcceptRate {
+                    per_second: 0.001,
+                    burst: 0,
+                }),
+                ..Default::default()
+            }),
+            ..TestListenerConfig::new("localhost:0")
+        };
+        // Send the start of a TLS ClientHello or an HTTP request.
+        let (request, expected): (&[u8], _) = match mode {
+            TestMode::Tcp => (b"GET /\r\n\r\n", StreamType::HTTP0x.go_away_message()),
+            _ => (
+                &[0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01],
+                StreamType::SSLTLS.go_away_message(),
+            ),
+        };
+        run_test_service_with_config(config, TestMode::Tcp, move |mut stm| async move {
+            stm.write_all(request).await?;
+            let mut buf = vec![];
+            stm.read_to_end(&mut buf).await?;
+            assert_eq!(buf, expected);
+            Ok(())
+        });
+    }
+
     #[rstest]
     #[test_log::test]
     #[timeout(Duration::from_secs(30))]
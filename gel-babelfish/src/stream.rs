@@ -7,7 +7,7 @@ use gel_stream::{
     Preview, PreviewConfiguration, RawStream, RemoteAddress, ResolvedTarget, StreamUpgrade,
 };
 use hyper::{HeaderMap, Uri, Version};
-use std::{collections::HashMap, mem::MaybeUninit, sync::Arc, time::Duration};
+use std::{borrow::Cow, collections::HashMap, mem::MaybeUninit, sync::Arc, time::Duration};
 use strum::IntoDiscriminant;
 use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadBuf};
 use tracing::{trace, warn};
@@ -95,6 +95,10 @@ stream_properties! {
     /// The peer credentials (for Unix domain sockets)
     #[cfg(unix)]
     pub peer_creds: Option<tokio::net::unix::UCred>,
+    /// The reason the connection was rejected by the listener's admission control
+    pub admission_rejection: Option<gel_stream::AdmissionRejection>,
+    /// The byte counters and timings of the connection
+    pub metrics: Option<gel_stream::StreamMetrics>,
     /// The HTTP version used (for HTTP connections)
     pub http_version: Option<Version>,
     /// The HTTP request headers (for HTTP connections)
@@ -104,11 +108,11 @@ stream_properties! {
     /// The stream parameters (for PG/EDB connections)
     pub stream_params: Option<HashMap<String, String>>,
     /// The peer's SSL certificate (for SSL connections)
-    pub peer_certificate: Option<x509_parser::prelude::X509Certificate<'static>>,
+    pub peer_certificate: Option<gel_stream::CertificateInfo>,
     /// The SSL/TLS version.
     pub ssl_version: Option<gel_stream::SslVersion>,
-    /// The SSL/TLS version.
-    pub ssl_cipher_name: Option<&'static str>,
+    /// The SSL/TLS cipher suite.
+    pub ssl_cipher_name: Option<Cow<'static, str>>,
     /// The Server Name Indication (SNI) provided by the client (for SSL connections)
     pub server_name_indication: Option<String>,
     /// The negotiated protocol (e.g., for ALPN in SSL connections, protocol for WebSocket)
@@ -229,6 +233,8 @@ impl ListenerStream {
         let stream_properties = StreamProperties {
             peer_addr: stream.remote_address().ok(),
             local_addr: stream.remote_address().ok(),
+            admission_rejection: stream.admission_rejection(),
+            metrics: Some(stream.metrics().clone()),
             ..StreamProperties::new(TransportType::Tcp)
         }
         .into();
@@ -251,6 +257,8 @@ impl ListenerStream {
             peer_addr,
             local_addr,
             peer_creds,
+            admission_rejection: stream.admission_rejection(),
+            metrics: Some(stream.metrics().clone()),
             ..StreamProperties::new(TransportType::Unix)
         }
         .into();
@@ -290,6 +298,7 @@ impl ListenerStream {
 
                 let mut stream_properties = StreamProperties {
                     parent: Some(parent_stream_properties),
+                    metrics: Some(ssl_stream.metrics().clone()),
                     ..StreamProperties::new(TransportType::Ssl)
                 };
 
@@ -298,11 +307,17 @@ impl ListenerStream {
                         handshake.sni.as_ref().map(|s| s.as_ref().to_string());
                     stream_properties.protocol =
                         handshake.alpn.as_ref().and_then(|s| known_protocol(s));
-                    // TODO
-                    // stream_properties.peer_certificate = handshake.cert.map(|c| c.into());
+                    stream_properties.peer_certificate = match handshake.peer_certificate_info() {
+                        Some(Ok(info)) => Some(info),
+                        Some(Err(e)) => {
+                            warn!("Failed to parse peer certificate: {e}");
+                            None
+                        }
+                        None => None,
+                    };
                     stream_properties.ssl_version = handshake.version;
-                    // TODO
-                    // stream_properties.ssl_cipher_name = handshake.cipher_name;
+                    stream_properties.ssl_cipher_name =
+                        handshake.cipher_suite().map(|s| Cow::Owned(s.to_owned()));
                 }
 
                 Ok(ListenerStream {
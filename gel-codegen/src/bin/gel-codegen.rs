@@ -0,0 +1,144 @@
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::Arc;
+
+use gel_codegen::{load_description, save_description, type_name, Generator};
+use gel_protocol::common::{Capabilities, Cardinality, CompilationOptions};
+use gel_protocol::common::{InputLanguage, IoFormat};
+use gel_protocol::server_message::CommandDataDescription1;
+use gel_tokio::raw::{Pool, PoolState};
+use gel_tokio::Builder;
+
+const USAGE: &str = "\
+Usage: gel-codegen [OPTIONS] <QUERY_FILE>
+       gel-codegen [OPTIONS] --descriptor <FILE>
+
+Generates Rust types for the result and arguments of a query. The query is
+described by the instance configured in the current project or environment.
+
+Options:
+    --name <NAME>             Name of the result type [default: file name]
+    --descriptor <FILE>       Use a saved descriptor instead of a database
+    --save-descriptor <FILE>  Save the descriptor of the query
+    --derive <PATH>           Path of the derive [default: gel_derive::Queryable]
+    --crate-path <PATH>       Path of the gel-protocol crate [default: gel_protocol]
+    -o, --output <FILE>       Write the code to the file instead of stdout
+    -h, --help                Print this help
+";
+
+#[derive(Debug, Default)]
+struct Options {
+    query: Option<PathBuf>,
+    name: Option<String>,
+    descriptor: Option<PathBuf>,
+    save_descriptor: Option<PathBuf>,
+    derive: Option<String>,
+    crate_path: Option<String>,
+    output: Option<PathBuf>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("option `{arg}` requires a value"))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                exit(0);
+            }
+            "--name" => options.name = Some(value()?),
+            "--descriptor" => options.descriptor = Some(value()?.into()),
+            "--save-descriptor" => options.save_descriptor = Some(value()?.into()),
+            "--derive" => options.derive = Some(value()?),
+            "--crate-path" => options.crate_path = Some(value()?),
+            "-o" | "--output" => options.output = Some(value()?.into()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if options.query.is_none() => options.query = Some(arg.into()),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    match (&options.query, &options.descriptor) {
+        (None, None) => Err("query file or `--descriptor` is required".into()),
+        (Some(_), Some(_)) => Err("query file and `--descriptor` are exclusive".into()),
+        _ => Ok(options),
+    }
+}
+
+async fn describe(query: &str) -> Result<CommandDataDescription1, gel_tokio::Error> {
+    let config = Builder::default().build()?;
+    let pool = Pool::new(&config);
+    let mut conn = pool.acquire().await?;
+    let flags = CompilationOptions {
+        implicit_limit: None,
+        implicit_typenames: false,
+        implicit_typeids: false,
+        explicit_objectids: true,
+        allow_capabilities: Capabilities::ALL,
+        input_language: InputLanguage::EdgeQL,
+        io_format: IoFormat::Binary,
+        expected_cardinality: Cardinality::Many,
+    };
+    let state = PoolState::default();
+    conn.parse(&flags, query, &&state, &Arc::new(Default::default()))
+        .await
+}
+
+fn default_name(path: &Path) -> String {
+    let name = path
+        .file_stem()
+        .map(|stem| type_name(&stem.to_string_lossy()))
+        .unwrap_or_default();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("Query{name}")
+    }
+}
+
+async fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    let (desc, source) = if let Some(path) = &options.descriptor {
+        (load_description(&tokio::fs::read(path).await?)?, path)
+    } else {
+        let path = options.query.as_ref().expect("checked in parse_options");
+        let query = tokio::fs::read_to_string(path).await?;
+        (describe(&query).await?, path)
+    };
+    if let Some(path) = &options.save_descriptor {
+        tokio::fs::write(path, save_description(&desc)?).await?;
+    }
+    let name = options.name.unwrap_or_else(|| default_name(source));
+    let mut generator = Generator::new(name);
+    if let Some(derive) = options.derive {
+        generator.derive_path(derive);
+    }
+    if let Some(crate_path) = options.crate_path {
+        generator.crate_path(crate_path);
+    }
+    let code = generator.generate(&desc)?;
+    match &options.output {
+        Some(path) => tokio::fs::write(path, code).await?,
+        None => print!("{code}"),
+    }
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("gel-codegen: {e}\n\n{USAGE}");
+            exit(2);
+        }
+    };
+    if let Err(e) = run(options).await {
+        eprintln!("gel-codegen: {e}");
+        exit(1);
+    }
+}
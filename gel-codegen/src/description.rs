@@ -0,0 +1,79 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use gel_protocol::encoding::{Input, Output};
+use gel_protocol::features::ProtocolVersion;
+use gel_protocol::server_message::{CommandDataDescription1, ServerMessage};
+
+use crate::Error;
+
+const MAGIC: &[u8; 8] = b"GELDESC\0";
+
+/// Serializes a query description, so code can be generated without a
+/// database later.
+///
+/// The format is the `CommandDataDescription` message prefixed with a header
+/// containing the protocol version the message was received with.
+pub fn save_description(desc: &CommandDataDescription1) -> Result<Vec<u8>, Error> {
+    let proto = &desc.output.proto;
+    let (major, minor) = proto.version_tuple();
+    let mut buf = BytesMut::with_capacity(64);
+    buf.extend_from_slice(MAGIC);
+    buf.put_u16(major);
+    buf.put_u16(minor);
+    ServerMessage::CommandDataDescription1(desc.clone())
+        .encode(&mut Output::new(proto, &mut buf))?;
+    Ok(buf.to_vec())
+}
+
+/// Reads a query description written by [`save_description`].
+pub fn load_description(data: &[u8]) -> Result<CommandDataDescription1, Error> {
+    let mut data = Bytes::copy_from_slice(data);
+    if data.len() < MAGIC.len() + 4 || !data.starts_with(MAGIC) {
+        return Err(Error::InvalidFile("no descriptor file header"));
+    }
+    data.advance(MAGIC.len());
+    let proto = ProtocolVersion::new(data.get_u16(), data.get_u16());
+    match ServerMessage::decode(&mut Input::new(proto, data))? {
+        ServerMessage::CommandDataDescription1(desc) => Ok(desc),
+        _ => Err(Error::InvalidFile("unexpected message")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use gel_protocol::common::{Capabilities, Cardinality, RawTypedesc};
+    use gel_protocol::features::ProtocolVersion;
+    use gel_protocol::model::Uuid;
+    use gel_protocol::server_message::CommandDataDescription1;
+
+    use super::{load_description, save_description};
+    use crate::Error;
+
+    #[test]
+    fn roundtrip() {
+        let proto = ProtocolVersion::new(2, 0);
+        let desc = CommandDataDescription1 {
+            annotations: Default::default(),
+            capabilities: Capabilities::MODIFICATIONS,
+            result_cardinality: Cardinality::AtMostOne,
+            input: RawTypedesc {
+                proto: proto.clone(),
+                id: Uuid::from_u128(0xFF),
+                data: Bytes::from_static(b"input"),
+            },
+            output: RawTypedesc {
+                proto,
+                id: Uuid::from_u128(0x101),
+                data: Bytes::from_static(b"output"),
+            },
+        };
+        let data = save_description(&desc).unwrap();
+        assert_eq!(load_description(&data).unwrap(), desc);
+        assert!(matches!(
+            load_description(b"select 1"),
+            Err(Error::InvalidFile(_))
+        ));
+    }
+}
@@ -0,0 +1,878 @@
+use std::collections::HashMap;
+
+use gel_protocol::codec;
+use gel_protocol::common::Cardinality;
+use gel_protocol::descriptors::{Descriptor, EnumerationTypeDescriptor};
+use gel_protocol::descriptors::{ObjectShapeDescriptor, TypePos, Typedesc};
+use gel_protocol::model::Uuid;
+use gel_protocol::server_message::CommandDataDescription1;
+
+use crate::names::{field_name, type_name, Names};
+use crate::Error;
+
+const DEFAULT_DERIVE: &str = "gel_derive::Queryable";
+const DEFAULT_CRATE: &str = "gel_protocol";
+const MAX_TUPLE: usize = 12;
+
+/// Generates Rust code for a described query.
+///
+/// ```rust,no_run
+/// # fn main_(desc: gel_protocol::server_message::CommandDataDescription1)
+/// # -> Result<(), gel_codegen::Error> {
+/// let code = gel_codegen::Generator::new("User")
+///     .derive_path("gel_tokio::Queryable")
+///     .generate(&desc)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Result of the query is emitted as the type named as passed to
+/// [`Generator::new`]: a structure for object shapes, or a type alias for
+/// anything else. Nested shapes get their own structures named after the
+/// parent structure and the field. Arguments are emitted as the structure
+/// with `Args` suffix, which converts into named arguments using its
+/// `into_args` method.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    name: String,
+    derive_path: String,
+    crate_path: String,
+}
+
+#[derive(Debug)]
+struct Enum {
+    item: usize,
+    name: String,
+    schema_name: Option<String>,
+    members: Vec<String>,
+    argument: bool,
+}
+
+struct Emitter<'a> {
+    generator: &'a Generator,
+    items: Vec<String>,
+    names: Names,
+    enums: HashMap<Uuid, Enum>,
+}
+
+impl Generator {
+    /// Creates a generator naming the result type `name`.
+    pub fn new(name: impl Into<String>) -> Generator {
+        Generator {
+            name: name.into(),
+            derive_path: DEFAULT_DERIVE.into(),
+            crate_path: DEFAULT_CRATE.into(),
+        }
+    }
+    /// Sets path to the `Queryable` derive macro.
+    ///
+    /// Default is `gel_derive::Queryable`.
+    pub fn derive_path(&mut self, path: impl Into<String>) -> &mut Self {
+        self.derive_path = path.into();
+        self
+    }
+    /// Sets path to the `gel-protocol` crate used in generated code.
+    ///
+    /// Default is `gel_protocol`. Other paths are also passed to the derive
+    /// using `#[gel(crate_path = ...)]`.
+    pub fn crate_path(&mut self, path: impl Into<String>) -> &mut Self {
+        self.crate_path = path.into();
+        self
+    }
+    /// Generates code for the query result and arguments.
+    pub fn generate(&self, desc: &CommandDataDescription1) -> Result<String, Error> {
+        let output = desc.output()?;
+        let input = desc.input()?;
+        let mut emitter = Emitter {
+            generator: self,
+            items: Vec::new(),
+            names: Names::default(),
+            enums: HashMap::new(),
+        };
+        if let Some(pos) = output.root_pos() {
+            emitter.result(&output, pos, desc.result_cardinality)?;
+        }
+        if let Some(pos) = input.root_pos() {
+            emitter.arguments(&input, pos)?;
+        }
+        Ok(emitter.finish())
+    }
+}
+
+impl Emitter<'_> {
+    fn result(&mut self, desc: &Typedesc, pos: TypePos, card: Cardinality) -> Result<(), Error> {
+        let name = self.names.unique(&self.generator.name);
+        let doc = format!("Result of the query (cardinality `{card:?}`).");
+        match desc.get(pos)? {
+            Descriptor::ObjectShape(shape) => self.object(desc, shape, &name, &doc, ""),
+            _ => {
+                let slot = self.reserve();
+                let ty = self.output_type(desc, pos, &name, "Element of the result.", "")?;
+                self.items[slot] = format!("/// {doc}\npub type {name} = {ty};\n");
+                Ok(())
+            }
+        }
+    }
+
+    fn object(
+        &mut self,
+        desc: &Typedesc,
+        shape: &ObjectShapeDescriptor,
+        name: &str,
+        doc: &str,
+        path: &str,
+    ) -> Result<(), Error> {
+        let slot = self.reserve();
+        let mut code = format!("/// {doc}\n");
+        let schema_name = shape.type_pos.map(|pos| desc.get(pos)).transpose()?;
+        if let Some(Descriptor::Object(obj)) = schema_name {
+            if let Some(schema_name) = &obj.name {
+                code.push_str(&format!("///\n/// Shape of `{schema_name}`.\n"));
+            }
+        } else if let Some(Descriptor::Compound(obj)) = schema_name {
+            if let Some(schema_name) = &obj.name {
+                code.push_str(&format!("///\n/// Shape of `{schema_name}`.\n"));
+            }
+        }
+        code.push_str(&self.derives("Debug, Clone"));
+        code.push_str(&format!("pub struct {name} {{\n"));
+        let mut fields = Names::default();
+        // Implicit elements (`__tid__`, `__tname__` and `id`) are skipped by
+        // the derived implementation.
+        for element in shape.elements.iter().filter(|el| !el.flag_implicit) {
+            let field = fields.unique(&field_name(&element.name));
+            let path = format!("{path}.{}", element.name);
+            let hint = format!("{name}{}", type_name(&element.name));
+            let doc = format!("Element of `{}` in [`{name}`].", element.name);
+            let ty = self.output_type(desc, element.type_pos, &hint, &doc, &path)?;
+            let ty = match desc.get(element.type_pos)? {
+                // Sets are already decoded into vectors
+                Descriptor::Set(_) => ty,
+                _ => with_cardinality(ty, element.cardinality),
+            };
+            code.push_str(&field_attrs(&field, &element.name));
+            code.push_str(&format!("    pub {field}: {ty},\n"));
+        }
+        code.push_str("}\n");
+        self.items[slot] = code;
+        Ok(())
+    }
+
+    fn output_type(
+        &mut self,
+        desc: &Typedesc,
+        pos: TypePos,
+        hint: &str,
+        doc: &str,
+        path: &str,
+    ) -> Result<String, Error> {
+        let ty = match desc.get(pos)? {
+            Descriptor::Set(set) => {
+                format!(
+                    "Vec<{}>",
+                    self.output_type(desc, set.type_pos, hint, doc, path)?
+                )
+            }
+            Descriptor::Array(arr) => {
+                format!(
+                    "Vec<{}>",
+                    self.output_type(desc, arr.type_pos, hint, doc, path)?
+                )
+            }
+            Descriptor::ObjectShape(shape) => {
+                let name = self.names.unique(hint);
+                self.object(desc, shape, &name, doc, path)?;
+                name
+            }
+            Descriptor::BaseScalar(_) | Descriptor::Scalar(_) => {
+                let id = scalar_id(desc, pos)?;
+                match self.scalar_type(id) {
+                    Some((ty, _)) => ty,
+                    None => return Err(unsupported(desc, pos, path)),
+                }
+            }
+            Descriptor::Tuple(tuple)
+                if !tuple.element_types.is_empty() && tuple.element_types.len() <= MAX_TUPLE =>
+            {
+                let mut elements = Vec::with_capacity(tuple.element_types.len());
+                for (idx, el_pos) in tuple.element_types.iter().enumerate() {
+                    let hint = format!("{hint}{idx}");
+                    let path = format!("{path}.{idx}");
+                    let doc = format!("Element of `{}`.", display_path(&path));
+                    elements.push(self.output_type(desc, *el_pos, &hint, &doc, &path)?);
+                }
+                if elements.len() == 1 {
+                    format!("({},)", elements[0])
+                } else {
+                    format!("({})", elements.join(", "))
+                }
+            }
+            Descriptor::Enumeration(en) => self.enumeration(en, hint, false),
+            _ => return Err(unsupported(desc, pos, path)),
+        };
+        Ok(ty)
+    }
+
+    fn arguments(&mut self, desc: &Typedesc, pos: TypePos) -> Result<(), Error> {
+        let shape = match desc.get(pos)? {
+            Descriptor::ObjectShape(shape) if !shape.elements.is_empty() => shape,
+            Descriptor::ObjectShape(_) => return Ok(()),
+            Descriptor::Tuple(tuple) if tuple.element_types.is_empty() => return Ok(()),
+            _ => return Err(unsupported(desc, pos, "arguments")),
+        };
+        let name = self.names.unique(&format!("{}Args", self.generator.name));
+        let slot = self.reserve();
+        let crate_path = &self.generator.crate_path;
+        let mut code = String::from("/// Arguments of the query.\n");
+        code.push_str("#[derive(Debug, Clone)]\n");
+        code.push_str(&format!("pub struct {name} {{\n"));
+        let mut fields = Names::default();
+        let mut conversion = String::new();
+        for element in &shape.elements {
+            let field = if element.name.bytes().all(|b| b.is_ascii_digit()) {
+                fields.unique(&format!("arg{}", element.name))
+            } else {
+                fields.unique(&field_name(&element.name))
+            };
+            let hint = format!("{name}{}", type_name(&element.name));
+            let ty = match self.argument_type(desc, element.type_pos, &hint)? {
+                Some(ty) => ty,
+                None => {
+                    code.push_str(&format!(
+                        "    /// Argument `${}` has no typed representation.\n",
+                        element.name
+                    ));
+                    format!("{crate_path}::value::Value")
+                }
+            };
+            let ty = match element.cardinality {
+                Some(Cardinality::AtMostOne) => format!("Option<{ty}>"),
+                _ => ty,
+            };
+            code.push_str(&format!("    pub {field}: {ty},\n"));
+            conversion.push_str(&format!(
+                "            {:?} => self.{field},\n",
+                element.name
+            ));
+        }
+        code.push_str("}\n\n");
+        code.push_str(&format!("impl {name} {{\n"));
+        code.push_str("    /// Converts into named arguments accepted by query methods.\n");
+        code.push_str(&format!(
+            "    pub fn into_args(self) -> \
+            ::std::collections::HashMap<&'static str, {crate_path}::value_opt::ValueOpt> {{\n"
+        ));
+        code.push_str(&format!("        {crate_path}::named_args! {{\n"));
+        code.push_str(&conversion);
+        code.push_str("        }\n    }\n}\n");
+        self.items[slot] = code;
+        Ok(())
+    }
+
+    /// Returns `None` if there is no conversion of the type into a `Value`.
+    fn argument_type(
+        &mut self,
+        desc: &Typedesc,
+        pos: TypePos,
+        hint: &str,
+    ) -> Result<Option<String>, Error> {
+        let ty = match desc.get(pos)? {
+            Descriptor::BaseScalar(_) | Descriptor::Scalar(_) => {
+                let id = scalar_id(desc, pos)?;
+                self.scalar_type(id)
+                    .and_then(|(ty, into_value)| into_value.then_some(ty))
+            }
+            Descriptor::Enumeration(en) => Some(self.enumeration(en, hint, true)),
+            Descriptor::Array(arr) => self
+                .argument_type(desc, arr.type_pos, hint)?
+                .map(|ty| format!("Vec<{ty}>")),
+            _ => None,
+        };
+        Ok(ty)
+    }
+
+    /// Returns the Rust type and whether it converts into a `Value`.
+    fn scalar_type(&self, id: Uuid) -> Option<(String, bool)> {
+        let model = |name: &str| format!("{}::model::{name}", self.generator.crate_path);
+        let ty = match id {
+            codec::STD_UUID => (model("Uuid"), true),
+            codec::STD_STR => ("String".into(), true),
+            codec::STD_BYTES => ("Vec<u8>".into(), false),
+            codec::STD_INT16 => ("i16".into(), true),
+            codec::STD_INT32 => ("i32".into(), true),
+            codec::STD_INT64 => ("i64".into(), true),
+            codec::STD_FLOAT32 => ("f32".into(), true),
+            codec::STD_FLOAT64 => ("f64".into(), true),
+            codec::STD_DECIMAL => (model("Decimal"), true),
+            codec::STD_BOOL => ("bool".into(), true),
+            codec::STD_DATETIME => (model("Datetime"), true),
+            codec::CAL_LOCAL_DATETIME => (model("LocalDatetime"), true),
+            codec::CAL_LOCAL_DATE => (model("LocalDate"), true),
+            codec::CAL_LOCAL_TIME => (model("LocalTime"), true),
+            codec::STD_DURATION => (model("Duration"), true),
+            codec::CAL_RELATIVE_DURATION => (model("RelativeDuration"), false),
+            codec::CAL_DATE_DURATION => (model("DateDuration"), false),
+            codec::STD_JSON => (model("Json"), true),
+            codec::STD_BIGINT => (model("BigInt"), true),
+            codec::CFG_MEMORY => (model("ConfigMemory"), false),
+            codec::PGVECTOR_VECTOR => (model("Vector"), false),
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    fn enumeration(
+        &mut self,
+        en: &EnumerationTypeDescriptor,
+        hint: &str,
+        argument: bool,
+    ) -> String {
+        if let Some(known) = self.enums.get_mut(&*en.id) {
+            known.argument |= argument;
+            return known.name.clone();
+        }
+        let base = en
+            .name
+            .as_deref()
+            .map(type_name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| hint.to_string());
+        let name = self.names.unique(&base);
+        let item = self.reserve();
+        self.enums.insert(
+            *en.id,
+            Enum {
+                item,
+                name: name.clone(),
+                schema_name: en.name.clone(),
+                members: en.members.clone(),
+                argument,
+            },
+        );
+        name
+    }
+
+    fn render_enum(&self, en: &Enum) -> String {
+        let mut code = match &en.schema_name {
+            Some(schema_name) => format!("/// Enumeration `{schema_name}`.\n"),
+            None => "/// Enumeration.\n".to_string(),
+        };
+        code.push_str(&self.derives("Debug, Clone, Copy, PartialEq, Eq"));
+        code.push_str(&format!("pub enum {} {{\n", en.name));
+        let mut variants = Names::default();
+        let mut names = Vec::with_capacity(en.members.len());
+        for member in &en.members {
+            let mut variant = type_name(member);
+            if !variant.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                variant.insert(0, 'V');
+            }
+            let variant = variants.unique(&variant);
+            if variant != *member {
+                code.push_str(&format!("    #[gel(rename = {member:?})]\n"));
+            }
+            code.push_str(&format!("    {variant},\n"));
+            names.push(variant);
+        }
+        code.push_str("}\n");
+        if en.argument {
+            let value = format!("{}::value::Value", self.generator.crate_path);
+            code.push_str(&format!("\nimpl From<{}> for {value} {{\n", en.name));
+            code.push_str(&format!("    fn from(value: {}) -> {value} {{\n", en.name));
+            code.push_str("        let name = match value {\n");
+            for (variant, member) in names.iter().zip(&en.members) {
+                code.push_str(&format!(
+                    "            {}::{variant} => {member:?},\n",
+                    en.name
+                ));
+            }
+            code.push_str("        };\n");
+            code.push_str(&format!("        {value}::Enum(name.into())\n"));
+            code.push_str("    }\n}\n");
+        }
+        code
+    }
+
+    fn derives(&self, traits: &str) -> String {
+        let mut code = format!("#[derive({traits}, {})]\n", self.generator.derive_path);
+        if self.generator.crate_path != DEFAULT_CRATE {
+            code.push_str(&format!(
+                "#[gel(crate_path = {})]\n",
+                self.generator.crate_path
+            ));
+        }
+        code
+    }
+
+    fn reserve(&mut self) -> usize {
+        self.items.push(String::new());
+        self.items.len() - 1
+    }
+
+    fn finish(mut self) -> String {
+        for en in self.enums.values() {
+            self.items[en.item] = self.render_enum(en);
+        }
+        let mut code = String::from("// This file is generated by gel-codegen, do not edit.\n");
+        for item in &self.items {
+            code.push('\n');
+            code.push_str(item);
+        }
+        code
+    }
+}
+
+fn field_attrs(field: &str, name: &str) -> String {
+    if field == name {
+        String::new()
+    } else {
+        format!("    #[gel(rename = {name:?})]\n")
+    }
+}
+
+fn with_cardinality(ty: String, card: Option<Cardinality>) -> String {
+    match card {
+        Some(Cardinality::AtMostOne) => format!("Option<{ty}>"),
+        Some(Cardinality::Many | Cardinality::AtLeastOne) => format!("Vec<{ty}>"),
+        _ => ty,
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    path.strip_prefix('.').unwrap_or(path)
+}
+
+/// Resolves custom scalars into the base scalar type id.
+fn scalar_id(desc: &Typedesc, mut pos: TypePos) -> Result<Uuid, Error> {
+    loop {
+        match desc.get(pos)? {
+            Descriptor::BaseScalar(scalar) => return Ok(*scalar.id),
+            Descriptor::Scalar(scalar) => match scalar.base_type_pos {
+                Some(base) => pos = base,
+                None => return Ok(*scalar.id),
+            },
+            _ => return Err(unsupported(desc, pos, "")),
+        }
+    }
+}
+
+fn unsupported(desc: &Typedesc, pos: TypePos, path: &str) -> Error {
+    let kind = match desc.get(pos) {
+        Ok(Descriptor::Scalar(scalar)) => match &scalar.name {
+            Some(name) => format!("scalar `{name}`"),
+            None => format!("scalar {}", *scalar.id),
+        },
+        Ok(Descriptor::BaseScalar(scalar)) => format!("scalar {}", *scalar.id),
+        Ok(Descriptor::Set(_)) => "set".into(),
+        Ok(Descriptor::ObjectShape(_)) => "object shape".into(),
+        Ok(Descriptor::Tuple(tuple)) => format!("tuple of {} elements", tuple.element_types.len()),
+        Ok(Descriptor::NamedTuple(_)) => "named tuple".into(),
+        Ok(Descriptor::Array(_)) => "array".into(),
+        Ok(Descriptor::Enumeration(_)) => "enum".into(),
+        Ok(Descriptor::InputShape(_)) => "input shape".into(),
+        Ok(Descriptor::Range(_)) => "range".into(),
+        Ok(Descriptor::MultiRange(_)) => "multirange".into(),
+        Ok(Descriptor::Object(_)) => "object".into(),
+        Ok(Descriptor::Compound(_)) => "compound type".into(),
+        Ok(Descriptor::SQLRow(_)) => "SQL row".into(),
+        Ok(Descriptor::TypeAnnotation(_)) => "type annotation".into(),
+        Err(_) => "invalid type".into(),
+    };
+    Error::Unsupported {
+        kind,
+        path: match display_path(path) {
+            "" => "result".into(),
+            path => path.into(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::{BufMut, BytesMut};
+    use pretty_assertions::assert_eq;
+
+    use gel_protocol::common::{Capabilities, Cardinality, RawTypedesc};
+    use gel_protocol::features::ProtocolVersion;
+    use gel_protocol::model::Uuid;
+    use gel_protocol::server_message::CommandDataDescription1;
+
+    use super::Generator;
+    use crate::Error;
+
+    /// Builds descriptors in the protocol 3.0 binary format.
+    #[derive(Default)]
+    struct Builder {
+        buf: BytesMut,
+        count: u16,
+    }
+
+    impl Builder {
+        fn push(&mut self, tag: u8, id: u128, body: impl FnOnce(&mut BytesMut)) -> u16 {
+            let mut data = BytesMut::new();
+            data.put_u8(tag);
+            data.put_u128(id);
+            body(&mut data);
+            self.buf.put_u32(data.len() as u32);
+            self.buf.extend_from_slice(&data);
+            self.count += 1;
+            self.count - 1
+        }
+        fn string(buf: &mut BytesMut, value: &str) {
+            buf.put_u32(value.len() as u32);
+            buf.extend_from_slice(value.as_bytes());
+        }
+        fn scalar(&mut self, id: u128, name: &str) -> u16 {
+            self.push(3, id, |buf| {
+                Builder::string(buf, name);
+                buf.put_u8(0); // not schema defined
+                buf.put_u16(0); // no ancestors
+            })
+        }
+        fn custom_scalar(&mut self, id: u128, name: &str, base: u16) -> u16 {
+            self.push(3, id, |buf| {
+                Builder::string(buf, name);
+                buf.put_u8(1);
+                buf.put_u16(1);
+                buf.put_u16(base);
+            })
+        }
+        fn object(&mut self, id: u128, name: &str) -> u16 {
+            self.push(10, id, |buf| {
+                Builder::string(buf, name);
+                buf.put_u8(1);
+            })
+        }
+        fn enumeration(&mut self, id: u128, name: &str, members: &[&str]) -> u16 {
+            self.push(7, id, |buf| {
+                Builder::string(buf, name);
+                buf.put_u8(1);
+                buf.put_u16(0);
+                buf.put_u16(members.len() as u16);
+                for member in members {
+                    Builder::string(buf, member);
+                }
+            })
+        }
+        fn set(&mut self, id: u128, element: u16) -> u16 {
+            self.push(0, id, |buf| buf.put_u16(element))
+        }
+        fn array(&mut self, id: u128, element: u16) -> u16 {
+            self.push(6, id, |buf| {
+                Builder::string(buf, "array<anytype>");
+                buf.put_u8(0);
+                buf.put_u16(0);
+                buf.put_u16(element);
+                buf.put_u16(1);
+                buf.put_i32(-1);
+            })
+        }
+        fn tuple(&mut self, id: u128, elements: &[u16]) -> u16 {
+            self.push(4, id, |buf| {
+                Builder::string(buf, "tuple<anytype>");
+                buf.put_u8(0);
+                buf.put_u16(0);
+                buf.put_u16(elements.len() as u16);
+                for el in elements {
+                    buf.put_u16(*el);
+                }
+            })
+        }
+        fn range(&mut self, id: u128, element: u16) -> u16 {
+            self.push(9, id, |buf| {
+                Builder::string(buf, "range<anytype>");
+                buf.put_u8(0);
+                buf.put_u16(0);
+                buf.put_u16(element);
+            })
+        }
+        fn shape(&mut self, id: u128, object: u16, elements: &[(&str, u8, u16)]) -> u16 {
+            self.shape_with_implicit(id, object, &[], elements)
+        }
+        /// Shape with the `implicit` elements in front of the others.
+        fn shape_with_implicit(
+            &mut self,
+            id: u128,
+            object: u16,
+            implicit: &[(&str, u16)],
+            elements: &[(&str, u8, u16)],
+        ) -> u16 {
+            self.push(1, id, |buf| {
+                buf.put_u8(0); // ephemeral_free_shape
+                buf.put_u16(object);
+                buf.put_u16((implicit.len() + elements.len()) as u16);
+                let implicit = implicit
+                    .iter()
+                    .map(|(name, type_pos)| (1, name, ONE, type_pos));
+                let elements = elements
+                    .iter()
+                    .map(|(name, cardinality, type_pos)| (0, name, *cardinality, type_pos));
+                for (flags, name, cardinality, type_pos) in implicit.chain(elements) {
+                    buf.put_u32(flags);
+                    buf.put_u8(cardinality);
+                    Builder::string(buf, name);
+                    buf.put_u16(*type_pos);
+                    buf.put_u16(object);
+                }
+            })
+        }
+        fn arguments(&mut self, id: u128, elements: &[(&str, u8, u16)]) -> u16 {
+            self.push(1, id, |buf| {
+                buf.put_u8(0);
+                buf.put_u16(u16::MAX);
+                buf.put_u16(elements.len() as u16);
+                for (name, cardinality, type_pos) in elements {
+                    buf.put_u32(0);
+                    buf.put_u8(*cardinality);
+                    Builder::string(buf, name);
+                    buf.put_u16(*type_pos);
+                    buf.put_u16(u16::MAX);
+                }
+            })
+        }
+        fn build(self, id: u128) -> RawTypedesc {
+            RawTypedesc {
+                proto: ProtocolVersion::current(),
+                id: Uuid::from_u128(id),
+                data: self.buf.freeze(),
+            }
+        }
+    }
+
+    const ONE: u8 = b'A';
+    const AT_MOST_ONE: u8 = b'o';
+    const MANY: u8 = b'm';
+
+    fn no_arguments() -> RawTypedesc {
+        let mut inp = Builder::default();
+        inp.tuple(0xFF, &[]);
+        inp.build(0xFF)
+    }
+
+    fn description(input: RawTypedesc, output: RawTypedesc) -> CommandDataDescription1 {
+        CommandDataDescription1 {
+            annotations: Default::default(),
+            capabilities: Capabilities::empty(),
+            result_cardinality: Cardinality::Many,
+            input,
+            output,
+        }
+    }
+
+    #[test]
+    fn nested_shapes() {
+        let mut out = Builder::default();
+        let uuid = out.scalar(0x100, "std::uuid");
+        let str = out.scalar(0x101, "std::str");
+        let int = out.scalar(0x105, "std::int64");
+        let user = out.object(0x1001, "default::User");
+        let status = out.enumeration(0x1002, "default::Status", &["Active", "on hold"]);
+        let friend = out.shape(
+            0x2001,
+            user,
+            &[("name", ONE, str), ("@since", AT_MOST_ONE, int)],
+        );
+        let friends = out.set(0x2002, friend);
+        let tags = out.array(0x2003, str);
+        out.shape(
+            0x2000,
+            user,
+            &[
+                ("id", ONE, uuid),
+                ("firstName", ONE, str),
+                ("type", AT_MOST_ONE, str),
+                ("status", ONE, status),
+                ("friends", MANY, friends),
+                ("tags", AT_MOST_ONE, tags),
+            ],
+        );
+        let desc = description(no_arguments(), out.build(0x2000));
+        let code = Generator::new("User").generate(&desc).unwrap();
+        assert_eq!(
+            code,
+            r#"// This file is generated by gel-codegen, do not edit.
+
+/// Result of the query (cardinality `Many`).
+///
+/// Shape of `default::User`.
+#[derive(Debug, Clone, gel_derive::Queryable)]
+pub struct User {
+    pub id: gel_protocol::model::Uuid,
+    #[gel(rename = "firstName")]
+    pub first_name: String,
+    #[gel(rename = "type")]
+    pub r#type: Option<String>,
+    pub status: Status,
+    pub friends: Vec<UserFriends>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Enumeration `default::Status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, gel_derive::Queryable)]
+pub enum Status {
+    Active,
+    #[gel(rename = "on hold")]
+    OnHold,
+}
+
+/// Element of `friends` in [`User`].
+///
+/// Shape of `default::User`.
+#[derive(Debug, Clone, gel_derive::Queryable)]
+pub struct UserFriends {
+    pub name: String,
+    #[gel(rename = "@since")]
+    pub since: Option<i64>,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn implicit_elements() {
+        let mut out = Builder::default();
+        let uuid = out.scalar(0x100, "std::uuid");
+        let str = out.scalar(0x101, "std::str");
+        let user = out.object(0x1001, "default::User");
+        out.shape_with_implicit(
+            0x2000,
+            user,
+            &[("__tid__", uuid), ("id", uuid)],
+            &[("name", ONE, str)],
+        );
+        let desc = description(no_arguments(), out.build(0x2000));
+        let code = Generator::new("User").generate(&desc).unwrap();
+        assert_eq!(
+            code,
+            r#"// This file is generated by gel-codegen, do not edit.
+
+/// Result of the query (cardinality `Many`).
+///
+/// Shape of `default::User`.
+#[derive(Debug, Clone, gel_derive::Queryable)]
+pub struct User {
+    pub name: String,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn scalar_result() {
+        let mut out = Builder::default();
+        let str = out.scalar(0x101, "std::str");
+        let int = out.scalar(0x105, "std::int64");
+        let custom = out.custom_scalar(0x1001, "default::label", str);
+        out.tuple(0x2000, &[custom, int]);
+        let desc = description(no_arguments(), out.build(0x2000));
+        let code = Generator::new("Pair").generate(&desc).unwrap();
+        assert_eq!(
+            code,
+            "// This file is generated by gel-codegen, do not edit.\n\
+            \n\
+            /// Result of the query (cardinality `Many`).\n\
+            pub type Pair = (String, i64);\n"
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        let mut inp = Builder::default();
+        let uuid = inp.scalar(0x100, "std::uuid");
+        let bytes = inp.scalar(0x102, "std::bytes");
+        let status = inp.enumeration(0x1002, "default::Status", &["active", "Blocked"]);
+        let statuses = inp.array(0x2001, status);
+        inp.arguments(
+            0x2000,
+            &[
+                ("id", ONE, uuid),
+                ("statuses", AT_MOST_ONE, statuses),
+                ("payload", ONE, bytes),
+            ],
+        );
+        let mut out = Builder::default();
+        let status = out.enumeration(0x1002, "default::Status", &["active", "Blocked"]);
+        out.set(0x3000, status);
+        let desc = description(inp.build(0x2000), out.build(0x3000));
+        let code = Generator::new("Statuses")
+            .derive_path("gel_tokio::Queryable")
+            .generate(&desc)
+            .unwrap();
+        assert_eq!(
+            code,
+            r#"// This file is generated by gel-codegen, do not edit.
+
+/// Result of the query (cardinality `Many`).
+pub type Statuses = Vec<Status>;
+
+/// Enumeration `default::Status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, gel_tokio::Queryable)]
+pub enum Status {
+    #[gel(rename = "active")]
+    Active,
+    Blocked,
+}
+
+impl From<Status> for gel_protocol::value::Value {
+    fn from(value: Status) -> gel_protocol::value::Value {
+        let name = match value {
+            Status::Active => "active",
+            Status::Blocked => "Blocked",
+        };
+        gel_protocol::value::Value::Enum(name.into())
+    }
+}
+
+/// Arguments of the query.
+#[derive(Debug, Clone)]
+pub struct StatusesArgs {
+    pub id: gel_protocol::model::Uuid,
+    pub statuses: Option<Vec<Status>>,
+    /// Argument `$payload` has no typed representation.
+    pub payload: gel_protocol::value::Value,
+}
+
+impl StatusesArgs {
+    /// Converts into named arguments accepted by query methods.
+    pub fn into_args(self) -> ::std::collections::HashMap<&'static str, gel_protocol::value_opt::ValueOpt> {
+        gel_protocol::named_args! {
+            "id" => self.id,
+            "statuses" => self.statuses,
+            "payload" => self.payload,
+        }
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn positional_arguments() {
+        let mut inp = Builder::default();
+        let int = inp.scalar(0x105, "std::int64");
+        inp.arguments(0x2000, &[("0", ONE, int), ("1", AT_MOST_ONE, int)]);
+        let mut out = Builder::default();
+        out.scalar(0x105, "std::int64");
+        let desc = description(inp.build(0x2000), out.build(0x105));
+        let code = Generator::new("Sum")
+            .crate_path("gel_tokio::gel_protocol")
+            .generate(&desc)
+            .unwrap();
+        assert!(code
+            .contains("pub struct SumArgs {\n    pub arg0: i64,\n    pub arg1: Option<i64>,\n}"));
+        assert!(code.contains("\"0\" => self.arg0,\n            \"1\" => self.arg1,\n"));
+        assert!(code.contains("gel_tokio::gel_protocol::named_args!"));
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut out = Builder::default();
+        let user = out.object(0x1001, "default::User");
+        let int = out.scalar(0x105, "std::int64");
+        let range = out.range(0x2001, int);
+        out.shape(0x2000, user, &[("during", ONE, range)]);
+        let desc = description(no_arguments(), out.build(0x2000));
+        let err = Generator::new("User").generate(&desc).unwrap_err();
+        assert!(matches!(err, Error::Unsupported { .. }));
+        assert_eq!(err.to_string(), "unsupported type range at `during`");
+    }
+}
@@ -0,0 +1,57 @@
+/*!
+Generates Rust types for the results and arguments of Gel queries.
+
+The query is described by the server, and the type descriptors are turned
+into `#[derive(Queryable)]` structures, enums for enumeration types and an
+argument structure:
+
+```rust,no_run
+# async fn main_(conn: &mut gel_tokio::raw::PoolConnection,
+#     flags: &gel_protocol::common::CompilationOptions,
+#     state: &dyn gel_tokio::raw::State,
+#     annotations: &std::sync::Arc<gel_protocol::encoding::Annotations>)
+# -> Result<(), Box<dyn std::error::Error>> {
+let desc = conn.parse(flags, "select User { name }", state, annotations).await?;
+let code = gel_codegen::Generator::new("User").generate(&desc)?;
+std::fs::write("src/user.rs", code)?;
+// Keep the descriptor to regenerate code without a database
+std::fs::write("queries/user.desc", gel_codegen::save_description(&desc)?)?;
+# Ok(())
+# }
+```
+
+The `gel-codegen` binary (enabled by the `cli` feature) does the same for a
+query file.
+*/
+
+mod description;
+mod generator;
+mod names;
+
+use gel_protocol::errors::{CodecError, DecodeError, EncodeError};
+
+pub use description::{load_description, save_description};
+pub use generator::Generator;
+
+/// Converts a name into a type name as used for generated types.
+///
+/// For example, `"user_by_id"` is converted into `"UserById"`.
+pub fn type_name(name: &str) -> String {
+    names::type_name(name)
+}
+
+/// Error generating code.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("error decoding type descriptor: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("error encoding type descriptor: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("invalid type descriptor: {0}")]
+    Descriptor(#[from] CodecError),
+    #[error("invalid descriptor file: {0}")]
+    InvalidFile(&'static str),
+    #[error("unsupported type {kind} at `{path}`")]
+    Unsupported { kind: String, path: String },
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't be used as raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Set of identifiers already used in a scope.
+#[derive(Debug, Default)]
+pub struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    /// Returns `name`, or `name` with a numeric suffix if it's already taken.
+    pub fn unique(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut index = 2;
+        while self.used.contains(&candidate) {
+            candidate = format!("{name}{index}");
+            index += 1;
+        }
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// Converts a name of a shape element or an argument into a field name.
+pub fn field_name(name: &str) -> String {
+    let name = name.trim_start_matches('@');
+    let mut result = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+            prev_lower = false;
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            result.push(c);
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            result.push('_');
+            prev_lower = false;
+        }
+    }
+    if result.is_empty() {
+        return "field".into();
+    }
+    if result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    if RESERVED.contains(&result.as_str()) {
+        result.push('_');
+    } else if KEYWORDS.contains(&result.as_str()) {
+        result.insert_str(0, "r#");
+    }
+    result
+}
+
+/// Converts a name into a `PascalCase` type or variant name.
+///
+/// Module part of a qualified name (`default::`) is dropped. Returns an empty
+/// string if there are no usable characters in the name.
+pub fn type_name(name: &str) -> String {
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let mut result = String::with_capacity(name.len());
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            result.push(first.to_ascii_uppercase());
+            result.extend(chars);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{field_name, type_name, Names};
+
+    #[test]
+    fn fields() {
+        assert_eq!(field_name("name"), "name");
+        assert_eq!(field_name("firstName"), "first_name");
+        assert_eq!(field_name("@weight"), "weight");
+        assert_eq!(field_name("__tname__"), "__tname__");
+        assert_eq!(field_name("type"), "r#type");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(field_name("0"), "_0");
+        assert_eq!(field_name("foo-bar"), "foo_bar");
+        assert_eq!(field_name(""), "field");
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(type_name("default::Color"), "Color");
+        assert_eq!(type_name("friends"), "Friends");
+        assert_eq!(type_name("in_progress"), "InProgress");
+        assert_eq!(type_name("Done Already"), "DoneAlready");
+        assert_eq!(type_name("firstName"), "FirstName");
+        assert_eq!(type_name("?"), "");
+    }
+
+    #[test]
+    fn unique() {
+        let mut names = Names::default();
+        assert_eq!(names.unique("User"), "User");
+        assert_eq!(names.unique("User"), "User2");
+        assert_eq!(names.unique("User"), "User3");
+    }
+}
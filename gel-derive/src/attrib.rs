@@ -4,11 +4,18 @@ use syn::punctuated::Punctuated;
 enum FieldAttr {
     Json,
     Rename(syn::LitStr),
+    Flatten,
+    Default(kw::default, Option<syn::Path>),
+    Skip,
+    With(syn::Path),
 }
 
 enum ContainerAttr {
     Json,
     CratePath(syn::Path),
+    Discriminator(syn::LitStr),
+    MatchByName,
+    Transparent,
 }
 
 struct FieldAttrList(pub Punctuated<FieldAttr, syn::Token![,]>);
@@ -17,11 +24,26 @@ struct ContainerAttrList(pub Punctuated<ContainerAttr, syn::Token![,]>);
 pub struct FieldAttrs {
     pub json: bool,
     pub rename: Option<syn::LitStr>,
+    pub flatten: bool,
+    pub default: Option<FieldDefault>,
+    pub skip: bool,
+    pub with: Option<syn::Path>,
+}
+
+/// Value of a field that is missing from the shape.
+pub enum FieldDefault {
+    /// `Default::default()`
+    Trait,
+    /// Function returning the value
+    Path(syn::Path),
 }
 
 pub struct ContainerAttrs {
     pub json: bool,
     pub crate_path: Option<syn::Path>,
+    pub discriminator: Option<syn::LitStr>,
+    pub match_by_name: bool,
+    pub transparent: bool,
 }
 
 impl ContainerAttrs {
@@ -36,6 +58,13 @@ mod kw {
     syn::custom_keyword!(json);
     syn::custom_keyword!(crate_path);
     syn::custom_keyword!(rename);
+    syn::custom_keyword!(discriminator);
+    syn::custom_keyword!(flatten);
+    syn::custom_keyword!(default);
+    syn::custom_keyword!(skip);
+    syn::custom_keyword!(with);
+    syn::custom_keyword!(match_by_name);
+    syn::custom_keyword!(transparent);
 }
 
 impl Parse for FieldAttr {
@@ -48,6 +77,24 @@ impl Parse for FieldAttr {
             input.parse::<kw::rename>()?;
             input.parse::<syn::Token![=]>()?;
             Ok(FieldAttr::Rename(input.parse()?))
+        } else if lookahead.peek(kw::flatten) {
+            input.parse::<kw::flatten>()?;
+            Ok(FieldAttr::Flatten)
+        } else if lookahead.peek(kw::default) {
+            let kw = input.parse::<kw::default>()?;
+            if input.peek(syn::Token![=]) {
+                input.parse::<syn::Token![=]>()?;
+                Ok(FieldAttr::Default(kw, Some(input.parse()?)))
+            } else {
+                Ok(FieldAttr::Default(kw, None))
+            }
+        } else if lookahead.peek(kw::skip) {
+            input.parse::<kw::skip>()?;
+            Ok(FieldAttr::Skip)
+        } else if lookahead.peek(kw::with) {
+            input.parse::<kw::with>()?;
+            input.parse::<syn::Token![=]>()?;
+            Ok(FieldAttr::With(input.parse()?))
         } else {
             Err(lookahead.error())
         }
@@ -64,6 +111,16 @@ impl Parse for ContainerAttr {
             input.parse::<kw::crate_path>()?;
             input.parse::<syn::Token![=]>()?;
             Ok(ContainerAttr::CratePath(input.parse()?))
+        } else if lookahead.peek(kw::discriminator) {
+            input.parse::<kw::discriminator>()?;
+            input.parse::<syn::Token![=]>()?;
+            Ok(ContainerAttr::Discriminator(input.parse()?))
+        } else if lookahead.peek(kw::match_by_name) {
+            input.parse::<kw::match_by_name>()?;
+            Ok(ContainerAttr::MatchByName)
+        } else if lookahead.peek(kw::transparent) {
+            input.parse::<kw::transparent>()?;
+            Ok(ContainerAttr::Transparent)
         } else {
             Err(lookahead.error())
         }
@@ -87,6 +144,10 @@ impl FieldAttrs {
         FieldAttrs {
             json: false,
             rename: None,
+            flatten: false,
+            default: None,
+            skip: false,
+            with: None,
         }
     }
     pub fn from_syn(attrs: &[syn::Attribute]) -> syn::Result<FieldAttrs> {
@@ -106,6 +167,29 @@ impl FieldAttrs {
                             }
                             res.rename = Some(name)
                         }
+                        FieldAttr::Flatten => res.flatten = true,
+                        FieldAttr::Default(kw, path) => {
+                            if res.default.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    kw,
+                                    "duplicate gel attribute `default`",
+                                ));
+                            }
+                            res.default = Some(match path {
+                                Some(path) => FieldDefault::Path(path),
+                                None => FieldDefault::Trait,
+                            });
+                        }
+                        FieldAttr::Skip => res.skip = true,
+                        FieldAttr::With(path) => {
+                            if res.with.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    path,
+                                    "duplicate gel attribute `with`",
+                                ));
+                            }
+                            res.with = Some(path)
+                        }
                     }
                 }
             }
@@ -119,6 +203,9 @@ impl ContainerAttrs {
         ContainerAttrs {
             json: false,
             crate_path: None,
+            discriminator: None,
+            match_by_name: false,
+            transparent: false,
         }
     }
     pub fn from_syn(attrs: &[syn::Attribute]) -> syn::Result<ContainerAttrs> {
@@ -138,6 +225,17 @@ impl ContainerAttrs {
                             }
                             res.crate_path = Some(path)
                         }
+                        ContainerAttr::Discriminator(name) => {
+                            if res.discriminator.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    name,
+                                    "duplicate gel attribute `discriminator`",
+                                ));
+                            }
+                            res.discriminator = Some(name)
+                        }
+                        ContainerAttr::MatchByName => res.match_by_name = true,
+                        ContainerAttr::Transparent => res.transparent = true,
                     }
                 }
             }
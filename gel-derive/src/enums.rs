@@ -9,7 +9,10 @@ pub fn derive_enum(
 ) -> syn::Result<TokenStream> {
     let gel_protocol = container_attrs.gel_protocol_path();
     let type_name = &s.ident;
-    let (impl_generics, ty_generics, _) = s.generics.split_for_impl();
+    let lifetime = crate::decode_lifetime();
+    let generics = crate::add_decode_lifetime(&s.generics, &lifetime);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = s.generics.split_for_impl();
     let branches = s
         .variants
         .iter()
@@ -31,11 +34,11 @@ pub fn derive_enum(
         })
         .collect::<Result<Vec<_>, _>>()?;
     let expanded = quote! {
-        impl #impl_generics #gel_protocol::queryable::Queryable
-            for #type_name #ty_generics {
+        impl #impl_generics #gel_protocol::queryable::Queryable<#lifetime>
+            for #type_name #ty_generics #where_clause {
             type Args = ();
 
-            fn decode(decoder: &#gel_protocol::queryable::Decoder, _args: &(), buf: &[u8])
+            fn decode(decoder: &#gel_protocol::queryable::Decoder, _args: &(), buf: &#lifetime [u8])
                 -> Result<Self, #gel_protocol::errors::DecodeError>
             {
                 match buf {
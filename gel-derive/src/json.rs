@@ -5,15 +5,9 @@ use crate::attrib::ContainerAttrs;
 
 pub fn derive(item: &syn::Item, container_attrs: &ContainerAttrs) -> syn::Result<TokenStream> {
     let gel_protocol = container_attrs.gel_protocol_path();
-    let (name, impl_generics, ty_generics) = match item {
-        syn::Item::Struct(s) => {
-            let (impl_generics, ty_generics, _) = s.generics.split_for_impl();
-            (&s.ident, impl_generics, ty_generics)
-        }
-        syn::Item::Enum(e) => {
-            let (impl_generics, ty_generics, _) = e.generics.split_for_impl();
-            (&e.ident, impl_generics, ty_generics)
-        }
+    let (name, generics) = match item {
+        syn::Item::Struct(s) => (&s.ident, &s.generics),
+        syn::Item::Enum(e) => (&e.ident, &e.generics),
         _ => {
             return Err(syn::Error::new_spanned(
                 item,
@@ -21,12 +15,16 @@ pub fn derive(item: &syn::Item, container_attrs: &ContainerAttrs) -> syn::Result
             ));
         }
     };
+    let lifetime = crate::decode_lifetime();
+    let impl_generics = crate::add_decode_lifetime(generics, &lifetime);
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
     let expanded = quote! {
-        impl #impl_generics #gel_protocol::queryable::Queryable
-            for #name #ty_generics {
+        impl #impl_generics #gel_protocol::queryable::Queryable<#lifetime>
+            for #name #ty_generics #where_clause {
             type Args = ();
 
-            fn decode(decoder: &#gel_protocol::queryable::Decoder, _args: &(), buf: &[u8])
+            fn decode(decoder: &#gel_protocol::queryable::Decoder, _args: &(), buf: &#lifetime [u8])
                 -> Result<Self, #gel_protocol::errors::DecodeError>
             {
                 let json: #gel_protocol::model::Json =
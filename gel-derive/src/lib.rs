@@ -3,10 +3,11 @@ Derive macro that allows structs and enums to be populated by database
 queries.
 
 This derive can be used on structures with named fields (which correspond
-to "shapes" in Gel). Note that field order matters, so the struct below
-corresponds to an Gel `User` query with `first_name` followed by `age`.
-A `DescriptorMismatch` will be returned if the fields in the Rust struct
-are not in the same order as those in the query shape.
+to "shapes" in Gel). Fields are matched to the elements of the shape by
+name, so the struct below corresponds to an Gel `User` query selecting
+`first_name` and `age`. A `DescriptorMismatch` will be returned if the
+shape contains a different set of elements than the Rust struct (see
+`#[gel(match_by_name)]` to allow extra elements).
 
 ```rust
 # use gel_derive::Queryable;
@@ -28,6 +29,54 @@ let query_res: Vec<User> = client.query(query, &()).await?;
 let query_res: Vec<Value> = client.query(query, &()).await?;
 ```
 
+# Borrowed fields
+
+Structures can have a lifetime parameter and borrow strings and byte arrays
+from the query result instead of allocating a copy of each field. Such
+structures can't be returned from `query()` methods directly, use
+`query_rows()` and decode rows while the buffer is alive instead.
+
+```rust
+# use gel_derive::Queryable;
+#[derive(Queryable)]
+struct User<'a> {
+    first_name: &'a str,
+    avatar: Option<&'a [u8]>,
+}
+```
+
+# Polymorphic results
+
+Enums with fields in the variants decode objects of different types, for
+example when selecting an abstract type. The variant is chosen by the
+`__tname__` element of the shape: it matches the `#[gel(rename)]` of the
+variant if there is one, or the variant name otherwise (with the module name
+stripped from the type name). Fields of all variants must be present in the
+shape, but only the fields of the matching variant are decoded.
+
+```rust
+# use gel_derive::Queryable;
+// select Content {
+//     __tname__ := .__type__.name,
+//     title,
+//     [is Movie].runtime,
+//     [is Show].seasons,
+// }
+#[derive(Queryable)]
+enum Content {
+    Movie { title: String, runtime: Option<i64> },
+    Show { title: String, seasons: Option<i64> },
+    #[gel(rename = "default::Trailer")]
+    Clip,
+}
+```
+
+A variant can also contain a single unnamed field, which is decoded from the
+whole object (so it has to match the full shape). To choose the variant by
+another string element of the shape instead of `__tname__`, put
+`#[gel(discriminator = "kind")]` on the enum, where `kind` is the name of
+that element.
+
 # Field attributes
 
 ## JSON
@@ -48,8 +97,141 @@ struct User {
 }
 ```
 
+## Flatten
+
+The `#[gel(flatten)]` attribute decodes a field from the elements of the
+same shape. The type of the field must be a structure deriving `Queryable`.
+
+```rust
+# use gel_derive::Queryable;
+#[derive(Queryable)]
+struct Audit {
+    created_by: String,
+    modified_by: Option<String>,
+}
+
+// select Post { title, created_by, modified_by }
+#[derive(Queryable)]
+struct Post {
+    title: String,
+    #[gel(flatten)]
+    audit: Audit,
+}
+```
+
+## Default and skip
+
+A field marked with `#[gel(default)]` may be missing from the shape, in
+which case it's set to `Default::default()`. Use `#[gel(default = path)]` to
+call a function returning the value instead. Fields marked with
+`#[gel(skip)]` are never decoded and are always set to the default.
+
+```rust
+# use gel_derive::Queryable;
+fn unlimited() -> i64 {
+    i64::MAX
+}
+
+#[derive(Queryable)]
+struct Account {
+    name: String,
+    #[gel(default)]
+    tags: Vec<String>,
+    #[gel(default = unlimited)]
+    quota: i64,
+    #[gel(skip)]
+    cache: Option<Vec<u8>>,
+}
+```
+
+## Custom decoding
+
+The `#[gel(with = module)]` attribute decodes a field using functions of the
+module instead of the `Queryable` implementation of the field type. The
+module must define the same items as the `Queryable` trait, for a field of
+type `T`:
+
+* `type Args`
+* `fn check_descriptor(ctx: &DescriptorContext, type_pos: TypePos) -> Result<Args, DescriptorMismatch>`
+* `fn decode_optional(decoder: &Decoder, args: &Args, buf: Option<&[u8]>) -> Result<T, DecodeError>`
+
+```rust
+# use gel_derive::Queryable;
+mod uppercase {
+    use gel_protocol::descriptors::TypePos;
+    use gel_protocol::errors::DecodeError;
+    use gel_protocol::queryable::{Decoder, DescriptorContext, DescriptorMismatch, Queryable};
+
+    pub type Args = ();
+
+    pub fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Args, DescriptorMismatch> {
+        <String as Queryable>::check_descriptor(ctx, type_pos)
+    }
+
+    pub fn decode_optional(
+        decoder: &Decoder,
+        args: &Args,
+        buf: Option<&[u8]>,
+    ) -> Result<String, DecodeError> {
+        <String as Queryable>::decode_optional(decoder, args, buf).map(|s| s.to_uppercase())
+    }
+}
+
+#[derive(Queryable)]
+struct Country {
+    #[gel(with = uppercase)]
+    code: String,
+}
+```
+
+# Newtypes and tuples
+
+A structure with a single field marked `#[gel(transparent)]` is decoded
+exactly like the field itself, which is useful for giving scalars a
+dedicated type. Other tuple structures are decoded from Gel tuples with
+the same number of elements.
+
+The same forms can be passed as query arguments by deriving `QueryArg`.
+The field of a transparent structure must be a scalar argument, so the
+newtype can also be used in `Option` and `Vec` arguments.
+
+```rust
+# use gel_derive::{Queryable, QueryArg};
+# use gel_protocol::model::Uuid;
+// select <uuid>$0
+#[derive(Queryable, QueryArg)]
+#[gel(transparent)]
+struct UserId(Uuid);
+
+// select (<str>$0, <int64>$1)
+#[derive(Queryable, QueryArg)]
+struct Version(String, i64);
+```
+
 # Container attributes
 
+## Match by name
+
+By default, every element of the shape must correspond to a field of the
+structure. With `#[gel(match_by_name)]`, elements that don't correspond to
+any field are ignored, so the same structure can be used for queries
+selecting more data.
+
+```rust
+# use gel_derive::Queryable;
+// select User { first_name, last_name, age }
+#[derive(Queryable)]
+#[gel(match_by_name)]
+struct UserName {
+    first_name: String,
+    last_name: String,
+}
+```
+
+
 ## JSON
 
 The `#[gel(json)]` attribute can be used to unpack the structure from
@@ -84,7 +266,9 @@ mod attrib;
 mod enums;
 mod json;
 mod shape;
+mod tuple;
 mod variables;
+mod variants;
 
 #[proc_macro_derive(Queryable, attributes(gel))]
 pub fn queryable(input: TokenStream) -> TokenStream {
@@ -111,7 +295,13 @@ fn derive(item: &syn::Item) -> syn::Result<proc_macro2::TokenStream> {
         json::derive(item, &attrs)
     } else {
         match item {
+            syn::Item::Struct(s)
+                if attrs.transparent || matches!(s.fields, syn::Fields::Unnamed(_)) =>
+            {
+                tuple::derive_queryable(s, &attrs)
+            }
             syn::Item::Struct(s) => shape::derive_struct(s, &attrs),
+            syn::Item::Enum(s) if is_tagged(s, &attrs) => variants::derive_tagged_enum(s, &attrs),
             syn::Item::Enum(s) => enums::derive_enum(s, &attrs),
             _ => Err(syn::Error::new_spanned(
                 item,
@@ -122,6 +312,47 @@ fn derive(item: &syn::Item) -> syn::Result<proc_macro2::TokenStream> {
     }
 }
 
+/// Returns `true` if the enum is decoded from objects rather than from
+/// scalar enum values.
+fn is_tagged(item: &syn::ItemEnum, attrs: &attrib::ContainerAttrs) -> bool {
+    attrs.discriminator.is_some()
+        || item
+            .variants
+            .iter()
+            .any(|v| !matches!(v.fields, syn::Fields::Unit))
+}
+
+/// Lifetime of the buffer that the derived `Queryable` implementation
+/// decodes from.
+fn decode_lifetime() -> syn::Lifetime {
+    syn::Lifetime::new("'__gel_de", proc_macro2::Span::mixed_site())
+}
+
+/// Adds the decode lifetime to the generics of the item, bounded so that it
+/// outlives every lifetime of the item itself.
+fn add_decode_lifetime(generics: &syn::Generics, lifetime: &syn::Lifetime) -> syn::Generics {
+    let mut param = syn::LifetimeParam::new(lifetime.clone());
+    param.bounds = generics.lifetimes().map(|l| l.lifetime.clone()).collect();
+    let mut generics = generics.clone();
+    generics
+        .params
+        .insert(0, syn::GenericParam::Lifetime(param));
+    generics
+}
+
+#[proc_macro_derive(QueryArg, attributes(gel))]
+pub fn query_arg(input: TokenStream) -> TokenStream {
+    let s = parse_macro_input!(input as syn::ItemStruct);
+    let attrs = match attrib::ContainerAttrs::from_syn(&s.attrs) {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    match tuple::derive_query_arg(&s, &attrs) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 #[proc_macro_derive(GlobalsDelta, attributes(gel))]
 pub fn globals_delta(input: TokenStream) -> TokenStream {
     let s = parse_macro_input!(input as syn::ItemStruct);
@@ -139,3 +370,21 @@ pub fn config_delta(input: TokenStream) -> TokenStream {
         Err(e) => e.to_compile_error().into(),
     }
 }
+
+#[proc_macro_derive(AliasesDelta, attributes(gel))]
+pub fn aliases_delta(input: TokenStream) -> TokenStream {
+    let s = parse_macro_input!(input as syn::ItemStruct);
+    match variables::derive_aliases(&s) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(FromGlobals, attributes(gel))]
+pub fn from_globals(input: TokenStream) -> TokenStream {
+    let s = parse_macro_input!(input as syn::ItemStruct);
+    match variables::derive_from_globals(&s) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
@@ -1,7 +1,7 @@
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
 
-use crate::attrib::{ContainerAttrs, FieldAttrs};
+use crate::attrib::{ContainerAttrs, FieldAttrs, FieldDefault};
 
 struct Field {
     name: syn::Ident,
@@ -10,6 +10,37 @@ struct Field {
     attrs: FieldAttrs,
 }
 
+impl Field {
+    /// Field decoded from a single element of the shape.
+    fn is_element(&self) -> bool {
+        !self.attrs.flatten && !self.attrs.skip
+    }
+    /// Element field that must be present in the shape.
+    fn is_required(&self) -> bool {
+        self.is_element() && self.attrs.default.is_none()
+    }
+}
+
+fn validate(field: &syn::Field, attrs: &FieldAttrs) -> syn::Result<()> {
+    let error = |message| Err(syn::Error::new_spanned(field, message));
+    if attrs.flatten
+        && (attrs.json
+            || attrs.rename.is_some()
+            || attrs.default.is_some()
+            || attrs.skip
+            || attrs.with.is_some())
+    {
+        return error("`flatten` can't be combined with other gel attributes");
+    }
+    if attrs.skip && (attrs.json || attrs.rename.is_some() || attrs.with.is_some()) {
+        return error("`skip` can only be combined with `default`");
+    }
+    if attrs.json && attrs.with.is_some() {
+        return error("`json` and `with` are mutually exclusive");
+    }
+    Ok(())
+}
+
 pub fn derive_struct(
     s: &syn::ItemStruct,
     container_attrs: &ContainerAttrs,
@@ -18,16 +49,22 @@ pub fn derive_struct(
     let name = &s.ident;
     let decoder = syn::Ident::new("decoder", Span::mixed_site());
     let buf = syn::Ident::new("buf", Span::mixed_site());
-    let nfields = syn::Ident::new("nfields", Span::mixed_site());
     let elements = syn::Ident::new("elements", Span::mixed_site());
+    let values = syn::Ident::new("values", Span::mixed_site());
+    let used = syn::Ident::new("used", Span::mixed_site());
+    let nelements = syn::Ident::new("nelements", Span::mixed_site());
     let order = syn::Ident::new("order", Span::mixed_site());
     let sub_args = syn::Ident::new("sub_args", Span::mixed_site());
-    let (impl_generics, ty_generics, _) = s.generics.split_for_impl();
+    let lifetime = crate::decode_lifetime();
+    let generics = crate::add_decode_lifetime(&s.generics, &lifetime);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = s.generics.split_for_impl();
     let fields = match &s.fields {
         syn::Fields::Named(named) => {
             let mut fields = Vec::with_capacity(named.named.len());
             for field in &named.named {
                 let attrs = FieldAttrs::from_syn(&field.attrs)?;
+                validate(field, &attrs)?;
                 let name = field.ident.clone().unwrap();
                 let str_name = if let Some(rename) = &attrs.rename {
                     rename.clone()
@@ -51,7 +88,6 @@ pub fn derive_struct(
         }
     };
     let fieldname = fields.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
-    let base_fields = fields.len();
     let type_id_block = Some(quote! {
         if #decoder.has_implicit_tid {
             #elements.skip_element()?;
@@ -91,69 +127,141 @@ pub fn derive_struct(
             idx += 1;
         }
     });
+
+    let default_value = |field: &Field| match &field.attrs.default {
+        Some(FieldDefault::Path(path)) => quote! { #path() },
+        Some(FieldDefault::Trait) | None => quote! { ::std::default::Default::default() },
+    };
+    let element_args_ty = |field: &Field| {
+        if field.attrs.json {
+            quote! { () }
+        } else if let Some(with) = &field.attrs.with {
+            quote! { #with::Args }
+        } else {
+            let ty = &field.ty;
+            quote! { <#ty as #gel_protocol::queryable::Queryable<#lifetime>>::Args }
+        }
+    };
+    let decode_element = |field: &Field, arg: TokenStream, element: TokenStream| {
+        if field.attrs.json {
+            quote! {{
+                let json: #gel_protocol::model::Json =
+                    <#gel_protocol::model::Json as
+                        #gel_protocol::queryable::Queryable>
+                    ::decode_optional(#decoder, #arg, #element)?;
+                ::serde_json::from_str(json.as_ref())
+                    .map_err(#gel_protocol::errors::decode_error)?
+            }}
+        } else if let Some(with) = &field.attrs.with {
+            quote! { #with::decode_optional(#decoder, #arg, #element)? }
+        } else {
+            let ty = &field.ty;
+            quote! {
+                <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                ::decode_optional(#decoder, #arg, #element)?
+            }
+        }
+    };
+    let check_element = |field: &Field| {
+        if field.attrs.json {
+            quote! {
+                <#gel_protocol::model::Json as
+                    #gel_protocol::queryable::Queryable>
+                    ::check_descriptor(ctx, el.type_pos)?
+            }
+        } else if let Some(with) = &field.attrs.with {
+            quote! { #with::check_descriptor(ctx, el.type_pos)? }
+        } else {
+            let ty = &field.ty;
+            quote! {
+                <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                    ::check_descriptor(ctx, el.type_pos)?
+            }
+        }
+    };
+    let get_value = |position: TokenStream| {
+        quote! {
+            *#values.get(#position).ok_or_else(|| {
+                #gel_protocol::errors::ObjectSizeMismatch.build()
+            })?
+        }
+    };
+
+    let mut slot = 0usize;
     let field_decoders = fields
         .iter()
         .enumerate()
         .map(|(index, field)| {
             let fieldname = &field.name;
-
-            let index_lit = syn::LitInt::new(&index.to_string(), Span::mixed_site());
+            let index_lit = syn::Index::from(index);
             let sub_arg = quote! { &#sub_args.#index_lit };
-            let buf = quote! { fields[#order[#index]].as_deref() };
-
-            if field.attrs.json {
+            let value = if field.attrs.skip {
+                default_value(field)
+            } else if field.attrs.flatten {
+                let ty = &field.ty;
                 quote! {
-                    let #fieldname: #gel_protocol::model::Json =
-                        <#gel_protocol::model::Json as
-                            #gel_protocol::queryable::Queryable>
-                        ::decode_optional(#decoder, #sub_arg, #buf)?;
-                    let #fieldname = ::serde_json::from_str(#fieldname.as_ref())
-                        .map_err(#gel_protocol::errors::decode_error)?;
+                    <#ty as #gel_protocol::queryable::DecodeFields<#lifetime>>
+                    ::decode_fields(#decoder, #sub_arg, #values)?
                 }
-            } else {
+            } else if field.attrs.default.is_some() {
+                let element = get_value(quote! { *position });
+                let decode = decode_element(field, quote! { arg }, element);
+                let default = default_value(field);
                 quote! {
-                    let #fieldname =
-                        #gel_protocol::queryable::Queryable
-                        ::decode_optional(#decoder, #sub_arg, #buf)?;
+                    match #sub_arg {
+                        ::std::option::Option::Some((position, arg)) => #decode,
+                        ::std::option::Option::None => #default,
+                    }
                 }
-            }
+            } else {
+                let element = get_value(quote! { #order[#slot] });
+                slot += 1;
+                decode_element(field, sub_arg, element)
+            };
+            quote! { let #fieldname = #value; }
         })
         .collect::<TokenStream>();
     let field_checks = fields
         .iter()
         .enumerate()
         .map(|(field_index, field)| {
+            let arg_ident = quote::format_ident!("arg_{field_index}");
             let name_str = &field.str_name;
-            let description_str = syn::LitStr::new(
-                &format!("field {}", field.str_name.value()),
-                field.str_name.span(),
-            );
-            let get_element = quote! {
-                let ::std::option::Option::Some((position, el)) = elements.get(#name_str) else {
-                    return ::std::result::Result::Err(ctx.expected(#description_str));
-                };
-                order.push(*position);
-            };
-
-            let fieldtype = &field.ty;
-            let check_descriptor = if field.attrs.json {
+            if field.attrs.skip {
+                quote! { let #arg_ident = (); }
+            } else if field.attrs.flatten {
+                let ty = &field.ty;
                 quote! {
-                    <#gel_protocol::model::Json as
-                        #gel_protocol::queryable::Queryable>
-                        ::check_descriptor(ctx, el.type_pos)?
+                    let #arg_ident =
+                        <#ty as #gel_protocol::queryable::DecodeFields<#lifetime>>
+                        ::check_fields(ctx, elements, #used)?;
+                }
+            } else if field.attrs.default.is_some() {
+                let check = check_element(field);
+                quote! {
+                    let #arg_ident = match elements.get(#name_str) {
+                        ::std::option::Option::Some((position, el)) => {
+                            *#used += 1;
+                            ::std::option::Option::Some((*position, #check))
+                        }
+                        ::std::option::Option::None => ::std::option::Option::None,
+                    };
                 }
             } else {
+                let description_str = syn::LitStr::new(
+                    &format!("field {}", field.str_name.value()),
+                    field.str_name.span(),
+                );
+                let check = check_element(field);
                 quote! {
-                    <#fieldtype as #gel_protocol::queryable::Queryable>
-                        ::check_descriptor(ctx, el.type_pos)?
+                    let ::std::option::Option::Some((position, el)) = elements.get(#name_str)
+                    else {
+                        return ::std::result::Result::Err(ctx.expected(#description_str));
+                    };
+                    order.push(*position);
+                    *#used += 1;
+                    let #arg_ident = #check;
                 }
-            };
-
-            let arg_ident = quote::format_ident!("arg_{field_index}");
-
-            quote! {
-                #get_element
-                let #arg_ident = #check_descriptor;
             }
         })
         .collect::<TokenStream>();
@@ -168,45 +276,120 @@ pub fn derive_struct(
     let args_ty = fields
         .iter()
         .map(|field| {
-            if field.attrs.json {
+            let ty = &field.ty;
+            if field.attrs.skip {
                 quote! { (), }
+            } else if field.attrs.flatten {
+                quote! {
+                    <#ty as #gel_protocol::queryable::DecodeFields<#lifetime>>::FieldArgs,
+                }
+            } else if field.attrs.default.is_some() {
+                let args = element_args_ty(field);
+                quote! { ::std::option::Option<(usize, #args)>, }
             } else {
-                let ty = &field.ty;
-                quote! { <#ty as #gel_protocol::queryable::Queryable>::Args, }
+                let args = element_args_ty(field);
+                quote! { #args, }
             }
         })
         .collect::<TokenStream>();
 
-    let field_count = fields.len();
+    let required_count = fields.iter().filter(|f| f.is_required()).count();
+    // Number of elements is known upfront unless some fields are optional
+    let static_count = fields.iter().all(|f| f.is_required() || f.attrs.skip);
+    let (count_check, used_check) = if container_attrs.match_by_name {
+        (quote! {}, quote! {})
+    } else if static_count {
+        let check = quote! {
+            if(shape.elements.len() - idx != #required_count) {
+                return ::std::result::Result::Err(ctx.field_number(
+                    #required_count, shape.elements.len() - idx)
+                );
+            }
+        };
+        (check, quote! {})
+    } else {
+        let check = quote! {
+            if(shape.elements.len() - idx != #used) {
+                return ::std::result::Result::Err(ctx.field_number(
+                    #used, shape.elements.len() - idx)
+                );
+            }
+        };
+        (quote! {}, check)
+    };
+    // The element count is validated against the data on decode. When it
+    // depends on the descriptor (elements matched by name, defaulted or
+    // flattened fields) it's stored in front of the field arguments.
+    let (queryable_args_ty, count_arg, element_count, count_result) =
+        if !container_attrs.match_by_name && static_count {
+            (
+                quote! { (::std::vec::Vec<usize>, (#args_ty)) },
+                quote! { args },
+                quote! {
+                    #required_count
+                        + if #decoder.has_implicit_id { 1 } else { 0 }
+                        + if #decoder.has_implicit_tid { 1 } else { 0 }
+                        + if #decoder.has_implicit_tname { 1 } else { 0 }
+                },
+                quote! { args },
+            )
+        } else {
+            (
+                quote! { (usize, (::std::vec::Vec<usize>, (#args_ty))) },
+                quote! { (#nelements, args) },
+                quote! { *#nelements },
+                quote! { (shape.elements.len(), args) },
+            )
+        };
 
     let expanded = quote! {
-        impl #impl_generics #gel_protocol::queryable::Queryable
-            for #name #ty_generics {
-            type Args = (::std::vec::Vec<usize>, (#args_ty));
+        impl #impl_generics #gel_protocol::queryable::DecodeFields<#lifetime>
+            for #name #ty_generics #where_clause {
+            type FieldArgs = (::std::vec::Vec<usize>, (#args_ty));
+
+            fn decode_fields(
+                #decoder: &#gel_protocol::queryable::Decoder,
+                (#order, #sub_args): &Self::FieldArgs,
+                #values: &[::std::option::Option<&#lifetime [u8]>],
+            ) -> ::std::result::Result<Self, #gel_protocol::errors::DecodeError> {
+                #field_decoders
+                ::std::result::Result::Ok(#name {
+                    #(
+                        #fieldname,
+                    )*
+                })
+            }
+            fn check_fields(
+                ctx: &#gel_protocol::queryable::DescriptorContext,
+                elements: &#gel_protocol::queryable::ShapeElements,
+                #used: &mut usize,
+            ) -> ::std::result::Result<Self::FieldArgs, #gel_protocol::queryable::DescriptorMismatch>
+            {
+                let mut order = ::std::vec::Vec::with_capacity(#required_count);
+                #field_checks
+                ::std::result::Result::Ok((order, (#construct_sub_args)))
+            }
+        }
+
+        impl #impl_generics #gel_protocol::queryable::Queryable<#lifetime>
+            for #name #ty_generics #where_clause {
+            type Args = #queryable_args_ty;
 
             fn decode(
                 #decoder: &#gel_protocol::queryable::Decoder,
-                (#order, #sub_args): &Self::Args,
-                #buf: &[u8]
+                #count_arg: &Self::Args,
+                #buf: &#lifetime [u8]
             ) -> ::std::result::Result<Self, #gel_protocol::errors::DecodeError> {
-                let #nfields = #base_fields
-                    + if #decoder.has_implicit_id { 1 } else { 0 }
-                    + if #decoder.has_implicit_tid { 1 } else { 0 }
-                    + if #decoder.has_implicit_tname { 1 } else { 0 };
                 let mut #elements =
                     #gel_protocol::serialization::decode::DecodeTupleLike
-                    ::new_object(#buf, #nfields)?;
+                    ::new_object(#buf, #element_count)?;
 
                 #type_id_block
                 #type_name_block
                 #id_block
-                let fields = #elements.read_n(#field_count)?;
-                #field_decoders
-                ::std::result::Result::Ok(#name {
-                    #(
-                        #fieldname,
-                    )*
-                })
+                let #values = #elements.read_n(#elements.count())?;
+                <Self as #gel_protocol::queryable::DecodeFields<#lifetime>>
+                    ::decode_fields(#decoder, args, &#values)
             }
             fn check_descriptor(
                 ctx: &#gel_protocol::queryable::DescriptorContext,
@@ -227,20 +410,19 @@ pub fn derive_struct(
                 #type_id_check
                 #type_name_check
                 #id_check
-                if(shape.elements.len() != #field_count) {
-                    return ::std::result::Result::Err(ctx.field_number(
-                        #field_count, shape.elements.len())
-                    );
-                }
+                #count_check
 
                 let mut elements = ::std::collections::HashMap::with_capacity(shape.elements.len());
                 use ::std::iter::Iterator;
-                for (position, element) in shape.elements.iter().enumerate() {
+                // positions are relative to the first non-implicit element
+                for (position, element) in shape.elements[idx..].iter().enumerate() {
                     elements.insert(element.name.as_str(), (position, element));
                 }
-                let mut order = ::std::vec::Vec::with_capacity(shape.elements.len());
-                #field_checks
-                ::std::result::Result::Ok((order, (#construct_sub_args)))
+                let mut #used = 0;
+                let args = <Self as #gel_protocol::queryable::DecodeFields<#lifetime>>
+                    ::check_fields(ctx, &elements, &mut #used)?;
+                #used_check
+                ::std::result::Result::Ok(#count_result)
             }
         }
     };
@@ -0,0 +1,297 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::attrib::{ContainerAttrs, FieldAttrs};
+
+struct Field {
+    /// Index or name of the field
+    member: syn::Member,
+    ty: syn::Type,
+}
+
+fn fields(s: &syn::ItemStruct, container_attrs: &ContainerAttrs) -> syn::Result<Vec<Field>> {
+    let mut fields = Vec::with_capacity(s.fields.len());
+    for (index, field) in s.fields.iter().enumerate() {
+        let attrs = FieldAttrs::from_syn(&field.attrs)?;
+        if attrs.json
+            || attrs.rename.is_some()
+            || attrs.flatten
+            || attrs.default.is_some()
+            || attrs.skip
+            || attrs.with.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "field attributes are not supported on tuple and transparent structs",
+            ));
+        }
+        let member = match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(index)),
+        };
+        fields.push(Field {
+            member,
+            ty: field.ty.clone(),
+        });
+    }
+    if container_attrs.transparent && fields.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            &s.fields,
+            "`transparent` requires exactly one field",
+        ));
+    }
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &s.fields,
+            "tuple structs must have at least one field",
+        ));
+    }
+    Ok(fields)
+}
+
+/// Builds a value of the struct from the field values in order.
+fn construct(s: &syn::ItemStruct, values: impl Iterator<Item = TokenStream>) -> TokenStream {
+    let name = &s.ident;
+    match &s.fields {
+        syn::Fields::Named(named) => {
+            let idents = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+            quote! { #name { #(#idents: #values,)* } }
+        }
+        _ => quote! { #name(#(#values,)*) },
+    }
+}
+
+/// Derives `Queryable` for a transparent struct or a tuple struct.
+///
+/// A transparent struct is decoded exactly like its only field. Other tuple
+/// structs are decoded from Gel tuples with the same number of elements.
+pub fn derive_queryable(
+    s: &syn::ItemStruct,
+    container_attrs: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let gel_protocol = container_attrs.gel_protocol_path();
+    let name = &s.ident;
+    let decoder = syn::Ident::new("decoder", Span::mixed_site());
+    let args = syn::Ident::new("args", Span::mixed_site());
+    let buf = syn::Ident::new("buf", Span::mixed_site());
+    let elements = syn::Ident::new("elements", Span::mixed_site());
+    let lifetime = crate::decode_lifetime();
+    let generics = crate::add_decode_lifetime(&s.generics, &lifetime);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = s.generics.split_for_impl();
+    let fields = fields(s, container_attrs)?;
+
+    if container_attrs.transparent {
+        let ty = &fields[0].ty;
+        let queryable = quote! { <#ty as #gel_protocol::queryable::Queryable<#lifetime>> };
+        let decode = construct(
+            s,
+            std::iter::once(quote! { #queryable::decode(#decoder, #args, #buf)? }),
+        );
+        let decode_optional = construct(
+            s,
+            std::iter::once(quote! { #queryable::decode_optional(#decoder, #args, #buf)? }),
+        );
+        return Ok(quote! {
+            impl #impl_generics #gel_protocol::queryable::Queryable<#lifetime>
+                for #name #ty_generics #where_clause {
+                type Args = #queryable::Args;
+
+                fn decode(
+                    #decoder: &#gel_protocol::queryable::Decoder,
+                    #args: &Self::Args,
+                    #buf: &#lifetime [u8],
+                ) -> ::std::result::Result<Self, #gel_protocol::errors::DecodeError> {
+                    ::std::result::Result::Ok(#decode)
+                }
+                fn decode_optional(
+                    #decoder: &#gel_protocol::queryable::Decoder,
+                    #args: &Self::Args,
+                    #buf: ::std::option::Option<&#lifetime [u8]>,
+                ) -> ::std::result::Result<Self, #gel_protocol::errors::DecodeError> {
+                    ::std::result::Result::Ok(#decode_optional)
+                }
+                fn check_descriptor(
+                    ctx: &#gel_protocol::queryable::DescriptorContext,
+                    type_pos: #gel_protocol::descriptors::TypePos,
+                ) -> ::std::result::Result<Self::Args, #gel_protocol::queryable::DescriptorMismatch>
+                {
+                    #queryable::check_descriptor(ctx, type_pos)
+                }
+            }
+        });
+    }
+
+    let count = fields.len();
+    let args_ty = fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote! { <#ty as #gel_protocol::queryable::Queryable<#lifetime>>::Args, }
+    });
+    let decode = construct(
+        s,
+        fields.iter().enumerate().map(|(index, field)| {
+            let ty = &field.ty;
+            let index = syn::Index::from(index);
+            quote! {
+                <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                    ::decode_optional(#decoder, &#args.#index, #elements.read()?)?
+            }
+        }),
+    );
+    let checks = fields.iter().enumerate().map(|(index, field)| {
+        let ty = &field.ty;
+        quote! {
+            <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                ::check_descriptor(ctx, desc.element_types[#index])?,
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics #gel_protocol::queryable::Queryable<#lifetime>
+            for #name #ty_generics #where_clause {
+            type Args = (#(#args_ty)*);
+
+            fn decode(
+                #decoder: &#gel_protocol::queryable::Decoder,
+                #args: &Self::Args,
+                #buf: &#lifetime [u8],
+            ) -> ::std::result::Result<Self, #gel_protocol::errors::DecodeError> {
+                let mut #elements =
+                    #gel_protocol::serialization::decode::DecodeTupleLike
+                    ::new_tuple(#buf, #count)?;
+                ::std::result::Result::Ok(#decode)
+            }
+            fn check_descriptor(
+                ctx: &#gel_protocol::queryable::DescriptorContext,
+                type_pos: #gel_protocol::descriptors::TypePos,
+            ) -> ::std::result::Result<Self::Args, #gel_protocol::queryable::DescriptorMismatch>
+            {
+                use #gel_protocol::descriptors::Descriptor::Tuple;
+                let desc = ctx.get(type_pos)?;
+                let desc = match desc {
+                    Tuple(desc) => desc,
+                    _ => return ::std::result::Result::Err(ctx.wrong_type(desc, "tuple")),
+                };
+                if desc.element_types.len() != #count {
+                    return ::std::result::Result::Err(
+                        ctx.field_number(#count, desc.element_types.len()));
+                }
+                ::std::result::Result::Ok((#(#checks)*))
+            }
+        }
+    })
+}
+
+/// Derives `QueryArg` for a transparent struct or a tuple struct.
+///
+/// A transparent struct implements `ScalarArg` delegating to its only field,
+/// so it can also be used in `Option` and `Vec` arguments. Other tuple
+/// structs are encoded as Gel tuples.
+pub fn derive_query_arg(
+    s: &syn::ItemStruct,
+    container_attrs: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    if !container_attrs.transparent && !matches!(s.fields, syn::Fields::Unnamed(_)) {
+        return Err(syn::Error::new_spanned(
+            s,
+            "QueryArg can only be derived for tuple structs and `#[gel(transparent)]` structs",
+        ));
+    }
+    let gel_protocol = container_attrs.gel_protocol_path();
+    let name = &s.ident;
+    let enc = syn::Ident::new("enc", Span::mixed_site());
+    let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+    let fields = fields(s, container_attrs)?;
+
+    if container_attrs.transparent {
+        let member = &fields[0].member;
+        let ty = &fields[0].ty;
+        let scalar_arg = quote! { #gel_protocol::query_arg::ScalarArg };
+        return Ok(quote! {
+            impl #impl_generics #scalar_arg for #name #ty_generics #where_clause {
+                fn encode(
+                    &self,
+                    #enc: &mut #gel_protocol::query_arg::Encoder,
+                ) -> ::std::result::Result<(), #gel_protocol::query_arg::Error> {
+                    #scalar_arg::encode(&self.#member, #enc)
+                }
+                fn check_descriptor(
+                    ctx: &#gel_protocol::query_arg::DescriptorContext,
+                    pos: #gel_protocol::descriptors::TypePos,
+                ) -> ::std::result::Result<(), #gel_protocol::query_arg::Error> {
+                    <#ty as #scalar_arg>::check_descriptor(ctx, pos)
+                }
+                fn to_value(
+                    &self,
+                ) -> ::std::result::Result<
+                    #gel_protocol::value::Value,
+                    #gel_protocol::query_arg::Error,
+                > {
+                    #scalar_arg::to_value(&self.#member)
+                }
+            }
+        });
+    }
+
+    let query_arg = quote! { #gel_protocol::query_arg::QueryArg };
+    let count = fields.len();
+    let count_u32 = count as u32;
+    let encode_fields = fields.iter().map(|field| {
+        let member = &field.member;
+        quote! {
+            #enc.buf.extend_from_slice(&[0; 4]); // reserved
+            #query_arg::encode_slot(&self.#member, #enc)?;
+        }
+    });
+    let checks = fields.iter().enumerate().map(|(index, field)| {
+        let member = &field.member;
+        quote! {
+            #query_arg::check_descriptor(&self.#member, ctx, desc.element_types[#index])?;
+        }
+    });
+    let values = fields.iter().map(|field| {
+        let member = &field.member;
+        quote! { #query_arg::to_value(&self.#member)?, }
+    });
+    Ok(quote! {
+        impl #impl_generics #query_arg for #name #ty_generics #where_clause {
+            fn encode_slot(
+                &self,
+                #enc: &mut #gel_protocol::query_arg::Encoder,
+            ) -> ::std::result::Result<(), #gel_protocol::query_arg::Error> {
+                #enc.length_prefixed(|#enc| {
+                    #enc.buf.extend_from_slice(&#count_u32.to_be_bytes());
+                    #(#encode_fields)*
+                    ::std::result::Result::Ok(())
+                })
+            }
+            fn check_descriptor(
+                &self,
+                ctx: &#gel_protocol::query_arg::DescriptorContext,
+                pos: #gel_protocol::descriptors::TypePos,
+            ) -> ::std::result::Result<(), #gel_protocol::query_arg::Error> {
+                use #gel_protocol::descriptors::Descriptor::Tuple;
+                let desc = ctx.get(pos)?;
+                let desc = match desc {
+                    Tuple(desc) => desc,
+                    _ => return ::std::result::Result::Err(ctx.wrong_type(desc, "tuple")),
+                };
+                if desc.element_types.len() != #count {
+                    return ::std::result::Result::Err(
+                        ctx.field_number(#count, desc.element_types.len()));
+                }
+                #(#checks)*
+                ::std::result::Result::Ok(())
+            }
+            fn to_value(
+                &self,
+            ) -> ::std::result::Result<
+                #gel_protocol::value::Value,
+                #gel_protocol::query_arg::Error,
+            > {
+                ::std::result::Result::Ok(#gel_protocol::value::Value::Tuple(
+                    ::std::vec![#(#values)*]
+                ))
+            }
+        }
+    })
+}
@@ -78,3 +78,84 @@ pub fn derive_config(item: &syn::ItemStruct) -> syn::Result<proc_macro2::TokenSt
     };
     Ok(expanded)
 }
+
+pub fn derive_aliases(item: &syn::ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let man = syn::Ident::new("man", Span::mixed_site());
+
+    let fields = match &item.fields {
+        syn::Fields::Named(fields) => fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &item.fields,
+                "only named fields are supported",
+            ));
+        }
+    };
+    let set_aliases = fields
+        .named
+        .iter()
+        .map(|f| {
+            let ident = f.ident.as_ref().expect("a named field");
+            let name = ident.to_string();
+            quote! {
+                #man.set(#name, ::std::convert::AsRef::<str>::as_ref(&self.#ident));
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_c) = item.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics ::gel_tokio::state::AliasesDelta
+            for &'_ #name #ty_generics
+            #where_c
+        {
+            fn apply(self, #man: &mut ::gel_tokio::state::AliasesModifier)
+            {
+                #(#set_aliases)*
+            }
+        }
+    };
+    Ok(expanded)
+}
+
+pub fn derive_from_globals(item: &syn::ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let reader = syn::Ident::new("reader", Span::mixed_site());
+
+    let fields = match &item.fields {
+        syn::Fields::Named(fields) => fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &item.fields,
+                "only named fields are supported",
+            ));
+        }
+    };
+    let get_vars = fields
+        .named
+        .iter()
+        .map(|f| {
+            let ident = f.ident.as_ref().expect("a named field");
+            let name = ident.to_string();
+            quote! { #ident: #reader.get(#name)?, }
+        })
+        .collect::<Vec<_>>();
+
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_c) = item.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics ::gel_tokio::state::FromGlobals
+            for #name #ty_generics
+            #where_c
+        {
+            fn from_globals(#reader: &::gel_tokio::state::GlobalsReader<'_>)
+                -> ::std::result::Result<Self, ::gel_tokio::Error>
+            {
+                ::std::result::Result::Ok(#name {
+                    #(#get_vars)*
+                })
+            }
+        }
+    };
+    Ok(expanded)
+}
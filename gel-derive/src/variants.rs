@@ -0,0 +1,315 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::attrib::{ContainerAttrs, FieldAttrs};
+
+/// Discriminator used when there is no `#[gel(discriminator = ...)]`.
+const TYPE_NAME: &str = "__tname__";
+
+struct Field {
+    name: syn::Ident,
+    str_name: syn::LitStr,
+    ty: syn::Type,
+    attrs: FieldAttrs,
+}
+
+enum Kind {
+    Unit,
+    Named(Vec<Field>),
+    Newtype(syn::Type),
+}
+
+struct Variant {
+    name: syn::Ident,
+    kind: Kind,
+    /// Exact value of the discriminator, if renamed.
+    rename: Option<syn::LitStr>,
+}
+
+/// Derives `Queryable` for an enum with data-carrying variants.
+///
+/// The object is decoded into the variant selected by the value of the
+/// discriminator element (type name by default). A variant matches if the
+/// value is equal to its `#[gel(rename)]`, or if the value, with the module
+/// name stripped, is equal to the name of the variant.
+pub fn derive_tagged_enum(
+    s: &syn::ItemEnum,
+    container_attrs: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    let gel_protocol = container_attrs.gel_protocol_path();
+    let type_name = &s.ident;
+    let decoder = syn::Ident::new("decoder", Span::mixed_site());
+    let buf = syn::Ident::new("buf", Span::mixed_site());
+    let nfields = syn::Ident::new("nfields", Span::mixed_site());
+    let tag_pos = syn::Ident::new("tag_pos", Span::mixed_site());
+    let tag = syn::Ident::new("tag", Span::mixed_site());
+    let short_tag = syn::Ident::new("short_tag", Span::mixed_site());
+    let values = syn::Ident::new("values", Span::mixed_site());
+    let sub_args = syn::Ident::new("sub_args", Span::mixed_site());
+    let lifetime = crate::decode_lifetime();
+    let generics = crate::add_decode_lifetime(&s.generics, &lifetime);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = s.generics.split_for_impl();
+    let discriminator = container_attrs
+        .discriminator
+        .clone()
+        .unwrap_or_else(|| syn::LitStr::new(TYPE_NAME, Span::call_site()));
+    let discriminator_str = syn::LitStr::new(
+        &format!("field {}", discriminator.value()),
+        discriminator.span(),
+    );
+
+    let variants = s
+        .variants
+        .iter()
+        .map(parse_variant)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let field_type = |field: &Field| {
+        if field.attrs.json {
+            quote! { #gel_protocol::model::Json }
+        } else {
+            let ty = &field.ty;
+            quote! { #ty }
+        }
+    };
+
+    let args_ty = variants
+        .iter()
+        .map(|variant| match &variant.kind {
+            Kind::Unit => quote! { (), },
+            Kind::Named(fields) => {
+                let args = fields.iter().map(|field| {
+                    let ty = field_type(field);
+                    quote! { <#ty as #gel_protocol::queryable::Queryable<#lifetime>>::Args, }
+                });
+                quote! { (::std::vec::Vec<usize>, (#(#args)*)), }
+            }
+            Kind::Newtype(ty) => {
+                quote! { <#ty as #gel_protocol::queryable::Queryable<#lifetime>>::Args, }
+            }
+        })
+        .collect::<TokenStream>();
+
+    let decoders = variants
+        .iter()
+        .enumerate()
+        .map(|(variant_index, variant)| {
+            let name = &variant.name;
+            let index_lit = syn::Index::from(variant_index);
+            let condition = match &variant.rename {
+                Some(rename) => quote! { #tag == #rename },
+                None => {
+                    let name_str = syn::LitStr::new(&name.to_string(), name.span());
+                    quote! { #short_tag == #name_str }
+                }
+            };
+            let construct = match &variant.kind {
+                Kind::Unit => quote! { #type_name::#name },
+                Kind::Newtype(ty) => quote! {
+                    #type_name::#name(
+                        <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                        ::decode(#decoder, &#sub_args.#index_lit, #buf)?
+                    )
+                },
+                Kind::Named(fields) => {
+                    let order = syn::Ident::new("order", Span::mixed_site());
+                    let variant_args = syn::Ident::new("variant_args", Span::mixed_site());
+                    let field_decoders = fields.iter().enumerate().map(|(index, field)| {
+                        let fieldname = &field.name;
+                        let index_lit = syn::Index::from(index);
+                        let ty = field_type(field);
+                        let decode = quote! {
+                            <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                            ::decode_optional(
+                                #decoder,
+                                &#variant_args.#index_lit,
+                                #values[#order[#index]],
+                            )?
+                        };
+                        if field.attrs.json {
+                            quote! {
+                                let #fieldname: #gel_protocol::model::Json = #decode;
+                                let #fieldname = ::serde_json::from_str(#fieldname.as_ref())
+                                    .map_err(#gel_protocol::errors::decode_error)?;
+                            }
+                        } else {
+                            quote! { let #fieldname = #decode; }
+                        }
+                    });
+                    let fieldnames = fields.iter().map(|f| &f.name);
+                    quote! {{
+                        let (#order, #variant_args) = &#sub_args.#index_lit;
+                        #(#field_decoders)*
+                        #type_name::#name { #(#fieldnames,)* }
+                    }}
+                }
+            };
+            quote! {
+                if #condition {
+                    return ::std::result::Result::Ok(#construct);
+                }
+            }
+        })
+        .collect::<TokenStream>();
+
+    let checks = variants
+        .iter()
+        .enumerate()
+        .map(|(variant_index, variant)| {
+            let arg_ident = quote::format_ident!("variant_{variant_index}");
+            match &variant.kind {
+                Kind::Unit => quote! { let #arg_ident = (); },
+                Kind::Newtype(ty) => quote! {
+                    let #arg_ident =
+                        <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                        ::check_descriptor(ctx, type_pos)?;
+                },
+                Kind::Named(fields) => {
+                    let field_checks = fields.iter().enumerate().map(|(index, field)| {
+                        let name_str = &field.str_name;
+                        let description_str = syn::LitStr::new(
+                            &format!("field {}", field.str_name.value()),
+                            field.str_name.span(),
+                        );
+                        let ty = field_type(field);
+                        let field_arg = quote::format_ident!("arg_{index}");
+                        quote! {
+                            let ::std::option::Option::Some((position, el)) =
+                                elements.get(#name_str)
+                            else {
+                                return ::std::result::Result::Err(
+                                    ctx.expected(#description_str));
+                            };
+                            order.push(*position);
+                            let #field_arg =
+                                <#ty as #gel_protocol::queryable::Queryable<#lifetime>>
+                                ::check_descriptor(ctx, el.type_pos)?;
+                        }
+                    });
+                    let field_args =
+                        (0..fields.len()).map(|index| quote::format_ident!("arg_{index}"));
+                    let count = fields.len();
+                    quote! {
+                        let #arg_ident = {
+                            let mut order = ::std::vec::Vec::with_capacity(#count);
+                            #(#field_checks)*
+                            (order, (#(#field_args,)*))
+                        };
+                    }
+                }
+            }
+        })
+        .collect::<TokenStream>();
+    let variant_args = (0..variants.len()).map(|index| quote::format_ident!("variant_{index}"));
+
+    let expanded = quote! {
+        impl #impl_generics #gel_protocol::queryable::Queryable<#lifetime>
+            for #type_name #ty_generics #where_clause {
+            type Args = (usize, usize, (#args_ty));
+
+            fn decode(
+                #decoder: &#gel_protocol::queryable::Decoder,
+                (#nfields, #tag_pos, #sub_args): &Self::Args,
+                #buf: &#lifetime [u8],
+            ) -> ::std::result::Result<Self, #gel_protocol::errors::DecodeError> {
+                let mut elements =
+                    #gel_protocol::serialization::decode::DecodeTupleLike
+                    ::new_object(#buf, *#nfields)?;
+                let #values = elements.read_n(*#nfields)?;
+                let #tag = <&#lifetime str as #gel_protocol::queryable::Queryable<#lifetime>>
+                    ::decode_optional(#decoder, &(), #values[*#tag_pos])?;
+                let #short_tag = #tag.rsplit("::").next().unwrap_or(#tag);
+                #decoders
+                ::std::result::Result::Err(
+                    #gel_protocol::errors::UnknownVariant { name: #tag }.build()
+                )
+            }
+            fn check_descriptor(
+                ctx: &#gel_protocol::queryable::DescriptorContext,
+                type_pos: #gel_protocol::descriptors::TypePos,
+            ) -> ::std::result::Result<Self::Args, #gel_protocol::queryable::DescriptorMismatch>
+            {
+                use #gel_protocol::descriptors::Descriptor::ObjectShape;
+                let desc = ctx.get(type_pos)?;
+                let shape = match desc {
+                    ObjectShape(shape) => shape,
+                    _ => {
+                        return ::std::result::Result::Err(ctx.wrong_type(desc, "object"))
+                    }
+                };
+
+                let mut elements = ::std::collections::HashMap::with_capacity(shape.elements.len());
+                use ::std::iter::Iterator;
+                for (position, element) in shape.elements.iter().enumerate() {
+                    elements.insert(element.name.as_str(), (position, element));
+                }
+                let ::std::option::Option::Some((tag_pos, tag_el)) = elements.get(#discriminator)
+                else {
+                    return ::std::result::Result::Err(ctx.expected(#discriminator_str));
+                };
+                <&str as #gel_protocol::queryable::Queryable>
+                    ::check_descriptor(ctx, tag_el.type_pos)?;
+                #checks
+                ::std::result::Result::Ok((
+                    shape.elements.len(),
+                    *tag_pos,
+                    (#(#variant_args,)*),
+                ))
+            }
+        }
+    };
+    Ok(expanded)
+}
+
+fn parse_variant(variant: &syn::Variant) -> syn::Result<Variant> {
+    let attrs = FieldAttrs::from_syn(&variant.attrs)?;
+    if attrs.json {
+        return Err(syn::Error::new_spanned(
+            variant,
+            "`json` is not supported on enum variants",
+        ));
+    }
+    let kind = match &variant.fields {
+        syn::Fields::Unit => Kind::Unit,
+        syn::Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            Kind::Newtype(unnamed.unnamed[0].ty.clone())
+        }
+        syn::Fields::Unnamed(unnamed) => {
+            return Err(syn::Error::new_spanned(
+                unnamed,
+                "only a single unnamed field is supported in enum variants",
+            ));
+        }
+        syn::Fields::Named(named) => {
+            let mut fields = Vec::with_capacity(named.named.len());
+            for field in &named.named {
+                let attrs = FieldAttrs::from_syn(&field.attrs)?;
+                if attrs.flatten || attrs.default.is_some() || attrs.skip || attrs.with.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        field,
+                        "only `json` and `rename` are supported on fields of enum variants",
+                    ));
+                }
+                let name = field.ident.clone().unwrap();
+                let str_name = if let Some(rename) = &attrs.rename {
+                    rename.clone()
+                } else {
+                    syn::LitStr::new(&name.to_string(), name.span())
+                };
+                fields.push(Field {
+                    name,
+                    str_name,
+                    ty: field.ty.clone(),
+                    attrs,
+                });
+            }
+            Kind::Named(fields)
+        }
+    };
+    Ok(Variant {
+        name: variant.ident.clone(),
+        kind,
+        rename: attrs.rename,
+    })
+}
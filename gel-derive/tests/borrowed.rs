@@ -0,0 +1,26 @@
+use gel_derive::Queryable;
+use gel_protocol::queryable::{Decoder, Queryable};
+
+#[derive(Queryable, Debug, PartialEq)]
+struct ScalarType<'a> {
+    name: &'a str,
+    extending: Option<&'a str>,
+    kind: String,
+}
+
+#[test]
+fn decode_borrowed() {
+    let data = b"\0\0\0\x03\0\0\0\x19\0\0\0\x0fcal::local_date\
+               \0\0\0\x19\xff\xff\xff\xff\
+               \0\0\0\x19\0\0\0\x06normal";
+    let order = (vec![0, 1, 2], ((), (), ()));
+    let res = ScalarType::decode(&Decoder::default(), &order, data);
+    assert_eq!(
+        res.unwrap(),
+        ScalarType {
+            name: "cal::local_date",
+            extending: None,
+            kind: "normal".into(),
+        }
+    );
+}
@@ -0,0 +1,102 @@
+//! Builders for the type descriptors and data used in the tests.
+#![allow(dead_code)]
+
+use bytes::{BufMut, Bytes, BytesMut};
+use gel_protocol::common::RawTypedesc;
+use gel_protocol::features::ProtocolVersion;
+use gel_protocol::model::Uuid;
+
+pub fn string(buf: &mut BytesMut, value: &str) {
+    buf.put_u32(value.len() as u32);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+pub fn descriptor(buf: &mut BytesMut, tag: u8, id: u128, body: impl FnOnce(&mut BytesMut)) {
+    let mut data = BytesMut::new();
+    data.put_u8(tag);
+    data.put_u128(id);
+    body(&mut data);
+    buf.put_u32(data.len() as u32);
+    buf.extend_from_slice(&data);
+}
+
+fn scalar(buf: &mut BytesMut, id: u128, name: &str) {
+    descriptor(buf, 3, id, |buf| {
+        string(buf, name);
+        buf.put_u8(0);
+        buf.put_u16(0);
+    });
+}
+
+fn typedesc(id: u128, buf: BytesMut) -> RawTypedesc {
+    RawTypedesc {
+        proto: ProtocolVersion::current(),
+        id: Uuid::from_u128(id),
+        data: buf.freeze(),
+    }
+}
+
+pub fn scalar_descriptor(id: u128, name: &str) -> RawTypedesc {
+    let mut buf = BytesMut::new();
+    scalar(&mut buf, id, name);
+    typedesc(id, buf)
+}
+
+/// Shape of the object type `type_name` with optional elements, element
+/// types being `std::str` (0) or `std::int64` (1).
+pub fn shape_descriptor(type_name: &str, elements: &[(&str, u16)]) -> RawTypedesc {
+    let mut buf = BytesMut::new();
+    scalar(&mut buf, 0x101, "std::str");
+    scalar(&mut buf, 0x105, "std::int64");
+    descriptor(&mut buf, 10, 0x1000, |buf| {
+        string(buf, type_name);
+        buf.put_u8(1);
+    });
+    descriptor(&mut buf, 1, 0x2000, |buf| {
+        buf.put_u8(0);
+        buf.put_u16(2);
+        buf.put_u16(elements.len() as u16);
+        for (name, type_pos) in elements {
+            buf.put_u32(0);
+            buf.put_u8(b'o');
+            string(buf, name);
+            buf.put_u16(*type_pos);
+            buf.put_u16(2);
+        }
+    });
+    typedesc(0x2000, buf)
+}
+
+/// Tuple of `std::str` (0) and `std::int64` (1) elements.
+pub fn tuple_descriptor(elements: &[u16]) -> RawTypedesc {
+    let mut buf = BytesMut::new();
+    scalar(&mut buf, 0x101, "std::str");
+    scalar(&mut buf, 0x105, "std::int64");
+    descriptor(&mut buf, 4, 0x3000, |buf| {
+        string(buf, "tuple<std::str, std::int64>");
+        buf.put_u8(0);
+        buf.put_u16(0);
+        buf.put_u16(elements.len() as u16);
+        for type_pos in elements {
+            buf.put_u16(*type_pos);
+        }
+    });
+    typedesc(0x3000, buf)
+}
+
+/// Encodes object (or tuple) data, `None` being an empty set.
+pub fn object(elements: &[Option<&[u8]>]) -> Bytes {
+    let mut buf = BytesMut::new();
+    buf.put_u32(elements.len() as u32);
+    for element in elements {
+        buf.put_u32(0);
+        match element {
+            Some(data) => {
+                buf.put_u32(data.len() as u32);
+                buf.extend_from_slice(data);
+            }
+            None => buf.put_i32(-1),
+        }
+    }
+    buf.freeze()
+}
@@ -0,0 +1,180 @@
+use gel_derive::Queryable;
+use gel_protocol::queryable::{Decoder, Queryable};
+
+mod common;
+use common::{object, shape_descriptor};
+
+#[derive(Queryable, Debug, PartialEq)]
+struct Audit {
+    created_by: String,
+    revision: i64,
+}
+
+mod comma_list {
+    use gel_protocol::descriptors::TypePos;
+    use gel_protocol::errors::DecodeError;
+    use gel_protocol::queryable::{Decoder, DescriptorContext, DescriptorMismatch, Queryable};
+
+    pub type Args = ();
+
+    pub fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Args, DescriptorMismatch> {
+        <&str as Queryable>::check_descriptor(ctx, type_pos)
+    }
+
+    pub fn decode_optional(
+        decoder: &Decoder,
+        args: &Args,
+        buf: Option<&[u8]>,
+    ) -> Result<Vec<String>, DecodeError> {
+        let value = <Option<&str> as Queryable>::decode_optional(decoder, args, buf)?;
+        Ok(value
+            .map(|v| v.split(',').map(String::from).collect())
+            .unwrap_or_default())
+    }
+}
+
+fn default_score() -> i64 {
+    100
+}
+
+#[derive(Queryable, Debug, PartialEq)]
+struct Post {
+    title: String,
+    #[gel(flatten)]
+    audit: Audit,
+    #[gel(default)]
+    views: i64,
+    #[gel(default = default_score)]
+    score: i64,
+    #[gel(skip)]
+    cached: Option<String>,
+    #[gel(with = comma_list)]
+    tags: Vec<String>,
+}
+
+#[derive(Queryable, Debug, PartialEq)]
+#[gel(match_by_name)]
+struct Summary {
+    title: String,
+    #[gel(default)]
+    score: Option<i64>,
+}
+
+#[test]
+fn flatten_default_skip_with() {
+    let typedesc = shape_descriptor(
+        "default::Post",
+        &[
+            ("tags", 0),
+            ("revision", 1),
+            ("title", 0),
+            ("created_by", 0),
+            ("views", 1),
+        ],
+    )
+    .decode()
+    .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let args = Post::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    assert_eq!(args.0, 5);
+    assert_eq!(args.1 .0, vec![2, 0]);
+
+    let data = object(&[
+        Some(b"rust,gel"),
+        Some(&3i64.to_be_bytes()),
+        Some(b"Hello"),
+        Some(b"alice"),
+        Some(&42i64.to_be_bytes()),
+    ]);
+    assert_eq!(
+        Post::decode(&Decoder::default(), &args, &data).unwrap(),
+        Post {
+            title: "Hello".into(),
+            audit: Audit {
+                created_by: "alice".into(),
+                revision: 3,
+            },
+            views: 42,
+            score: 100,
+            cached: None,
+            tags: vec!["rust".into(), "gel".into()],
+        }
+    );
+}
+
+#[test]
+fn missing_and_extra_elements() {
+    let typedesc = shape_descriptor(
+        "default::Post",
+        &[
+            ("title", 0),
+            ("created_by", 0),
+            ("revision", 1),
+            ("tags", 0),
+        ],
+    )
+    .decode()
+    .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let args = Post::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    let data = object(&[
+        Some(b"Hello"),
+        Some(b"bob"),
+        Some(&1i64.to_be_bytes()),
+        None,
+    ]);
+    let post = Post::decode(&Decoder::default(), &args, &data).unwrap();
+    assert_eq!(post.views, 0);
+    assert_eq!(post.tags, Vec::<String>::new());
+    // element count must match the descriptor even if trailing fields
+    // could be defaulted
+    let data = object(&[Some(b"Hello"), Some(b"bob"), Some(&1i64.to_be_bytes())]);
+    let err = Post::decode(&Decoder::default(), &args, &data).unwrap_err();
+    assert_eq!(err.to_string(), "object data size does not match its shape");
+
+    let typedesc = shape_descriptor(
+        "default::Post",
+        &[
+            ("title", 0),
+            ("created_by", 0),
+            ("revision", 1),
+            ("tags", 0),
+            ("body", 0),
+        ],
+    )
+    .decode()
+    .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let err = Post::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "expected 4 fields, got 5");
+
+    let typedesc = shape_descriptor("default::Post", &[("title", 0), ("tags", 0)])
+        .decode()
+        .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let err = Post::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "expected field created_by");
+}
+
+#[test]
+fn match_by_name() {
+    let typedesc = shape_descriptor("default::Post", &[("body", 0), ("score", 1), ("title", 0)])
+        .decode()
+        .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let args = Summary::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    let data = object(&[Some(b"..."), Some(&7i64.to_be_bytes()), Some(b"Hello")]);
+    assert_eq!(
+        Summary::decode(&Decoder::default(), &args, &data).unwrap(),
+        Summary {
+            title: "Hello".into(),
+            score: Some(7),
+        }
+    );
+    // unmatched elements are still counted
+    let data = object(&[Some(&7i64.to_be_bytes()), Some(b"Hello")]);
+    assert!(Summary::decode(&Decoder::default(), &args, &data).is_err());
+}
@@ -0,0 +1,95 @@
+use bytes::BytesMut;
+use gel_derive::{QueryArg, Queryable};
+use gel_protocol::model::Uuid;
+use gel_protocol::query_arg::{Encoder, QueryArg};
+use gel_protocol::queryable::{Decoder, Queryable};
+use gel_protocol::value::Value;
+
+mod common;
+use common::{object, scalar_descriptor, tuple_descriptor};
+
+#[derive(Queryable, QueryArg, Debug, PartialEq)]
+#[gel(transparent)]
+struct UserId(Uuid);
+
+#[derive(Queryable, Debug, PartialEq)]
+#[gel(transparent)]
+struct Name {
+    value: String,
+}
+
+#[derive(Queryable, QueryArg, Debug, PartialEq)]
+struct Version(String, i64);
+
+#[test]
+fn transparent() {
+    let id = Uuid::from_u128(0x42);
+    let typedesc = scalar_descriptor(0x100, "std::uuid").decode().unwrap();
+    let ctx = typedesc.as_queryable_context();
+    <UserId as Queryable>::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    let user_id = UserId::decode(&Decoder::default(), &(), id.as_bytes()).unwrap();
+    assert_eq!(user_id, UserId(id));
+
+    let ctx = typedesc.as_query_arg_context();
+    let mut buf = BytesMut::new();
+    let mut enc = Encoder::new(&ctx, &mut buf);
+    user_id.encode_slot(&mut enc).unwrap();
+    Some(UserId(id)).encode_slot(&mut enc).unwrap();
+    let mut expected = Vec::new();
+    for _ in 0..2 {
+        expected.extend_from_slice(&16u32.to_be_bytes());
+        expected.extend_from_slice(id.as_bytes());
+    }
+    assert_eq!(&buf[..], &expected[..]);
+    assert_eq!(user_id.to_value().unwrap(), Value::Uuid(id));
+
+    let typedesc = scalar_descriptor(0x101, "std::str").decode().unwrap();
+    let ctx = typedesc.as_queryable_context();
+    assert!(<UserId as Queryable>::check_descriptor(&ctx, typedesc.root_pos().unwrap()).is_err());
+    <Name as Queryable>::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    assert_eq!(
+        Name::decode(&Decoder::default(), &(), b"alice").unwrap(),
+        Name {
+            value: "alice".into()
+        }
+    );
+}
+
+#[test]
+fn tuple() {
+    let typedesc = tuple_descriptor(&[0, 1]).decode().unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let args =
+        <Version as Queryable>::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    let data = object(&[Some(b"beta"), Some(&3i64.to_be_bytes())]);
+    let version = Version::decode(&Decoder::default(), &args, &data).unwrap();
+    assert_eq!(version, Version("beta".into(), 3));
+
+    let ctx = typedesc.as_query_arg_context();
+    QueryArg::check_descriptor(&version, &ctx, typedesc.root_pos().unwrap()).unwrap();
+    let mut buf = BytesMut::new();
+    let mut enc = Encoder::new(&ctx, &mut buf);
+    version.encode_slot(&mut enc).unwrap();
+    assert_eq!(&buf[..4], &(data.len() as u32).to_be_bytes());
+    assert_eq!(&buf[4..], &data[..]);
+    assert_eq!(
+        version.to_value().unwrap(),
+        Value::Tuple(vec![Value::Str("beta".into()), Value::Int64(3)])
+    );
+}
+
+#[test]
+fn tuple_mismatch() {
+    let typedesc = tuple_descriptor(&[0]).decode().unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let err =
+        <Version as Queryable>::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "expected 2 fields, got 1");
+
+    let typedesc = tuple_descriptor(&[1, 0]).decode().unwrap();
+    let ctx = typedesc.as_queryable_context();
+    assert!(<Version as Queryable>::check_descriptor(&ctx, typedesc.root_pos().unwrap()).is_err());
+    let ctx = typedesc.as_query_arg_context();
+    let version = Version("beta".into(), 3);
+    assert!(QueryArg::check_descriptor(&version, &ctx, typedesc.root_pos().unwrap()).is_err());
+}
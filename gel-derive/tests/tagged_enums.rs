@@ -0,0 +1,142 @@
+use gel_derive::Queryable;
+use gel_protocol::queryable::{Decoder, Queryable};
+
+mod common;
+use common::{object, shape_descriptor};
+
+#[derive(Queryable, Debug, PartialEq)]
+enum Content {
+    Movie {
+        title: String,
+        runtime: Option<i64>,
+    },
+    Show {
+        title: String,
+        #[gel(rename = "seasons")]
+        season_count: Option<i64>,
+    },
+    #[gel(rename = "default::Trailer")]
+    Trailer,
+}
+
+#[derive(Queryable, Debug, PartialEq)]
+struct Click {
+    kind: String,
+    x: i64,
+    code: Option<i64>,
+}
+
+#[derive(Queryable, Debug, PartialEq)]
+#[gel(discriminator = "kind")]
+enum Event {
+    #[gel(rename = "click")]
+    Click(Click),
+    #[gel(rename = "key")]
+    Key { code: i64 },
+}
+
+fn content_args() -> <Content as Queryable<'static>>::Args {
+    (4, 0, ((vec![1, 2], ((), ())), (vec![1, 3], ((), ())), ()))
+}
+
+#[test]
+fn decode_by_type_name() {
+    let dec = Decoder::default();
+    let args = content_args();
+    let data = object(&[
+        Some(b"default::Movie"),
+        Some(b"Alien"),
+        Some(&117i64.to_be_bytes()),
+        None,
+    ]);
+    assert_eq!(
+        Content::decode(&dec, &args, &data).unwrap(),
+        Content::Movie {
+            title: "Alien".into(),
+            runtime: Some(117),
+        }
+    );
+    let data = object(&[
+        Some(b"default::Show"),
+        Some(b"Firefly"),
+        None,
+        Some(&1i64.to_be_bytes()),
+    ]);
+    assert_eq!(
+        Content::decode(&dec, &args, &data).unwrap(),
+        Content::Show {
+            title: "Firefly".into(),
+            season_count: Some(1),
+        }
+    );
+    let data = object(&[Some(b"default::Trailer"), Some(b"Teaser"), None, None]);
+    assert_eq!(
+        Content::decode(&dec, &args, &data).unwrap(),
+        Content::Trailer
+    );
+    // renamed variants only match the exact name
+    let data = object(&[Some(b"Trailer"), Some(b"Teaser"), None, None]);
+    let err = Content::decode(&dec, &args, &data).unwrap_err();
+    assert_eq!(err.to_string(), "no enum variant matches type \"Trailer\"");
+}
+
+#[test]
+fn decode_by_discriminator() {
+    let dec = Decoder::default();
+    let args = (3, 1, ((vec![1, 0, 2], ((), (), ())), (vec![2], ((),))));
+    let data = object(&[Some(&10i64.to_be_bytes()), Some(b"click"), None]);
+    assert_eq!(
+        Event::decode(&dec, &args, &data).unwrap(),
+        Event::Click(Click {
+            kind: "click".into(),
+            x: 10,
+            code: None,
+        })
+    );
+    let data = object(&[None, Some(b"key"), Some(&13i64.to_be_bytes())]);
+    assert_eq!(
+        Event::decode(&dec, &args, &data).unwrap(),
+        Event::Key { code: 13 }
+    );
+}
+
+#[test]
+fn check_descriptor() {
+    let typedesc = shape_descriptor(
+        "default::Content",
+        &[
+            ("title", 0),
+            ("seasons", 1),
+            ("__tname__", 0),
+            ("runtime", 1),
+        ],
+    )
+    .decode()
+    .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let args = Content::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap();
+    assert_eq!(args.0, 4);
+    assert_eq!(args.1, 2);
+    assert_eq!((args.2).0 .0, vec![0, 3]);
+    assert_eq!((args.2).1 .0, vec![0, 1]);
+
+    let typedesc = shape_descriptor(
+        "default::Content",
+        &[("title", 0), ("seasons", 1), ("runtime", 1)],
+    )
+    .decode()
+    .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let err = Content::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "expected field __tname__");
+
+    let typedesc = shape_descriptor(
+        "default::Content",
+        &[("title", 0), ("__tname__", 0), ("runtime", 1)],
+    )
+    .decode()
+    .unwrap();
+    let ctx = typedesc.as_queryable_context();
+    let err = Content::check_descriptor(&ctx, typedesc.root_pos().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "expected field seasons");
+}
@@ -0,0 +1,76 @@
+use crate::fields::RequestInfo;
+use crate::kinds::{
+    BinaryProtocolError, ClientConnectionError, ClientConnectionFailedError, ClientEncodingError,
+    ClientError, ClientInconsistentError, InterfaceError, ProtocolEncodingError,
+    ProtocolOutOfOrderError, ProtocolTlsError, UserError,
+};
+use crate::kinds::{SHOULD_RECONNECT, SHOULD_RETRY};
+use crate::Error;
+
+/// Verdict on whether the failed operation can be retried.
+///
+/// Returned by [`Error::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Classification {
+    /// The error is likely to go away if the operation is repeated, e.g. a
+    /// transaction conflict or a dropped connection.
+    pub transient: bool,
+    /// The operation might have side effects and might have been applied by
+    /// the server (or nothing is known about it), so repeating it is only
+    /// safe if it's idempotent. Never set together with
+    /// `definitely_not_applied`.
+    pub idempotency_required: bool,
+    /// The server has definitely not applied the operation.
+    pub definitely_not_applied: bool,
+    /// The connection the error happened on can't be used anymore.
+    pub connection_broken: bool,
+}
+
+impl Classification {
+    /// Returns `true` if the operation can be repeated as is.
+    pub fn is_retryable(&self) -> bool {
+        self.transient && !self.idempotency_required
+    }
+}
+
+impl Error {
+    /// Classifies the error for retrying.
+    ///
+    /// The verdict is computed from the kind and tags of the error, and from
+    /// the state of the request set by the client in the
+    /// [`RequestInfo`](crate::fields::RequestInfo) field. A request can be
+    /// repeated if the error is tagged with [`SHOULD_RETRY`], and the
+    /// request either was definitely not applied or has no side effects.
+    /// Without the request info, only errors reported by the server or
+    /// raised before anything is sent are considered safe to repeat.
+    pub fn classify(&self) -> Classification {
+        let request = self.get::<RequestInfo>();
+        let client_protocol_error = self.is::<ProtocolTlsError>()
+            || self.is::<ProtocolOutOfOrderError>()
+            || self.is::<ProtocolEncodingError>();
+        let from_server =
+            !self.is::<ClientError>() && !self.is::<UserError>() && !client_protocol_error;
+        let not_sent = match request {
+            Some(request) => !request.sent,
+            // raised before anything is sent
+            None => {
+                self.is::<ClientConnectionFailedError>()
+                    || self.is::<InterfaceError>()
+                    || self.is::<ClientEncodingError>()
+            }
+        };
+        let side_effect_free = request.map(|r| !r.sent || r.read_only).unwrap_or(false);
+        let definitely_not_applied = from_server || not_sent;
+        Classification {
+            transient: self.has_tag(SHOULD_RETRY),
+            idempotency_required: !(side_effect_free || definitely_not_applied),
+            definitely_not_applied,
+            connection_broken: self.has_tag(SHOULD_RECONNECT)
+                || self.is::<ClientConnectionError>()
+                || self.is::<ClientInconsistentError>()
+                || self.is::<BinaryProtocolError>()
+                || client_protocol_error,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+/*!
+Error kinds defined by applications.
+
+Applications can define their own error kinds with
+[`define_error_kinds!`](crate::define_error_kinds). Such kinds work like the
+built-in ones: [`Error::is`] checks them (and [`UserError`](crate::UserError)
+as their parent) and [`Error::has_tag`] reports their tags, so an error kind
+tagged with [`SHOULD_RETRY`](crate::SHOULD_RETRY) makes a transaction
+retry.
+
+```rust
+use gel_errors::{define_error_kinds, ErrorKind, Tag, UserError, SHOULD_RETRY};
+
+pub static PAYMENT: Tag = Tag::custom(0);
+
+define_error_kinds! {
+    /// Payment provider is temporarily unavailable.
+    pub struct PaymentUnavailableError = 0xFE_01_00_00, tags(SHOULD_RETRY, PAYMENT);
+    /// Card was declined.
+    pub struct CardDeclinedError = 0xFE_01_01_00, tags(PAYMENT);
+}
+
+let err = PaymentUnavailableError::with_message("gateway timeout");
+assert!(err.is::<PaymentUnavailableError>());
+assert!(err.is::<UserError>());
+assert!(err.has_tag(SHOULD_RETRY));
+assert!(err.has_tag(PAYMENT));
+assert_eq!(err.kind_name(), "PaymentUnavailableError");
+
+let err = CardDeclinedError::build();
+assert!(err.is::<PaymentUnavailableError>());
+assert!(!err.has_tag(SHOULD_RETRY));
+```
+
+Codes of application error kinds must lie in the range of
+[`UserError`](crate::UserError), i.e. start with `0xFE`. As with built-in
+kinds, a kind is a subclass of every kind whose code matches the leading
+non-zero bytes of its code. Tags are not inherited, so they should be listed
+for every kind.
+
+Errors created by the kinds always carry their name and tags. Errors
+constructed from a code alone, with [`Error::from_code`], only know the
+application kinds that were registered with [`register_kind`]:
+
+```rust
+# use gel_errors::{define_error_kinds, Error, SHOULD_RETRY};
+# define_error_kinds! {
+#     pub struct PaymentUnavailableError = 0xFE_01_00_00, tags(SHOULD_RETRY);
+# }
+gel_errors::register_kind::<PaymentUnavailableError>();
+let err = Error::from_code(0xFE_01_00_00);
+assert_eq!(err.kind_name(), "PaymentUnavailableError");
+assert!(err.has_tag(SHOULD_RETRY));
+```
+*/
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use crate::traits::ErrorKind;
+
+#[cfg(doc)]
+use crate::Error;
+
+static KINDS: RwLock<BTreeMap<u32, (&'static str, u32)>> = RwLock::new(BTreeMap::new());
+
+/// Defines error kinds of the application.
+///
+/// Every kind is declared as `struct Name = code` optionally followed by
+/// `tags(...)`. See the [module documentation](crate::custom) for details.
+#[macro_export]
+macro_rules! define_error_kinds {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident = $code:expr $(, tags($($tag:expr),* $(,)?))?;
+    )*) => {
+        $(
+            $(#[$meta])*
+            $vis struct $name;
+
+            impl $crate::__private::Sealed for $name {
+                const CODE: u32 = {
+                    let code: u32 = $code;
+                    assert!(
+                        code & 0xFF00_0000 == 0xFE00_0000 && code != 0xFE00_0000,
+                        "application error codes must start with 0xFE",
+                    );
+                    code
+                };
+                const NAME: &'static str = stringify!($name);
+                const TAGS: u32 = 0 $($(| $tag.mask())*)?;
+            }
+
+            impl $crate::ErrorKind for $name {}
+        )*
+    };
+}
+
+/// Registers an application error kind, so that errors received with its
+/// code are recognized by [`Error::from_code`].
+pub fn register_kind<K: ErrorKind>() {
+    KINDS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(K::CODE, (K::NAME, K::TAGS));
+}
+
+pub(crate) fn registered(code: u32) -> Option<(&'static str, u32)> {
+    KINDS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&code)
+        .copied()
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 
+use crate::fields::QueryText;
 use crate::{Error, InternalServerError};
 
 pub struct DisplayError<'a>(&'a Error, bool);
@@ -75,3 +76,299 @@ impl fmt::Display for VerboseError<'_> {
         Ok(())
     }
 }
+
+/// Annotated snippet of the query an error refers to.
+///
+/// See [`display_diagnostic`].
+pub struct DisplayDiagnostic<'a> {
+    error: &'a Error,
+    color: bool,
+}
+
+/// Structured JSON representation of an error.
+///
+/// See [`display_json`].
+pub struct JsonError<'a>(&'a Error);
+
+/// Renders an error together with the annotated part of the query text.
+///
+/// The query text is taken from the [`QueryText`] field, which is set by
+/// the client for errors returned by queries. With `color` set, the output
+/// is highlighted with ANSI escape codes.
+///
+/// ```rust
+/// # use gel_errors::{ErrorKind, InvalidReferenceError};
+/// # use gel_errors::display::display_diagnostic;
+/// # use gel_errors::fields::QueryText;
+/// # use std::collections::HashMap;
+/// let err = InvalidReferenceError::with_message("object type 'default::Usr' does not exist")
+///     .with_headers(HashMap::from([
+///         (0x0001, "did you mean 'User'?".into()),
+///         (0xFFF1, "7".into()),
+///         (0xFFF2, "10".into()),
+///     ]))
+///     .set::<QueryText>("select Usr { name };");
+/// assert_eq!(
+///     display_diagnostic(&err, false).to_string(),
+///     "\
+/// error: InvalidReferenceError: object type 'default::Usr' does not exist
+///  --> query:1:8
+///   |
+/// 1 | select Usr { name };
+///   |        ^^^ did you mean 'User'?
+/// ",
+/// );
+/// ```
+pub fn display_diagnostic(e: &Error, color: bool) -> DisplayDiagnostic {
+    DisplayDiagnostic { error: e, color }
+}
+
+/// Formats an error as a single-line JSON object for log pipelines.
+///
+/// The object contains `kind`, `code` and `message` of the error, and
+/// `hint`, `details`, `span` and `query` when they are known. The span
+/// contains character offsets of the error in the query and 1-based `line`
+/// and `column` of its start.
+///
+/// ```rust
+/// # use gel_errors::{ErrorKind, InvalidReferenceError};
+/// # use gel_errors::display::display_json;
+/// # use gel_errors::fields::QueryText;
+/// # use std::collections::HashMap;
+/// let err = InvalidReferenceError::with_message("object type 'default::Usr' does not exist")
+///     .with_headers(HashMap::from([(0xFFF1, "7".into()), (0xFFF2, "10".into())]))
+///     .set::<QueryText>("select Usr;");
+/// assert_eq!(
+///     display_json(&err).to_string(),
+///     r#"{"kind":"InvalidReferenceError","code":67305472,"#.to_owned()
+///         + r#""message":"object type 'default::Usr' does not exist","#
+///         + r#""span":{"start":7,"end":10,"line":1,"column":8},"#
+///         + r#""query":"select Usr;"}"#,
+/// );
+/// ```
+pub fn display_json(e: &Error) -> JsonError {
+    JsonError(e)
+}
+
+/// Message of the error with contexts and sources, without the kind.
+fn message(e: &Error) -> String {
+    let mut parts =
+        e.0.messages
+            .iter()
+            .rev()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>();
+    parts.extend(e.chain().skip(1).map(|src| src.to_string()));
+    parts.join(": ")
+}
+
+/// Location of the error in the query text.
+struct Span {
+    /// Character offsets
+    start: usize,
+    end: usize,
+    /// Byte offsets
+    byte_start: usize,
+    byte_end: usize,
+    /// 1-based line and column of the start
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    fn find(e: &Error, text: &str) -> Option<Span> {
+        let start = e.position_start()?;
+        let end = e.position_end().unwrap_or(start).max(start);
+        let byte_offset = |pos: usize| {
+            text.char_indices()
+                .nth(pos)
+                .map(|(idx, _)| idx)
+                .unwrap_or(text.len())
+        };
+        let byte_start = byte_offset(start);
+        let byte_end = byte_offset(end);
+        let before = &text[..byte_start];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        Some(Span {
+            start,
+            end,
+            byte_start,
+            byte_end,
+            line: before.matches('\n').count() + 1,
+            column: text[line_start..byte_start].chars().count() + 1,
+        })
+    }
+}
+
+#[cfg(feature = "miette")]
+pub(crate) fn byte_span(e: &Error, text: &str) -> Option<(usize, usize)> {
+    Span::find(e, text).map(|s| (s.byte_start, s.byte_end))
+}
+
+struct Style {
+    error: &'static str,
+    gutter: &'static str,
+    marker: &'static str,
+    note: &'static str,
+    reset: &'static str,
+}
+
+const PLAIN: Style = Style {
+    error: "",
+    gutter: "",
+    marker: "",
+    note: "",
+    reset: "",
+};
+
+const ANSI: Style = Style {
+    error: "\x1b[1;31m",
+    gutter: "\x1b[1;34m",
+    marker: "\x1b[1;31m",
+    note: "\x1b[1;36m",
+    reset: "\x1b[0m",
+};
+
+/// Width of the text in columns, with tabs expanded to four spaces.
+fn width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+impl fmt::Display for DisplayDiagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let e = self.error;
+        let s = if self.color { &ANSI } else { &PLAIN };
+        writeln!(
+            f,
+            "{}error{}: {}: {}",
+            s.error,
+            s.reset,
+            e.kind_name(),
+            message(e)
+        )?;
+        let text = e.get::<QueryText>();
+        let span = text.and_then(|text| Span::find(e, text).map(|span| (text, span)));
+        let Some((text, span)) = span else {
+            if let Some((line, column)) = e.line().zip(e.column()) {
+                writeln!(f, "{}-->{} query:{line}:{column}", s.gutter, s.reset)?;
+            }
+            if let Some(hint) = e.hint() {
+                writeln!(f, "{}= hint:{} {hint}", s.note, s.reset)?;
+            }
+            if let Some(details) = e.details() {
+                writeln!(f, "{}= details:{} {details}", s.note, s.reset)?;
+            }
+            return Ok(());
+        };
+
+        let first_line = span.line;
+        let lines = text[span.byte_start..span.byte_end].matches('\n').count();
+        let last_line = first_line + lines;
+        let pad = last_line.to_string().len();
+        writeln!(
+            f,
+            "{:pad$}{}-->{} query:{}:{}",
+            "", s.gutter, s.reset, span.line, span.column
+        )?;
+        writeln!(f, "{:pad$} {}|{}", "", s.gutter, s.reset)?;
+        let mut offset = text
+            .split('\n')
+            .take(first_line - 1)
+            .map(|line| line.len() + 1)
+            .sum::<usize>();
+        for (idx, line) in text
+            .split('\n')
+            .enumerate()
+            .skip(first_line - 1)
+            .take(lines + 1)
+        {
+            let raw_len = line.len();
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let line_end = offset + line.len();
+            let mark_start = span.byte_start.clamp(offset, line_end) - offset;
+            let mark_end = span.byte_end.clamp(offset, line_end) - offset;
+            let indent = width(&line[..mark_start]);
+            let marks = width(&line[mark_start..mark_end]).max(1);
+            writeln!(
+                f,
+                "{}{:>pad$} |{} {}",
+                s.gutter,
+                idx + 1,
+                s.reset,
+                line.replace('\t', "    ")
+            )?;
+            write!(
+                f,
+                "{:pad$} {}|{} {:indent$}{}{}{}",
+                "",
+                s.gutter,
+                s.reset,
+                "",
+                s.marker,
+                "^".repeat(marks),
+                s.reset
+            )?;
+            if idx + 1 == last_line {
+                if let Some(hint) = e.hint() {
+                    write!(f, " {}{hint}{}", s.marker, s.reset)?;
+                }
+            }
+            writeln!(f)?;
+            offset += raw_len + 1;
+        }
+        if let Some(details) = e.details() {
+            writeln!(f, "{:pad$} {}|{}", "", s.gutter, s.reset)?;
+            writeln!(f, "{:pad$} {}= details:{} {details}", "", s.note, s.reset)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_json_str(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for JsonError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let e = self.0;
+        f.write_str("{\"kind\":")?;
+        write_json_str(f, e.kind_name())?;
+        write!(f, ",\"code\":{},\"message\":", e.code())?;
+        write_json_str(f, &message(e))?;
+        if let Some(hint) = e.hint() {
+            f.write_str(",\"hint\":")?;
+            write_json_str(f, hint)?;
+        }
+        if let Some(details) = e.details() {
+            f.write_str(",\"details\":")?;
+            write_json_str(f, details)?;
+        }
+        let text = e.get::<QueryText>();
+        if let Some(span) = text.and_then(|text| Span::find(e, text)) {
+            write!(
+                f,
+                ",\"span\":{{\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}",
+                span.start, span.end, span.line, span.column
+            )?;
+        } else if let Some((line, column)) = e.line().zip(e.column()) {
+            write!(f, ",\"span\":{{\"line\":{line},\"column\":{column}}}")?;
+        }
+        if let Some(text) = text {
+            f.write_str(",\"query\":")?;
+            write_json_str(f, text)?;
+        }
+        f.write_str("}")
+    }
+}
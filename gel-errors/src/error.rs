@@ -5,8 +5,8 @@ use std::error::Error as StdError;
 use std::fmt;
 use std::str;
 
+use crate::kinds::kind_info;
 use crate::kinds::UserError;
-use crate::kinds::{error_name, tag_check};
 use crate::traits::{ErrorKind, Field};
 
 const FIELD_HINT: u16 = 0x_00_01;
@@ -33,6 +33,21 @@ pub struct Tag {
     pub(crate) bit: u32,
 }
 
+impl Tag {
+    /// Tag defined by the application.
+    ///
+    /// Up to 16 application tags are supported, `index` must be less than
+    /// 16. Application tags never overlap with the tags of Gel errors.
+    pub const fn custom(index: u32) -> Tag {
+        assert!(index < 16, "application tag index must be less than 16");
+        Tag { bit: 16 + index }
+    }
+    #[doc(hidden)]
+    pub const fn mask(&self) -> u32 {
+        1 << self.bit
+    }
+}
+
 pub(crate) enum Source {
     Box(Box<dyn StdError + Send + Sync + 'static>),
     Ref(Box<dyn AsRef<dyn StdError + Send + Sync + 'static> + Send + Sync + 'static>),
@@ -41,6 +56,8 @@ pub(crate) enum Source {
 #[derive(Debug)]
 pub(crate) struct Inner {
     pub code: u32,
+    pub name: &'static str,
+    pub tags: u32,
     // TODO(tailhook) possibly put message into the fields too
     pub messages: Vec<Cow<'static, str>>,
     pub error: Option<Source>,
@@ -55,7 +72,7 @@ impl Error {
         T::is_superclass_of(self.0.code)
     }
     pub fn has_tag(&self, tag: Tag) -> bool {
-        tag_check(self.0.code, tag.bit)
+        self.0.tags & tag.mask() != 0
     }
     pub fn chain(&self) -> Chain {
         Chain(Some(self))
@@ -79,10 +96,10 @@ impl Error {
         self
     }
     pub fn kind_name(&self) -> &str {
-        error_name(self.0.code)
+        self.0.name
     }
     pub fn kind_debug(&self) -> impl fmt::Display {
-        format!("{} [0x{:08X}]", error_name(self.0.code), self.0.code)
+        format!("{} [0x{:08X}]", self.0.name, self.0.code)
     }
     pub fn initial_message(&self) -> Option<&str> {
         self.0.messages.first().map(|m| &m[..])
@@ -135,8 +152,11 @@ impl Error {
         })
     }
     pub fn from_code(code: u32) -> Error {
+        let (name, tags) = kind_info(code);
         Error(Box::new(Inner {
             code,
+            name,
+            tags,
             messages: Vec::new(),
             error: None,
             headers: HashMap::new(),
@@ -149,6 +169,8 @@ impl Error {
     }
     pub fn refine_kind<T: ErrorKind>(mut self) -> Error {
         self.0.code = T::CODE;
+        self.0.name = T::NAME;
+        self.0.tags = T::TAGS;
         self
     }
     pub fn set<T: Field>(mut self, value: impl Into<T::Value>) -> Error {
@@ -6,3 +6,25 @@ impl Field for QueryText {
     const NAME: &'static str = "source_code";
     type Value = String;
 }
+
+/// State of the request that failed, set by the client.
+///
+/// Used by [`Error::classify`](crate::Error::classify) to decide whether the
+/// request might have been applied.
+pub struct RequestInfo;
+
+/// Value of the [`RequestInfo`] field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Request {
+    /// Request that could have side effects was (at least partially)
+    /// written to the connection.
+    pub sent: bool,
+    /// Query is known to have no side effects (it was parsed and has no
+    /// capabilities).
+    pub read_only: bool,
+}
+
+impl Field for RequestInfo {
+    const NAME: &'static str = "request";
+    type Value = Request;
+}
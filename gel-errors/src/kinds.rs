@@ -18,23 +18,13 @@ macro_rules! define_errors {
 
             impl ErrorKind for $id {}
         )*
-        pub(crate) fn tag_check(code: u32, bit: u32) -> bool {
-            return get_tags(code) & (1 << bit) != 0;
-        }
-        pub(crate) fn get_tags(code: u32) -> u32 {
-            match code {
-                $(
-                    $code => $tags,
-                )*
-                _ => 0,
-            }
-        }
-        pub(crate) fn error_name(code: u32) -> &'static str {
+        /// Returns name and tags of the error kind with the code.
+        pub(crate) fn kind_info(code: u32) -> (&'static str, u32) {
             match code {
                 $(
-                    $code => stringify!($id),
+                    $code => (stringify!($id), $tags),
                 )*
-                _ => "GelError",
+                _ => crate::custom::registered(code).unwrap_or(("GelError", 0)),
             }
         }
     }
@@ -53,6 +53,10 @@ assert!(err2.is::<ExecutionError>());
 assert!(!err2.is::<ClientError>());
 ```
 
+Whether an operation that failed can be retried depends on more than tags,
+e.g. on whether the query has side effects and if it could have been
+applied. [`Error::classify`] combines all of that into a [`Classification`].
+
 [`anyhow::Error`]: https://docs.rs/anyhow/latest/anyhow/struct.Error.html
 
 # Errors in Transactions
@@ -71,20 +75,34 @@ Special care for errors must be taken in transactions. Generally:
    `.source()` chain but must not be swallowed, otherwise retrying
    transaction may work incorrectly.
 
+Applications can also define their own error kinds in the [`UserError`]
+range, optionally tagged with [`SHOULD_RETRY`], see the [`custom`] module.
+
 # Nice Error Reporting
 
 Refer to documentation in the [gel-tokio](https://docs.rs/gel-tokio) crate.
 */
+mod classify;
 mod error;
 mod traits;
 
+pub mod custom;
 pub mod display;
 pub mod fields;
 pub mod kinds;
 
 #[cfg(feature = "miette")]
 pub mod miette;
+#[cfg(feature = "serde")]
+pub mod serde;
 
+pub use classify::Classification;
+pub use custom::register_kind;
 pub use error::{Error, Tag};
 pub use kinds::*;
 pub use traits::{ErrorKind, Field, ResultExt};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::traits::Sealed;
+}
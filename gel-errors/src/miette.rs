@@ -16,8 +16,12 @@ impl miette::Diagnostic for Error {
         self.get::<QueryText>().map(|s| s as _)
     }
     fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
-        let (start, end) = self.position_start().zip(self.position_end())?;
-        let len = end - start;
+        let (start, end) = match self.get::<QueryText>() {
+            // positions are sent in characters, while miette uses bytes
+            Some(text) => crate::display::byte_span(self, text)?,
+            None => self.position_start().zip(self.position_end())?,
+        };
+        let len = end.saturating_sub(start);
         Some(Box::new(
             Some(LabeledSpan::new(self.hint().map(Into::into), start, len)).into_iter(),
         ))
@@ -0,0 +1,177 @@
+//! Serde support for Gel errors. Add "serde" feature flag to enable.
+//!
+//! Errors are serialized as a map with the error code, kind name (only
+//! informational, the kind is restored from the code), messages, text of
+//! the source chain, headers, annotations and the query text. Headers that
+//! are valid UTF-8 are written as strings in human-readable formats and as
+//! bytes otherwise. Other typed fields and the source error itself can't be
+//! serialized, the source is restored as an opaque error with the same
+//! text.
+//!
+//! ```rust
+//! use gel_errors::{Error, ErrorKind, InvalidReferenceError};
+//!
+//! let err = InvalidReferenceError::with_message("object type 'User' does not exist")
+//!     .with_headers([(0x0001, "did you mean 'default::Users'?".into())].into());
+//! let json = serde_json::to_string(&err).unwrap();
+//! let decoded: Error = serde_json::from_str(&json).unwrap();
+//! assert!(decoded.is::<InvalidReferenceError>());
+//! assert_eq!(decoded.hint(), Some("did you mean 'default::Users'?"));
+//! assert_eq!(decoded.to_string(), err.to_string());
+//! ```
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use bytes::Bytes;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Inner, Source};
+use crate::fields::QueryText;
+use crate::kinds::kind_info;
+use crate::Error;
+
+#[derive(Serialize)]
+struct SerError<'a> {
+    code: u32,
+    kind: &'a str,
+    messages: &'a [Cow<'static, str>],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    headers: HashMap<u16, Header<'a>>,
+    annotations: &'a HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct DeError {
+    code: u32,
+    #[serde(default)]
+    messages: Vec<String>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    headers: HashMap<u16, HeaderBuf>,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+    #[serde(default)]
+    query: Option<String>,
+}
+
+struct Header<'a>(&'a Bytes);
+
+struct HeaderBuf(Bytes);
+
+/// Source error restored from its message.
+#[derive(Debug)]
+struct RemoteSource(String);
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerError {
+            code: self.0.code,
+            kind: self.0.name,
+            messages: &self.0.messages,
+            source: self.0.error.as_ref().map(|_| {
+                self.chain()
+                    .skip(1)
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(": ")
+            }),
+            headers: self
+                .0
+                .headers
+                .iter()
+                .map(|(key, value)| (*key, Header(value)))
+                .collect(),
+            annotations: &self.0.annotations,
+            query: self.get::<QueryText>().map(|q| &q[..]),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Error, D::Error> {
+        let data = DeError::deserialize(deserializer)?;
+        let (name, tags) = kind_info(data.code);
+        let err = Error(Box::new(Inner {
+            code: data.code,
+            name,
+            tags,
+            messages: data.messages.into_iter().map(Cow::Owned).collect(),
+            error: data.source.map(|s| Source::Box(Box::new(RemoteSource(s)))),
+            headers: data
+                .headers
+                .into_iter()
+                .map(|(key, value)| (key, value.0))
+                .collect(),
+            annotations: data.annotations,
+            fields: HashMap::new(),
+        }));
+        Ok(match data.query {
+            Some(query) => err.set::<QueryText>(query),
+            None => err,
+        })
+    }
+}
+
+impl Serialize for Header<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(self.0) {
+            Ok(s) if serializer.is_human_readable() => serializer.serialize_str(s),
+            _ => serializer.serialize_bytes(self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HeaderBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<HeaderBuf, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(HeaderVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(HeaderVisitor)
+        }
+    }
+}
+
+struct HeaderVisitor;
+
+impl<'de> Visitor<'de> for HeaderVisitor {
+    type Value = HeaderBuf;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("string or bytes")
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<HeaderBuf, E> {
+        Ok(HeaderBuf(Bytes::copy_from_slice(v.as_bytes())))
+    }
+    fn visit_string<E: de::Error>(self, v: String) -> Result<HeaderBuf, E> {
+        Ok(HeaderBuf(v.into()))
+    }
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<HeaderBuf, E> {
+        Ok(HeaderBuf(Bytes::copy_from_slice(v)))
+    }
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<HeaderBuf, E> {
+        Ok(HeaderBuf(v.into()))
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<HeaderBuf, A::Error> {
+        let mut buf = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            buf.push(byte);
+        }
+        Ok(HeaderBuf(buf.into()))
+    }
+}
+
+impl fmt::Display for RemoteSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RemoteSource {}
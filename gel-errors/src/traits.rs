@@ -5,7 +5,8 @@ use crate::error::{Error, Inner, Source};
 
 /// Trait that marks Gel errors.
 ///
-/// This is currently sealed because Gel errors will be changed in future.
+/// This is sealed, application error kinds are defined using
+/// [`define_error_kinds!`](crate::define_error_kinds).
 pub trait ErrorKind: Sealed {
     fn with_message<S: Into<Cow<'static, str>>>(s: S) -> Error {
         Self::build().context(s)
@@ -13,6 +14,8 @@ pub trait ErrorKind: Sealed {
     fn with_source<E: std::error::Error + Send + Sync + 'static>(src: E) -> Error {
         Error(Box::new(Inner {
             code: Self::CODE,
+            name: Self::NAME,
+            tags: Self::TAGS,
             messages: Vec::new(),
             error: Some(Source::Box(src.into())),
             headers: HashMap::new(),
@@ -23,6 +26,8 @@ pub trait ErrorKind: Sealed {
     fn with_source_box(src: Box<dyn std::error::Error + Send + Sync>) -> Error {
         Error(Box::new(Inner {
             code: Self::CODE,
+            name: Self::NAME,
+            tags: Self::TAGS,
             messages: Vec::new(),
             error: Some(Source::Box(src)),
             headers: HashMap::new(),
@@ -37,6 +42,8 @@ pub trait ErrorKind: Sealed {
     {
         Error(Box::new(Inner {
             code: Self::CODE,
+            name: Self::NAME,
+            tags: Self::TAGS,
             messages: Vec::new(),
             error: Some(Source::Ref(Box::new(src))),
             headers: HashMap::new(),
@@ -47,6 +54,8 @@ pub trait ErrorKind: Sealed {
     fn build() -> Error {
         Error(Box::new(Inner {
             code: Self::CODE,
+            name: Self::NAME,
+            tags: Self::TAGS,
             messages: Vec::new(),
             error: None,
             headers: HashMap::new(),
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use gel_errors::display::{display_diagnostic, display_json};
+use gel_errors::fields::QueryText;
+use gel_errors::{Error, ErrorKind, InvalidReferenceError};
+
+fn error(message: &str, headers: &[(u16, &str)], query: &str) -> Error {
+    InvalidReferenceError::with_message(message.to_owned())
+        .with_headers(
+            headers
+                .iter()
+                .map(|(k, v)| (*k, (*v).to_owned().into()))
+                .collect::<HashMap<_, _>>(),
+        )
+        .set::<QueryText>(query.to_owned())
+}
+
+#[test]
+fn json_escapes_control_characters() {
+    let message = "quote \" backslash \\ bell \u{7} nul \0 del \u{7f}";
+    let query = "select 'a\r\n\tb' ++ Usr;\u{1b}[0m";
+    let err = error(
+        message,
+        &[(0x0001, "new\nline"), (0xFFF1, "18"), (0xFFF2, "21")],
+        query,
+    );
+    let text = display_json(&err).to_string();
+    assert!(!text.contains('\n'), "{}", text);
+    assert!(!text.chars().any(|c| c < ' '), "{:?}", text);
+
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(json["message"], message);
+    assert_eq!(json["hint"], "new\nline");
+    assert_eq!(json["query"], query);
+    assert_eq!(json["span"]["start"], 18);
+    assert_eq!(json["span"]["end"], 21);
+    assert_eq!(json["span"]["line"], 2);
+    assert_eq!(json["span"]["column"], 8);
+}
+
+#[test]
+fn json_non_ascii() {
+    let err = error(
+        "unknown name",
+        &[(0xFFF1, "7"), (0xFFF2, "10")],
+        "select Üsr ++ '\u{2028}✓';",
+    );
+    let json: serde_json::Value = serde_json::from_str(&display_json(&err).to_string()).unwrap();
+    assert_eq!(json["query"], "select Üsr ++ '\u{2028}✓';");
+    assert_eq!(json["span"]["column"], 8);
+}
+
+#[test]
+fn multi_line_span() {
+    let err = error(
+        "unknown name",
+        &[
+            (0x0001, "check the spelling"),
+            (0xFFF1, "13"),
+            (0xFFF2, "31"),
+        ],
+        "select {\n  x := Usr {\n    name }\n};",
+    );
+    assert_eq!(
+        display_diagnostic(&err, false).to_string(),
+        "\
+error: InvalidReferenceError: unknown name
+ --> query:2:5
+  |
+2 |   x := Usr {
+  |     ^^^^^^^^
+3 |     name }
+  | ^^^^^^^^^ check the spelling
+",
+    );
+}
+
+#[test]
+fn ansi_output() {
+    let err = error(
+        "unknown name",
+        &[
+            (0x0001, "did you mean 'User'?"),
+            (0x0002, "no such type"),
+            (0xFFF1, "7"),
+            (0xFFF2, "10"),
+        ],
+        "select Usr;",
+    );
+    assert_eq!(
+        display_diagnostic(&err, true).to_string(),
+        "\
+\x1b[1;31merror\x1b[0m: InvalidReferenceError: unknown name
+ \x1b[1;34m-->\x1b[0m query:1:8
+  \x1b[1;34m|\x1b[0m
+\x1b[1;34m1 |\x1b[0m select Usr;
+  \x1b[1;34m|\x1b[0m        \x1b[1;31m^^^\x1b[0m \x1b[1;31mdid you mean 'User'?\x1b[0m
+  \x1b[1;34m|\x1b[0m
+  \x1b[1;36m= details:\x1b[0m no such type
+",
+    );
+}
+
+#[test]
+fn no_query_text() {
+    let err = InvalidReferenceError::with_message("unknown name").with_headers(HashMap::from([
+        (0xFFF3, "3".into()),
+        (0xFFF4, "5".into()),
+        (0x0001, "a hint".into()),
+    ]));
+    assert_eq!(
+        display_diagnostic(&err, false).to_string(),
+        "error: InvalidReferenceError: unknown name\n--> query:3:5\n= hint: a hint\n",
+    );
+    assert_eq!(
+        display_json(&err).to_string(),
+        r#"{"kind":"InvalidReferenceError","code":67305472,"message":"unknown name","hint":"a hint","span":{"line":3,"column":5}}"#,
+    );
+}
@@ -325,6 +325,12 @@ impl Deref for InputObjectShape {
     }
 }
 
+impl NamedTupleShape {
+    pub fn new(elements: Vec<TupleElement>) -> NamedTupleShape {
+        NamedTupleShape(Arc::new(NamedTupleShapeInfo { elements }))
+    }
+}
+
 impl Deref for NamedTupleShape {
     type Target = NamedTupleShapeInfo;
     fn deref(&self) -> &NamedTupleShapeInfo {
@@ -279,6 +279,27 @@ pub struct StateBorrow<'a> {
 }
 
 impl Typedesc {
+    /// Creates a type descriptor from decoded descriptors, e.g. to decode
+    /// values built in memory.
+    pub fn new(
+        proto: ProtocolVersion,
+        descriptors: Vec<Descriptor>,
+        root_pos: Option<TypePos>,
+    ) -> Result<Typedesc, CodecError> {
+        let root_id = match root_pos {
+            Some(pos) => *descriptors
+                .get(pos.0 as usize)
+                .context(UnexpectedTypePos { position: pos.0 })?
+                .id(),
+            None => Uuid::from_u128(0),
+        };
+        Ok(Typedesc {
+            proto,
+            array: descriptors,
+            root_id,
+            root_pos,
+        })
+    }
     pub fn id(&self) -> &Uuid {
         &self.root_id
     }
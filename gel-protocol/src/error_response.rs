@@ -1,11 +1,58 @@
-use gel_errors::Error;
+use gel_errors::{Error, Field};
 
-use crate::server_message::ErrorResponse;
+use crate::server_message::{ErrorResponse, ErrorSeverity};
+
+/// Severity of the `ErrorResponse` the error was received in.
+///
+/// Only set if the severity is not [`ErrorSeverity::Error`], so that the
+/// error can be encoded back into an identical message.
+pub struct Severity;
+
+impl Field for Severity {
+    const NAME: &'static str = "severity";
+    type Value = ErrorSeverity;
+}
 
 impl From<ErrorResponse> for Error {
     fn from(val: ErrorResponse) -> Self {
-        Error::from_code(val.code)
+        let err = Error::from_code(val.code)
             .context(val.message)
-            .with_headers(val.attributes)
+            .with_headers(val.attributes);
+        match val.severity {
+            ErrorSeverity::Error => err,
+            severity => err.set::<Severity>(severity),
+        }
+    }
+}
+
+impl From<&Error> for ErrorResponse {
+    /// Encodes the error as a server message, e.g. to forward it to a client.
+    ///
+    /// The message is the text of the error with its contexts and source
+    /// chain (as in the alternate `Display`, without the kind name). Errors
+    /// received from the server are encoded into the original message.
+    fn from(err: &Error) -> ErrorResponse {
+        let mut parts = err
+            .contexts()
+            .rev()
+            .chain(err.initial_message())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        parts.extend(err.chain().skip(1).map(|e| e.to_string()));
+        ErrorResponse {
+            severity: err
+                .get::<Severity>()
+                .copied()
+                .unwrap_or(ErrorSeverity::Error),
+            code: err.code(),
+            message: parts.join(": "),
+            attributes: err.headers().clone(),
+        }
+    }
+}
+
+impl From<Error> for ErrorResponse {
+    fn from(err: Error) -> ErrorResponse {
+        ErrorResponse::from(&err)
     }
 }
@@ -93,6 +93,8 @@ pub enum DecodeError {
     InvalidJsonFormat { backtrace: Backtrace },
     #[snafu(display("enum value returned is not in type descriptor"))]
     ExtraEnumValue { backtrace: Backtrace },
+    #[snafu(display("no enum variant matches type {:?}", name))]
+    UnknownVariant { backtrace: Backtrace, name: String },
     #[snafu(display("too may descriptors ({})", index))]
     TooManyDescriptors { backtrace: Backtrace, index: usize },
     #[snafu(display("invalid index in input shape ({})", index))]
@@ -116,6 +118,11 @@ pub enum DecodeError {
     },
     #[snafu(display("invalid type operation value"))]
     InvalidTypeOperation { backtrace: Backtrace },
+    #[snafu(display("cannot deserialize value: {message}"))]
+    Deserialize {
+        backtrace: Backtrace,
+        message: String,
+    },
 }
 
 #[derive(Snafu, Debug)]
@@ -166,6 +173,11 @@ pub enum EncodeError {
     TupleShapeMismatch { backtrace: Backtrace },
     #[snafu(display("enum value is not in type descriptor"))]
     MissingEnumValue { backtrace: Backtrace },
+    #[snafu(display("cannot serialize value: {message}"))]
+    Serialize {
+        backtrace: Backtrace,
+        message: String,
+    },
 }
 
 impl From<crate::new_protocol::prelude::ParseError> for DecodeError {
@@ -195,6 +207,37 @@ pub enum CodecError {
     },
 }
 
+/// Error returned by path accessors of [Value], such as [Value::get].
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+#[non_exhaustive]
+pub enum ValueAccessError {
+    #[snafu(display("invalid path `{}`: {}", path, reason))]
+    InvalidPath {
+        backtrace: Backtrace,
+        path: String,
+        reason: &'static str,
+    },
+    #[snafu(display("no field at `{}`", path))]
+    NoSuchField { backtrace: Backtrace, path: String },
+    #[snafu(display("index {} is out of range at `{}` (length {})", index, path, len))]
+    IndexOutOfRange {
+        backtrace: Backtrace,
+        path: String,
+        index: usize,
+        len: usize,
+    },
+    #[snafu(display("expected {} at `{}`, found {}", expected, path, found))]
+    WrongValueType {
+        backtrace: Backtrace,
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[snafu(display("missing value at `{}`", path))]
+    MissingValue { backtrace: Backtrace, path: String },
+}
+
 pub fn invalid_value(codec: &'static str, value: &Value) -> EncodeError {
     InvalidValue {
         codec,
@@ -0,0 +1,655 @@
+/*!
+Encoding of query arguments from untyped JSON.
+
+[QueryArgs] is implemented for [serde_json::Value], so a JSON object
+received from elsewhere (e.g. a body of an HTTP request) can be passed as
+arguments to a query directly. Every value is converted to the type that the
+server expects for the respective argument, using the input descriptor of the
+query:
+
+| Argument type | Accepted JSON |
+|---|---|
+| `int16`, `int32`, `int64` | integer numbers, range-checked |
+| `float32`, `float64` | numbers, `"NaN"`, `"Infinity"`, `"-Infinity"` |
+| `bigint`, `decimal` | numbers, strings in decimal notation |
+| `bool` | booleans |
+| `str`, enums | strings |
+| `uuid` | strings |
+| `bytes` and PostGIS types | base64 strings |
+| `json` | any value |
+| `datetime` | RFC 3339 strings, e.g. `2024-02-29T12:30:00+02:00` |
+| `cal::local_datetime` | `2024-02-29T12:30:00` |
+| `cal::local_date`, `cal::local_time` | `2024-02-29`, `12:30:00.5` |
+| `duration` | strings accepted by [Duration](crate::model::Duration) |
+| `cal::relative_duration`, `cal::date_duration` | ISO 8601 durations, e.g. `P1Y2M3DT4H` |
+| `cfg::memory` | integer number of bytes |
+| `ext::pgvector::vector` | arrays of numbers |
+| arrays, tuples | arrays |
+| named tuples | objects or arrays |
+| ranges | objects `{"lower", "upper", "inc_lower", "inc_upper", "empty"}` |
+
+Named arguments are taken from a JSON object and positional ones from either
+an array or an object with keys `"0"`, `"1"`, etc. An optional argument can
+be omitted or set to `null`.
+
+Errors contain the path to the offending value:
+
+```text
+invalid argument `$user.tags[2]`: expected int64, got string
+```
+*/
+
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+use base64::Engine;
+use bytes::Bytes;
+use serde_json::Value as JsonValue;
+
+use gel_errors::{ClientEncodingError, Error, ErrorKind};
+use gel_errors::{InvalidArgumentError, MissingArgumentError, UnknownArgumentError};
+
+use crate::codec::{self, InputObjectShape, NamedTupleShape, ObjectShape};
+use crate::common::Cardinality;
+use crate::descriptors::{Descriptor, TypePos};
+use crate::model::{BigInt, ConfigMemory, Decimal, Duration, Json, Range, Uuid};
+use crate::query_arg::{check_enum, DescriptorContext, Encoder, QueryArgs};
+use crate::value::{SparseObject, Value};
+
+impl QueryArgs for JsonValue {
+    fn encode(&self, encoder: &mut Encoder) -> Result<(), Error> {
+        let Some(root_pos) = encoder.ctx.root_pos else {
+            return match self {
+                JsonValue::Null => ().encode(encoder),
+                JsonValue::Object(map) if map.is_empty() => ().encode(encoder),
+                JsonValue::Array(items) if items.is_empty() => ().encode(encoder),
+                _ => Err(ClientEncodingError::with_message(
+                    "arguments were provided, but no arguments were expected by the server",
+                )),
+            };
+        };
+        let value = Converter { ctx: encoder.ctx }.arguments(root_pos, self)?;
+        value.encode(encoder)
+    }
+    fn to_args_value(&self) -> Result<Value, Error> {
+        // types of arguments are only known from the descriptor
+        Ok(Value::Json(Json::new_unchecked(self.to_string())))
+    }
+}
+
+struct Converter<'a> {
+    ctx: &'a DescriptorContext<'a>,
+}
+
+fn json_kind(json: &JsonValue) -> &'static str {
+    match json {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+fn invalid(path: &str, message: impl std::fmt::Display) -> Error {
+    InvalidArgumentError::with_message(format!("invalid argument `{path}`: {message}"))
+}
+
+fn mismatch(path: &str, expected: &str, json: &JsonValue) -> Error {
+    invalid(
+        path,
+        format_args!("expected {expected}, got {}", json_kind(json)),
+    )
+}
+
+fn child(path: &str, name: &str) -> String {
+    format!("{path}.{name}")
+}
+
+fn item(path: &str, index: usize) -> String {
+    let mut path = path.to_owned();
+    write!(path, "[{index}]").unwrap();
+    path
+}
+
+fn is_required(cardinality: Option<Cardinality>) -> bool {
+    matches!(
+        cardinality,
+        Some(Cardinality::One) | Some(Cardinality::AtLeastOne)
+    )
+}
+
+impl Converter<'_> {
+    fn arguments(&self, pos: TypePos, json: &JsonValue) -> Result<Value, Error> {
+        match self.ctx.get(pos)? {
+            Descriptor::ObjectShape(shape) => {
+                let fields = self.named(
+                    shape
+                        .elements
+                        .iter()
+                        .map(|el| (&el.name[..], el.type_pos, el.cardinality)),
+                    json,
+                )?;
+                Ok(Value::Object {
+                    shape: ObjectShape::new(shape.elements.iter().map(Into::into).collect()),
+                    fields,
+                })
+            }
+            Descriptor::InputShape(shape) => {
+                let JsonValue::Object(map) = json else {
+                    return Err(mismatch("$", "object", json));
+                };
+                self.check_unexpected(map, shape.elements.iter().map(|el| &el.name[..]))?;
+                let fields = shape
+                    .elements
+                    .iter()
+                    .map(|el| {
+                        map.get(&el.name)
+                            .map(|v| self.optional(el.type_pos, v, &format!("${}", el.name)))
+                            .transpose()
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::SparseObject(SparseObject {
+                    shape: InputObjectShape::new(shape.elements.iter().map(Into::into).collect()),
+                    fields,
+                }))
+            }
+            _ => self.value(pos, json, "$"),
+        }
+    }
+
+    fn check_unexpected<'x>(
+        &self,
+        map: &serde_json::Map<String, JsonValue>,
+        names: impl Iterator<Item = &'x str> + Clone,
+    ) -> Result<(), Error> {
+        if let Some(key) = map.keys().find(|k| !names.clone().any(|n| n == *k)) {
+            return Err(UnknownArgumentError::with_message(format!(
+                "unexpected argument `${key}`"
+            )));
+        }
+        Ok(())
+    }
+
+    fn named<'x>(
+        &self,
+        elements: impl ExactSizeIterator<Item = (&'x str, TypePos, Option<Cardinality>)> + Clone,
+        json: &JsonValue,
+    ) -> Result<Vec<Option<Value>>, Error> {
+        let values: Vec<Option<&JsonValue>> = match json {
+            JsonValue::Object(map) => {
+                self.check_unexpected(map, elements.clone().map(|(name, _, _)| name))?;
+                elements.clone().map(|(name, _, _)| map.get(name)).collect()
+            }
+            JsonValue::Array(items) => {
+                let positional = elements
+                    .clone()
+                    .enumerate()
+                    .all(|(idx, (name, _, _))| name == idx.to_string());
+                if !positional {
+                    return Err(mismatch("$", "object with named arguments", json));
+                }
+                if items.len() > elements.len() {
+                    return Err(UnknownArgumentError::with_message(format!(
+                        "expected at most {} positional arguments, got {}",
+                        elements.len(),
+                        items.len()
+                    )));
+                }
+                (0..elements.len()).map(|idx| items.get(idx)).collect()
+            }
+            _ => return Err(mismatch("$", "object", json)),
+        };
+        elements
+            .zip(values)
+            .map(|((name, type_pos, cardinality), value)| {
+                let path = format!("${name}");
+                match value.and_then(|v| self.optional(type_pos, v, &path).transpose()) {
+                    Some(value) => value.map(Some),
+                    None if is_required(cardinality) => Err(MissingArgumentError::with_message(
+                        format!("missing required argument `{path}`"),
+                    )),
+                    None => Ok(None),
+                }
+            })
+            .collect()
+    }
+
+    fn optional(&self, pos: TypePos, json: &JsonValue, path: &str) -> Result<Option<Value>, Error> {
+        match json {
+            JsonValue::Null => Ok(None),
+            _ => self.value(pos, json, path).map(Some),
+        }
+    }
+
+    fn value(&self, pos: TypePos, json: &JsonValue, path: &str) -> Result<Value, Error> {
+        let desc = self.ctx.get(pos)?.normalize_to_base(self.ctx)?;
+        match desc {
+            Descriptor::BaseScalar(d) => self.scalar(*d.id, json, path),
+            Descriptor::Enumeration(d) => {
+                let JsonValue::String(s) = json else {
+                    return Err(mismatch(path, "enum value", json));
+                };
+                check_enum(s, &d.members).map_err(|e| invalid(path, e))?;
+                Ok(Value::Enum(s[..].into()))
+            }
+            Descriptor::Array(d) => {
+                let JsonValue::Array(items) = json else {
+                    return Err(mismatch(path, "array", json));
+                };
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, v)| self.value(d.type_pos, v, &item(path, idx)))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Array)
+            }
+            Descriptor::Tuple(d) => {
+                let JsonValue::Array(items) = json else {
+                    return Err(mismatch(path, "array", json));
+                };
+                if items.len() != d.element_types.len() {
+                    return Err(invalid(
+                        path,
+                        format_args!(
+                            "expected tuple of {} elements, got {}",
+                            d.element_types.len(),
+                            items.len()
+                        ),
+                    ));
+                }
+                d.element_types
+                    .iter()
+                    .zip(items)
+                    .enumerate()
+                    .map(|(idx, (pos, v))| self.value(*pos, v, &item(path, idx)))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Tuple)
+            }
+            Descriptor::NamedTuple(d) => {
+                let fields = match json {
+                    JsonValue::Object(map) => {
+                        if let Some(key) = map
+                            .keys()
+                            .find(|k| !d.elements.iter().any(|el| el.name == **k))
+                        {
+                            return Err(invalid(path, format_args!("unexpected field `{key}`")));
+                        }
+                        d.elements
+                            .iter()
+                            .map(|el| {
+                                let path = child(path, &el.name);
+                                let v = map.get(&el.name).ok_or_else(|| {
+                                    InvalidArgumentError::with_message(format!(
+                                        "missing field `{path}`"
+                                    ))
+                                })?;
+                                self.value(el.type_pos, v, &path)
+                            })
+                            .collect::<Result<_, _>>()?
+                    }
+                    JsonValue::Array(items) if items.len() == d.elements.len() => d
+                        .elements
+                        .iter()
+                        .zip(items)
+                        .map(|(el, v)| self.value(el.type_pos, v, &child(path, &el.name)))
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(mismatch(path, "named tuple", json)),
+                };
+                Ok(Value::NamedTuple {
+                    shape: NamedTupleShape::from(&d.elements[..]),
+                    fields,
+                })
+            }
+            Descriptor::Range(d) => self.range(d.type_pos, json, path),
+            desc => Err(invalid(
+                path,
+                format_args!("arguments of type {desc:?} are not supported"),
+            )),
+        }
+    }
+
+    fn range(&self, pos: TypePos, json: &JsonValue, path: &str) -> Result<Value, Error> {
+        let JsonValue::Object(map) = json else {
+            return Err(mismatch(path, "range object", json));
+        };
+        if let Some(key) = map.keys().find(|k| {
+            !matches!(
+                &k[..],
+                "lower" | "upper" | "inc_lower" | "inc_upper" | "empty"
+            )
+        }) {
+            return Err(invalid(path, format_args!("unexpected field `{key}`")));
+        }
+        let flag = |name: &str, default: bool| match map.get(name) {
+            None | Some(JsonValue::Null) => Ok(default),
+            Some(JsonValue::Bool(b)) => Ok(*b),
+            Some(v) => Err(mismatch(&child(path, name), "boolean", v)),
+        };
+        if flag("empty", false)? {
+            return Ok(Value::Range(Range::empty()));
+        }
+        let bound = |name: &str| {
+            map.get(name)
+                .map(|v| self.optional(pos, v, &child(path, name)))
+                .transpose()
+                .map(|v| v.flatten().map(Box::new))
+        };
+        Ok(Value::Range(Range {
+            lower: bound("lower")?,
+            upper: bound("upper")?,
+            inc_lower: flag("inc_lower", true)?,
+            inc_upper: flag("inc_upper", false)?,
+            empty: false,
+        }))
+    }
+
+    fn scalar(&self, id: Uuid, json: &JsonValue, path: &str) -> Result<Value, Error> {
+        let value = match id {
+            codec::STD_STR => Value::Str(string(json, path, "str")?.to_owned()),
+            codec::STD_BOOL => match json {
+                JsonValue::Bool(b) => Value::Bool(*b),
+                _ => return Err(mismatch(path, "bool", json)),
+            },
+            codec::STD_INT16 => Value::Int16(integer(json, path, "int16")?),
+            codec::STD_INT32 => Value::Int32(integer(json, path, "int32")?),
+            codec::STD_INT64 => Value::Int64(integer(json, path, "int64")?),
+            codec::STD_FLOAT32 => {
+                let f = float(json, path, "float32")?;
+                if f.is_finite() && f.abs() > f32::MAX as f64 {
+                    return Err(invalid(
+                        path,
+                        format_args!("{f} is out of range for float32"),
+                    ));
+                }
+                Value::Float32(f as f32)
+            }
+            codec::STD_FLOAT64 => Value::Float64(float(json, path, "float64")?),
+            codec::STD_BIGINT => Value::BigInt(number_str(json, path, "bigint", BigInt::parse)?),
+            codec::STD_DECIMAL => {
+                Value::Decimal(number_str(json, path, "decimal", Decimal::parse)?)
+            }
+            codec::CFG_MEMORY => {
+                Value::ConfigMemory(ConfigMemory(integer(json, path, "cfg::memory")?))
+            }
+            codec::STD_UUID => {
+                let s = string(json, path, "uuid")?;
+                Value::Uuid(s.parse().map_err(|e| invalid(path, e))?)
+            }
+            codec::STD_BYTES => Value::Bytes(base64(json, path, "bytes")?),
+            codec::STD_JSON => Value::Json(Json::new_unchecked(json.to_string())),
+            codec::STD_DATETIME => Value::Datetime(parsed(json, path, "datetime")?),
+            codec::CAL_LOCAL_DATETIME => {
+                Value::LocalDatetime(parsed(json, path, "cal::local_datetime")?)
+            }
+            codec::CAL_LOCAL_DATE => Value::LocalDate(parsed(json, path, "cal::local_date")?),
+            codec::CAL_LOCAL_TIME => Value::LocalTime(parsed(json, path, "cal::local_time")?),
+            codec::STD_DURATION => {
+                let s = string(json, path, "duration")?;
+                Value::Duration(s.parse::<Duration>().map_err(|e| invalid(path, e))?)
+            }
+            codec::CAL_RELATIVE_DURATION => {
+                Value::RelativeDuration(parsed(json, path, "cal::relative_duration")?)
+            }
+            codec::CAL_DATE_DURATION => {
+                Value::DateDuration(parsed(json, path, "cal::date_duration")?)
+            }
+            codec::PGVECTOR_VECTOR => {
+                let JsonValue::Array(items) = json else {
+                    return Err(mismatch(path, "array of numbers", json));
+                };
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, v)| float(v, &item(path, idx), "float32").map(|f| f as f32))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Vector)?
+            }
+            codec::POSTGIS_GEOMETRY => Value::PostGisGeometry(base64(json, path, "geometry")?),
+            codec::POSTGIS_GEOGRAPHY => Value::PostGisGeography(base64(json, path, "geography")?),
+            codec::POSTGIS_BOX_2D => Value::PostGisBox2d(base64(json, path, "box2d")?),
+            codec::POSTGIS_BOX_3D => Value::PostGisBox3d(base64(json, path, "box3d")?),
+            _ => {
+                return Err(invalid(
+                    path,
+                    format_args!("arguments of type {id} are not supported"),
+                ))
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn string<'a>(json: &'a JsonValue, path: &str, expected: &str) -> Result<&'a str, Error> {
+    match json {
+        JsonValue::String(s) => Ok(s),
+        _ => Err(mismatch(path, expected, json)),
+    }
+}
+
+fn parsed<T: std::str::FromStr>(json: &JsonValue, path: &str, expected: &str) -> Result<T, Error> {
+    let s = string(json, path, expected)?;
+    s.parse()
+        .map_err(|_| invalid(path, format_args!("{s:?} is not a valid {expected}")))
+}
+
+fn integer<T: TryFrom<i64>>(json: &JsonValue, path: &str, expected: &str) -> Result<T, Error> {
+    let JsonValue::Number(n) = json else {
+        return Err(mismatch(path, expected, json));
+    };
+    let int = match n.as_i64() {
+        Some(i) => Some(i),
+        // integers written as floats, e.g. `3.0`
+        None => n
+            .as_f64()
+            .filter(|f| f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64)
+            .map(|f| f as i64),
+    };
+    match int {
+        Some(i) => T::try_from(i)
+            .map_err(|_| invalid(path, format_args!("{n} is out of range for {expected}"))),
+        None if n.is_u64() => Err(invalid(
+            path,
+            format_args!("{n} is out of range for {expected}"),
+        )),
+        None => Err(invalid(path, format_args!("expected {expected}, got {n}"))),
+    }
+}
+
+fn float(json: &JsonValue, path: &str, expected: &str) -> Result<f64, Error> {
+    match json {
+        JsonValue::Number(n) => Ok(n.as_f64().unwrap_or(f64::NAN)),
+        JsonValue::String(s) if s == "NaN" => Ok(f64::NAN),
+        JsonValue::String(s) if s == "Infinity" => Ok(f64::INFINITY),
+        JsonValue::String(s) if s == "-Infinity" => Ok(f64::NEG_INFINITY),
+        _ => Err(mismatch(path, expected, json)),
+    }
+}
+
+fn number_str<T>(
+    json: &JsonValue,
+    path: &str,
+    expected: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<T, Error> {
+    let s = match json {
+        JsonValue::Number(n) => n.to_string(),
+        JsonValue::String(s) => s.clone(),
+        _ => return Err(mismatch(path, expected, json)),
+    };
+    parse(&s).ok_or_else(|| invalid(path, format_args!("{s:?} is not a valid {expected}")))
+}
+
+fn base64(json: &JsonValue, path: &str, expected: &str) -> Result<Bytes, Error> {
+    let s = string(json, path, expected)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(s)
+        .map(Bytes::from)
+        .map_err(|e| invalid(path, format_args!("invalid base64: {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use serde_json::json;
+
+    use crate::common::Cardinality;
+    use crate::descriptors::{ArrayTypeDescriptor, BaseScalarTypeDescriptor, Descriptor};
+    use crate::descriptors::{NamedTupleTypeDescriptor, TupleElement, TypePos};
+    use crate::descriptors::{ObjectShapeDescriptor, RangeTypeDescriptor, ShapeElement};
+    use crate::features::ProtocolVersion;
+    use crate::model::{Datetime, Uuid};
+    use crate::query_arg::{DescriptorContext, Encoder, QueryArgs};
+    use crate::{codec, value::Value};
+
+    fn scalar(id: Uuid) -> Descriptor {
+        Descriptor::BaseScalar(BaseScalarTypeDescriptor { id: id.into() })
+    }
+
+    fn arg(name: &str, type_pos: u16, cardinality: Cardinality) -> ShapeElement {
+        ShapeElement {
+            flag_implicit: false,
+            flag_link_property: false,
+            flag_link: false,
+            cardinality: Some(cardinality),
+            name: name.into(),
+            type_pos: TypePos(type_pos),
+            source_type_pos: None,
+        }
+    }
+
+    fn descriptors() -> Vec<Descriptor> {
+        vec![
+            scalar(codec::STD_INT64),
+            scalar(codec::STD_STR),
+            Descriptor::Array(ArrayTypeDescriptor {
+                id: Uuid::from_u128(0x1000).into(),
+                type_pos: TypePos(0),
+                dimensions: vec![None],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            scalar(codec::STD_DATETIME),
+            Descriptor::NamedTuple(NamedTupleTypeDescriptor {
+                id: Uuid::from_u128(0x1001).into(),
+                elements: vec![
+                    TupleElement {
+                        name: "name".into(),
+                        type_pos: TypePos(1),
+                    },
+                    TupleElement {
+                        name: "tags".into(),
+                        type_pos: TypePos(2),
+                    },
+                ],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            Descriptor::Range(RangeTypeDescriptor {
+                id: Uuid::from_u128(0x1002).into(),
+                type_pos: TypePos(0),
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            scalar(codec::STD_FLOAT64),
+            Descriptor::ObjectShape(ObjectShapeDescriptor {
+                id: Uuid::from_u128(0x1003).into(),
+                ephemeral_free_shape: false,
+                type_pos: None,
+                elements: vec![
+                    arg("user", 4, Cardinality::One),
+                    arg("at", 3, Cardinality::AtMostOne),
+                    arg("span", 5, Cardinality::AtMostOne),
+                    arg("score", 6, Cardinality::AtMostOne),
+                ],
+            }),
+        ]
+    }
+
+    fn encode(json: serde_json::Value) -> Result<Value, gel_errors::Error> {
+        let descriptors = descriptors();
+        let proto = ProtocolVersion::current();
+        let ctx = DescriptorContext {
+            proto: &proto,
+            root_pos: Some(TypePos(7)),
+            descriptors: &descriptors,
+        };
+        let mut buf = BytesMut::new();
+        json.encode(&mut Encoder::new(&ctx, &mut buf))?;
+        let codec = ctx.build_codec().unwrap();
+        Ok(codec.decode(&buf).unwrap())
+    }
+
+    fn error(json: serde_json::Value) -> String {
+        encode(json).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn encode_args() {
+        let value = encode(json!({
+            "user": {"name": "alice", "tags": [1, 2.0, -3]},
+            "at": "2024-02-29T12:30:00+02:00",
+            "span": {"lower": 1, "upper": null},
+            "score": 2,
+        }))
+        .unwrap();
+        assert_eq!(
+            value.get("user.tags").unwrap(),
+            &Value::Array(vec![Value::Int64(1), Value::Int64(2), Value::Int64(-3)])
+        );
+        assert_eq!(
+            value.get_datetime("at").unwrap(),
+            Datetime::try_from_unix_micros(1_709_202_600_000_000).unwrap()
+        );
+        assert_eq!(value.get_f64("score").unwrap(), 2.0);
+        let Value::Range(span) = value.get("span").unwrap() else {
+            panic!("range expected");
+        };
+        assert_eq!(span.lower().map(|b| &**b), Some(&Value::Int64(1)));
+        assert_eq!(span.upper(), None);
+        assert!(span.inc_lower());
+
+        let value = encode(json!({"user": ["bob", []]})).unwrap();
+        assert_eq!(value.get_str("user.name").unwrap(), "bob");
+        assert_eq!(value.get_opt("at").unwrap(), None);
+    }
+
+    #[test]
+    fn encode_errors() {
+        assert_eq!(
+            error(json!({"user": {"name": "alice", "tags": [1, "2"]}})),
+            "InvalidArgumentError: invalid argument `$user.tags[1]`: \
+             expected int64, got string"
+        );
+        assert_eq!(
+            error(json!({"user": {"name": "alice", "tags": [1.5]}})),
+            "InvalidArgumentError: invalid argument `$user.tags[0]`: \
+             expected int64, got 1.5"
+        );
+        assert_eq!(
+            error(json!({"user": {"name": "alice"}})),
+            "InvalidArgumentError: missing field `$user.tags`"
+        );
+        assert_eq!(
+            error(json!({"at": null})),
+            "MissingArgumentError: missing required argument `$user`"
+        );
+        assert_eq!(
+            error(json!({"user": ["a", []], "extra": 1})),
+            "UnknownArgumentError: unexpected argument `$extra`"
+        );
+        assert_eq!(
+            error(json!({"user": ["a", []], "at": "yesterday"})),
+            "InvalidArgumentError: invalid argument `$at`: \
+             \"yesterday\" is not a valid datetime"
+        );
+        assert_eq!(
+            error(json!({"user": ["a", []], "span": {"lower": 1, "step": 2}})),
+            "InvalidArgumentError: invalid argument `$span`: unexpected field `step`"
+        );
+    }
+}
@@ -67,7 +67,11 @@ pub mod encoding;
 pub mod error_response;
 pub mod errors;
 pub mod features;
+#[cfg(feature = "with-serde")]
+pub mod json_args;
 pub mod queryable;
+#[cfg(feature = "with-serde")]
+pub mod serde;
 pub mod serialization;
 pub mod server_message;
 pub mod value;
@@ -80,3 +80,22 @@ impl ParseDurationError {
         self
     }
 }
+
+/// Error parsing string into a Gel date or time type.
+#[derive(Debug, PartialEq)]
+pub struct ParseDatetimeError {
+    pub(crate) expected: &'static str,
+}
+
+impl std::error::Error for ParseDatetimeError {}
+impl fmt::Display for ParseDatetimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} format", self.expected)
+    }
+}
+
+impl ParseDatetimeError {
+    pub(crate) fn new(expected: &'static str) -> Self {
+        Self { expected }
+    }
+}
@@ -58,6 +58,104 @@ impl BigInt {
     }
 }
 
+/// Parses a decimal string with an optional sign, fraction and exponent into
+/// base-10000 groups: `(negative, weight, digits, scale)`.
+#[cfg(feature = "with-serde")]
+fn parse_groups(s: &str) -> Option<(bool, i16, Vec<u16>, u16)> {
+    use std::convert::TryFrom;
+
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (mantissa, exp) = match s.find(['e', 'E']) {
+        Some(idx) => (&s[..idx], s[idx + 1..].parse::<i32>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    if !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if exp.abs() > i16::MAX as i32 {
+        return None;
+    }
+    let point = int.len() as i32 + exp;
+    let scale = u16::try_from((frac.len() as i32 - exp).max(0)).ok()?;
+    let mut groups = std::collections::BTreeMap::<i32, u16>::new();
+    for (idx, digit) in int.bytes().chain(frac.bytes()).enumerate() {
+        let power = point - 1 - idx as i32;
+        *groups.entry(power.div_euclid(4)).or_default() +=
+            (digit - b'0') as u16 * 10u16.pow(power.rem_euclid(4) as u32);
+    }
+    let weight = groups.keys().next_back().copied().unwrap_or(0);
+    let lowest = groups.keys().next().copied().unwrap_or(0);
+    if i16::try_from(lowest).is_err() {
+        return None;
+    }
+    let weight = i16::try_from(weight).ok()?;
+    let digits = groups.into_values().rev().collect();
+    Some((negative, weight, digits, scale))
+}
+
+impl BigInt {
+    /// Parses an integer written in decimal notation. Exponent is allowed
+    /// as long as the number has no fractional part.
+    #[cfg(feature = "with-serde")]
+    pub(crate) fn parse(s: &str) -> Option<BigInt> {
+        use std::convert::TryFrom;
+
+        let (negative, weight, digits, _) = parse_groups(s)?;
+        let int_groups = usize::try_from(i32::from(weight) + 1).unwrap_or(0);
+        if digits.iter().skip(int_groups).any(|d| *d != 0) {
+            return None;
+        }
+        let num = BigInt {
+            negative,
+            weight,
+            digits,
+        }
+        .normalize();
+        Some(if num.digits.is_empty() {
+            BigInt {
+                negative: false,
+                weight: 0,
+                digits: Vec::new(),
+            }
+        } else {
+            num
+        })
+    }
+}
+
+impl Decimal {
+    /// Parses a number written in decimal notation, possibly with an
+    /// exponent. The number of fractional digits is preserved.
+    #[cfg(feature = "with-serde")]
+    pub(crate) fn parse(s: &str) -> Option<Decimal> {
+        let (negative, weight, digits, decimal_digits) = parse_groups(s)?;
+        let num = Decimal {
+            negative,
+            weight,
+            decimal_digits,
+            digits,
+        }
+        .normalize();
+        Some(if num.digits.is_empty() {
+            Decimal {
+                negative: false,
+                weight: -1,
+                decimal_digits,
+                digits: Vec::new(),
+            }
+        } else {
+            num
+        })
+    }
+}
+
 impl std::fmt::Display for BigInt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.negative {
@@ -350,6 +448,38 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "with-serde")]
+    fn bigint_parse() {
+        for i in [0, 1, -1, 1_0000, -1_0000, 1_2345_6789, i64::MAX, i64::MIN] {
+            assert_eq!(
+                BigInt::parse(&i.to_string()).unwrap().to_string(),
+                i.to_string()
+            );
+        }
+        assert_eq!(BigInt::parse("1e8"), Some(BigInt::from(100_000_000)));
+        assert_eq!(BigInt::parse("12.00"), Some(BigInt::from(12)));
+        assert_eq!(BigInt::parse("12.5"), None);
+        assert_eq!(BigInt::parse("1e-1"), None);
+        assert_eq!(BigInt::parse("12a"), None);
+        assert_eq!(BigInt::parse(""), None);
+    }
+
+    #[test]
+    #[cfg(feature = "with-serde")]
+    fn decimal_parse() {
+        let cases = ["0.0", "42.0", "-1.25", "0.00012", "12345678.9", "-0.5000"];
+        for case in cases {
+            assert_eq!(Decimal::parse(case).unwrap().to_string(), case);
+        }
+        assert_eq!(Decimal::parse("1.5e3").unwrap().to_string(), "1500.0");
+        assert_eq!(Decimal::parse("15e-3").unwrap().to_string(), "0.015");
+        assert_eq!(Decimal::parse(".5").unwrap().to_string(), "0.5");
+        assert_eq!(Decimal::parse("-0").unwrap().to_string(), "0.0");
+        assert_eq!(Decimal::parse("1.2.3"), None);
+        assert_eq!(Decimal::parse("."), None);
+    }
+
     #[test]
     fn decimal_display() {
         assert_eq!(
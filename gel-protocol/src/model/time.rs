@@ -1,4 +1,4 @@
-use crate::model::{OutOfRangeError, ParseDurationError};
+use crate::model::{OutOfRangeError, ParseDatetimeError, ParseDurationError};
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Debug, Display};
 use std::str::FromStr;
@@ -534,7 +534,7 @@ impl LocalTime {
         self.micros
     }
 
-    fn to_hmsu(self) -> (u8, u8, u8, u32) {
+    pub(crate) fn to_hmsu(self) -> (u8, u8, u8, u32) {
         let micros = self.micros;
 
         let microsecond = (micros % 1_000_000) as u32;
@@ -620,7 +620,11 @@ impl LocalDate {
             .unwrap_or_else(|_| panic!("invalid date {:04}-{:02}-{:02}", year, month, day))
     }
 
-    fn try_from_ymd(year: i32, month: u8, day: u8) -> Result<LocalDate, OutOfRangeError> {
+    pub(crate) fn try_from_ymd(
+        year: i32,
+        month: u8,
+        day: u8,
+    ) -> Result<LocalDate, OutOfRangeError> {
         if !(1..=31).contains(&day) {
             return Err(OutOfRangeError);
         }
@@ -653,7 +657,7 @@ impl LocalDate {
         )
     }
 
-    fn to_ymd(self) -> (i32, u8, u8) {
+    pub(crate) fn to_ymd(self) -> (i32, u8, u8) {
         const DAYS_IN_100_YEARS: u32 = 100 * 365 + 24;
         const DAYS_IN_4_YEARS: u32 = 4 * 365 + 1;
         const DAYS_IN_1_YEAR: u32 = 365;
@@ -981,10 +985,271 @@ impl Display for Duration {
     }
 }
 
+/// Parses a string of ASCII digits of the given length.
+fn digits<T: FromStr>(s: &str, len: std::ops::RangeInclusive<usize>) -> Option<T> {
+    if !len.contains(&s.len()) || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_date(s: &str) -> Option<LocalDate> {
+    let mut parts = s.splitn(3, '-');
+    let year = digits(parts.next()?, 4..=4)?;
+    let month = digits(parts.next()?, 2..=2)?;
+    let day = digits(parts.next()?, 2..=2)?;
+    LocalDate::try_from_ymd(year, month, day).ok()
+}
+
+fn parse_time(s: &str) -> Option<LocalTime> {
+    let (hms, frac) = s.split_once('.').unwrap_or((s, ""));
+    let mut parts = hms.splitn(3, ':');
+    let hours: u64 = digits(parts.next()?, 2..=2)?;
+    let minutes: u64 = digits(parts.next()?, 2..=2)?;
+    let seconds: u64 = parts.next().map(|s| digits(s, 2..=2)).unwrap_or(Some(0))?;
+    let micros: u64 = if frac.is_empty() {
+        if s.ends_with('.') {
+            return None;
+        }
+        0
+    } else {
+        digits::<u64>(frac, 1..=6)? * 10u64.pow(6 - frac.len() as u32)
+    };
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    LocalTime::try_from_micros(
+        ((hours * 60 + minutes) * 60 + seconds) * MICROS_PER_SECOND as u64 + micros,
+    )
+    .ok()
+}
+
+fn parse_local_datetime(s: &str) -> Option<LocalDatetime> {
+    let (date, time) = s.split_once(['T', 't', ' '])?;
+    Some(LocalDatetime::new(parse_date(date)?, parse_time(time)?))
+}
+
+fn parse_datetime(s: &str) -> Option<Datetime> {
+    let (local, offset_secs) = if let Some(local) = s
+        .strip_suffix('Z')
+        .or_else(|| s.strip_suffix('z'))
+        .or_else(|| s.strip_suffix(" UTC"))
+    {
+        (local, 0)
+    } else {
+        // offset sign can only appear after the date part
+        let idx = s.get(10..)?.rfind(['+', '-'])? + 10;
+        let (local, offset) = s.split_at(idx);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let offset = &offset[1..];
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "00"),
+        };
+        let hours: i64 = digits(hours, 2..=2)?;
+        let minutes: i64 = digits(minutes, 2..=2)?;
+        (local, sign * (hours * 3600 + minutes * 60))
+    };
+    let local = parse_local_datetime(local)?;
+    let micros = local.to_utc().to_unix_micros() - offset_secs * MICROS_PER_SECOND;
+    Datetime::try_from_unix_micros(micros).ok()
+}
+
+/// Parses ISO 8601 duration into `(months, days, micros)`.
+fn parse_iso_duration(s: &str) -> Option<(i32, i32, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let s = s.strip_prefix(['P', 'p'])?;
+    let (date, time) = match s.split_once(['T', 't']) {
+        Some((_, "")) => return None,
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    if date.is_empty() && time.is_none() {
+        return None;
+    }
+    let (mut months, mut days, mut micros) = (0i32, 0i32, 0i64);
+    let mut rest = date;
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let num: i32 = rest[..end].parse().ok()?;
+        match rest[end..].chars().next()?.to_ascii_uppercase() {
+            'Y' => months = months.checked_add(num.checked_mul(12)?)?,
+            'M' => months = months.checked_add(num)?,
+            'W' => days = days.checked_add(num.checked_mul(7)?)?,
+            'D' => days = days.checked_add(num)?,
+            _ => return None,
+        }
+        rest = &rest[end + 1..];
+    }
+    let mut rest = time.unwrap_or("");
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let num = &rest[..end];
+        let value = match rest[end..].chars().next()?.to_ascii_uppercase() {
+            'H' => num.parse::<i64>().ok()?.checked_mul(MICROS_PER_HOUR)?,
+            'M' => num.parse::<i64>().ok()?.checked_mul(MICROS_PER_MINUTE)?,
+            'S' => {
+                let (secs, frac) = num.split_once('.').unwrap_or((num, ""));
+                let sign = if secs.starts_with('-') { -1 } else { 1 };
+                let frac = if frac.is_empty() {
+                    0
+                } else {
+                    digits::<i64>(frac, 1..=6)? * 10i64.pow(6 - frac.len() as u32)
+                };
+                let secs = match secs {
+                    "" | "-" => 0,
+                    _ => secs.parse::<i64>().ok()?,
+                };
+                secs.checked_mul(MICROS_PER_SECOND)?
+                    .checked_add(sign * frac)?
+            }
+            _ => return None,
+        };
+        micros = micros.checked_add(value)?;
+        rest = &rest[end + 1..];
+    }
+    if negative {
+        Some((
+            months.checked_neg()?,
+            days.checked_neg()?,
+            micros.checked_neg()?,
+        ))
+    } else {
+        Some((months, days, micros))
+    }
+}
+
+/// Parses an ISO 8601 date: `2024-02-29`.
+impl FromStr for LocalDate {
+    type Err = ParseDatetimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_date(s).ok_or(ParseDatetimeError::new("cal::local_date"))
+    }
+}
+
+/// Parses an ISO 8601 time with optional seconds and up to six fractional
+/// digits: `12:30`, `12:30:00.5`.
+impl FromStr for LocalTime {
+    type Err = ParseDatetimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_time(s).ok_or(ParseDatetimeError::new("cal::local_time"))
+    }
+}
+
+/// Parses a date and time separated by `T` or a space:
+/// `2024-02-29T12:30:00`.
+impl FromStr for LocalDatetime {
+    type Err = ParseDatetimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_local_datetime(s).ok_or(ParseDatetimeError::new("cal::local_datetime"))
+    }
+}
+
+/// Parses a local date and time followed by `Z`, ` UTC` or a UTC offset:
+/// `2024-02-29T12:30:00+02:00`. This accepts the output of [Display].
+impl FromStr for Datetime {
+    type Err = ParseDatetimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_datetime(s).ok_or(ParseDatetimeError::new("datetime"))
+    }
+}
+
+/// Parses an ISO 8601 duration: `P1Y2M3DT4H5M6.5S`.
+impl FromStr for RelativeDuration {
+    type Err = ParseDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (months, days, micros) = parse_iso_duration(s)
+            .ok_or_else(|| ParseDurationError::new("invalid ISO 8601 duration"))?;
+        Ok(RelativeDuration {
+            months,
+            days,
+            micros,
+        })
+    }
+}
+
+/// Parses an ISO 8601 duration without a time part: `P1Y2M3D`.
+impl FromStr for DateDuration {
+    type Err = ParseDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (months, days, micros) = parse_iso_duration(s)
+            .ok_or_else(|| ParseDurationError::new("invalid ISO 8601 duration"))?;
+        if micros != 0 {
+            return Err(ParseDurationError::new(
+                "date duration can't contain a time part",
+            ));
+        }
+        Ok(DateDuration { months, days })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn parse_datetime() {
+        let utc = |s: &str| s.parse::<Datetime>().map(|d| d.to_unix_micros());
+        assert_eq!(utc("1970-01-01T00:00:00Z"), Ok(0));
+        assert_eq!(utc("1970-01-01 00:00:01 UTC"), Ok(1_000_000));
+        assert_eq!(utc("1970-01-01T01:00:00.5+01:00"), Ok(500_000));
+        assert_eq!(utc("1970-01-01T00:00:00-0130"), Ok(5_400_000_000));
+        assert!(utc("1970-01-01T00:00:00").is_err());
+        assert!(utc("1970-13-01T00:00:00Z").is_err());
+        assert!(utc("1970-01-01T24:00:00Z").is_err());
+        let datetime = Datetime::try_from_unix_micros(1_709_202_600_123_456).unwrap();
+        assert_eq!(datetime.to_string().parse(), Ok(datetime));
+        assert!(
+            "2023-02-29T00:00:00".parse::<LocalDatetime>().is_err(),
+            "not a leap year"
+        );
+        assert_eq!("2024-02-29".parse(), Ok(LocalDate::from_ymd(2024, 2, 29)));
+        assert_eq!("12:30".parse(), Ok(LocalTime::from_micros(45_000_000_000)));
+        assert_eq!(
+            "2024-02-29T12:30"
+                .parse::<LocalDatetime>()
+                .unwrap()
+                .to_string(),
+            "2024-02-29 12:30:00"
+        );
+    }
+
+    #[test]
+    fn parse_iso_duration() {
+        let relative = |s: &str| {
+            s.parse::<RelativeDuration>()
+                .map(|d| (d.months, d.days, d.micros))
+        };
+        assert_eq!(relative("P1Y2M3D"), Ok((14, 3, 0)));
+        assert_eq!(
+            relative("P6Y8M-16DT52H5M7.6S"),
+            Ok((80, -16, 187_507_600_000))
+        );
+        assert_eq!(relative("PT-52.4S"), Ok((0, 0, -52_400_000)));
+        assert_eq!(relative("-P1W"), Ok((0, -7, 0)));
+        assert!(relative("P").is_err());
+        assert!(relative("P1DT").is_err());
+        assert!(relative("1D").is_err());
+        assert_eq!(
+            "P1Y2D".parse(),
+            Ok(DateDuration {
+                months: 12,
+                days: 2
+            })
+        );
+        assert!("P1DT1H".parse::<DateDuration>().is_err());
+    }
+
     #[test]
     fn micros_conv() {
         let datetime = Datetime::from_unix_micros(1645681383000002);
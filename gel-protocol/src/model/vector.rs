@@ -28,7 +28,7 @@ impl DerefMut for Vector {
     }
 }
 
-impl Queryable for Vector {
+impl Queryable<'_> for Vector {
     type Args = ();
 
     fn decode(_decoder: &Decoder, _args: &(), mut buf: &[u8]) -> Result<Self, DecodeError> {
@@ -12,7 +12,9 @@ use uuid::Uuid;
 
 use gel_errors::ParameterTypeMismatchError;
 use gel_errors::{ClientEncodingError, DescriptorMismatch, ProtocolError};
-use gel_errors::{Error, ErrorKind, InvalidReferenceError};
+use gel_errors::{ErrorKind, InvalidReferenceError};
+
+pub use gel_errors::Error;
 
 use crate::codec::{self, build_codec, Codec};
 use crate::descriptors::TypePos;
@@ -47,6 +49,16 @@ pub trait ScalarArg: Send + Sync + Sized {
 /// should include named arguments rather than numeric ones).
 pub trait QueryArgs: Send + Sync {
     fn encode(&self, encoder: &mut Encoder) -> Result<(), Error>;
+    /// Converts arguments into a [Value] without a descriptor.
+    ///
+    /// Positional arguments become a [Value::Tuple] and named arguments a
+    /// [Value::Object] with fields sorted by name. Used to inspect arguments
+    /// in tests, e.g. in a mock executor.
+    fn to_args_value(&self) -> Result<Value, Error> {
+        Err(ClientEncodingError::with_message(
+            "arguments can't be converted to a value",
+        ))
+    }
 }
 
 pub struct DescriptorContext<'a> {
@@ -141,6 +153,9 @@ impl QueryArgs for () {
         }
         Ok(())
     }
+    fn to_args_value(&self) -> Result<Value, Error> {
+        Ok(Value::Tuple(Vec::new()))
+    }
 }
 
 impl QueryArg for Value {
@@ -285,6 +300,9 @@ impl QueryArgs for Value {
             .encode(enc.buf, self)
             .map_err(ClientEncodingError::with_source)
     }
+    fn to_args_value(&self) -> Result<Value, Error> {
+        Ok(self.clone())
+    }
 }
 
 impl<T: ScalarArg> QueryArg for T {
@@ -539,6 +557,11 @@ macro_rules! implement_tuple {
                 )*
                 Ok(())
             }
+            fn to_args_value(&self) -> Result<Value, Error> {
+                #![allow(non_snake_case)]
+                let ($(ref $name,)+) = self;
+                Ok(Value::Tuple(vec![$(QueryArg::to_value($name)?,)+]))
+            }
         }
     }
 }
@@ -21,26 +21,27 @@ pub trait Sealed: Sized {}
 /// This is implemented for scalars and tuples. To receive a shape from Gel
 /// derive [`Queryable`](Queryable) for a structure. This will automatically
 /// implement `QueryResult` for you.
+///
+/// Only types that don't borrow from the data buffer are query results, i.e.
+/// types that implement [`Queryable`] for any lifetime.
 pub trait QueryResult: Sealed {
     type State;
     fn prepare(ctx: &DescriptorContext, root_pos: TypePos) -> Result<Self::State, Error>;
     fn decode(state: &mut Self::State, msg: &Bytes) -> Result<Self, Error>;
 }
 
-impl<T: Queryable> Sealed for T {}
+impl<T, A> Sealed for T where T: for<'a> Queryable<'a, Args = A> {}
 
 impl Sealed for Value {}
 
-impl<T: Queryable> QueryResult for T {
-    type State = (Decoder, T::Args);
+impl<T, A> QueryResult for T
+where
+    T: for<'a> Queryable<'a, Args = A>,
+{
+    type State = (Decoder, A);
     fn prepare(ctx: &DescriptorContext, root_pos: TypePos) -> Result<Self::State, Error> {
         let args = T::check_descriptor(ctx, root_pos).map_err(DescriptorMismatch::with_source)?;
-        let decoder = Decoder {
-            has_implicit_id: ctx.has_implicit_id,
-            has_implicit_tid: ctx.has_implicit_tid,
-            has_implicit_tname: ctx.has_implicit_tname,
-        };
-        Ok((decoder, args))
+        Ok((ctx.decoder(), args))
     }
     fn decode((decoder, args): &mut Self::State, msg: &Bytes) -> Result<Self, Error> {
         Queryable::decode(decoder, args, msg).map_err(ProtocolEncodingError::with_source)
@@ -2,11 +2,12 @@
 Contains the [Queryable] trait.
 */
 use snafu::{ensure, Snafu};
+use std::collections::HashMap;
 use std::default::Default;
 use std::sync::Arc;
 
 use crate::codec::{build_codec, Codec};
-use crate::descriptors::{Descriptor, TypePos};
+use crate::descriptors::{Descriptor, ShapeElement, TypePos};
 use crate::errors::{self, DecodeError};
 use gel_errors::{Error, ErrorKind, ProtocolEncodingError};
 
@@ -18,17 +19,23 @@ pub struct Decoder {
     pub has_implicit_tname: bool,
 }
 
-pub trait Queryable: Sized {
+/// A type that can be decoded from a query result.
+///
+/// The `'a` lifetime is the lifetime of the buffer the value is decoded from.
+/// Owned types (`String`, `Vec<T>`, most structs) implement `Queryable<'a>`
+/// for any lifetime, while borrowed types like `&'a str` or `&'a [u8]` (and
+/// structs containing them) only live as long as the buffer they reference.
+pub trait Queryable<'a>: Sized {
     /// Data returned by [Queryable::check_descriptor], that can be used during decoding.
     /// For example, this is used to pass the order of object pointers (which is sent in
     /// type descriptors) to decode function.
     type Args;
 
-    fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8]) -> Result<Self, DecodeError>;
+    fn decode(decoder: &Decoder, args: &Self::Args, buf: &'a [u8]) -> Result<Self, DecodeError>;
     fn decode_optional(
         decoder: &Decoder,
         args: &Self::Args,
-        buf: Option<&[u8]>,
+        buf: Option<&'a [u8]>,
     ) -> Result<Self, DecodeError> {
         ensure!(buf.is_some(), errors::MissingRequiredElement);
         Self::decode(decoder, args, buf.unwrap())
@@ -39,6 +46,33 @@ pub trait Queryable: Sized {
     ) -> Result<Self::Args, DescriptorMismatch>;
 }
 
+/// Elements of an object shape by name, along with their positions.
+#[doc(hidden)]
+pub type ShapeElements<'s> = HashMap<&'s str, (usize, &'s ShapeElement)>;
+
+/// Decoding of a structure from a subset of the elements of an object.
+///
+/// Implemented by `#[derive(Queryable)]` for structures, so that they can be
+/// embedded into other structures with `#[gel(flatten)]`.
+#[doc(hidden)]
+pub trait DecodeFields<'a>: Sized {
+    type FieldArgs;
+
+    /// Decodes the structure from all the elements of the object.
+    fn decode_fields(
+        decoder: &Decoder,
+        args: &Self::FieldArgs,
+        elements: &[Option<&'a [u8]>],
+    ) -> Result<Self, DecodeError>;
+    /// Looks up the fields in the shape, adding the number of elements used
+    /// to `used`.
+    fn check_fields(
+        ctx: &DescriptorContext,
+        elements: &ShapeElements,
+        used: &mut usize,
+    ) -> Result<Self::FieldArgs, DescriptorMismatch>;
+}
+
 #[derive(Snafu, Debug)]
 #[non_exhaustive]
 pub enum DescriptorMismatch {
@@ -76,6 +110,18 @@ impl DescriptorContext<'_> {
             has_implicit_tname: false,
         }
     }
+    #[cfg(feature = "with-serde")]
+    pub(crate) fn descriptors(&self) -> &[Descriptor] {
+        self.descriptors
+    }
+    /// Returns a [Decoder] matching the implicit fields of this context
+    pub fn decoder(&self) -> Decoder {
+        Decoder {
+            has_implicit_id: self.has_implicit_id,
+            has_implicit_tid: self.has_implicit_tid,
+            has_implicit_tname: self.has_implicit_tname,
+        }
+    }
     pub fn build_codec(&self, root_pos: TypePos) -> Result<Arc<dyn Codec>, Error> {
         build_codec(Some(root_pos), self.descriptors).map_err(ProtocolEncodingError::with_source)
     }
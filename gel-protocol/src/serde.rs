@@ -0,0 +1,2148 @@
+/*!
+Decoding query results and encoding query arguments with [serde].
+
+[Deserializer] reads the binary data of a query result using its type
+descriptor, so any type implementing [Deserialize] can be used as a result
+without requesting JSON output from the server. Any type implementing
+[Serialize] can be used as arguments of a query.
+
+Both are most conveniently used through the [Serde] wrapper, which implements
+[Queryable] and [QueryArgs]:
+
+```rust,ignore
+#[derive(serde::Deserialize)]
+struct User {
+    name: String,
+    friends: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct Filter<'a> {
+    name: &'a str,
+}
+
+let users: Vec<Serde<User>> = client
+    .query(
+        "select User { name, friends := .friends.name } filter .name = <str>$name",
+        &Serde(Filter { name: "alice" }),
+    )
+    .await?;
+```
+
+Results are represented in the serde data model as follows:
+
+| Gel type | serde |
+|---|---|
+| objects, named tuples | maps (implicit fields and empty elements are skipped) |
+| tuples, arrays, sets | sequences |
+| `str`, enums | borrowed strings |
+| `bytes` | borrowed bytes |
+| integers, floats, `bool` | respective primitives |
+| `json` | the contents of the JSON document, or the raw text when a string is requested |
+| other scalars and ranges | as in [Value::to_json_lossless](crate::value::Value::to_json_lossless) |
+
+[Serializer] writes arguments directly in the binary format of the input
+descriptor of the query, converting every value to the type of the respective
+argument:
+
+| serde | Gel type |
+|---|---|
+| integers | integer types (range-checked), floats, `bigint`, `decimal`, `cfg::memory` |
+| floats | floats, `decimal`, integer types if the value is integral |
+| `bool` | `bool` |
+| strings | `str`, enums, and types parsed from their text form: `uuid`, `bigint`, `decimal`, date/time and duration types |
+| unit variants | `str`, enums |
+| bytes (e.g. with `serde_bytes`) | `bytes` and PostGIS types |
+| sequences | arrays, tuples, named tuples, `ext::pgvector::vector` |
+| maps, structures | named tuples, ranges (`lower`, `upper`, `inc_lower`, `inc_upper`, `empty`) |
+| any value | `json`, written with [serde_json] |
+
+Named arguments are taken from a map or structure and positional ones from
+either a sequence or a map with keys `"0"`, `"1"`, etc. An optional argument
+can be omitted or set to `None`.
+*/
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use ::serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use ::serde::ser;
+use ::serde::{forward_to_deserialize_any, Deserialize, Serialize};
+use bytes::{BufMut, BytesMut};
+use snafu::OptionExt;
+
+use gel_errors::{ClientEncodingError, Error, ErrorKind};
+
+use crate::codec::{self, build_codec};
+use crate::common::Cardinality;
+use crate::descriptors::{Descriptor, InputShapeElement, ShapeElement};
+use crate::descriptors::{TupleElement, TypePos, Typedesc};
+use crate::errors::{self, DecodeError, EncodeError};
+use crate::model::{range, BigInt, Decimal};
+use crate::query_arg::{DescriptorContext as InputContext, Encoder, QueryArgs};
+use crate::queryable::{Decoder, DescriptorContext, DescriptorMismatch, Queryable};
+use crate::serialization::decode::{DecodeArrayLike, DecodeTupleLike, RawCodec};
+use crate::value::Value;
+
+/// Wrapper that decodes query results with [Deserialize] and encodes query
+/// arguments with [Serialize].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Serde<T>(pub T);
+
+/// Deserializes a value from the binary data of a query result.
+pub fn from_slice<'de, T>(typedesc: &Typedesc, data: &'de [u8]) -> Result<T, DecodeError>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(Deserializer {
+        descriptors: typedesc.descriptors(),
+        pos: typedesc.root_pos(),
+        buf: Some(data),
+    })
+}
+
+/// Serde deserializer of a single value in the binary protocol format.
+pub struct Deserializer<'d, 'de> {
+    descriptors: &'d [Descriptor],
+    pos: Option<TypePos>,
+    buf: Option<&'de [u8]>,
+}
+
+impl<'d, 'de> Deserializer<'d, 'de> {
+    /// Creates a deserializer for the data of the type at `pos` in
+    /// `descriptors`.
+    pub fn new(descriptors: &'d [Descriptor], pos: TypePos, buf: &'de [u8]) -> Self {
+        Deserializer {
+            descriptors,
+            pos: Some(pos),
+            buf: Some(buf),
+        }
+    }
+
+    fn child(&self, pos: TypePos, buf: Option<&'de [u8]>) -> Self {
+        Deserializer {
+            descriptors: self.descriptors,
+            pos: Some(pos),
+            buf,
+        }
+    }
+
+    fn descriptor(&self, pos: TypePos) -> Result<&'d Descriptor, DecodeError> {
+        self.descriptors.get(pos.0 as usize).ok_or_else(|| {
+            errors::decode_error(errors::UnexpectedTypePos { position: pos.0 }.build())
+        })
+    }
+
+    /// Resolves custom scalars to the id of their base scalar.
+    fn base_scalar(&self, mut pos: TypePos) -> Result<Option<&'d uuid::Uuid>, DecodeError> {
+        loop {
+            match self.descriptor(pos)? {
+                Descriptor::BaseScalar(d) => return Ok(Some(&d.id)),
+                Descriptor::Scalar(d) => match d.base_type_pos {
+                    Some(base) => pos = base,
+                    None => return Ok(Some(&d.id)),
+                },
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// Decodes the value into a [Value](crate::value::Value) and deserializes
+    /// its JSON representation.
+    fn lossless<V: Visitor<'de>>(
+        &self,
+        pos: TypePos,
+        buf: &[u8],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let codec = build_codec(Some(pos), self.descriptors).map_err(errors::decode_error)?;
+        let json = codec.decode(buf)?.to_json_lossless();
+        de::Deserializer::deserialize_any(json, visitor).map_err(deserialize_error)
+    }
+
+    fn scalar<V: Visitor<'de>>(
+        &self,
+        pos: TypePos,
+        id: &uuid::Uuid,
+        buf: &'de [u8],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        match *id {
+            codec::STD_STR | codec::STD_PG_JSON => {
+                visitor.visit_borrowed_str(RawCodec::decode(buf)?)
+            }
+            codec::STD_BYTES => visitor.visit_borrowed_bytes(buf),
+            codec::STD_INT16 => visitor.visit_i16(RawCodec::decode(buf)?),
+            codec::STD_INT32 => visitor.visit_i32(RawCodec::decode(buf)?),
+            codec::STD_INT64 => visitor.visit_i64(RawCodec::decode(buf)?),
+            codec::STD_FLOAT32 => visitor.visit_f32(RawCodec::decode(buf)?),
+            codec::STD_FLOAT64 => visitor.visit_f64(RawCodec::decode(buf)?),
+            codec::STD_BOOL => visitor.visit_bool(RawCodec::decode(buf)?),
+            codec::STD_UUID => {
+                let uuid: uuid::Uuid = RawCodec::decode(buf)?;
+                visitor.visit_string(uuid.to_string())
+            }
+            codec::STD_JSON => {
+                let mut json = serde_json::Deserializer::from_str(json_text(buf)?);
+                let value = de::Deserializer::deserialize_any(&mut json, visitor)
+                    .map_err(deserialize_error)?;
+                json.end().map_err(deserialize_error)?;
+                Ok(value)
+            }
+            _ => self.lossless(pos, buf, visitor),
+        }
+    }
+
+    /// Returns text of string-like scalars and enums.
+    fn text(&self) -> Result<Option<&'de str>, DecodeError> {
+        let (Some(pos), Some(buf)) = (self.pos, self.buf) else {
+            return Ok(None);
+        };
+        if let Descriptor::Enumeration(_) = self.descriptor(pos)? {
+            return RawCodec::decode(buf).map(Some);
+        }
+        match self.base_scalar(pos)? {
+            Some(&codec::STD_STR) | Some(&codec::STD_PG_JSON) => RawCodec::decode(buf).map(Some),
+            Some(&codec::STD_JSON) => json_text(buf).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn deserialize_error(error: impl Display) -> DecodeError {
+    de::Error::custom(error)
+}
+
+fn json_text(buf: &[u8]) -> Result<&str, DecodeError> {
+    match buf.split_first() {
+        Some((1, text)) => RawCodec::decode(text),
+        _ => errors::InvalidJsonFormat.fail(),
+    }
+}
+
+/// Strips the header that wraps arrays nested in sets.
+fn array_in_set(buf: &[u8]) -> Result<&[u8], DecodeError> {
+    let mut elements = DecodeTupleLike::new_tuple(buf, 1)?;
+    elements
+        .read()?
+        .ok_or_else(|| errors::InvalidArrayShape.build())
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_, 'de> {
+    type Error = DecodeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        let Some(pos) = self.pos else {
+            return visitor.visit_unit();
+        };
+        let Some(buf) = self.buf else {
+            return visitor.visit_none();
+        };
+        match self.descriptor(pos)? {
+            Descriptor::BaseScalar(_) | Descriptor::Scalar(_) => {
+                let id = self.base_scalar(pos)?.expect("descriptor is a scalar");
+                self.scalar(pos, id, buf, visitor)
+            }
+            Descriptor::Enumeration(_) => visitor.visit_borrowed_str(RawCodec::decode(buf)?),
+            Descriptor::ObjectShape(d) => {
+                let mut elements = DecodeTupleLike::new_object(buf, d.elements.len())?;
+                let mut fields = Vec::with_capacity(d.elements.len());
+                for element in &d.elements {
+                    let value = elements.read()?;
+                    if !element.flag_implicit {
+                        fields.push((&element.name[..], element.type_pos, value));
+                    }
+                }
+                visitor.visit_map(MapAccess::new(self, fields))
+            }
+            Descriptor::SQLRow(d) => {
+                let mut elements = DecodeTupleLike::new_object(buf, d.elements.len())?;
+                let mut fields = Vec::with_capacity(d.elements.len());
+                for element in &d.elements {
+                    fields.push((&element.name[..], element.type_pos, elements.read()?));
+                }
+                visitor.visit_map(MapAccess::new(self, fields))
+            }
+            Descriptor::NamedTuple(d) => {
+                let mut elements = DecodeTupleLike::new_tuple(buf, d.elements.len())?;
+                let mut fields = Vec::with_capacity(d.elements.len());
+                for TupleElement { name, type_pos } in &d.elements {
+                    fields.push((&name[..], *type_pos, elements.read()?));
+                }
+                visitor.visit_map(MapAccess::new(self, fields))
+            }
+            Descriptor::Tuple(d) => {
+                let mut elements = DecodeTupleLike::new_tuple(buf, d.element_types.len())?;
+                let mut items = Vec::with_capacity(d.element_types.len());
+                for type_pos in &d.element_types {
+                    items.push((*type_pos, elements.read()?));
+                }
+                visitor.visit_seq(SeqAccess::new(self, items))
+            }
+            Descriptor::Array(d) => {
+                let items = DecodeArrayLike::new_array(buf)?
+                    .map(|item| Ok((d.type_pos, Some(item?))))
+                    .collect::<Result<_, DecodeError>>()?;
+                visitor.visit_seq(SeqAccess::new(self, items))
+            }
+            Descriptor::Set(d) => {
+                let nested_array = matches!(self.descriptor(d.type_pos)?, Descriptor::Array(_));
+                let items = DecodeArrayLike::new_set(buf)?
+                    .map(|item| {
+                        let item = if nested_array {
+                            array_in_set(item?)?
+                        } else {
+                            item?
+                        };
+                        Ok((d.type_pos, Some(item)))
+                    })
+                    .collect::<Result<_, DecodeError>>()?;
+                visitor.visit_seq(SeqAccess::new(self, items))
+            }
+            Descriptor::Range(_) | Descriptor::MultiRange(_) => self.lossless(pos, buf, visitor),
+            Descriptor::Object(_) | Descriptor::Compound(_) => visitor.visit_unit(),
+            desc @ (Descriptor::InputShape(_) | Descriptor::TypeAnnotation(_)) => Err(
+                de::Error::custom(format_args!("unsupported descriptor {desc:?}")),
+            ),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.buf {
+            Some(_) => visitor.visit_some(self),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.text()? {
+            Some(text) => visitor.visit_borrowed_str(text),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        if let (Some(pos), Some(buf)) = (self.pos, self.buf) {
+            if self.base_scalar(pos)? == Some(&codec::STD_JSON) {
+                let mut json = serde_json::Deserializer::from_str(json_text(buf)?);
+                let value = de::Deserializer::deserialize_enum(&mut json, name, variants, visitor)
+                    .map_err(deserialize_error)?;
+                json.end().map_err(deserialize_error)?;
+                return Ok(value);
+            }
+        }
+        match self.text()? {
+            Some(text) => visitor.visit_enum(text.into_deserializer()),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct MapAccess<'d, 'de> {
+    parent: Deserializer<'d, 'de>,
+    fields: std::vec::IntoIter<(&'d str, TypePos, Option<&'de [u8]>)>,
+    value: Option<(TypePos, &'de [u8])>,
+}
+
+impl<'d, 'de> MapAccess<'d, 'de> {
+    fn new(
+        parent: Deserializer<'d, 'de>,
+        mut fields: Vec<(&'d str, TypePos, Option<&'de [u8]>)>,
+    ) -> Self {
+        // Empty elements are skipped, so that they are handled as missing
+        // fields: `None` for options and `#[serde(default)]` for others.
+        fields.retain(|(_, _, value)| value.is_some());
+        MapAccess {
+            parent,
+            fields: fields.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+    type Error = DecodeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DecodeError> {
+        let Some((name, pos, value)) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.value = value.map(|value| (pos, value));
+        seed.deserialize(name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (pos, value) = self
+            .value
+            .take()
+            .expect("next_value_seed is called after next_key_seed");
+        seed.deserialize(self.parent.child(pos, Some(value)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+struct SeqAccess<'d, 'de> {
+    parent: Deserializer<'d, 'de>,
+    items: std::vec::IntoIter<(TypePos, Option<&'de [u8]>)>,
+}
+
+impl<'d, 'de> SeqAccess<'d, 'de> {
+    fn new(parent: Deserializer<'d, 'de>, items: Vec<(TypePos, Option<&'de [u8]>)>) -> Self {
+        SeqAccess {
+            parent,
+            items: items.into_iter(),
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+    type Error = DecodeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DecodeError> {
+        match self.items.next() {
+            Some((pos, value)) => seed.deserialize(self.parent.child(pos, value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+impl de::Error for DecodeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        errors::Deserialize {
+            message: msg.to_string(),
+        }
+        .build()
+    }
+}
+
+impl<'a, T: Deserialize<'a>> Queryable<'a> for Serde<T> {
+    type Args = (Arc<[Descriptor]>, TypePos);
+
+    fn decode(
+        _decoder: &Decoder,
+        (descriptors, pos): &Self::Args,
+        buf: &'a [u8],
+    ) -> Result<Self, DecodeError> {
+        T::deserialize(Deserializer::new(descriptors, *pos, buf)).map(Serde)
+    }
+
+    fn decode_optional(
+        _decoder: &Decoder,
+        (descriptors, pos): &Self::Args,
+        buf: Option<&'a [u8]>,
+    ) -> Result<Self, DecodeError> {
+        T::deserialize(Deserializer {
+            descriptors,
+            pos: Some(*pos),
+            buf,
+        })
+        .map(Serde)
+    }
+
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<Self::Args, DescriptorMismatch> {
+        check_descriptor(ctx, type_pos)?;
+        Ok((ctx.descriptors().into(), type_pos))
+    }
+}
+
+/// Checks that values of the type at `pos` can be deserialized.
+///
+/// The shape of `T` is only known while deserializing, so this only rejects
+/// descriptors that [Deserializer] can't decode at all.
+fn check_descriptor(ctx: &DescriptorContext, pos: TypePos) -> Result<(), DescriptorMismatch> {
+    let desc = ctx.get(pos)?;
+    match desc {
+        Descriptor::BaseScalar(d) => {
+            codec::scalar_codec(&d.id).map_err(|_| ctx.wrong_type(desc, "a known scalar"))?;
+        }
+        Descriptor::Scalar(d) => match d.base_type_pos {
+            Some(base) => check_descriptor(ctx, base)?,
+            None => {
+                codec::scalar_codec(&d.id).map_err(|_| ctx.wrong_type(desc, "a known scalar"))?;
+            }
+        },
+        Descriptor::Enumeration(_) | Descriptor::Object(_) | Descriptor::Compound(_) => {}
+        Descriptor::ObjectShape(d) => {
+            for element in &d.elements {
+                check_descriptor(ctx, element.type_pos)?;
+            }
+        }
+        Descriptor::SQLRow(d) => {
+            for element in &d.elements {
+                check_descriptor(ctx, element.type_pos)?;
+            }
+        }
+        Descriptor::NamedTuple(d) => {
+            for element in &d.elements {
+                check_descriptor(ctx, element.type_pos)?;
+            }
+        }
+        Descriptor::Tuple(d) => {
+            for type_pos in &d.element_types {
+                check_descriptor(ctx, *type_pos)?;
+            }
+        }
+        Descriptor::Array(d) => check_descriptor(ctx, d.type_pos)?,
+        Descriptor::Set(d) => check_descriptor(ctx, d.type_pos)?,
+        Descriptor::Range(d) => check_descriptor(ctx, d.type_pos)?,
+        Descriptor::MultiRange(d) => check_descriptor(ctx, d.type_pos)?,
+        Descriptor::InputShape(_) | Descriptor::TypeAnnotation(_) => {
+            return Err(ctx.wrong_type(desc, "an output type"));
+        }
+    }
+    Ok(())
+}
+
+impl<T: Serialize + Send + Sync> QueryArgs for Serde<T> {
+    fn encode(&self, encoder: &mut Encoder) -> Result<(), Error> {
+        self.0
+            .serialize(Serializer::new(encoder))
+            .map_err(ClientEncodingError::with_source)?;
+        Ok(())
+    }
+    fn to_args_value(&self) -> Result<Value, Error> {
+        // types of arguments are only known from the descriptor
+        serde_json::to_value(&self.0)
+            .map_err(ClientEncodingError::with_source)?
+            .to_args_value()
+    }
+}
+
+/// Serde serializer writing query arguments in the binary protocol format.
+///
+/// Every value is converted to the type of the respective argument in the
+/// input descriptor of the query while it is written, see the
+/// [module documentation](self) for the accepted values.
+pub struct Serializer<'a, 'b> {
+    ctx: &'a InputContext<'a>,
+    target: Target,
+    buf: &'b mut BytesMut,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    /// The arguments described by the root input descriptor.
+    Arguments,
+    /// A value of the type at the position.
+    Type(TypePos),
+    /// A value of a scalar type that has no descriptor: an element of a
+    /// vector or a flag of a range.
+    Scalar(uuid::Uuid),
+}
+
+/// The type a value is serialized as.
+enum Kind<'a> {
+    NoArguments,
+    Arguments(&'a [ShapeElement]),
+    SparseArguments(&'a [InputShapeElement]),
+    Scalar(uuid::Uuid),
+    Enum(&'a [String]),
+    Array(TypePos),
+    Tuple(&'a [TypePos]),
+    NamedTuple(&'a [TupleElement]),
+    Range(TypePos),
+    Unsupported(&'a Descriptor),
+}
+
+fn serialize_error(message: impl Display) -> EncodeError {
+    ser::Error::custom(message)
+}
+
+fn scalar_name(id: &uuid::Uuid) -> &'static str {
+    match *id {
+        codec::STD_UUID => "uuid",
+        codec::STD_STR => "str",
+        codec::STD_BYTES => "bytes",
+        codec::STD_INT16 => "int16",
+        codec::STD_INT32 => "int32",
+        codec::STD_INT64 => "int64",
+        codec::STD_FLOAT32 => "float32",
+        codec::STD_FLOAT64 => "float64",
+        codec::STD_DECIMAL => "decimal",
+        codec::STD_BOOL => "bool",
+        codec::STD_DATETIME => "datetime",
+        codec::CAL_LOCAL_DATETIME => "cal::local_datetime",
+        codec::CAL_LOCAL_DATE => "cal::local_date",
+        codec::CAL_LOCAL_TIME => "cal::local_time",
+        codec::STD_DURATION => "duration",
+        codec::CAL_RELATIVE_DURATION => "cal::relative_duration",
+        codec::CAL_DATE_DURATION => "cal::date_duration",
+        codec::STD_JSON => "json",
+        codec::STD_BIGINT => "bigint",
+        codec::CFG_MEMORY => "cfg::memory",
+        codec::PGVECTOR_VECTOR => "ext::pgvector::vector",
+        codec::POSTGIS_GEOMETRY => "ext::postgis::geometry",
+        codec::POSTGIS_GEOGRAPHY => "ext::postgis::geography",
+        codec::POSTGIS_BOX_2D => "ext::postgis::box2d",
+        codec::POSTGIS_BOX_3D => "ext::postgis::box3d",
+        _ => "scalar",
+    }
+}
+
+fn mismatch(kind: &Kind, got: &str) -> EncodeError {
+    let expected = match kind {
+        Kind::NoArguments => "no arguments",
+        Kind::Arguments(_) | Kind::SparseArguments(_) => "arguments",
+        Kind::Scalar(id) => scalar_name(id),
+        Kind::Enum(_) => "enum value",
+        Kind::Array(_) => "array",
+        Kind::Tuple(_) => "tuple",
+        Kind::NamedTuple(_) => "named tuple",
+        Kind::Range(_) => "range",
+        Kind::Unsupported(desc) => {
+            return serialize_error(format_args!("arguments of type {desc:?} are not supported"))
+        }
+    };
+    serialize_error(format_args!("expected {expected}, got {got}"))
+}
+
+/// Adds the name of the argument to errors of its value.
+fn in_argument(name: &str, error: EncodeError) -> EncodeError {
+    let message = match error {
+        EncodeError::Serialize { message, .. } => message,
+        error => error.to_string(),
+    };
+    serialize_error(format_args!("invalid argument `${name}`: {message}"))
+}
+
+fn parse<T: std::str::FromStr>(s: &str, id: &uuid::Uuid) -> Result<T, EncodeError> {
+    s.parse()
+        .map_err(|_| serialize_error(format_args!("{s:?} is not a valid {}", scalar_name(id))))
+}
+
+/// Writes a value prefixed with its length, or nothing if there is no value.
+fn write_element<T: ?Sized + Serialize>(
+    ctx: &InputContext,
+    buf: &mut BytesMut,
+    target: Target,
+    value: &T,
+) -> Result<bool, EncodeError> {
+    let pos = buf.len();
+    buf.put_u32(0); // replaced after serializing a value
+    if !(Serializer { ctx, target, buf }).value(value)? {
+        buf.truncate(pos);
+        return Ok(false);
+    }
+    let len = buf.len() - pos - 4;
+    buf[pos..pos + 4].copy_from_slice(
+        &i32::try_from(len)
+            .ok()
+            .context(errors::ElementTooLong)?
+            .to_be_bytes(),
+    );
+    Ok(true)
+}
+
+impl<'a, 'b> Serializer<'a, 'b> {
+    /// Creates a serializer of query arguments, writing to the encoder.
+    pub fn new(encoder: &'b mut Encoder<'a>) -> Self {
+        Serializer {
+            ctx: encoder.ctx,
+            target: Target::Arguments,
+            buf: encoder.buf,
+        }
+    }
+
+    fn kind(&self) -> Result<Kind<'a>, EncodeError> {
+        let mut pos = match self.target {
+            Target::Scalar(id) => return Ok(Kind::Scalar(id)),
+            Target::Type(pos) => pos,
+            Target::Arguments => match self.ctx.root_pos {
+                None => return Ok(Kind::NoArguments),
+                Some(pos) => match self.descriptor(pos)? {
+                    Descriptor::ObjectShape(d) => return Ok(Kind::Arguments(&d.elements)),
+                    Descriptor::InputShape(d) => return Ok(Kind::SparseArguments(&d.elements)),
+                    _ => pos,
+                },
+            },
+        };
+        loop {
+            let kind = match self.descriptor(pos)? {
+                Descriptor::BaseScalar(d) => Kind::Scalar(*d.id),
+                Descriptor::Scalar(d) => match d.base_type_pos {
+                    Some(base) => {
+                        pos = base;
+                        continue;
+                    }
+                    None => Kind::Scalar(*d.id),
+                },
+                Descriptor::Enumeration(d) => Kind::Enum(&d.members),
+                Descriptor::Array(d) => Kind::Array(d.type_pos),
+                Descriptor::Tuple(d) => Kind::Tuple(&d.element_types),
+                Descriptor::NamedTuple(d) => Kind::NamedTuple(&d.elements),
+                Descriptor::Range(d) => Kind::Range(d.type_pos),
+                desc => Kind::Unsupported(desc),
+            };
+            return Ok(kind);
+        }
+    }
+
+    fn descriptor(&self, pos: TypePos) -> Result<&'a Descriptor, EncodeError> {
+        self.ctx
+            .descriptors
+            .get(pos.0 as usize)
+            .ok_or_else(|| serialize_error("invalid type descriptor"))
+    }
+
+    /// Serializes a value, writing `json` values with [serde_json]. Returns
+    /// whether a value was written, which is not the case for `None`.
+    fn value<T: ?Sized + Serialize>(self, value: &T) -> Result<bool, EncodeError> {
+        if !matches!(self.kind()?, Kind::Scalar(codec::STD_JSON)) {
+            return value.serialize(self);
+        }
+        let pos = self.buf.len();
+        self.buf.put_u8(1);
+        serde_json::to_writer((&mut *self.buf).writer(), value).map_err(serialize_error)?;
+        // `null` is a missing value, as with JSON arguments
+        if self.buf[pos + 1..] == *b"null" {
+            self.buf.truncate(pos);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn encode(self, id: uuid::Uuid, value: Value) -> Result<bool, EncodeError> {
+        codec::scalar_codec(&id)
+            .map_err(serialize_error)?
+            .encode(self.buf, &value)?;
+        Ok(true)
+    }
+
+    fn integer(self, v: i128) -> Result<bool, EncodeError> {
+        let kind = self.kind()?;
+        let Kind::Scalar(id) = kind else {
+            return Err(mismatch(&kind, "integer"));
+        };
+        let out_of_range =
+            || serialize_error(format_args!("{v} is out of range for {}", scalar_name(&id)));
+        match id {
+            codec::STD_INT16 => self
+                .buf
+                .put_i16(i16::try_from(v).map_err(|_| out_of_range())?),
+            codec::STD_INT32 => self
+                .buf
+                .put_i32(i32::try_from(v).map_err(|_| out_of_range())?),
+            codec::STD_INT64 | codec::CFG_MEMORY => self
+                .buf
+                .put_i64(i64::try_from(v).map_err(|_| out_of_range())?),
+            codec::STD_FLOAT32 => self.buf.put_f32(v as f32),
+            codec::STD_FLOAT64 => self.buf.put_f64(v as f64),
+            codec::STD_BIGINT => {
+                let v = match i64::try_from(v) {
+                    Ok(v) => BigInt::from(v),
+                    Err(_) => BigInt::parse(&v.to_string()).ok_or_else(out_of_range)?,
+                };
+                return self.encode(id, Value::BigInt(v));
+            }
+            codec::STD_DECIMAL => {
+                let v = Decimal::parse(&v.to_string()).ok_or_else(out_of_range)?;
+                return self.encode(id, Value::Decimal(v));
+            }
+            _ => return Err(mismatch(&kind, "integer")),
+        }
+        Ok(true)
+    }
+
+    fn float(self, v: f64) -> Result<bool, EncodeError> {
+        let kind = self.kind()?;
+        let Kind::Scalar(id) = kind else {
+            return Err(mismatch(&kind, "float"));
+        };
+        match id {
+            codec::STD_FLOAT32 => {
+                if v.is_finite() && v.abs() > f32::MAX as f64 {
+                    return Err(serialize_error(format_args!(
+                        "{v} is out of range for float32"
+                    )));
+                }
+                self.buf.put_f32(v as f32);
+            }
+            codec::STD_FLOAT64 => self.buf.put_f64(v),
+            codec::STD_DECIMAL => {
+                let v = Decimal::parse(&v.to_string()).ok_or_else(|| {
+                    serialize_error(format_args!("{v} is not a valid decimal"))
+                })?;
+                return self.encode(id, Value::Decimal(v));
+            }
+            codec::STD_INT16
+            | codec::STD_INT32
+            | codec::STD_INT64
+            | codec::STD_BIGINT
+            | codec::CFG_MEMORY
+                // integers written as floats, e.g. `3.0`
+                if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 =>
+            {
+                return self.integer(v as i128);
+            }
+            _ => return Err(mismatch(&kind, "float")),
+        }
+        Ok(true)
+    }
+}
+
+impl<'a, 'b> ser::Serializer for Serializer<'a, 'b> {
+    type Ok = bool;
+    type Error = EncodeError;
+
+    type SerializeSeq = Compound<'a, 'b>;
+    type SerializeTuple = Compound<'a, 'b>;
+    type SerializeTupleStruct = Compound<'a, 'b>;
+    type SerializeTupleVariant = ser::Impossible<bool, EncodeError>;
+    type SerializeMap = Compound<'a, 'b>;
+    type SerializeStruct = Compound<'a, 'b>;
+    type SerializeStructVariant = ser::Impossible<bool, EncodeError>;
+
+    fn serialize_bool(self, v: bool) -> Result<bool, EncodeError> {
+        match self.kind()? {
+            Kind::Scalar(codec::STD_BOOL) => self.buf.put_u8(v as u8),
+            kind => return Err(mismatch(&kind, "boolean")),
+        }
+        Ok(true)
+    }
+    fn serialize_i8(self, v: i8) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_i16(self, v: i16) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_i32(self, v: i32) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_i64(self, v: i64) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_i128(self, v: i128) -> Result<bool, EncodeError> {
+        self.integer(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_u16(self, v: u16) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_u32(self, v: u32) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_u64(self, v: u64) -> Result<bool, EncodeError> {
+        self.integer(v.into())
+    }
+    fn serialize_u128(self, v: u128) -> Result<bool, EncodeError> {
+        let v =
+            i128::try_from(v).map_err(|_| serialize_error(format_args!("{v} is out of range")))?;
+        self.integer(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<bool, EncodeError> {
+        self.float(v.into())
+    }
+    fn serialize_f64(self, v: f64) -> Result<bool, EncodeError> {
+        self.float(v)
+    }
+    fn serialize_char(self, v: char) -> Result<bool, EncodeError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+    fn serialize_str(self, v: &str) -> Result<bool, EncodeError> {
+        let kind = self.kind()?;
+        let id = match kind {
+            Kind::Scalar(codec::STD_STR) => {
+                self.buf.extend_from_slice(v.as_bytes());
+                return Ok(true);
+            }
+            Kind::Enum(members) => {
+                if !members.iter().any(|member| member == v) {
+                    return Err(serialize_error(format_args!(
+                        "{v:?} is not a member of the enum"
+                    )));
+                }
+                self.buf.extend_from_slice(v.as_bytes());
+                return Ok(true);
+            }
+            Kind::Scalar(id) => id,
+            kind => return Err(mismatch(&kind, "string")),
+        };
+        let value = match id {
+            codec::STD_UUID => {
+                let uuid: uuid::Uuid = parse(v, &id)?;
+                self.buf.extend_from_slice(uuid.as_bytes());
+                return Ok(true);
+            }
+            codec::STD_BIGINT => Value::BigInt(
+                BigInt::parse(v)
+                    .ok_or_else(|| serialize_error(format_args!("{v:?} is not a valid bigint")))?,
+            ),
+            codec::STD_DECIMAL => Value::Decimal(
+                Decimal::parse(v)
+                    .ok_or_else(|| serialize_error(format_args!("{v:?} is not a valid decimal")))?,
+            ),
+            codec::STD_DATETIME => Value::Datetime(parse(v, &id)?),
+            codec::CAL_LOCAL_DATETIME => Value::LocalDatetime(parse(v, &id)?),
+            codec::CAL_LOCAL_DATE => Value::LocalDate(parse(v, &id)?),
+            codec::CAL_LOCAL_TIME => Value::LocalTime(parse(v, &id)?),
+            codec::STD_DURATION => Value::Duration(parse(v, &id)?),
+            codec::CAL_RELATIVE_DURATION => Value::RelativeDuration(parse(v, &id)?),
+            codec::CAL_DATE_DURATION => Value::DateDuration(parse(v, &id)?),
+            _ => return Err(mismatch(&kind, "string")),
+        };
+        self.encode(id, value)
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<bool, EncodeError> {
+        match self.kind()? {
+            Kind::Scalar(
+                codec::STD_BYTES
+                | codec::POSTGIS_GEOMETRY
+                | codec::POSTGIS_GEOGRAPHY
+                | codec::POSTGIS_BOX_2D
+                | codec::POSTGIS_BOX_3D,
+            ) => self.buf.extend_from_slice(v),
+            kind => return Err(mismatch(&kind, "bytes")),
+        }
+        Ok(true)
+    }
+    fn serialize_none(self) -> Result<bool, EncodeError> {
+        match self.target {
+            // no arguments are provided
+            Target::Arguments => ser::SerializeMap::end(self.serialize_map(Some(0))?),
+            _ => Ok(false),
+        }
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<bool, EncodeError> {
+        self.value(value)
+    }
+    fn serialize_unit(self) -> Result<bool, EncodeError> {
+        self.serialize_none()
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<bool, EncodeError> {
+        self.serialize_none()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<bool, EncodeError> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<bool, EncodeError> {
+        self.value(value)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<bool, EncodeError> {
+        Err(mismatch(&self.kind()?, &format!("enum variant {variant}")))
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, 'b>, EncodeError> {
+        let state = match self.kind()? {
+            Kind::NoArguments => State::NoArguments,
+            Kind::Arguments(elements) => State::fields(Fields::Arguments(elements)),
+            Kind::SparseArguments(elements) => State::sparse(elements, self.buf),
+            Kind::Array(element) => {
+                self.buf.reserve(20);
+                self.buf.put_u32(1); // ndims
+                self.buf.put_u32(0); // reserved0
+                self.buf.put_u32(0); // reserved1
+                let count_pos = self.buf.len();
+                self.buf.put_u32(0); // replaced after serializing the items
+                self.buf.put_u32(1); // lower
+                State::Array {
+                    element,
+                    count_pos,
+                    count: 0,
+                }
+            }
+            Kind::Scalar(codec::PGVECTOR_VECTOR) => {
+                let count_pos = self.buf.len();
+                self.buf.put_i16(0); // replaced after serializing the items
+                self.buf.put_i16(0); // reserved
+                State::Vector {
+                    count_pos,
+                    count: 0,
+                }
+            }
+            Kind::Tuple(elements) => State::tuple(Items::Tuple(elements), self.buf),
+            Kind::NamedTuple(elements) => State::tuple(Items::NamedTuple(elements), self.buf),
+            kind => return Err(mismatch(&kind, "sequence")),
+        };
+        Ok(Compound {
+            ctx: self.ctx,
+            buf: self.buf,
+            state,
+            key: None,
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, 'b>, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, 'b>, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, EncodeError> {
+        Err(mismatch(&self.kind()?, &format!("enum variant {variant}")))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, 'b>, EncodeError> {
+        let state = match self.kind()? {
+            Kind::NoArguments => State::NoArguments,
+            Kind::Arguments(elements) => State::fields(Fields::Arguments(elements)),
+            Kind::SparseArguments(elements) => State::sparse(elements, self.buf),
+            Kind::NamedTuple(elements) => State::fields(Fields::NamedTuple(elements)),
+            Kind::Range(element) => State::Range {
+                element,
+                scratch: BytesMut::new(),
+                lower: None,
+                upper: None,
+                inc_lower: true,
+                inc_upper: false,
+                empty: false,
+            },
+            kind => return Err(mismatch(&kind, "map")),
+        };
+        Ok(Compound {
+            ctx: self.ctx,
+            buf: self.buf,
+            state,
+            key: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, 'b>, EncodeError> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EncodeError> {
+        Err(mismatch(&self.kind()?, &format!("enum variant {variant}")))
+    }
+}
+
+/// Sequences, maps and structures being serialized by [Serializer].
+#[doc(hidden)]
+pub struct Compound<'a, 'b> {
+    ctx: &'a InputContext<'a>,
+    buf: &'b mut BytesMut,
+    state: State<'a>,
+    /// The key of the map entry whose value is serialized next.
+    key: Option<String>,
+}
+
+enum State<'a> {
+    NoArguments,
+    Array {
+        element: TypePos,
+        count_pos: usize,
+        count: u32,
+    },
+    Vector {
+        count_pos: usize,
+        count: u16,
+    },
+    Tuple {
+        items: Items<'a>,
+        index: usize,
+    },
+    /// Fields are serialized into `scratch` and written in the order of the
+    /// descriptor at the end.
+    Fields {
+        fields: Fields<'a>,
+        scratch: BytesMut,
+        slots: Vec<Option<std::ops::Range<usize>>>,
+        index: usize,
+    },
+    /// Fields are written in any order, preceded by their index.
+    Sparse {
+        elements: &'a [InputShapeElement],
+        count_pos: usize,
+        count: u32,
+        index: usize,
+    },
+    Range {
+        element: TypePos,
+        scratch: BytesMut,
+        lower: Option<std::ops::Range<usize>>,
+        upper: Option<std::ops::Range<usize>>,
+        inc_lower: bool,
+        inc_upper: bool,
+        empty: bool,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum Items<'a> {
+    Tuple(&'a [TypePos]),
+    NamedTuple(&'a [TupleElement]),
+}
+
+impl Items<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Items::Tuple(elements) => elements.len(),
+            Items::NamedTuple(elements) => elements.len(),
+        }
+    }
+    fn get(&self, index: usize) -> Option<TypePos> {
+        match self {
+            Items::Tuple(elements) => elements.get(index).copied(),
+            Items::NamedTuple(elements) => elements.get(index).map(|el| el.type_pos),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Fields<'a> {
+    Arguments(&'a [ShapeElement]),
+    NamedTuple(&'a [TupleElement]),
+}
+
+impl Fields<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Fields::Arguments(elements) => elements.len(),
+            Fields::NamedTuple(elements) => elements.len(),
+        }
+    }
+    fn name(&self, index: usize) -> &str {
+        match self {
+            Fields::Arguments(elements) => &elements[index].name,
+            Fields::NamedTuple(elements) => &elements[index].name,
+        }
+    }
+    fn find(&self, name: &str) -> Option<(usize, TypePos)> {
+        match self {
+            Fields::Arguments(elements) => elements
+                .iter()
+                .position(|el| el.name == name)
+                .map(|index| (index, elements[index].type_pos)),
+            Fields::NamedTuple(elements) => elements
+                .iter()
+                .position(|el| el.name == name)
+                .map(|index| (index, elements[index].type_pos)),
+        }
+    }
+}
+
+impl<'a> State<'a> {
+    fn fields(fields: Fields<'a>) -> Self {
+        State::Fields {
+            fields,
+            scratch: BytesMut::new(),
+            slots: vec![None; fields.len()],
+            index: 0,
+        }
+    }
+
+    fn sparse(elements: &'a [InputShapeElement], buf: &mut BytesMut) -> Self {
+        let count_pos = buf.len();
+        buf.put_u32(0); // replaced after serializing the fields
+        State::Sparse {
+            elements,
+            count_pos,
+            count: 0,
+            index: 0,
+        }
+    }
+
+    fn tuple(items: Items<'a>, buf: &mut BytesMut) -> Self {
+        buf.put_u32(items.len() as u32);
+        State::Tuple { items, index: 0 }
+    }
+}
+
+fn no_arguments() -> EncodeError {
+    serialize_error("arguments were provided, but no arguments were expected by the server")
+}
+
+impl Compound<'_, '_> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        let (ctx, buf) = (self.ctx, &mut *self.buf);
+        match &mut self.state {
+            State::NoArguments => return Err(no_arguments()),
+            State::Array { element, count, .. } => {
+                if !write_element(ctx, buf, Target::Type(*element), value)? {
+                    return Err(serialize_error("expected array element, got none"));
+                }
+                *count = count.checked_add(1).context(errors::ArrayTooLong)?;
+            }
+            State::Vector { count, .. } => {
+                let target = Target::Scalar(codec::STD_FLOAT32);
+                if !(Serializer { ctx, target, buf }).value(value)? {
+                    return Err(serialize_error("expected vector element, got none"));
+                }
+                *count = count.checked_add(1).context(errors::ArrayTooLong)?;
+            }
+            State::Tuple { items, index } => {
+                let pos = items.get(*index).ok_or_else(|| {
+                    serialize_error(format_args!(
+                        "expected tuple of {} elements, got more",
+                        items.len()
+                    ))
+                })?;
+                buf.put_u32(0); // reserved
+                if !write_element(ctx, buf, Target::Type(pos), value)? {
+                    return Err(serialize_error("expected tuple element, got none"));
+                }
+                *index += 1;
+            }
+            // positional arguments
+            State::Fields { index, .. } | State::Sparse { index, .. } => {
+                let name = index.to_string();
+                *index += 1;
+                return self.field(&name, value);
+            }
+            State::Range { .. } => return Err(serialize_error("expected range, got sequence")),
+        }
+        Ok(())
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, name: &str, value: &T) -> Result<(), EncodeError> {
+        let (ctx, buf) = (self.ctx, &mut *self.buf);
+        match &mut self.state {
+            State::Fields {
+                fields,
+                scratch,
+                slots,
+                ..
+            } => {
+                let Some((index, pos)) = fields.find(name) else {
+                    return Err(match fields {
+                        Fields::Arguments(_) => {
+                            serialize_error(format_args!("unexpected argument `${name}`"))
+                        }
+                        Fields::NamedTuple(_) => {
+                            serialize_error(format_args!("unexpected field `{name}`"))
+                        }
+                    });
+                };
+                let start = scratch.len();
+                let target = Target::Type(pos);
+                let written = Serializer {
+                    ctx,
+                    target,
+                    buf: scratch,
+                }
+                .value(value);
+                let written = match fields {
+                    Fields::Arguments(_) => written.map_err(|e| in_argument(name, e))?,
+                    Fields::NamedTuple(_) => written?,
+                };
+                slots[index] = written.then(|| start..scratch.len());
+            }
+            State::Sparse {
+                elements, count, ..
+            } => {
+                let Some(index) = elements.iter().position(|el| el.name == name) else {
+                    return Err(serialize_error(format_args!(
+                        "unexpected argument `${name}`"
+                    )));
+                };
+                buf.put_u32(index as u32);
+                let target = Target::Type(elements[index].type_pos);
+                if !write_element(ctx, buf, target, value).map_err(|e| in_argument(name, e))? {
+                    buf.put_i32(-1);
+                }
+                *count += 1;
+            }
+            State::Range {
+                element,
+                scratch,
+                lower,
+                upper,
+                inc_lower,
+                inc_upper,
+                empty,
+            } => {
+                let start = scratch.len();
+                let target = match name {
+                    "lower" | "upper" => Target::Type(*element),
+                    "inc_lower" | "inc_upper" | "empty" => Target::Scalar(codec::STD_BOOL),
+                    _ => return Err(serialize_error(format_args!("unexpected field `{name}`"))),
+                };
+                let written = Serializer {
+                    ctx,
+                    target,
+                    buf: scratch,
+                }
+                .value(value)?;
+                let flag = written && scratch[start] != 0;
+                match name {
+                    "lower" => *lower = written.then(|| start..scratch.len()),
+                    "upper" => *upper = written.then(|| start..scratch.len()),
+                    "inc_lower" => *inc_lower = !written || flag,
+                    "inc_upper" => *inc_upper = flag,
+                    _ => *empty = flag,
+                }
+                if !matches!(name, "lower" | "upper") {
+                    scratch.truncate(start);
+                }
+            }
+            State::NoArguments => return Err(no_arguments()),
+            _ => return Err(serialize_error("expected sequence, got map")),
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<bool, EncodeError> {
+        let Compound {
+            ctx, buf, state, ..
+        } = self;
+        match state {
+            State::NoArguments => {
+                QueryArgs::encode(&(), &mut Encoder::new(ctx, buf)).map_err(serialize_error)?;
+            }
+            State::Array {
+                count_pos, count, ..
+            } => {
+                buf[count_pos..count_pos + 4].copy_from_slice(&count.to_be_bytes());
+            }
+            State::Vector { count_pos, count } => {
+                buf[count_pos..count_pos + 2].copy_from_slice(&count.to_be_bytes());
+            }
+            State::Tuple { items, index } => {
+                if index != items.len() {
+                    return Err(serialize_error(format_args!(
+                        "expected tuple of {} elements, got {index}",
+                        items.len()
+                    )));
+                }
+            }
+            State::Fields {
+                fields,
+                scratch,
+                slots,
+                ..
+            } => {
+                buf.reserve(4 + 8 * slots.len() + scratch.len());
+                buf.put_u32(slots.len() as u32);
+                for (index, slot) in slots.into_iter().enumerate() {
+                    buf.put_u32(0); // reserved
+                    match (slot, fields) {
+                        (Some(range), _) => {
+                            let len = i32::try_from(range.len())
+                                .ok()
+                                .context(errors::ElementTooLong)?;
+                            buf.put_i32(len);
+                            buf.extend_from_slice(&scratch[range]);
+                        }
+                        (None, Fields::Arguments(elements))
+                            if !matches!(
+                                elements[index].cardinality,
+                                Some(Cardinality::One) | Some(Cardinality::AtLeastOne)
+                            ) =>
+                        {
+                            buf.put_i32(-1);
+                        }
+                        (None, Fields::Arguments(_)) => {
+                            return Err(serialize_error(format_args!(
+                                "missing required argument `${}`",
+                                fields.name(index)
+                            )));
+                        }
+                        (None, Fields::NamedTuple(_)) => {
+                            return Err(serialize_error(format_args!(
+                                "missing field `{}`",
+                                fields.name(index)
+                            )));
+                        }
+                    }
+                }
+            }
+            State::Sparse {
+                count_pos, count, ..
+            } => {
+                buf[count_pos..count_pos + 4].copy_from_slice(&count.to_be_bytes());
+            }
+            State::Range {
+                scratch,
+                lower,
+                upper,
+                inc_lower,
+                inc_upper,
+                empty,
+                ..
+            } => {
+                if empty {
+                    buf.put_u8(range::EMPTY as u8);
+                    return Ok(true);
+                }
+                let flags = (if inc_lower { range::LB_INC } else { 0 })
+                    | (if inc_upper { range::UB_INC } else { 0 })
+                    | (if lower.is_none() { range::LB_INF } else { 0 })
+                    | (if upper.is_none() { range::UB_INF } else { 0 });
+                buf.put_u8(flags as u8);
+                for bound in [lower, upper].iter().flatten() {
+                    let len = u32::try_from(bound.len())
+                        .ok()
+                        .context(errors::ElementTooLong)?;
+                    buf.put_u32(len);
+                    buf.extend_from_slice(&scratch[bound.clone()]);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_, '_> {
+    type Ok = bool;
+    type Error = EncodeError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<bool, EncodeError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_, '_> {
+    type Ok = bool;
+    type Error = EncodeError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<bool, EncodeError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_, '_> {
+    type Ok = bool;
+    type Error = EncodeError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.element(value)
+    }
+    fn end(self) -> Result<bool, EncodeError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_, '_> {
+    type Ok = bool;
+    type Error = EncodeError;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), EncodeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value is called after serialize_key");
+        self.field(&key, value)
+    }
+    fn end(self) -> Result<bool, EncodeError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_, '_> {
+    type Ok = bool;
+    type Error = EncodeError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        self.field(key, value)
+    }
+    fn end(self) -> Result<bool, EncodeError> {
+        Compound::end(self)
+    }
+}
+
+/// Serializes keys of maps, which name arguments and fields.
+struct KeySerializer;
+
+fn key_error() -> EncodeError {
+    serialize_error("map keys must be strings or integers")
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = EncodeError;
+
+    type SerializeSeq = ser::Impossible<String, EncodeError>;
+    type SerializeTuple = ser::Impossible<String, EncodeError>;
+    type SerializeTupleStruct = ser::Impossible<String, EncodeError>;
+    type SerializeTupleVariant = ser::Impossible<String, EncodeError>;
+    type SerializeMap = ser::Impossible<String, EncodeError>;
+    type SerializeStruct = ser::Impossible<String, EncodeError>;
+    type SerializeStructVariant = ser::Impossible<String, EncodeError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_i8(self, v: i8) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i16(self, v: i16) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i32(self, v: i32) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_i64(self, v: i64) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u8(self, v: u8) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u16(self, v: u16) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u32(self, v: u32) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_u64(self, v: u64) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_f32(self, _v: f32) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_f64(self, _v: f64) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_char(self, v: char) -> Result<String, EncodeError> {
+        Ok(v.to_string())
+    }
+    fn serialize_str(self, v: &str) -> Result<String, EncodeError> {
+        Ok(v.to_owned())
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_none(self) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_unit(self) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, EncodeError> {
+        Ok(variant.to_owned())
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, EncodeError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, EncodeError> {
+        Err(key_error())
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EncodeError> {
+        Err(key_error())
+    }
+}
+
+impl ser::Error for EncodeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        errors::Serialize {
+            message: msg.to_string(),
+        }
+        .build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bytes::BytesMut;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Deserializer, Serde};
+    use crate::codec::{self, build_codec, ObjectShape};
+    use crate::common::Cardinality;
+    use crate::descriptors::{ArrayTypeDescriptor, BaseScalarTypeDescriptor, Descriptor};
+    use crate::descriptors::{EnumerationTypeDescriptor, TupleTypeDescriptor, TypePos};
+    use crate::descriptors::{InputShapeElement, InputShapeTypeDescriptor, TupleElement};
+    use crate::descriptors::{NamedTupleTypeDescriptor, RangeTypeDescriptor};
+    use crate::descriptors::{ObjectShapeDescriptor, SetDescriptor, ShapeElement};
+    use crate::features::ProtocolVersion;
+    use crate::model::{Json, LocalDate, Uuid};
+    use crate::query_arg::{DescriptorContext, Encoder, QueryArgs};
+    use crate::queryable::{self, Queryable};
+    use crate::value::{EnumValue, Value};
+
+    fn scalar(id: Uuid) -> Descriptor {
+        Descriptor::BaseScalar(BaseScalarTypeDescriptor { id: id.into() })
+    }
+
+    fn element(name: &str, type_pos: u16, implicit: bool) -> ShapeElement {
+        ShapeElement {
+            flag_implicit: implicit,
+            flag_link_property: false,
+            flag_link: false,
+            cardinality: Some(Cardinality::AtMostOne),
+            name: name.into(),
+            type_pos: TypePos(type_pos),
+            source_type_pos: None,
+        }
+    }
+
+    fn descriptors() -> Vec<Descriptor> {
+        vec![
+            scalar(codec::STD_UUID),
+            scalar(codec::STD_STR),
+            scalar(codec::STD_INT64),
+            Descriptor::Array(ArrayTypeDescriptor {
+                id: Uuid::from_u128(0x1000).into(),
+                type_pos: TypePos(2),
+                dimensions: vec![None],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            Descriptor::Tuple(TupleTypeDescriptor {
+                id: Uuid::from_u128(0x1001).into(),
+                element_types: vec![TypePos(1), TypePos(2)],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            scalar(codec::STD_JSON),
+            scalar(codec::CAL_LOCAL_DATE),
+            Descriptor::Enumeration(EnumerationTypeDescriptor {
+                id: Uuid::from_u128(0x1002).into(),
+                members: vec!["Draft".into(), "Published".into()],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            Descriptor::ObjectShape(ObjectShapeDescriptor {
+                id: Uuid::from_u128(0x1003).into(),
+                ephemeral_free_shape: false,
+                type_pos: None,
+                elements: vec![
+                    element("id", 0, true),
+                    element("title", 1, false),
+                    element("tags", 3, false),
+                    element("subtitle", 1, false),
+                    element("version", 4, false),
+                    element("meta", 5, false),
+                    element("published", 6, false),
+                    element("status", 7, false),
+                ],
+            }),
+            Descriptor::Set(SetDescriptor {
+                id: Uuid::from_u128(0x1004).into(),
+                type_pos: TypePos(3),
+            }),
+        ]
+    }
+
+    fn encode(descriptors: &[Descriptor], pos: u16, value: &Value) -> BytesMut {
+        let codec = build_codec(Some(TypePos(pos)), descriptors).unwrap();
+        let mut buf = BytesMut::new();
+        codec.encode(&mut buf, value).unwrap();
+        buf
+    }
+
+    fn post() -> Value {
+        let elements = match &descriptors()[8] {
+            Descriptor::ObjectShape(d) => d.elements.iter().map(Into::into).collect(),
+            _ => unreachable!(),
+        };
+        Value::Object {
+            shape: ObjectShape::new(elements),
+            fields: vec![
+                Some(Value::Uuid(Uuid::from_u128(0x42))),
+                Some(Value::Str("Hello".into())),
+                Some(Value::Array(vec![Value::Int64(1), Value::Int64(2)])),
+                None,
+                Some(Value::Tuple(vec![
+                    Value::Str("beta".into()),
+                    Value::Int64(3),
+                ])),
+                Some(Value::Json(Json::new_unchecked(
+                    r#"{"pinned": true}"#.into(),
+                ))),
+                Some(Value::LocalDate(LocalDate::from_ymd(2024, 2, 29))),
+                Some(Value::Enum(EnumValue::from("Published"))),
+            ],
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Meta {
+        pinned: bool,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Status {
+        Draft,
+        Published,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Post<'a> {
+        title: &'a str,
+        tags: Vec<i64>,
+        subtitle: Option<String>,
+        version: (String, i64),
+        meta: Meta,
+        published: String,
+        status: Status,
+    }
+
+    #[test]
+    fn deserialize_object() {
+        let descriptors = descriptors();
+        let data = encode(&descriptors, 8, &post());
+        let post = Post::deserialize(Deserializer::new(&descriptors, TypePos(8), &data)).unwrap();
+        assert_eq!(
+            post,
+            Post {
+                title: "Hello",
+                tags: vec![1, 2],
+                subtitle: None,
+                version: ("beta".into(), 3),
+                meta: Meta { pinned: true },
+                published: "2024-02-29".into(),
+                status: Status::Published,
+            }
+        );
+
+        let map = HashMap::<String, serde_json::Value>::deserialize(Deserializer::new(
+            &descriptors,
+            TypePos(8),
+            &data,
+        ))
+        .unwrap();
+        assert!(!map.contains_key("id"));
+        assert!(!map.contains_key("subtitle"));
+        assert_eq!(map["meta"], serde_json::json!({"pinned": true}));
+    }
+
+    #[test]
+    fn deserialize_set_of_arrays() {
+        let descriptors = descriptors();
+        let value = Value::Set(vec![
+            Value::Array(vec![Value::Int64(1)]),
+            Value::Array(vec![]),
+        ]);
+        let data = encode(&descriptors, 9, &value);
+        let sets = Vec::<Vec<i64>>::deserialize(Deserializer::new(&descriptors, TypePos(9), &data))
+            .unwrap();
+        assert_eq!(sets, vec![vec![1], vec![]]);
+    }
+
+    #[test]
+    fn deserialize_datetime() {
+        let descriptors = vec![scalar(codec::STD_DATETIME)];
+        let datetime = "2024-02-29T12:30:00.25Z".parse().unwrap();
+        let data = encode(&descriptors, 0, &Value::Datetime(datetime));
+        let text = String::deserialize(Deserializer::new(&descriptors, TypePos(0), &data)).unwrap();
+        assert_eq!(text, "2024-02-29T12:30:00.250000Z");
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let descriptors = descriptors();
+        let data = encode(&descriptors, 8, &post());
+        let err = <(String, i64)>::deserialize(Deserializer::new(&descriptors, TypePos(8), &data))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot deserialize value: invalid type: map, expected a tuple of size 2"
+        );
+    }
+
+    #[test]
+    fn check_descriptors() {
+        type Post = Serde<HashMap<String, serde_json::Value>>;
+        let mut descriptors = descriptors();
+        let ctx = queryable::DescriptorContext::new(&descriptors);
+        assert!(Post::check_descriptor(&ctx, TypePos(8)).is_ok());
+        assert!(Post::check_descriptor(&ctx, TypePos(100)).is_err());
+
+        descriptors[2] = scalar(Uuid::from_u128(0x2000));
+        let ctx = queryable::DescriptorContext::new(&descriptors);
+        assert!(Post::check_descriptor(&ctx, TypePos(1)).is_ok());
+        let err = Post::check_descriptor(&ctx, TypePos(9)).unwrap_err();
+        assert!(
+            err.to_string().contains("expected a known scalar"),
+            "{}",
+            err
+        );
+    }
+
+    fn input(name: &str, type_pos: u16, cardinality: Cardinality) -> InputShapeElement {
+        InputShapeElement {
+            cardinality: Some(cardinality),
+            name: name.into(),
+            type_pos: TypePos(type_pos),
+        }
+    }
+
+    fn argument_descriptors(root: Descriptor) -> Vec<Descriptor> {
+        vec![
+            scalar(codec::STD_STR),
+            scalar(codec::STD_BYTES),
+            scalar(codec::STD_INT64),
+            scalar(codec::STD_JSON),
+            scalar(codec::STD_DATETIME),
+            scalar(codec::STD_BIGINT),
+            Descriptor::Array(ArrayTypeDescriptor {
+                id: Uuid::from_u128(0x1000).into(),
+                type_pos: TypePos(2),
+                dimensions: vec![None],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            Descriptor::NamedTuple(NamedTupleTypeDescriptor {
+                id: Uuid::from_u128(0x1001).into(),
+                elements: vec![
+                    TupleElement {
+                        name: "name".into(),
+                        type_pos: TypePos(0),
+                    },
+                    TupleElement {
+                        name: "tags".into(),
+                        type_pos: TypePos(6),
+                    },
+                ],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            Descriptor::Enumeration(EnumerationTypeDescriptor {
+                id: Uuid::from_u128(0x1002).into(),
+                members: vec!["Draft".into(), "Published".into()],
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            Descriptor::Range(RangeTypeDescriptor {
+                id: Uuid::from_u128(0x1003).into(),
+                type_pos: TypePos(2),
+                name: None,
+                schema_defined: None,
+                ancestors: vec![],
+            }),
+            root,
+        ]
+    }
+
+    fn named_arguments() -> Descriptor {
+        Descriptor::ObjectShape(ObjectShapeDescriptor {
+            id: Uuid::from_u128(0x2000).into(),
+            ephemeral_free_shape: false,
+            type_pos: None,
+            elements: vec![
+                argument("title", 0, Cardinality::One),
+                argument("token", 1, Cardinality::AtMostOne),
+                argument("limit", 2, Cardinality::AtMostOne),
+                argument("meta", 3, Cardinality::AtMostOne),
+                argument("at", 4, Cardinality::AtMostOne),
+                argument("big", 5, Cardinality::AtMostOne),
+                argument("user", 7, Cardinality::AtMostOne),
+                argument("status", 8, Cardinality::AtMostOne),
+                argument("span", 9, Cardinality::AtMostOne),
+            ],
+        })
+    }
+
+    fn argument(name: &str, type_pos: u16, cardinality: Cardinality) -> ShapeElement {
+        ShapeElement {
+            cardinality: Some(cardinality),
+            ..element(name, type_pos, false)
+        }
+    }
+
+    fn serialize(root: Descriptor, args: impl Serialize + Send + Sync) -> Result<Value, String> {
+        let descriptors = argument_descriptors(root);
+        let proto = ProtocolVersion::current();
+        let ctx = DescriptorContext {
+            proto: &proto,
+            root_pos: Some(TypePos(10)),
+            descriptors: &descriptors,
+        };
+        let mut buf = BytesMut::new();
+        Serde(args)
+            .encode(&mut Encoder::new(&ctx, &mut buf))
+            .map_err(|e| format!("{e:#}"))?;
+        Ok(ctx.build_codec().unwrap().decode(&buf).unwrap())
+    }
+
+    #[derive(Serialize)]
+    struct Filter<'a> {
+        title: &'a str,
+        #[serde(serialize_with = "as_bytes")]
+        token: Vec<u8>,
+        limit: Option<u32>,
+        meta: serde_json::Value,
+        at: &'a str,
+        big: i128,
+        user: User<'a>,
+        status: Status,
+    }
+
+    #[derive(Serialize)]
+    struct User<'a> {
+        tags: Vec<i64>,
+        name: &'a str,
+    }
+
+    fn as_bytes<S: serde::Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(v)
+    }
+
+    #[test]
+    fn serialize_args() {
+        let value = serialize(
+            named_arguments(),
+            Filter {
+                title: "Hello",
+                token: vec![0, 255],
+                limit: None,
+                meta: serde_json::json!({"pinned": true}),
+                at: "2024-02-29T12:30:00+02:00",
+                big: 1 << 70,
+                user: User {
+                    tags: vec![1, 2],
+                    name: "alice",
+                },
+                status: Status::Published,
+            },
+        )
+        .unwrap();
+        assert_eq!(value.get_str("title").unwrap(), "Hello");
+        assert_eq!(
+            value.get("token").unwrap(),
+            &Value::Bytes(bytes::Bytes::from_static(&[0, 255]))
+        );
+        assert_eq!(value.get_opt("limit").unwrap(), None);
+        assert_eq!(
+            value.get("meta").unwrap(),
+            &Value::Json(Json::new_unchecked(r#"{"pinned":true}"#.into()))
+        );
+        assert_eq!(
+            value.get_datetime("at").unwrap(),
+            "2024-02-29T10:30:00Z".parse().unwrap()
+        );
+        assert_eq!(
+            value.get("big").unwrap(),
+            &Value::BigInt(crate::model::BigInt::parse("1180591620717411303424").unwrap())
+        );
+        assert_eq!(value.get_str("user.name").unwrap(), "alice");
+        assert_eq!(
+            value.get("user.tags").unwrap(),
+            &Value::Array(vec![Value::Int64(1), Value::Int64(2)])
+        );
+        assert_eq!(
+            value.get("status").unwrap(),
+            &Value::Enum(EnumValue::from("Published"))
+        );
+
+        // `null` JSON is a missing argument
+        let value = serialize(
+            named_arguments(),
+            serde_json::json!({"title": "Hi", "meta": null, "span": {"lower": 2.0}}),
+        )
+        .unwrap();
+        assert_eq!(value.get_opt("meta").unwrap(), None);
+        let Value::Range(span) = value.get("span").unwrap() else {
+            panic!("range expected");
+        };
+        assert_eq!(span.lower().map(|b| &**b), Some(&Value::Int64(2)));
+        assert_eq!(span.upper(), None);
+        assert!(span.inc_lower());
+    }
+
+    #[test]
+    fn serialize_positional_args() {
+        let root = Descriptor::ObjectShape(ObjectShapeDescriptor {
+            id: Uuid::from_u128(0x2000).into(),
+            ephemeral_free_shape: false,
+            type_pos: None,
+            elements: vec![
+                argument("0", 0, Cardinality::One),
+                argument("1", 2, Cardinality::AtMostOne),
+            ],
+        });
+        let value = serialize(root.clone(), ("a", 3)).unwrap();
+        assert_eq!(value.get_str("0").unwrap(), "a");
+        assert_eq!(value.get("1").unwrap(), &Value::Int64(3));
+        let value = serialize(root.clone(), ["b"]).unwrap();
+        assert_eq!(value.get_opt("1").unwrap(), None);
+        assert_eq!(
+            serialize(root, ("a", 3, 4)).unwrap_err(),
+            "ClientEncodingError: cannot serialize value: unexpected argument `$2`"
+        );
+    }
+
+    #[test]
+    fn serialize_sparse_args() {
+        let root = Descriptor::InputShape(InputShapeTypeDescriptor {
+            id: Uuid::from_u128(0x2000).into(),
+            elements: vec![
+                input("name", 0, Cardinality::AtMostOne),
+                input("limit", 2, Cardinality::AtMostOne),
+            ],
+        });
+        let value = serialize(root, HashMap::from([("limit", 5)])).unwrap();
+        let Value::SparseObject(object) = &value else {
+            panic!("sparse object expected");
+        };
+        assert_eq!(object.fields, [None, Some(Some(Value::Int64(5)))]);
+    }
+
+    #[test]
+    fn serialize_errors() {
+        let error = |args: serde_json::Value| serialize(named_arguments(), args).unwrap_err();
+        assert_eq!(
+            error(serde_json::json!({"limit": 1})),
+            "ClientEncodingError: cannot serialize value: missing required argument `$title`"
+        );
+        assert_eq!(
+            error(serde_json::json!({"title": "a", "extra": 1})),
+            "ClientEncodingError: cannot serialize value: unexpected argument `$extra`"
+        );
+        assert_eq!(
+            error(serde_json::json!({"title": 1})),
+            "ClientEncodingError: cannot serialize value: \
+             invalid argument `$title`: expected str, got integer"
+        );
+        assert_eq!(
+            error(serde_json::json!({"title": "a", "user": {"name": "b", "tags": [1.5]}})),
+            "ClientEncodingError: cannot serialize value: \
+             invalid argument `$user`: expected int64, got float"
+        );
+        assert_eq!(
+            error(serde_json::json!({"title": "a", "at": "yesterday"})),
+            "ClientEncodingError: cannot serialize value: \
+             invalid argument `$at`: \"yesterday\" is not a valid datetime"
+        );
+        assert_eq!(
+            error(serde_json::json!({"title": "a", "status": "Archived"})),
+            "ClientEncodingError: cannot serialize value: \
+             invalid argument `$status`: \"Archived\" is not a member of the enum"
+        );
+        assert_eq!(
+            error(serde_json::json!({"title": "a", "user": {"name": "b"}})),
+            "ClientEncodingError: cannot serialize value: \
+             invalid argument `$user`: missing field `tags`"
+        );
+    }
+}
@@ -5,17 +5,17 @@ use crate::queryable::{Decoder, DescriptorContext, Queryable};
 use crate::serialization::decode::DecodeArrayLike;
 use std::iter::FromIterator;
 
-impl<T: Queryable> Queryable for Option<T> {
+impl<'a, T: Queryable<'a>> Queryable<'a> for Option<T> {
     type Args = T::Args;
 
-    fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8]) -> Result<Self, DecodeError> {
+    fn decode(decoder: &Decoder, args: &Self::Args, buf: &'a [u8]) -> Result<Self, DecodeError> {
         Ok(Some(T::decode(decoder, args, buf)?))
     }
 
     fn decode_optional(
         decoder: &Decoder,
         args: &Self::Args,
-        buf: Option<&[u8]>,
+        buf: Option<&'a [u8]>,
     ) -> Result<Self, DecodeError> {
         buf.map(|buf| T::decode(decoder, args, buf)).transpose()
     }
@@ -30,14 +30,14 @@ impl<T: Queryable> Queryable for Option<T> {
 
 struct Collection<T>(T);
 
-impl<T: IntoIterator + FromIterator<<T as IntoIterator>::Item>> Collection<T>
+impl<'a, T: IntoIterator + FromIterator<<T as IntoIterator>::Item>> Collection<T>
 where
-    <T as IntoIterator>::Item: Queryable,
+    <T as IntoIterator>::Item: Queryable<'a>,
 {
     fn decode(
         decoder: &Decoder,
-        args: &<<T as IntoIterator>::Item as Queryable>::Args,
-        buf: &[u8],
+        args: &<<T as IntoIterator>::Item as Queryable<'a>>::Args,
+        buf: &'a [u8],
     ) -> Result<T, DecodeError> {
         let elements = DecodeArrayLike::new_collection(buf)?;
         let elements = elements.map(|e| <T as IntoIterator>::Item::decode(decoder, args, e?));
@@ -46,8 +46,8 @@ where
 
     fn decode_optional(
         decoder: &Decoder,
-        args: &<<T as IntoIterator>::Item as Queryable>::Args,
-        buf: Option<&[u8]>,
+        args: &<<T as IntoIterator>::Item as Queryable<'a>>::Args,
+        buf: Option<&'a [u8]>,
     ) -> Result<T, DecodeError> {
         match buf {
             Some(buf) => Self::decode(decoder, args, buf),
@@ -58,7 +58,7 @@ where
     fn check_descriptor(
         ctx: &DescriptorContext,
         type_pos: TypePos,
-    ) -> Result<<<T as IntoIterator>::Item as Queryable>::Args, DescriptorMismatch> {
+    ) -> Result<<<T as IntoIterator>::Item as Queryable<'a>>::Args, DescriptorMismatch> {
         let desc = ctx.get(type_pos)?;
         let element_type_pos = match desc {
             Descriptor::Set(desc) => desc.type_pos,
@@ -69,17 +69,17 @@ where
     }
 }
 
-impl<T: Queryable> Queryable for Vec<T> {
+impl<'a, T: Queryable<'a>> Queryable<'a> for Vec<T> {
     type Args = T::Args;
 
-    fn decode(decoder: &Decoder, args: &T::Args, buf: &[u8]) -> Result<Self, DecodeError> {
+    fn decode(decoder: &Decoder, args: &T::Args, buf: &'a [u8]) -> Result<Self, DecodeError> {
         Collection::<Vec<T>>::decode(decoder, args, buf)
     }
 
     fn decode_optional(
         decoder: &Decoder,
         args: &T::Args,
-        buf: Option<&[u8]>,
+        buf: Option<&'a [u8]>,
     ) -> Result<Self, DecodeError> {
         Collection::<Vec<T>>::decode_optional(decoder, args, buf)
     }
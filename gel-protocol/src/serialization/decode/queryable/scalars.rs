@@ -40,7 +40,7 @@ pub trait DecodeScalar: for<'a> RawCodec<'a> + Sized {
     fn typename() -> &'static str;
 }
 
-impl<T: DecodeScalar> Queryable for T {
+impl<T: DecodeScalar> Queryable<'_> for T {
     type Args = ();
 
     fn decode(_decoder: &Decoder, _args: &(), buf: &[u8]) -> Result<Self, DecodeError> {
@@ -54,6 +54,34 @@ impl<T: DecodeScalar> Queryable for T {
     }
 }
 
+impl<'a, 't: 'a> Queryable<'t> for &'a str {
+    type Args = ();
+
+    fn decode(_decoder: &Decoder, _args: &(), buf: &'t [u8]) -> Result<Self, DecodeError> {
+        RawCodec::decode(buf)
+    }
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<(), DescriptorMismatch> {
+        check_scalar(ctx, type_pos, String::uuid(), String::typename())
+    }
+}
+
+impl<'a, 't: 'a> Queryable<'t> for &'a [u8] {
+    type Args = ();
+
+    fn decode(_decoder: &Decoder, _args: &(), buf: &'t [u8]) -> Result<Self, DecodeError> {
+        RawCodec::decode(buf)
+    }
+    fn check_descriptor(
+        ctx: &DescriptorContext,
+        type_pos: TypePos,
+    ) -> Result<(), DescriptorMismatch> {
+        check_scalar(ctx, type_pos, Bytes::uuid(), Bytes::typename())
+    }
+}
+
 impl DecodeScalar for String {
     fn uuid() -> Uuid {
         codec::STD_STR
@@ -6,20 +6,20 @@ use crate::serialization::decode::DecodeTupleLike;
 
 macro_rules! implement_tuple {
     ( $count:expr, $(($name:ident, $index:tt),)+ ) => (
-        impl<$($name:Queryable),+> Queryable for ($($name,)+) {
+        impl<'a, $($name:Queryable<'a>),+> Queryable<'a> for ($($name,)+) {
             type Args = (
                 $(
-                    <$name as crate::queryable::Queryable>::Args,
+                    <$name as crate::queryable::Queryable<'a>>::Args,
                 )+
             );
 
-            fn decode(decoder: &Decoder, args: &Self::Args, buf: &[u8])
+            fn decode(decoder: &Decoder, args: &Self::Args, buf: &'a [u8])
                 -> Result<Self, DecodeError>
             {
                 let mut elements = DecodeTupleLike::new_tuple(buf, $count)?;
                 Ok((
                     $(
-                        <$name as crate::queryable::Queryable>::
+                        <$name as crate::queryable::Queryable<'a>>::
                             decode_optional(decoder, &args.$index, elements.read()?)?,
                     )+
                 ))
@@ -30,6 +30,11 @@ impl<'t> DecodeTupleLike<'t> {
         Ok(elements)
     }
 
+    /// Number of elements left to read.
+    pub fn count(&self) -> usize {
+        self.inner.count()
+    }
+
     pub fn read(&mut self) -> Result<Option<&'t [u8]>, DecodeError> {
         self.inner.read_object_element()
     }
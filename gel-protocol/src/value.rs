@@ -10,6 +10,10 @@ use crate::model::{BigInt, ConfigMemory, Decimal, Range, Uuid};
 use crate::model::{DateDuration, Json, RelativeDuration};
 use crate::model::{Datetime, Duration, LocalDate, LocalDatetime, LocalTime};
 
+mod access;
+
+pub use access::Fields;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Nothing,
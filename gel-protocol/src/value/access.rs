@@ -0,0 +1,597 @@
+use std::fmt::Write;
+use std::iter::Zip;
+use std::slice;
+
+use snafu::{ensure, OptionExt};
+
+use crate::codec::{InputShapeElement, SQLRowElement, ShapeElement, TupleElement};
+use crate::errors::{self, ValueAccessError};
+use crate::model::{Datetime, Uuid};
+use crate::value::Value;
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+/// Iterator over named fields of a [Value], returned by [Value::fields].
+///
+/// Yields `None` for fields that are present in the shape but have no value
+/// (an empty set).
+#[derive(Debug)]
+pub struct Fields<'a> {
+    inner: FieldsInner<'a>,
+}
+
+#[derive(Debug)]
+enum FieldsInner<'a> {
+    Object(Zip<slice::Iter<'a, ShapeElement>, slice::Iter<'a, Option<Value>>>),
+    NamedTuple(Zip<slice::Iter<'a, TupleElement>, slice::Iter<'a, Value>>),
+    SQLRow(Zip<slice::Iter<'a, SQLRowElement>, slice::Iter<'a, Option<Value>>>),
+    SparseObject(Zip<slice::Iter<'a, InputShapeElement>, slice::Iter<'a, Option<Option<Value>>>>),
+    Empty,
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (&'a str, Option<&'a Value>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            FieldsInner::Object(iter) => iter.next().map(|(el, v)| (&*el.name, v.as_ref())),
+            FieldsInner::NamedTuple(iter) => iter.next().map(|(el, v)| (&*el.name, Some(v))),
+            FieldsInner::SQLRow(iter) => iter.next().map(|(el, v)| (&*el.name, v.as_ref())),
+            FieldsInner::SparseObject(iter) => loop {
+                let (el, v) = iter.next()?;
+                if let Some(v) = v {
+                    break Some((&*el.name, v.as_ref()));
+                }
+            },
+            FieldsInner::Empty => None,
+        }
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment<'_>>, ValueAccessError> {
+    let invalid = |reason: &'static str| errors::InvalidPath { path, reason }.build();
+    let mut segments = Vec::new();
+    let mut rest = path;
+    let mut expect_field = true;
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']').ok_or_else(|| invalid("unclosed `[`"))?;
+            let index = tail[..end]
+                .parse()
+                .map_err(|_| invalid("index must be a non-negative integer"))?;
+            segments.push(Segment::Index(index));
+            rest = &tail[end + 1..];
+            expect_field = false;
+        } else {
+            if !expect_field {
+                rest = rest
+                    .strip_prefix('.')
+                    .ok_or_else(|| invalid("expected `.` or `[`"))?;
+            }
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            ensure!(
+                end > 0,
+                errors::InvalidPath {
+                    path,
+                    reason: "empty field name"
+                }
+            );
+            segments.push(Segment::Field(&rest[..end]));
+            rest = &rest[end..];
+            expect_field = false;
+        }
+    }
+    Ok(segments)
+}
+
+fn push_segment(prefix: &mut String, segment: &Segment) {
+    match segment {
+        Segment::Field(name) if prefix.is_empty() => prefix.push_str(name),
+        Segment::Field(name) => write!(prefix, ".{name}").unwrap(),
+        Segment::Index(idx) => write!(prefix, "[{idx}]").unwrap(),
+    }
+}
+
+fn by_index<'a>(
+    items: impl ExactSizeIterator<Item = Option<&'a Value>>,
+    index: usize,
+    path: &str,
+) -> Result<Option<&'a Value>, ValueAccessError> {
+    let len = items.len();
+    let mut items = items;
+    items
+        .nth(index)
+        .context(errors::IndexOutOfRange { path, index, len })
+}
+
+impl Value {
+    /// Returns the value at the path, or `None` if the value is absent.
+    ///
+    /// Path is a sequence of field names separated by dots and zero-based
+    /// indexes in square brackets, for example `user.friends[0].name`.
+    /// Fields can be looked up in objects, named tuples, SQL rows and sparse
+    /// objects. Indexes work on arrays, sets and (named) tuples; an unnamed
+    /// tuple element can also be referenced as a field: `pair.0`.
+    ///
+    /// Returns `Ok(None)` if an object property holds an empty set. Returns
+    /// an error if the path is malformed or doesn't match the value.
+    pub fn get_opt(&self, path: &str) -> Result<Option<&Value>, ValueAccessError> {
+        let mut cur = self;
+        let mut prefix = String::with_capacity(path.len());
+        for segment in parse_path(path)? {
+            push_segment(&mut prefix, &segment);
+            let next = match (&segment, cur) {
+                (
+                    Segment::Field(name),
+                    Value::Object { .. }
+                    | Value::NamedTuple { .. }
+                    | Value::SQLRow { .. }
+                    | Value::SparseObject(_),
+                ) => cur
+                    .fields()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, value)| value)
+                    .context(errors::NoSuchField { path: &prefix })?,
+                (Segment::Field(name), Value::Tuple(items)) => {
+                    let index = name
+                        .parse()
+                        .ok()
+                        .context(errors::NoSuchField { path: &prefix })?;
+                    by_index(items.iter().map(Some), index, &prefix)?
+                }
+                (Segment::Index(index), Value::Array(items) | Value::Set(items))
+                | (Segment::Index(index), Value::Tuple(items)) => {
+                    by_index(items.iter().map(Some), *index, &prefix)?
+                }
+                (Segment::Index(index), Value::NamedTuple { fields, .. }) => {
+                    by_index(fields.iter().map(Some), *index, &prefix)?
+                }
+                (Segment::Field(_), _) => {
+                    return errors::WrongValueType {
+                        path: prefix,
+                        expected: "object or tuple",
+                        found: cur.kind(),
+                    }
+                    .fail();
+                }
+                (Segment::Index(_), _) => {
+                    return errors::WrongValueType {
+                        path: prefix,
+                        expected: "array, set or tuple",
+                        found: cur.kind(),
+                    }
+                    .fail();
+                }
+            };
+            match next {
+                Some(next) => cur = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(cur))
+    }
+
+    /// Returns the value at the path.
+    ///
+    /// See [Value::get_opt] for the path syntax. Unlike `get_opt` this
+    /// method returns an error if the value is absent.
+    pub fn get(&self, path: &str) -> Result<&Value, ValueAccessError> {
+        self.get_opt(path)?.context(errors::MissingValue { path })
+    }
+
+    fn get_typed<'a, T>(
+        &'a self,
+        path: &str,
+        expected: &'static str,
+        f: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<T, ValueAccessError> {
+        let value = self.get(path)?;
+        f(value).context(errors::WrongValueType {
+            path,
+            expected,
+            found: value.kind(),
+        })
+    }
+
+    /// Returns the string at the path.
+    pub fn get_str(&self, path: &str) -> Result<&str, ValueAccessError> {
+        self.get_typed(path, "str", |v| match v {
+            Value::Str(s) => Some(&s[..]),
+            _ => None,
+        })
+    }
+
+    /// Returns the integer at the path, `int16` and `int32` are widened.
+    pub fn get_i64(&self, path: &str) -> Result<i64, ValueAccessError> {
+        self.get_typed(path, "int64", |v| match *v {
+            Value::Int16(i) => Some(i.into()),
+            Value::Int32(i) => Some(i.into()),
+            Value::Int64(i) => Some(i),
+            _ => None,
+        })
+    }
+
+    /// Returns the float at the path, `float32` is widened.
+    pub fn get_f64(&self, path: &str) -> Result<f64, ValueAccessError> {
+        self.get_typed(path, "float64", |v| match *v {
+            Value::Float32(f) => Some(f.into()),
+            Value::Float64(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    /// Returns the boolean at the path.
+    pub fn get_bool(&self, path: &str) -> Result<bool, ValueAccessError> {
+        self.get_typed(path, "bool", |v| match *v {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        })
+    }
+
+    /// Returns the uuid at the path.
+    pub fn get_uuid(&self, path: &str) -> Result<Uuid, ValueAccessError> {
+        self.get_typed(path, "uuid", |v| match *v {
+            Value::Uuid(u) => Some(u),
+            _ => None,
+        })
+    }
+
+    /// Returns the datetime at the path.
+    pub fn get_datetime(&self, path: &str) -> Result<Datetime, ValueAccessError> {
+        self.get_typed(path, "datetime", |v| match *v {
+            Value::Datetime(d) => Some(d),
+            _ => None,
+        })
+    }
+
+    /// Returns elements of the array or set at the path.
+    pub fn get_array(&self, path: &str) -> Result<&[Value], ValueAccessError> {
+        self.get_typed(path, "array or set", |v| match v {
+            Value::Array(items) | Value::Set(items) => Some(&items[..]),
+            _ => None,
+        })
+    }
+
+    /// Iterates over the named fields of an object, named tuple, SQL row or
+    /// sparse object.
+    ///
+    /// For other values the iterator is empty.
+    pub fn fields(&self) -> Fields<'_> {
+        let inner = match self {
+            Value::Object { shape, fields } => {
+                FieldsInner::Object(shape.elements.iter().zip(fields.iter()))
+            }
+            Value::NamedTuple { shape, fields } => {
+                FieldsInner::NamedTuple(shape.elements.iter().zip(fields.iter()))
+            }
+            Value::SQLRow { shape, fields } => {
+                FieldsInner::SQLRow(shape.elements.iter().zip(fields.iter()))
+            }
+            Value::SparseObject(obj) => {
+                FieldsInner::SparseObject(obj.shape.elements.iter().zip(obj.fields.iter()))
+            }
+            _ => FieldsInner::Empty,
+        };
+        Fields { inner }
+    }
+}
+
+#[cfg(feature = "with-serde")]
+mod json {
+    use base64::Engine;
+    use serde_json::{Map, Number, Value as JsonValue};
+
+    use crate::model::LocalDatetime;
+    use crate::value::Value;
+
+    /// Formats as RFC 3339 without an offset (`2024-02-29T12:30:00.000005`),
+    /// with six fractional digits unless the microseconds are zero.
+    fn datetime(d: LocalDatetime) -> String {
+        let (year, month, day) = d.date().to_ymd();
+        let (hour, minute, second, micros) = d.time().to_hmsu();
+        let mut s = format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}");
+        if micros != 0 {
+            s.push_str(&format!(".{micros:06}"));
+        }
+        s
+    }
+
+    fn float(f: f64) -> JsonValue {
+        match Number::from_f64(f) {
+            Some(n) => JsonValue::Number(n),
+            None if f.is_nan() => JsonValue::String("NaN".into()),
+            None if f > 0.0 => JsonValue::String("Infinity".into()),
+            None => JsonValue::String("-Infinity".into()),
+        }
+    }
+
+    fn bytes(b: &[u8]) -> JsonValue {
+        JsonValue::String(base64::engine::general_purpose::STANDARD.encode(b))
+    }
+
+    fn object<'a>(fields: impl Iterator<Item = (&'a str, Option<&'a Value>)>) -> JsonValue {
+        JsonValue::Object(
+            fields
+                .map(|(name, value)| {
+                    let value = value
+                        .map(Value::to_json_lossless)
+                        .unwrap_or(JsonValue::Null);
+                    (name.to_owned(), value)
+                })
+                .collect::<Map<_, _>>(),
+        )
+    }
+
+    impl Value {
+        /// Converts the value into JSON without losing information.
+        ///
+        /// Integers and finite floats become JSON numbers. Values that
+        /// can't be represented exactly by a JSON number (`bigint`,
+        /// `decimal`, non-finite floats) and date/time values are encoded as
+        /// strings in the format they are parsed from. Bytes are encoded as
+        /// base64 strings. Embedded `json` values are inlined.
+        ///
+        /// Objects and named tuples become JSON objects (including implicit
+        /// fields, such as `id`), and sets, arrays and tuples become arrays.
+        pub fn to_json_lossless(&self) -> JsonValue {
+            match self {
+                Value::Nothing => JsonValue::Null,
+                Value::Uuid(u) => JsonValue::String(u.to_string()),
+                Value::Str(s) => JsonValue::String(s.clone()),
+                Value::Bytes(b) => bytes(b),
+                Value::Int16(i) => JsonValue::from(*i),
+                Value::Int32(i) => JsonValue::from(*i),
+                Value::Int64(i) => JsonValue::from(*i),
+                Value::Float32(f) => float((*f).into()),
+                Value::Float64(f) => float(*f),
+                Value::BigInt(b) => JsonValue::String(b.to_string()),
+                Value::ConfigMemory(m) => JsonValue::String(m.to_string()),
+                Value::Decimal(d) => JsonValue::String(d.to_string()),
+                Value::Bool(b) => JsonValue::Bool(*b),
+                Value::Datetime(d) => JsonValue::String(datetime((*d).into()) + "Z"),
+                Value::LocalDatetime(d) => JsonValue::String(datetime(*d)),
+                Value::LocalDate(d) => JsonValue::String(d.to_string()),
+                Value::LocalTime(t) => JsonValue::String(t.to_string()),
+                Value::Duration(d) => JsonValue::String(d.to_string()),
+                Value::RelativeDuration(d) => JsonValue::String(d.to_string()),
+                Value::DateDuration(d) => JsonValue::String(d.to_string()),
+                Value::Json(j) => {
+                    serde_json::from_str(j).unwrap_or_else(|_| JsonValue::String(j.to_string()))
+                }
+                Value::Set(items) | Value::Array(items) | Value::Tuple(items) => {
+                    JsonValue::Array(items.iter().map(Value::to_json_lossless).collect())
+                }
+                Value::Object { .. }
+                | Value::NamedTuple { .. }
+                | Value::SQLRow { .. }
+                | Value::SparseObject(_) => object(self.fields()),
+                Value::Vector(v) => {
+                    JsonValue::Array(v.iter().map(|f| float((*f).into())).collect())
+                }
+                Value::Enum(e) => JsonValue::String(e.to_string()),
+                Value::Range(r) => {
+                    let bound = |b: Option<&Box<Value>>| {
+                        b.map(|b| b.to_json_lossless()).unwrap_or(JsonValue::Null)
+                    };
+                    let mut map = Map::new();
+                    map.insert("lower".into(), bound(r.lower()));
+                    map.insert("upper".into(), bound(r.upper()));
+                    map.insert("inc_lower".into(), JsonValue::Bool(r.inc_lower()));
+                    map.insert("inc_upper".into(), JsonValue::Bool(r.inc_upper()));
+                    map.insert("empty".into(), JsonValue::Bool(r.is_empty()));
+                    JsonValue::Object(map)
+                }
+                Value::PostGisGeometry(b)
+                | Value::PostGisGeography(b)
+                | Value::PostGisBox2d(b)
+                | Value::PostGisBox3d(b) => bytes(b),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_path, Segment};
+    use crate::codec::{
+        InputObjectShape, InputShapeElement, NamedTupleShape, ObjectShape, ShapeElement,
+    };
+    use crate::descriptors::TupleElement;
+    use crate::errors::ValueAccessError;
+    use crate::value::{SparseObject, Value};
+
+    fn object(pairs: Vec<(&str, Option<Value>)>) -> Value {
+        let (names, fields): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+        Value::Object {
+            shape: ObjectShape::new(
+                names
+                    .into_iter()
+                    .map(|name| ShapeElement {
+                        flag_implicit: false,
+                        flag_link_property: false,
+                        flag_link: false,
+                        cardinality: None,
+                        name: name.into(),
+                    })
+                    .collect(),
+            ),
+            fields,
+        }
+    }
+
+    fn user() -> Value {
+        let friend = object(vec![("name", Some(Value::from("Bob"))), ("nickname", None)]);
+        let pair_shape: NamedTupleShape = [
+            TupleElement {
+                name: "x".into(),
+                type_pos: crate::descriptors::TypePos(0),
+            },
+            TupleElement {
+                name: "y".into(),
+                type_pos: crate::descriptors::TypePos(0),
+            },
+        ][..]
+            .into();
+        object(vec![(
+            "user",
+            Some(object(vec![
+                ("name", Some(Value::from("Alice"))),
+                ("age", Some(Value::Int16(31))),
+                ("friends", Some(Value::Set(vec![friend]))),
+                (
+                    "point",
+                    Some(Value::NamedTuple {
+                        shape: pair_shape,
+                        fields: vec![Value::Int32(1), Value::Int32(2)],
+                    }),
+                ),
+                (
+                    "pair",
+                    Some(Value::Tuple(vec![Value::from("a"), Value::Bool(true)])),
+                ),
+            ])),
+        )])
+    }
+
+    #[test]
+    fn parse() {
+        use Segment::*;
+        assert_eq!(parse_path("").unwrap(), vec![]);
+        assert_eq!(
+            parse_path("a.b[0][12].c").unwrap(),
+            vec![Field("a"), Field("b"), Index(0), Index(12), Field("c")]
+        );
+        assert_eq!(
+            parse_path("[1].@weight").unwrap(),
+            vec![Index(1), Field("@weight")]
+        );
+        assert!(parse_path("a..b").is_err());
+        assert!(parse_path("a[").is_err());
+        assert!(parse_path("a[-1]").is_err());
+        assert!(parse_path("a[0]b").is_err());
+        assert!(parse_path(".a").is_err());
+    }
+
+    #[test]
+    fn get() {
+        let value = user();
+        assert_eq!(value.get_str("user.friends[0].name").unwrap(), "Bob");
+        assert_eq!(value.get_i64("user.age").unwrap(), 31);
+        assert_eq!(value.get_i64("user.point.y").unwrap(), 2);
+        assert_eq!(value.get_i64("user.point[0]").unwrap(), 1);
+        assert_eq!(value.get_str("user.pair.0").unwrap(), "a");
+        assert!(value.get_bool("user.pair[1]").unwrap());
+        assert_eq!(value.get_array("user.friends").unwrap().len(), 1);
+        assert_eq!(value.get_opt("user.friends[0].nickname").unwrap(), None);
+    }
+
+    #[test]
+    fn errors() {
+        let value = user();
+        let err = |path: &str| value.get_str(path).unwrap_err().to_string();
+        assert_eq!(
+            err("user.friends[0].nickname"),
+            "missing value at `user.friends[0].nickname`"
+        );
+        assert_eq!(
+            err("user.friends[3].name"),
+            "index 3 is out of range at `user.friends[3]` (length 1)"
+        );
+        assert_eq!(err("user.email"), "no field at `user.email`");
+        assert_eq!(err("user.age"), "expected str at `user.age`, found int16");
+        assert_eq!(
+            err("user.name.first"),
+            "expected object or tuple at `user.name.first`, found str"
+        );
+        assert!(matches!(
+            value.get("user[").unwrap_err(),
+            ValueAccessError::InvalidPath { .. }
+        ));
+    }
+
+    #[test]
+    fn no_fields() {
+        let empty = object(vec![]);
+        assert_eq!(
+            empty.get_opt("name").unwrap_err().to_string(),
+            "no field at `name`"
+        );
+        // A sparse object with no fields set
+        let sparse = Value::SparseObject(SparseObject {
+            shape: InputObjectShape::new(vec![InputShapeElement {
+                cardinality: None,
+                name: "name".into(),
+            }]),
+            fields: vec![None],
+        });
+        assert_eq!(
+            sparse.get_opt("name").unwrap_err().to_string(),
+            "no field at `name`"
+        );
+        assert_eq!(
+            Value::SparseObject(SparseObject::empty())
+                .get_opt("name")
+                .unwrap_err()
+                .to_string(),
+            "no field at `name`"
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let value = user();
+        let names = value
+            .get("user")
+            .unwrap()
+            .fields()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["name", "age", "friends", "point", "pair"]);
+        assert_eq!(Value::Int64(1).fields().count(), 0);
+    }
+
+    #[cfg(feature = "with-serde")]
+    #[test]
+    fn json() {
+        let value = user();
+        assert_eq!(
+            value.to_json_lossless(),
+            serde_json::json!({
+                "user": {
+                    "name": "Alice",
+                    "age": 31,
+                    "friends": [{"name": "Bob", "nickname": null}],
+                    "point": {"x": 1, "y": 2},
+                    "pair": ["a", true],
+                }
+            })
+        );
+        assert_eq!(
+            Value::Bytes(bytes::Bytes::from_static(b"\x00\xff")).to_json_lossless(),
+            serde_json::json!("AP8=")
+        );
+        assert_eq!(
+            Value::Float64(f64::NAN).to_json_lossless(),
+            serde_json::json!("NaN")
+        );
+    }
+
+    #[cfg(feature = "with-serde")]
+    #[test]
+    fn json_datetime() {
+        let datetime = "2024-02-29T12:30:00.5+02:00".parse().unwrap();
+        assert_eq!(
+            Value::Datetime(datetime).to_json_lossless(),
+            serde_json::json!("2024-02-29T10:30:00.500000Z")
+        );
+        let datetime = "0001-01-01T00:00:00".parse().unwrap();
+        assert_eq!(
+            Value::LocalDatetime(datetime).to_json_lossless(),
+            serde_json::json!("0001-01-01T00:00:00")
+        );
+    }
+}
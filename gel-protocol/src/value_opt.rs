@@ -94,6 +94,24 @@ impl QueryArgs for HashMap<&str, ValueOpt> {
         }
         .encode(encoder)
     }
+    fn to_args_value(&self) -> Result<Value, Error> {
+        let mut names = self.keys().copied().collect::<Vec<_>>();
+        names.sort_unstable();
+        let elements = names
+            .iter()
+            .map(|name| ShapeElement {
+                flag_implicit: false,
+                flag_link_property: false,
+                flag_link: false,
+                cardinality: None,
+                name: name.to_string(),
+            })
+            .collect();
+        Ok(Value::Object {
+            shape: ObjectShape::new(elements),
+            fields: names.iter().map(|name| self[name].0.clone()).collect(),
+        })
+    }
 }
 
 /// Constructs named query arguments that implement [QueryArgs] so they can be passed
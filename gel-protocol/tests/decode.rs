@@ -11,3 +11,14 @@ fn decode_vector() {
     .unwrap();
     assert_eq!(vec, Vector(vec![1., 2., 3.]));
 }
+
+#[test]
+fn decode_borrowed() {
+    let buf = b"hello".to_vec();
+    let s = <&str>::decode(&Default::default(), &(), &buf).unwrap();
+    assert_eq!(s, "hello");
+    assert_eq!(s.as_ptr(), buf.as_ptr());
+
+    let bytes = <&[u8]>::decode(&Default::default(), &(), &buf).unwrap();
+    assert_eq!(bytes.as_ptr(), buf.as_ptr());
+}
@@ -104,6 +104,42 @@ fn error_response() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn error_response_roundtrip() -> Result<(), Box<dyn Error>> {
+    use gel_errors::BinaryProtocolError;
+
+    let proto = ProtocolVersion::current();
+    let data = fs::read("tests/error_response.bin")?;
+    let message = ServerMessage::decode(&mut Input::new(proto.clone(), data.clone().into()))?;
+    let response = match message {
+        ServerMessage::ErrorResponse(response) => response,
+        other => panic!("expected ErrorResponse, got {:?}", other),
+    };
+    let err = gel_errors::Error::from(response);
+    assert!(err.is::<BinaryProtocolError>());
+    assert!(err.server_traceback().is_some());
+
+    let mut bytes = BytesMut::new();
+    ServerMessage::ErrorResponse(ErrorResponse::from(&err))
+        .encode(&mut Output::new(&proto, &mut bytes))?;
+    assert_eq!(&bytes[..], &data[..]);
+
+    let fatal = ErrorResponse {
+        severity: ErrorSeverity::Fatal,
+        code: 50397184,
+        message: String::from("connection is closed"),
+        attributes: HashMap::new(),
+    };
+    let err = gel_errors::Error::from(fatal.clone());
+    assert_eq!(ErrorResponse::from(&err), fatal);
+    let err = err.context("cannot query");
+    assert_eq!(
+        ErrorResponse::from(err).message,
+        "cannot query: connection is closed"
+    );
+    Ok(())
+}
+
 #[test]
 fn server_key_data() -> Result<(), Box<dyn Error>> {
     encoding_eq!(
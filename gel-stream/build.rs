@@ -0,0 +1,12 @@
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(ossl111)");
+
+    // openssl-sys reports the version of the linked OpenSSL to crates that
+    // depend on it directly. LibreSSL reports a separate variable instead.
+    if let Ok(version) = std::env::var("DEP_OPENSSL_VERSION_NUMBER") {
+        let version = u64::from_str_radix(&version, 16).unwrap();
+        if version >= 0x1010_1000 {
+            println!("cargo:rustc-cfg=ossl111");
+        }
+    }
+}
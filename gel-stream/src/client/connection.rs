@@ -1,9 +1,18 @@
 use std::marker::PhantomData;
+use std::time::Duration;
 
+use super::happy_eyeballs;
+use super::proxy::Proxy;
+#[cfg(unix)]
+use crate::common::memory::MemoryNetwork;
 use crate::common::resolver::Resolver;
+use crate::common::target::MaybeResolvedTarget;
 use crate::common::tokio_stream::TokioStream;
 use crate::Target;
-use crate::{ConnectionError, ResolvedTarget, Ssl, StreamUpgrade, TlsDriver, UpgradableStream};
+use crate::{
+    ConnectionError, ResolvedTarget, Ssl, StreamMetrics, StreamUpgrade, TlsDriver,
+    UpgradableStream, DEFAULT_CONNECTION_ATTEMPT_DELAY,
+};
 
 type Connection<S, D> = UpgradableStream<S, D>;
 
@@ -23,6 +32,11 @@ pub struct Connector<D: TlsDriver = Ssl> {
     #[debug(skip)]
     driver: PhantomData<D>,
     ignore_missing_close_notify: bool,
+    connection_attempt_delay: Duration,
+    connect_timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    #[cfg(unix)]
+    memory_network: Option<MemoryNetwork>,
     #[cfg(feature = "keepalive")]
     keepalive: Option<std::time::Duration>,
 }
@@ -48,32 +62,32 @@ impl Connector<Ssl> {
 impl<D: TlsDriver> Connector<D> {
     /// Create a new connector with the given TLS driver and default resolver.
     pub fn new_explicit(target: Target) -> Result<Self, std::io::Error> {
-        Ok(Self {
-            target: ConnectorInner::Unresolved(target, Resolver::new()?),
-            driver: PhantomData,
-            ignore_missing_close_notify: false,
-            #[cfg(feature = "keepalive")]
-            keepalive: None,
-        })
+        Ok(Self::new_inner(ConnectorInner::Unresolved(
+            target,
+            Resolver::new()?,
+        )))
     }
 
     /// Create a new connector with the given TLS driver and resolved target.
     pub fn new_explicit_resolved(target: ResolvedTarget) -> Self {
-        Self {
-            target: ConnectorInner::Resolved(target),
-            driver: PhantomData,
-            ignore_missing_close_notify: false,
-            #[cfg(feature = "keepalive")]
-            keepalive: None,
-        }
+        Self::new_inner(ConnectorInner::Resolved(target))
     }
 
     /// Create a new connector with the given TLS driver and resolver.
     pub fn new_explicit_with_resolver(target: Target, resolver: Resolver) -> Self {
+        Self::new_inner(ConnectorInner::Unresolved(target, resolver))
+    }
+
+    fn new_inner(target: ConnectorInner) -> Self {
         Self {
-            target: ConnectorInner::Unresolved(target, resolver),
+            target,
             driver: PhantomData,
             ignore_missing_close_notify: false,
+            connection_attempt_delay: DEFAULT_CONNECTION_ATTEMPT_DELAY,
+            connect_timeout: None,
+            proxy: None,
+            #[cfg(unix)]
+            memory_network: None,
             #[cfg(feature = "keepalive")]
             keepalive: None,
         }
@@ -86,6 +100,36 @@ impl<D: TlsDriver> Connector<D> {
         self.keepalive = keepalive;
     }
 
+    /// Set the delay before starting a connection attempt to the next address
+    /// when a host name resolves to multiple addresses. Attempts to IPv6 and
+    /// IPv4 addresses are interleaved and race each other, the first one to
+    /// connect is used.
+    ///
+    /// Defaults to [`DEFAULT_CONNECTION_ATTEMPT_DELAY`].
+    pub fn set_connection_attempt_delay(&mut self, delay: Duration) {
+        self.connection_attempt_delay = delay;
+    }
+
+    /// Set a timeout for each connection attempt. If unset, an attempt
+    /// may take as long as the operating system allows.
+    pub fn set_connect_timeout(&mut self, timeout: Option<Duration>) {
+        self.connect_timeout = timeout;
+    }
+
+    /// Tunnel connections through a proxy. This is only supported for TCP
+    /// targets. TLS, if any, is negotiated with the target through the tunnel.
+    pub fn set_proxy(&mut self, proxy: Option<Proxy>) {
+        self.proxy = proxy;
+    }
+
+    /// Connect to listeners on an in-memory network instead of the operating
+    /// system's sockets. Targets are resolved as usual. Only available on
+    /// Unix.
+    #[cfg(unix)]
+    pub fn set_memory_network(&mut self, network: Option<MemoryNetwork>) {
+        self.memory_network = network;
+    }
+
     /// For TLS connections, ignore a hard close where the socket was closed
     /// before receiving CLOSE_NOTIFY.
     ///
@@ -99,17 +143,23 @@ impl<D: TlsDriver> Connector<D> {
 
     /// Connect to the target.
     pub async fn connect(&self) -> Result<Connection<TokioStream, D>, ConnectionError> {
-        let target = match &self.target {
-            ConnectorInner::Unresolved(target, resolver) => {
-                resolver.resolve_remote(target.maybe_resolved()).await?
-            }
-            ConnectorInner::Resolved(target) => target.clone(),
+        let metrics = StreamMetrics::new();
+        let stream = if let Some(proxy) = &self.proxy {
+            self.connect_proxy(proxy).await?
+        } else {
+            let targets = match &self.target {
+                ConnectorInner::Unresolved(target, resolver) => happy_eyeballs::sort_addresses(
+                    resolver.resolve_remote_all(target.maybe_resolved()).await?,
+                ),
+                ConnectorInner::Resolved(target) => vec![target.clone()],
+            };
+            self.connect_any(&targets).await?
         };
-        let stream = target.connect().await?;
+        metrics.record_connect();
 
         #[cfg(feature = "keepalive")]
         if let Some(keepalive) = self.keepalive {
-            if target.is_tcp() {
+            if let TokioStream::Tcp(_) = &stream {
                 stream.set_keepalive(Some(keepalive))?;
             }
         }
@@ -118,18 +168,57 @@ impl<D: TlsDriver> Connector<D> {
             if let Some(ssl) = target.maybe_ssl() {
                 let ssl = D::init_client(ssl, target.name())?;
                 let mut stm = UpgradableStream::new_client(stream, Some(ssl));
+                stm.set_metrics(metrics);
                 if self.ignore_missing_close_notify {
                     stm.ignore_missing_close_notify();
                 }
                 if !target.is_starttls() {
                     stm = stm.secure_upgrade().await?;
                 }
-                Ok(stm)
-            } else {
-                Ok(UpgradableStream::new_client(stream, None))
+                return Ok(stm);
             }
-        } else {
-            Ok(UpgradableStream::new_client(stream, None))
         }
+
+        let mut stm = UpgradableStream::new_client(stream, None);
+        stm.set_metrics(metrics);
+        Ok(stm)
+    }
+
+    /// Connect to the proxy and establish a tunnel to the target.
+    async fn connect_proxy(&self, proxy: &Proxy) -> Result<TokioStream, ConnectionError> {
+        // A resolver is only created if there is a host name to resolve.
+        let resolver = || match &self.target {
+            ConnectorInner::Unresolved(_, resolver) => Ok::<_, std::io::Error>(resolver.clone()),
+            ConnectorInner::Resolved(_) => Resolver::new(),
+        };
+        let mut destination = match &self.target {
+            ConnectorInner::Unresolved(target, _) => target.maybe_resolved().clone(),
+            ConnectorInner::Resolved(target) => MaybeResolvedTarget::Resolved(target.clone()),
+        };
+        if proxy.needs_local_dns() {
+            if let MaybeResolvedTarget::Unresolved(..) = destination {
+                destination =
+                    MaybeResolvedTarget::Resolved(resolver()?.resolve_remote(&destination).await?);
+            }
+        }
+        let targets = match proxy.target().maybe_resolved() {
+            MaybeResolvedTarget::Resolved(target) => vec![target.clone()],
+            target => happy_eyeballs::sort_addresses(resolver()?.resolve_remote_all(target).await?),
+        };
+        let mut stream = self.connect_any(&targets).await?;
+        proxy.handshake(&mut stream, &destination).await?;
+        Ok(stream)
+    }
+
+    /// Connect to the first reachable target.
+    async fn connect_any(
+        &self,
+        targets: &[ResolvedTarget],
+    ) -> Result<TokioStream, ConnectionError> {
+        #[cfg(unix)]
+        if let Some(network) = &self.memory_network {
+            return Ok(TokioStream::Memory(Box::new(network.connect_any(targets)?)));
+        }
+        happy_eyeballs::connect(targets, self.connection_attempt_delay, self.connect_timeout).await
     }
 }
@@ -0,0 +1,276 @@
+//! Connection racing across multiple addresses of a host ("Happy Eyeballs",
+//! RFC 8305).
+//!
+//! Addresses are interleaved by family, starting with IPv6, and attempts are
+//! started one after another with a fixed delay. A new attempt is also started
+//! as soon as one fails, and the delay is counted from the start of the most
+//! recent attempt. The first successful connection wins and the remaining
+//! attempts are dropped.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::future::Either;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use tokio::time::Instant;
+
+use crate::common::tokio_stream::TokioStream;
+use crate::{ConnectAttemptsError, ConnectionError, ResolvedTarget};
+
+/// Order addresses for connection attempts: the families are interleaved,
+/// starting with IPv6, and the relative order within each family is kept.
+pub(crate) fn sort_addresses(targets: Vec<ResolvedTarget>) -> Vec<ResolvedTarget> {
+    let (v6, other): (Vec<_>, Vec<_>) = targets
+        .into_iter()
+        .partition(|target| matches!(target.tcp(), Some(SocketAddr::V6(_))));
+    let mut v6 = v6.into_iter();
+    let mut other = other.into_iter();
+    let mut sorted = Vec::with_capacity(v6.len() + other.len());
+    loop {
+        match (v6.next(), other.next()) {
+            (None, None) => break,
+            (a, b) => sorted.extend(a.into_iter().chain(b)),
+        }
+    }
+    sorted
+}
+
+async fn attempt<'a, S, F, Fut>(
+    index: usize,
+    target: &'a ResolvedTarget,
+    timeout: Option<Duration>,
+    connect: &F,
+) -> (usize, std::io::Result<S>)
+where
+    F: Fn(&'a ResolvedTarget) -> Fut,
+    Fut: Future<Output = std::io::Result<S>>,
+{
+    let res = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, connect(target)).await {
+            Ok(res) => res,
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Connection attempt timed out",
+            )),
+        },
+        None => connect(target).await,
+    };
+    (index, res)
+}
+
+/// Connect to the first reachable target, racing attempts with
+/// `attempt_delay` between them. Each attempt is limited by `timeout`.
+///
+/// If there is a single target its error is returned as is, otherwise the
+/// errors of all attempts are returned as [`ConnectAttemptsError`].
+pub(crate) async fn connect(
+    targets: &[ResolvedTarget],
+    attempt_delay: Duration,
+    timeout: Option<Duration>,
+) -> Result<TokioStream, ConnectionError> {
+    race(targets, attempt_delay, timeout, ResolvedTarget::connect).await
+}
+
+async fn race<'a, S, F, Fut>(
+    targets: &'a [ResolvedTarget],
+    attempt_delay: Duration,
+    timeout: Option<Duration>,
+    connect: F,
+) -> Result<S, ConnectionError>
+where
+    F: Fn(&'a ResolvedTarget) -> Fut,
+    Fut: Future<Output = std::io::Result<S>>,
+{
+    if let [target] = targets {
+        let (_, res) = attempt(0, target, timeout, &connect).await;
+        return Ok(res?);
+    }
+
+    let mut next = 0;
+    let mut attempts = FuturesUnordered::new();
+    let mut errors = Vec::new();
+    let delay = tokio::time::sleep(attempt_delay);
+    let mut delay = std::pin::pin!(delay);
+    // Each iteration starts an attempt: initially, after the delay, or after
+    // an attempt has failed.
+    loop {
+        if let Some(target) = targets.get(next) {
+            attempts.push(attempt(next, target, timeout, &connect));
+            next += 1;
+            delay.as_mut().reset(Instant::now() + attempt_delay);
+        }
+        let result = if next < targets.len() {
+            match futures::future::select(attempts.next(), delay.as_mut()).await {
+                Either::Left((result, _)) => result,
+                Either::Right(((), _)) => continue,
+            }
+        } else {
+            attempts.next().await
+        };
+        match result {
+            Some((_, Ok(stream))) => return Ok(stream),
+            Some((index, Err(err))) => errors.push((index, err)),
+            None => break,
+        }
+    }
+
+    errors.sort_by_key(|(index, _)| *index);
+    Err(ConnectAttemptsError::new(
+        errors
+            .into_iter()
+            .map(|(index, err)| (targets[index].clone(), err))
+            .collect(),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryNetwork, MemoryStream, RemoteAddress};
+    use std::sync::Mutex;
+
+    fn addr(s: &str) -> ResolvedTarget {
+        ResolvedTarget::SocketAddr(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_sort_addresses() {
+        let sorted = sort_addresses(vec![
+            addr("10.0.0.1:1"),
+            addr("10.0.0.2:1"),
+            addr("10.0.0.3:1"),
+            addr("[fd00::1]:1"),
+            addr("[fd00::2]:1"),
+        ]);
+        assert_eq!(
+            sorted,
+            vec![
+                addr("[fd00::1]:1"),
+                addr("10.0.0.1:1"),
+                addr("[fd00::2]:1"),
+                addr("10.0.0.2:1"),
+                addr("10.0.0.3:1"),
+            ]
+        );
+    }
+
+    /// Races `targets` on an in-memory network. Connecting to a target is
+    /// delayed by its entry in `delays`, or never completes if the delay is
+    /// `None`. Returns the result and the times the attempts were started.
+    async fn race_on(
+        network: &MemoryNetwork,
+        delays: &[(&ResolvedTarget, Option<Duration>)],
+        targets: &[ResolvedTarget],
+        attempt_delay: Duration,
+    ) -> (
+        Result<MemoryStream, ConnectionError>,
+        Vec<(ResolvedTarget, Duration)>,
+    ) {
+        let start = Instant::now();
+        let started = Mutex::new(Vec::new());
+        let connect = |target: &ResolvedTarget| {
+            started
+                .lock()
+                .unwrap()
+                .push((target.clone(), start.elapsed()));
+            let delay = delays
+                .iter()
+                .find(|(t, _)| *t == target)
+                .map(|(_, delay)| *delay)
+                .unwrap_or(Some(Duration::ZERO));
+            let target = target.clone();
+            async move {
+                match delay {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => std::future::pending().await,
+                }
+                network.connect(&target)
+            }
+        };
+        let result = race(targets, attempt_delay, None, connect).await;
+        (result, started.into_inner().unwrap())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_skips_failed_address() {
+        let network = MemoryNetwork::new();
+        let _listener = network.listen(&addr("10.0.0.2:1")).unwrap();
+        let targets = [addr("10.0.0.1:1"), addr("10.0.0.2:1")];
+        let (result, started) = race_on(&network, &[], &targets, Duration::from_secs(60)).await;
+        assert_eq!(result.unwrap().remote_address().unwrap(), targets[1]);
+        assert_eq!(
+            started,
+            vec![
+                (targets[0].clone(), Duration::ZERO),
+                (targets[1].clone(), Duration::ZERO),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_races_slow_address() {
+        let network = MemoryNetwork::new();
+        let _listener = network.listen(&addr("10.0.0.2:1")).unwrap();
+        let targets = [addr("[fd00::1]:1"), addr("10.0.0.2:1")];
+        let delays = [(&targets[0], None)];
+        let (result, started) =
+            race_on(&network, &delays, &targets, Duration::from_millis(250)).await;
+        assert_eq!(result.unwrap().remote_address().unwrap(), targets[1]);
+        assert_eq!(started[1], (targets[1].clone(), Duration::from_millis(250)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_failure_starts_next_attempt() {
+        let network = MemoryNetwork::new();
+        let _listener = network.listen(&addr("10.0.0.4:1")).unwrap();
+        let targets = [
+            addr("[fd00::1]:1"),
+            addr("10.0.0.2:1"),
+            addr("[fd00::3]:1"),
+            addr("10.0.0.4:1"),
+        ];
+        // The first and third attempts never complete, the second one fails
+        // while the first is still in flight.
+        let delays = [
+            (&targets[0], None),
+            (&targets[1], Some(Duration::from_millis(100))),
+            (&targets[2], None),
+            (&targets[3], Some(Duration::from_millis(10))),
+        ];
+        let (result, started) =
+            race_on(&network, &delays, &targets, Duration::from_millis(250)).await;
+        assert_eq!(result.unwrap().remote_address().unwrap(), targets[3]);
+        let started: Vec<_> = started.into_iter().map(|(_, at)| at).collect();
+        assert_eq!(
+            started,
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(250),
+                // right after the failure
+                Duration::from_millis(350),
+                // the delay is counted from the previous attempt
+                Duration::from_millis(600),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_reports_all_failures() {
+        let network = MemoryNetwork::new();
+        let targets = [addr("10.0.0.1:1"), addr("10.0.0.2:1"), addr("[::1]:1")];
+        let delays = [(&targets[0], Some(Duration::from_millis(500)))];
+        let (result, _) = race_on(&network, &delays, &targets, Duration::from_millis(10)).await;
+        let ConnectionError::AllAttemptsFailed(err) = result.unwrap_err() else {
+            panic!("unexpected error");
+        };
+        let failed: Vec<_> = err.attempts().iter().map(|(t, _)| t.clone()).collect();
+        assert_eq!(failed, targets);
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+        assert!(err
+            .to_string()
+            .starts_with("Failed to connect to any address: "));
+    }
+}
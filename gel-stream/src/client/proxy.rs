@@ -0,0 +1,532 @@
+//! Tunneling connections through HTTP CONNECT and SOCKS5 proxies.
+//!
+//! The tunnel is established on the raw TCP stream to the proxy, before any
+//! TLS upgrade, so TLS runs end-to-end between the client and the target.
+
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::common::target::MaybeResolvedTarget;
+use crate::{PeekableStream, ResolvedTarget, TargetName};
+
+/// The maximum size of the HTTP proxy response header we are willing to read.
+const MAX_HTTP_RESPONSE: usize = 8192;
+
+/// The protocol used to talk to a proxy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProxyKind {
+    /// An HTTP proxy supporting the `CONNECT` method.
+    HttpConnect,
+    /// A SOCKS5 proxy (RFC 1928).
+    Socks5,
+}
+
+/// A proxy server to tunnel TCP connections through.
+#[derive(Clone, derive_more::Debug)]
+pub struct Proxy {
+    kind: ProxyKind,
+    target: TargetName,
+    #[debug(skip)]
+    credentials: Option<(String, String)>,
+    remote_dns: bool,
+}
+
+impl Proxy {
+    /// Create a new HTTP CONNECT proxy. The target host name is always sent
+    /// to the proxy as is and resolved by the proxy.
+    pub fn new_http(target: TargetName) -> Self {
+        Self {
+            kind: ProxyKind::HttpConnect,
+            target,
+            credentials: None,
+            remote_dns: true,
+        }
+    }
+
+    /// Create a new SOCKS5 proxy. By default, host names are resolved by the
+    /// proxy (see [`Proxy::set_remote_dns`]).
+    pub fn new_socks5(target: TargetName) -> Self {
+        Self {
+            kind: ProxyKind::Socks5,
+            target,
+            credentials: None,
+            remote_dns: true,
+        }
+    }
+
+    /// Set the username and password to authenticate with. HTTP proxies
+    /// receive them using basic auth, SOCKS5 proxies using username/password
+    /// authentication (RFC 1929).
+    pub fn set_credentials(&mut self, username: impl Into<String>, password: impl Into<String>) {
+        self.credentials = Some((username.into(), password.into()));
+    }
+
+    /// For SOCKS5 proxies, whether host names are sent to the proxy to be
+    /// resolved (the default) or resolved locally before connecting.
+    /// HTTP proxies always resolve host names themselves.
+    pub fn set_remote_dns(&mut self, remote_dns: bool) {
+        self.remote_dns = remote_dns;
+    }
+
+    /// The protocol used to talk to the proxy.
+    pub fn kind(&self) -> ProxyKind {
+        self.kind
+    }
+
+    /// The address of the proxy server.
+    pub fn target(&self) -> &TargetName {
+        &self.target
+    }
+
+    /// Whether the target host name needs to be resolved before the tunnel
+    /// can be established.
+    pub(crate) fn needs_local_dns(&self) -> bool {
+        self.kind == ProxyKind::Socks5 && !self.remote_dns
+    }
+
+    /// Establish a tunnel to `destination` on a stream connected to the proxy.
+    pub(crate) async fn handshake<S: PeekableStream>(
+        &self,
+        stream: &mut S,
+        destination: &MaybeResolvedTarget,
+    ) -> std::io::Result<()> {
+        let destination = match destination {
+            MaybeResolvedTarget::Resolved(ResolvedTarget::SocketAddr(addr)) => {
+                Destination::Addr(*addr)
+            }
+            MaybeResolvedTarget::Unresolved(host, port, _) => {
+                // IP literals are sent as addresses rather than host names
+                let ip = host.strip_prefix('[').and_then(|h| h.strip_suffix(']'));
+                match ip.unwrap_or(host).parse::<IpAddr>() {
+                    Ok(ip) => Destination::Addr(SocketAddr::new(ip, *port)),
+                    Err(_) => Destination::Name(host, *port),
+                }
+            }
+            #[cfg(unix)]
+            MaybeResolvedTarget::Resolved(ResolvedTarget::UnixSocketAddr(_)) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Unix sockets cannot be reached through a proxy",
+                ))
+            }
+        };
+        match self.kind {
+            ProxyKind::HttpConnect => self.http_connect(stream, destination).await,
+            ProxyKind::Socks5 => self.socks5_connect(stream, destination).await,
+        }
+    }
+
+    async fn http_connect<S: PeekableStream>(
+        &self,
+        stream: &mut S,
+        destination: Destination<'_>,
+    ) -> std::io::Result<()> {
+        let authority = match destination {
+            Destination::Addr(addr) => addr.to_string(),
+            // IPv6 literals have to be bracketed (RFC 9110, section 7.2)
+            Destination::Name(host, port) if host.contains(':') && !host.starts_with('[') => {
+                format!("[{host}]:{port}")
+            }
+            Destination::Name(host, port) => format!("{host}:{port}"),
+        };
+        let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+        if let Some((username, password)) = &self.credentials {
+            let token = BASE64_STANDARD.encode(format!("{username}:{password}"));
+            request.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+        stream.flush().await?;
+
+        // Peek so that we never consume data past the end of the response
+        // header, which belongs to the tunneled connection. Peeked data
+        // without the end of the header is consumed before peeking again.
+        let mut response = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let n = Pin::new(&mut *stream).peek(&mut buf).await?;
+            if n == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            let search_from = response.len().saturating_sub(3);
+            let consumed = response.len();
+            response.extend_from_slice(&buf[..n]);
+            if let Some(pos) = response[search_from..]
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+            {
+                response.truncate(search_from + pos + 4);
+                stream
+                    .read_exact(&mut buf[..response.len() - consumed])
+                    .await?;
+                break;
+            }
+            if response.len() >= MAX_HTTP_RESPONSE {
+                return Err(invalid_data("HTTP proxy response is too long"));
+            }
+            stream.read_exact(&mut buf[..n]).await?;
+        }
+        let status_line = response
+            .split(|&b| b == b'\r')
+            .next()
+            .and_then(|line| std::str::from_utf8(line).ok())
+            .unwrap_or_default();
+        let mut parts = status_line.splitn(3, ' ');
+        let (Some(version), Some(status)) = (parts.next(), parts.next()) else {
+            return Err(invalid_data("Invalid HTTP proxy response"));
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(invalid_data("Invalid HTTP proxy response"));
+        }
+        match status.parse::<u16>() {
+            Ok(200..=299) => Ok(()),
+            Ok(407) => Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("HTTP proxy authentication failed: {status_line}"),
+            )),
+            Ok(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("HTTP proxy refused to connect: {status_line}"),
+            )),
+            Err(_) => Err(invalid_data("Invalid HTTP proxy response")),
+        }
+    }
+
+    async fn socks5_connect<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: &mut S,
+        destination: Destination<'_>,
+    ) -> std::io::Result<()> {
+        const VERSION: u8 = 5;
+        const NO_AUTH: u8 = 0;
+        const USERNAME_PASSWORD: u8 = 2;
+
+        if self.credentials.is_some() {
+            stream
+                .write_all(&[VERSION, 2, NO_AUTH, USERNAME_PASSWORD])
+                .await?;
+        } else {
+            stream.write_all(&[VERSION, 1, NO_AUTH]).await?;
+        }
+        stream.flush().await?;
+        let mut reply = [0; 2];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != VERSION {
+            return Err(invalid_data("Invalid SOCKS5 proxy response"));
+        }
+        match (reply[1], &self.credentials) {
+            (NO_AUTH, _) => {}
+            (USERNAME_PASSWORD, Some((username, password))) => {
+                let mut request = vec![1];
+                push_with_len(&mut request, username.as_bytes(), "username")?;
+                push_with_len(&mut request, password.as_bytes(), "password")?;
+                stream.write_all(&request).await?;
+                stream.flush().await?;
+                stream.read_exact(&mut reply).await?;
+                if reply[1] != 0 {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::PermissionDenied,
+                        "SOCKS5 proxy authentication failed",
+                    ));
+                }
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "SOCKS5 proxy does not support any offered authentication method",
+                ))
+            }
+        }
+
+        let mut request = vec![VERSION, 1, 0];
+        let port = match destination {
+            Destination::Addr(SocketAddr::V4(addr)) => {
+                request.push(1);
+                request.extend_from_slice(&addr.ip().octets());
+                addr.port()
+            }
+            Destination::Addr(SocketAddr::V6(addr)) => {
+                request.push(4);
+                request.extend_from_slice(&addr.ip().octets());
+                addr.port()
+            }
+            Destination::Name(host, port) => {
+                request.push(3);
+                push_with_len(&mut request, host.as_bytes(), "host name")?;
+                port
+            }
+        };
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await?;
+        stream.flush().await?;
+
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != VERSION {
+            return Err(invalid_data("Invalid SOCKS5 proxy response"));
+        }
+        let (kind, message) = match reply[1] {
+            0 => (None, ""),
+            2 => (
+                Some(std::io::ErrorKind::PermissionDenied),
+                "connection not allowed by ruleset",
+            ),
+            3 => (
+                Some(std::io::ErrorKind::NetworkUnreachable),
+                "network unreachable",
+            ),
+            4 => (
+                Some(std::io::ErrorKind::HostUnreachable),
+                "host unreachable",
+            ),
+            5 => (
+                Some(std::io::ErrorKind::ConnectionRefused),
+                "connection refused",
+            ),
+            6 => (Some(std::io::ErrorKind::TimedOut), "TTL expired"),
+            7 => (
+                Some(std::io::ErrorKind::Unsupported),
+                "command not supported",
+            ),
+            8 => (
+                Some(std::io::ErrorKind::Unsupported),
+                "address type not supported",
+            ),
+            _ => (Some(std::io::ErrorKind::Other), "general failure"),
+        };
+        if let Some(kind) = kind {
+            return Err(std::io::Error::new(
+                kind,
+                format!("SOCKS5 proxy failed to connect: {message}"),
+            ));
+        }
+        // Skip the bound address and port.
+        let len = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => stream.read_u8().await? as usize,
+            _ => return Err(invalid_data("Invalid SOCKS5 proxy response")),
+        };
+        let mut bound = vec![0; len + 2];
+        stream.read_exact(&mut bound).await?;
+        Ok(())
+    }
+}
+
+enum Destination<'a> {
+    Addr(SocketAddr),
+    Name(&'a str, u16),
+}
+
+fn invalid_data(message: &'static str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+fn push_with_len(buf: &mut Vec<u8>, value: &[u8], what: &str) -> std::io::Result<()> {
+    let len = u8::try_from(value.len()).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("SOCKS5 {what} is too long"),
+        )
+    })?;
+    buf.push(len);
+    buf.extend_from_slice(value);
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::common::tokio_stream::TokioStream;
+    use crate::{MemoryStream, MemoryStreamConfig};
+    use std::borrow::Cow;
+
+    fn pair() -> (TokioStream, TokioStream) {
+        let (a, b) = MemoryStream::pair(MemoryStreamConfig::default()).unwrap();
+        (
+            TokioStream::Memory(Box::new(a)),
+            TokioStream::Memory(Box::new(b)),
+        )
+    }
+
+    fn proxy_target() -> TargetName {
+        TargetName::new_tcp(("proxy.example.com", 3128))
+    }
+
+    fn destination() -> MaybeResolvedTarget {
+        MaybeResolvedTarget::Unresolved(Cow::Borrowed("db.example.com"), 5656, None)
+    }
+
+    #[tokio::test]
+    async fn test_http_connect() {
+        let mut proxy = Proxy::new_http(proxy_target());
+        proxy.set_credentials("user", "pass");
+        let (mut client, mut server) = pair();
+        let server = tokio::spawn(async move {
+            let mut buf = vec![0; 1024];
+            let n = server.read(&mut buf).await.unwrap();
+            buf.truncate(n);
+            // The end of the header is split across writes.
+            server
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r")
+                .await
+                .unwrap();
+            tokio::task::yield_now().await;
+            server.write_all(b"\ntunneled").await.unwrap();
+            String::from_utf8(buf).unwrap()
+        });
+        proxy.handshake(&mut client, &destination()).await.unwrap();
+        assert_eq!(
+            server.await.unwrap(),
+            "CONNECT db.example.com:5656 HTTP/1.1\r\n\
+            Host: db.example.com:5656\r\n\
+            Proxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n"
+        );
+        // Data after the response belongs to the tunnel.
+        let mut rest = String::new();
+        client.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "tunneled");
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_auth_required() {
+        let proxy = Proxy::new_http(proxy_target());
+        let (mut client, mut server) = pair();
+        tokio::spawn(async move {
+            let mut buf = vec![0; 1024];
+            _ = server.read(&mut buf).await.unwrap();
+            server
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let err = proxy
+            .handshake(&mut client, &destination())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_ipv6() {
+        let proxy = Proxy::new_http(proxy_target());
+        for (destination, authority) in [
+            (
+                MaybeResolvedTarget::Unresolved(Cow::Borrowed("fd00::1"), 5656, None),
+                "[fd00::1]:5656",
+            ),
+            (
+                MaybeResolvedTarget::Resolved(ResolvedTarget::SocketAddr(
+                    "[fd00::2]:5656".parse().unwrap(),
+                )),
+                "[fd00::2]:5656",
+            ),
+        ] {
+            let (mut client, mut server) = pair();
+            let server = tokio::spawn(async move {
+                let mut buf = vec![0; 1024];
+                let n = server.read(&mut buf).await.unwrap();
+                buf.truncate(n);
+                server.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+                String::from_utf8(buf).unwrap()
+            });
+            proxy.handshake(&mut client, &destination).await.unwrap();
+            assert_eq!(
+                server.await.unwrap(),
+                format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n\r\n")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connect() {
+        let mut proxy = Proxy::new_socks5(proxy_target());
+        proxy.set_credentials("user", "pass");
+        let (mut client, mut server) = pair();
+        let server = tokio::spawn(async move {
+            let mut greeting = [0; 4];
+            server.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 2, 0, 2]);
+            server.write_all(&[5, 2]).await.unwrap();
+            let mut auth = [0; 11];
+            server.read_exact(&mut auth).await.unwrap();
+            assert_eq!(&auth, b"\x01\x04user\x04pass");
+            server.write_all(&[1, 0]).await.unwrap();
+            let mut request = vec![0; 5 + 14 + 2];
+            server.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request, b"\x05\x01\x00\x03\x0edb.example.com\x16\x18");
+            server
+                .write_all(&[5, 0, 0, 1, 10, 0, 0, 1, 0x16, 0x18])
+                .await
+                .unwrap();
+            server.write_all(b"tunneled").await.unwrap();
+        });
+        proxy.handshake(&mut client, &destination()).await.unwrap();
+        server.await.unwrap();
+        let mut rest = String::new();
+        client.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "tunneled");
+    }
+
+    #[tokio::test]
+    async fn test_socks5_ip_literal() {
+        let proxy = Proxy::new_socks5(proxy_target());
+        for (host, expected) in [
+            ("10.0.0.3", &b"\x01\x0a\x00\x00\x03"[..]),
+            (
+                "[fd00::1]",
+                b"\x04\xfd\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01",
+            ),
+        ] {
+            let (mut client, mut server) = pair();
+            let server = tokio::spawn(async move {
+                let mut greeting = [0; 3];
+                server.read_exact(&mut greeting).await.unwrap();
+                server.write_all(&[5, 0]).await.unwrap();
+                let mut request = vec![0; 3 + expected.len() + 2];
+                server.read_exact(&mut request).await.unwrap();
+                server
+                    .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                    .await
+                    .unwrap();
+                request
+            });
+            let destination = MaybeResolvedTarget::Unresolved(Cow::Borrowed(host), 5656, None);
+            proxy.handshake(&mut client, &destination).await.unwrap();
+            let request = server.await.unwrap();
+            assert_eq!(&request[..3], &[5, 1, 0]);
+            assert_eq!(&request[3..request.len() - 2], expected, "{host}");
+            assert_eq!(&request[request.len() - 2..], &[0x16, 0x18]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socks5_connect_refused() {
+        let proxy = Proxy::new_socks5(proxy_target());
+        let (mut client, mut server) = pair();
+        tokio::spawn(async move {
+            let mut greeting = [0; 3];
+            server.read_exact(&mut greeting).await.unwrap();
+            server.write_all(&[5, 0]).await.unwrap();
+            let mut request = [0; 10];
+            server.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [5, 1, 0, 1, 10, 0, 0, 2, 0x16, 0x18]);
+            server
+                .write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+        let destination = MaybeResolvedTarget::Resolved(ResolvedTarget::SocketAddr(
+            "10.0.0.2:5656".parse().unwrap(),
+        ));
+        let err = proxy
+            .handshake(&mut client, &destination)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+    }
+}
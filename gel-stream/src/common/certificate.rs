@@ -0,0 +1,139 @@
+use std::{
+    net::IpAddr,
+    time::{Duration, SystemTime},
+};
+
+use rustls_pki_types::CertificateDer;
+use sha2::Digest;
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+/// A parsed view of an X.509 certificate, suitable for mapping client
+/// certificates to users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    /// The subject distinguished name (ie: `C=US, O=Example, CN=user`).
+    pub subject: String,
+    /// The first common name (CN) of the subject, if any.
+    pub common_name: Option<String>,
+    /// The issuer distinguished name.
+    pub issuer: String,
+    /// The serial number as colon-separated hex bytes.
+    pub serial: String,
+    /// DNS names from the subject alternative name extension.
+    pub dns_names: Vec<String>,
+    /// Email addresses from the subject alternative name extension.
+    pub email_addresses: Vec<String>,
+    /// URIs from the subject alternative name extension.
+    pub uris: Vec<String>,
+    /// IP addresses from the subject alternative name extension.
+    pub ip_addresses: Vec<IpAddr>,
+    /// The start of the certificate's validity period.
+    pub not_before: SystemTime,
+    /// The end of the certificate's validity period.
+    pub not_after: SystemTime,
+    /// The SHA-256 digest of the DER-encoded certificate.
+    pub sha256_fingerprint: [u8; 32],
+}
+
+impl CertificateInfo {
+    /// Parse a DER-encoded certificate.
+    pub fn parse(cert: &CertificateDer) -> Result<Self, std::io::Error> {
+        let invalid = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let (_, parsed) = X509Certificate::from_der(cert.as_ref())
+            .map_err(|e| invalid(format!("Invalid certificate: {e}")))?;
+
+        let mut info = Self {
+            subject: parsed.subject().to_string(),
+            common_name: parsed
+                .subject()
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(str::to_owned),
+            issuer: parsed.issuer().to_string(),
+            serial: parsed.raw_serial_as_string(),
+            dns_names: vec![],
+            email_addresses: vec![],
+            uris: vec![],
+            ip_addresses: vec![],
+            not_before: timestamp(parsed.validity().not_before.timestamp()),
+            not_after: timestamp(parsed.validity().not_after.timestamp()),
+            sha256_fingerprint: sha2::Sha256::digest(cert.as_ref()).into(),
+        };
+
+        let san = parsed
+            .subject_alternative_name()
+            .map_err(|e| invalid(format!("Invalid subject alternative name: {e}")))?;
+        for name in san.iter().flat_map(|san| &san.value.general_names) {
+            match name {
+                GeneralName::DNSName(name) => info.dns_names.push(name.to_string()),
+                GeneralName::RFC822Name(email) => info.email_addresses.push(email.to_string()),
+                GeneralName::URI(uri) => info.uris.push(uri.to_string()),
+                GeneralName::IPAddress(ip) => {
+                    if let Ok(ip) = <[u8; 4]>::try_from(*ip) {
+                        info.ip_addresses.push(IpAddr::from(ip));
+                    } else if let Ok(ip) = <[u8; 16]>::try_from(*ip) {
+                        info.ip_addresses.push(IpAddr::from(ip));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// The SHA-256 fingerprint as colon-separated uppercase hex bytes, in the
+    /// same format as `openssl x509 -fingerprint -sha256`.
+    pub fn sha256_fingerprint_hex(&self) -> String {
+        self.sha256_fingerprint
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+}
+
+fn timestamp(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        SystemTime::UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_client_certificate() {
+        let info = CertificateInfo::parse(&crate::test_keys::binary::CLIENT_CERT).unwrap();
+        assert_eq!(info.common_name.as_deref(), Some("ssl_user"));
+        assert!(info.subject.contains("CN=ssl_user"), "{}", info.subject);
+        assert!(
+            info.issuer.contains("CN=EdgeDB test client CA"),
+            "{}",
+            info.issuer
+        );
+        assert_eq!(info.dns_names, vec!["localhost"]);
+        assert!(info.email_addresses.is_empty());
+        assert!(info.ip_addresses.is_empty());
+        assert_eq!(
+            info.serial,
+            "3d:f7:a3:f0:84:3f:e5:b0:ab:8a:1a:96:22:6a:ea:da:f2:8f:3a:be"
+        );
+        assert_eq!(
+            info.sha256_fingerprint_hex(),
+            "0D:81:9E:4D:B9:8C:A4:E9:A1:24:5A:28:AF:49:B6:7D:\
+             52:72:E0:78:41:5D:81:04:68:EC:F8:CD:7D:77:3A:23"
+        );
+        assert!(info.not_before < info.not_after);
+    }
+
+    #[test]
+    fn test_parse_invalid_certificate() {
+        let err = CertificateInfo::parse(&CertificateDer::from(vec![1, 2, 3])).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,827 @@
+//! In-memory streams for deterministic testing without sockets.
+//!
+//! A [`MemoryStream`] pair behaves like a connected socket pair, with
+//! optional latency, fragmentation and fault injection. A [`MemoryNetwork`]
+//! routes [`crate::Connector`] and [`crate::Acceptor`] connections to
+//! in-memory listeners instead of the operating system's sockets.
+//!
+//! Memory streams are deliberately only available on Unix. Data flows
+//! through in-process buffers, but every [`crate::Stream`] must also provide
+//! an OS handle through [`crate::AsHandle`], so each memory stream holds half
+//! of an idle Unix socket pair that carries no data. Windows only offers
+//! socket handles there, which would require real loopback connections and
+//! defeat the purpose of an in-memory network.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures::{channel::mpsc, StreamExt};
+use tokio::{
+    io::ReadBuf,
+    time::{Instant, Sleep},
+};
+
+use crate::{
+    AsHandle, LocalAddress, PeerCred, RemoteAddress, ResolvedTarget, StreamMetadata, Transport,
+};
+
+/// The first port assigned to listeners bound to port zero.
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// Configuration for the behaviour of [`MemoryStream`]s.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryStreamConfig {
+    /// The delay before written data becomes readable by the peer.
+    pub latency: Duration,
+    /// The maximum number of bytes returned by a single read, to simulate
+    /// fragmented delivery.
+    pub max_read_size: Option<NonZeroUsize>,
+    /// The maximum number of bytes accepted by a single write, to simulate
+    /// partial writes.
+    pub max_write_size: Option<NonZeroUsize>,
+    /// The number of unread bytes buffered in each direction before writes
+    /// block.
+    pub buffer_size: NonZeroUsize,
+    /// Reset the connection once this many bytes have been written in either
+    /// direction. As with a TCP reset, data the peer has not read yet is
+    /// discarded. A limit of zero resets the connection as soon as it is
+    /// created.
+    pub reset_after: Option<u64>,
+}
+
+impl Default for MemoryStreamConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            max_read_size: None,
+            max_write_size: None,
+            buffer_size: NonZeroUsize::new(64 * 1024).unwrap(),
+            reset_after: None,
+        }
+    }
+}
+
+struct Chunk {
+    ready_at: Instant,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+/// One direction of a stream pair.
+#[derive(Default)]
+struct Pipe {
+    chunks: VecDeque<Chunk>,
+    /// Unread bytes in `chunks`.
+    buffered: usize,
+    /// Total bytes written.
+    written: u64,
+    /// The writer has shut down or was dropped.
+    write_closed: bool,
+    /// The reader has shut down or was dropped.
+    read_closed: bool,
+    reset: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn wake(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Copy ready data into `buf`, consuming it if `consume` is set.
+    fn copy_ready(&mut self, buf: &mut [u8], consume: bool) -> usize {
+        let now = Instant::now();
+        let mut copied = 0;
+        let mut index = 0;
+        while copied < buf.len() {
+            let Some(chunk) = self.chunks.get_mut(index) else {
+                break;
+            };
+            if chunk.ready_at > now {
+                break;
+            }
+            let available = &chunk.data[chunk.offset..];
+            let n = available.len().min(buf.len() - copied);
+            buf[copied..copied + n].copy_from_slice(&available[..n]);
+            copied += n;
+            if !consume {
+                index += 1;
+            } else if n == available.len() {
+                self.chunks.pop_front();
+            } else {
+                chunk.offset += n;
+            }
+        }
+        if consume && copied > 0 {
+            self.buffered -= copied;
+            if let Some(waker) = self.write_waker.take() {
+                waker.wake();
+            }
+        }
+        copied
+    }
+}
+
+fn reset_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        "Memory stream was reset",
+    )
+}
+
+fn broken_pipe_error() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Memory stream is closed")
+}
+
+/// One end of an in-memory stream pair.
+///
+/// Each end owns one half of an idle Unix socket pair so that it can provide
+/// a file descriptor for socket options and peer credentials. No data is sent
+/// over it.
+#[derive(derive_more::Debug)]
+#[debug("MemoryStream({local:?} -> {remote:?})")]
+pub struct MemoryStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+    config: MemoryStreamConfig,
+    delay: Mutex<Option<Pin<Box<Sleep>>>>,
+    local: ResolvedTarget,
+    remote: ResolvedTarget,
+    descriptor: std::os::unix::net::UnixStream,
+}
+
+impl MemoryStream {
+    /// Create a connected pair of streams. Both ends report unnamed Unix
+    /// socket addresses.
+    pub fn pair(config: MemoryStreamConfig) -> std::io::Result<(Self, Self)> {
+        let (a, b) = std::os::unix::net::UnixStream::pair()?;
+        let a_addr = ResolvedTarget::from(a.local_addr()?);
+        let b_addr = ResolvedTarget::from(b.local_addr()?);
+        Ok(Self::pair_with_descriptors(
+            config,
+            (a, a_addr),
+            (b, b_addr),
+        ))
+    }
+
+    /// Create a connected pair of streams reporting the given addresses.
+    fn pair_with_addresses(
+        config: MemoryStreamConfig,
+        a_addr: ResolvedTarget,
+        b_addr: ResolvedTarget,
+    ) -> std::io::Result<(Self, Self)> {
+        let (a, b) = std::os::unix::net::UnixStream::pair()?;
+        Ok(Self::pair_with_descriptors(
+            config,
+            (a, a_addr),
+            (b, b_addr),
+        ))
+    }
+
+    fn pair_with_descriptors(
+        config: MemoryStreamConfig,
+        (a, a_addr): (std::os::unix::net::UnixStream, ResolvedTarget),
+        (b, b_addr): (std::os::unix::net::UnixStream, ResolvedTarget),
+    ) -> (Self, Self) {
+        let a_to_b = Arc::new(Mutex::new(Pipe::default()));
+        let b_to_a = Arc::new(Mutex::new(Pipe::default()));
+        let a = Self {
+            read: b_to_a.clone(),
+            write: a_to_b.clone(),
+            config,
+            delay: Mutex::new(None),
+            local: a_addr.clone(),
+            remote: b_addr.clone(),
+            descriptor: a,
+        };
+        let b = Self {
+            read: a_to_b,
+            write: b_to_a,
+            config,
+            delay: Mutex::new(None),
+            local: b_addr,
+            remote: a_addr,
+            descriptor: b,
+        };
+        if config.reset_after == Some(0) {
+            a.reset();
+        }
+        (a, b)
+    }
+
+    /// Reset the connection: all further reads and writes on both ends fail
+    /// with [`std::io::ErrorKind::ConnectionReset`].
+    pub fn reset(&self) {
+        for pipe in [&self.read, &self.write] {
+            let mut pipe = pipe.lock().unwrap();
+            pipe.reset = true;
+            pipe.chunks.clear();
+            pipe.buffered = 0;
+            pipe.wake();
+        }
+    }
+
+    /// Poll until data, EOF or an error is available to read.
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        loop {
+            let ready_at = {
+                let mut pipe = self.read.lock().unwrap();
+                if pipe.reset || pipe.read_closed {
+                    return Poll::Ready(Ok(()));
+                }
+                let ready_at = pipe.chunks.front().map(|chunk| chunk.ready_at);
+                match ready_at {
+                    Some(ready_at) if ready_at <= Instant::now() => return Poll::Ready(Ok(())),
+                    None if pipe.write_closed => return Poll::Ready(Ok(())),
+                    _ => {}
+                }
+                pipe.read_waker = Some(cx.waker().clone());
+                match ready_at {
+                    Some(ready_at) => ready_at,
+                    None => return Poll::Pending,
+                }
+            };
+
+            // The next chunk is delayed by the configured latency.
+            let mut delay = self.delay.lock().unwrap();
+            match delay.as_mut() {
+                Some(delay) => delay.as_mut().reset(ready_at),
+                None => *delay = Some(Box::pin(tokio::time::sleep_until(ready_at))),
+            }
+            ready!(delay.as_mut().unwrap().as_mut().poll(cx));
+        }
+    }
+
+    /// Poll until there is room to write, or writing would fail.
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let mut pipe = self.write.lock().unwrap();
+        if pipe.reset
+            || pipe.write_closed
+            || pipe.read_closed
+            || pipe.buffered < self.config.buffer_size.get()
+        {
+            return Poll::Ready(Ok(()));
+        }
+        pipe.write_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Read available data without blocking, returning
+    /// [`std::io::ErrorKind::WouldBlock`] if there is none.
+    pub fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.try_read_inner(buf, true)
+    }
+
+    /// Read available data without consuming it.
+    pub fn try_peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.try_read_inner(buf, false)
+    }
+
+    fn try_read_inner(&self, buf: &mut [u8], consume: bool) -> std::io::Result<usize> {
+        let mut pipe = self.read.lock().unwrap();
+        if pipe.reset {
+            return Err(reset_error());
+        }
+        if pipe.read_closed || buf.is_empty() {
+            return Ok(0);
+        }
+        let limit = match self.config.max_read_size {
+            Some(max) => buf.len().min(max.get()),
+            None => buf.len(),
+        };
+        match pipe.copy_ready(&mut buf[..limit], consume) {
+            0 if pipe.chunks.is_empty() && pipe.write_closed => Ok(0),
+            0 => Err(std::io::ErrorKind::WouldBlock.into()),
+            n => Ok(n),
+        }
+    }
+
+    /// Write data without blocking, returning
+    /// [`std::io::ErrorKind::WouldBlock`] if the buffer is full.
+    pub fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut pipe = self.write.lock().unwrap();
+        if pipe.reset {
+            return Err(reset_error());
+        }
+        if pipe.write_closed || pipe.read_closed {
+            return Err(broken_pipe_error());
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut n = buf
+            .len()
+            .min(self.config.buffer_size.get() - pipe.buffered.min(self.config.buffer_size.get()));
+        if let Some(max) = self.config.max_write_size {
+            n = n.min(max.get());
+        }
+        if let Some(limit) = self.config.reset_after {
+            n = n.min(limit.saturating_sub(pipe.written) as usize);
+        }
+        if n == 0 {
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+        pipe.chunks.push_back(Chunk {
+            ready_at: Instant::now() + self.config.latency,
+            data: buf[..n].to_vec(),
+            offset: 0,
+        });
+        pipe.buffered += n;
+        pipe.written += n as u64;
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+
+        let reset = self
+            .config
+            .reset_after
+            .is_some_and(|limit| pipe.written >= limit);
+        drop(pipe);
+        if reset {
+            self.reset();
+        }
+        Ok(n)
+    }
+
+    /// Shut down the read half, write half or both halves of the stream.
+    pub(crate) fn close(&self, how: std::net::Shutdown) {
+        if matches!(how, std::net::Shutdown::Read | std::net::Shutdown::Both) {
+            let mut pipe = self.read.lock().unwrap();
+            pipe.read_closed = true;
+            pipe.wake();
+        }
+        if matches!(how, std::net::Shutdown::Write | std::net::Shutdown::Both) {
+            let mut pipe = self.write.lock().unwrap();
+            pipe.write_closed = true;
+            pipe.wake();
+        }
+    }
+}
+
+impl Drop for MemoryStream {
+    fn drop(&mut self) {
+        self.close(std::net::Shutdown::Both);
+    }
+}
+
+impl tokio::io::AsyncRead for MemoryStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            ready!(self.poll_read_ready(cx))?;
+            match self.try_read(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
+
+impl tokio::io::AsyncWrite for MemoryStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        loop {
+            ready!(self.poll_write_ready(cx))?;
+            match self.try_write(buf) {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+                res => return Poll::Ready(res),
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if self.write.lock().unwrap().reset {
+            return Poll::Ready(Err(reset_error()));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if self.write.lock().unwrap().reset {
+            return Poll::Ready(Err(reset_error()));
+        }
+        self.close(std::net::Shutdown::Write);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl std::os::fd::AsFd for MemoryStream {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.descriptor.as_fd()
+    }
+}
+
+impl std::os::fd::AsRawFd for MemoryStream {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.descriptor.as_raw_fd()
+    }
+}
+
+impl AsHandle for MemoryStream {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd {
+        <Self as std::os::fd::AsFd>::as_fd(self)
+    }
+}
+
+impl LocalAddress for MemoryStream {
+    fn local_address(&self) -> std::io::Result<ResolvedTarget> {
+        Ok(self.local.clone())
+    }
+}
+
+impl RemoteAddress for MemoryStream {
+    fn remote_address(&self) -> std::io::Result<ResolvedTarget> {
+        Ok(self.remote.clone())
+    }
+}
+
+impl PeerCred for MemoryStream {
+    /// The credentials of this process for streams reporting Unix socket
+    /// addresses, like the Unix socket pair backing the stream. Streams
+    /// pretending to be TCP connections have no peer credentials.
+    fn peer_cred(&self) -> std::io::Result<tokio::net::unix::UCred> {
+        if self.local.is_tcp() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "TCP sockets do not support peer credentials",
+            ));
+        }
+        let descriptor = self.descriptor.try_clone()?;
+        descriptor.set_nonblocking(true)?;
+        tokio::net::UnixStream::from_std(descriptor)?.peer_cred()
+    }
+}
+
+impl StreamMetadata for MemoryStream {
+    fn transport(&self) -> Transport {
+        if self.local.is_tcp() {
+            Transport::Tcp
+        } else {
+            Transport::Unix
+        }
+    }
+}
+
+type Incoming = (MemoryStream, ResolvedTarget);
+
+#[derive(Default)]
+struct NetworkInner {
+    listeners: HashMap<ResolvedTarget, mpsc::UnboundedSender<Incoming>>,
+    next_port: u16,
+    config: MemoryStreamConfig,
+}
+
+/// A set of in-memory listeners that connections can be routed to instead of
+/// the operating system's sockets. Only available on Unix.
+///
+/// Listeners are addressed by [`ResolvedTarget`], so TCP and Unix socket
+/// targets work unchanged: a listener bound to port zero is assigned a port,
+/// and a listener bound to an unspecified address accepts connections to any
+/// address with the same port.
+///
+/// ```no_run
+/// # use gel_stream::*;
+/// # use futures::StreamExt;
+/// # async fn example() -> Result<(), ConnectionError> {
+/// let network = MemoryNetwork::new();
+/// let mut listener = Acceptor::new_tcp("127.0.0.1:0".parse().unwrap())
+///     .with_memory_network(&network)
+///     .bind()
+///     .await?;
+/// let addr = listener.local_address()?;
+/// let mut connector = Connector::new_resolved(addr);
+/// connector.set_memory_network(Some(network));
+/// let (client, server) = tokio::join!(connector.connect(), listener.next());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default, derive_more::Debug)]
+#[debug("MemoryNetwork(...)")]
+pub struct MemoryNetwork {
+    inner: Arc<Mutex<NetworkInner>>,
+}
+
+impl MemoryNetwork {
+    /// Create a network with the default stream configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a network whose connections use the given configuration.
+    pub fn with_config(config: MemoryStreamConfig) -> Self {
+        let network = Self::default();
+        network.set_config(config);
+        network
+    }
+
+    /// Change the configuration of subsequent connections.
+    pub fn set_config(&self, config: MemoryStreamConfig) {
+        self.inner.lock().unwrap().config = config;
+    }
+
+    /// Listen for connections to the given address.
+    pub fn listen(&self, target: &ResolvedTarget) -> std::io::Result<MemoryListener> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.listeners.retain(|_, sender| !sender.is_closed());
+
+        let mut target = target.clone();
+        if let Some(addr) = target.tcp().filter(|addr| addr.port() == 0) {
+            if inner.next_port < FIRST_EPHEMERAL_PORT {
+                inner.next_port = FIRST_EPHEMERAL_PORT;
+            }
+            loop {
+                let candidate = ResolvedTarget::from(SocketAddr::new(addr.ip(), inner.next_port));
+                inner.next_port = inner
+                    .next_port
+                    .checked_add(1)
+                    .unwrap_or(FIRST_EPHEMERAL_PORT);
+                if !inner.listeners.contains_key(&candidate) {
+                    target = candidate;
+                    break;
+                }
+            }
+        } else if inner.listeners.contains_key(&target) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "Address already in use",
+            ));
+        }
+
+        let (sender, receiver) = mpsc::unbounded();
+        inner.listeners.insert(target.clone(), sender);
+        Ok(MemoryListener { target, receiver })
+    }
+
+    /// Connect to a listener on this network.
+    pub fn connect(&self, target: &ResolvedTarget) -> std::io::Result<MemoryStream> {
+        let mut inner = self.inner.lock().unwrap();
+        let any = target.tcp().map(|addr| {
+            let ip = match addr {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            };
+            ResolvedTarget::from(SocketAddr::new(ip, addr.port()))
+        });
+        let Some(sender) = inner
+            .listeners
+            .get(target)
+            .or_else(|| any.and_then(|any| inner.listeners.get(&any)))
+            .filter(|sender| !sender.is_closed())
+            .cloned()
+        else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "Connection refused",
+            ));
+        };
+
+        let client_addr = match target.tcp() {
+            Some(addr) => {
+                let ip = if addr.is_ipv4() {
+                    IpAddr::V4(Ipv4Addr::LOCALHOST)
+                } else {
+                    IpAddr::V6(Ipv6Addr::LOCALHOST)
+                };
+                if inner.next_port < FIRST_EPHEMERAL_PORT {
+                    inner.next_port = FIRST_EPHEMERAL_PORT;
+                }
+                let port = inner.next_port;
+                inner.next_port = inner
+                    .next_port
+                    .checked_add(1)
+                    .unwrap_or(FIRST_EPHEMERAL_PORT);
+                Some(ResolvedTarget::from(SocketAddr::new(ip, port)))
+            }
+            None => None,
+        };
+        let config = inner.config;
+        drop(inner);
+
+        let (client, server) = match client_addr {
+            Some(client_addr) => {
+                MemoryStream::pair_with_addresses(config, client_addr, target.clone())?
+            }
+            None => {
+                // Unix clients are unnamed, like a real Unix socket client.
+                let (a, b) = std::os::unix::net::UnixStream::pair()?;
+                let client_addr = ResolvedTarget::from(a.local_addr()?);
+                MemoryStream::pair_with_descriptors(config, (a, client_addr), (b, target.clone()))
+            }
+        };
+        let peer = client.local.clone();
+        sender.unbounded_send((server, peer)).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "Connection refused")
+        })?;
+        Ok(client)
+    }
+
+    /// Connect to the first target with a listener. If there is a single
+    /// target its error is returned as is, otherwise the errors of all
+    /// targets are returned as [`crate::ConnectAttemptsError`].
+    #[cfg(feature = "client")]
+    pub(crate) fn connect_any(
+        &self,
+        targets: &[ResolvedTarget],
+    ) -> Result<MemoryStream, crate::ConnectionError> {
+        let mut errors = Vec::new();
+        for target in targets {
+            match self.connect(target) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => errors.push((target.clone(), e)),
+            }
+        }
+        if errors.len() == 1 {
+            Err(errors.pop().unwrap().1.into())
+        } else {
+            Err(crate::ConnectAttemptsError::new(errors).into())
+        }
+    }
+}
+
+/// A listener on a [`MemoryNetwork`]. Incoming connections are queued until
+/// accepted, and refused once the listener is dropped.
+#[derive(derive_more::Debug)]
+#[debug("MemoryListener({target:?})")]
+pub struct MemoryListener {
+    target: ResolvedTarget,
+    receiver: mpsc::UnboundedReceiver<Incoming>,
+}
+
+impl LocalAddress for MemoryListener {
+    fn local_address(&self) -> std::io::Result<ResolvedTarget> {
+        Ok(self.target.clone())
+    }
+}
+
+impl futures::Stream for MemoryListener {
+    type Item = std::io::Result<(MemoryStream, ResolvedTarget)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver
+            .poll_next_unpin(cx)
+            .map(|incoming| incoming.map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_pair() {
+        let (mut a, mut b) = MemoryStream::pair(MemoryStreamConfig::default()).unwrap();
+        a.write_all(b"hello").await.unwrap();
+        a.shutdown().await.unwrap();
+        let mut buf = String::new();
+        b.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "hello");
+        assert_eq!(b.transport(), Transport::Unix);
+        b.peer_cred().unwrap();
+
+        let (a, _b) = MemoryStream::pair_with_addresses(
+            MemoryStreamConfig::default(),
+            ResolvedTarget::SocketAddr(([127, 0, 0, 1], 1234).into()),
+            ResolvedTarget::SocketAddr(([127, 0, 0, 1], 5656).into()),
+        )
+        .unwrap();
+        assert_eq!(a.transport(), Transport::Tcp);
+        let err = a.peer_cred().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[tokio::test]
+    async fn test_fragmentation() {
+        let config = MemoryStreamConfig {
+            max_read_size: NonZeroUsize::new(3),
+            max_write_size: NonZeroUsize::new(2),
+            buffer_size: NonZeroUsize::new(4).unwrap(),
+            ..Default::default()
+        };
+        let (a, mut b) = MemoryStream::pair(config).unwrap();
+        assert_eq!(a.try_write(b"hello").unwrap(), 2);
+        assert_eq!(a.try_write(b"llo").unwrap(), 2);
+        // The buffer is full.
+        assert_eq!(
+            a.try_write(b"o").unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+        let mut buf = [0; 8];
+        assert_eq!(b.try_peek(&mut buf).unwrap(), 3);
+        assert_eq!(b.read(&mut buf).await.unwrap(), 3);
+        assert_eq!(&buf[..3], b"hel");
+        assert_eq!(a.try_write(b"o").unwrap(), 1);
+        assert_eq!(b.read(&mut buf).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"lo");
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let config = MemoryStreamConfig {
+            latency: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let (mut a, mut b) = MemoryStream::pair(config).unwrap();
+        let start = Instant::now();
+        a.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        assert_eq!(
+            b.try_read(&mut buf).unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+        b.read_exact(&mut buf).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_reset() {
+        let config = MemoryStreamConfig {
+            reset_after: Some(4),
+            ..Default::default()
+        };
+        let (mut a, mut b) = MemoryStream::pair(config).unwrap();
+        assert_eq!(a.write(b"hello").await.unwrap(), 4);
+        let mut buf = [0; 8];
+        assert_eq!(
+            b.read(&mut buf).await.unwrap_err().kind(),
+            std::io::ErrorKind::ConnectionReset
+        );
+        assert_eq!(
+            a.write(b"o").await.unwrap_err().kind(),
+            std::io::ErrorKind::ConnectionReset
+        );
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(1_000)]
+    async fn test_reset_immediately() {
+        let config = MemoryStreamConfig {
+            reset_after: Some(0),
+            ..Default::default()
+        };
+        let (mut a, mut b) = MemoryStream::pair(config).unwrap();
+        assert_eq!(
+            a.write(b"hello").await.unwrap_err().kind(),
+            std::io::ErrorKind::ConnectionReset
+        );
+        let mut buf = [0; 8];
+        assert_eq!(
+            b.read(&mut buf).await.unwrap_err().kind(),
+            std::io::ErrorKind::ConnectionReset
+        );
+    }
+
+    #[tokio::test]
+    async fn test_network() {
+        let network = MemoryNetwork::new();
+        let mut listener = network
+            .listen(&ResolvedTarget::from(
+                "0.0.0.0:0".parse::<SocketAddr>().unwrap(),
+            ))
+            .unwrap();
+        let port = listener.local_address().unwrap().tcp().unwrap().port();
+        assert_eq!(port, FIRST_EPHEMERAL_PORT);
+
+        let target = ResolvedTarget::from(SocketAddr::from(([127, 0, 0, 1], port)));
+        let mut client = network.connect(&target).unwrap();
+        let (mut server, peer) = listener.next().await.unwrap().unwrap();
+        assert_eq!(peer, client.local_address().unwrap());
+        assert_eq!(server.local_address().unwrap(), target);
+        assert_eq!(server.transport(), Transport::Tcp);
+
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        drop(listener);
+        assert_eq!(
+            network.connect(&target).unwrap_err().kind(),
+            std::io::ErrorKind::ConnectionRefused
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Byte counters and timings for a single connection, recorded by its
+/// [`crate::UpgradableStream`].
+///
+/// The metrics are shared, so a clone may be kept by another task (for
+/// example, a metrics collector) and read with [`StreamMetrics::snapshot`]
+/// while the connection is in use or after it has been dropped.
+#[derive(Clone, derive_more::Debug)]
+#[debug("{:?}", self.snapshot())]
+pub struct StreamMetrics {
+    inner: Arc<StreamMetricsInner>,
+}
+
+struct StreamMetricsInner {
+    started: Instant,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    // Durations in nanoseconds, offset by one so that zero means unset.
+    connect_time: AtomicU64,
+    tls_handshake_time: AtomicU64,
+    time_to_first_byte: AtomicU64,
+}
+
+/// A point-in-time copy of a connection's [`StreamMetrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamMetricsSnapshot {
+    /// The number of bytes read from the stream, after decryption.
+    pub bytes_read: u64,
+    /// The number of bytes written to the stream, before encryption.
+    pub bytes_written: u64,
+    /// How long the client took to establish the connection, including name
+    /// resolution and any proxy handshake. Not recorded for accepted
+    /// connections.
+    pub connect_time: Option<Duration>,
+    /// How long the TLS handshake took, if the stream was upgraded.
+    pub tls_handshake_time: Option<Duration>,
+    /// How long after the connection was started (or accepted) the first
+    /// byte was read.
+    pub time_to_first_byte: Option<Duration>,
+}
+
+impl Default for StreamMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamMetrics {
+    /// Create metrics for a connection starting now.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(StreamMetricsInner {
+                started: Instant::now(),
+                bytes_read: AtomicU64::new(0),
+                bytes_written: AtomicU64::new(0),
+                connect_time: AtomicU64::new(0),
+                tls_handshake_time: AtomicU64::new(0),
+                time_to_first_byte: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Read the current values.
+    pub fn snapshot(&self) -> StreamMetricsSnapshot {
+        let inner = &self.inner;
+        StreamMetricsSnapshot {
+            bytes_read: inner.bytes_read.load(Ordering::Relaxed),
+            bytes_written: inner.bytes_written.load(Ordering::Relaxed),
+            connect_time: load_duration(&inner.connect_time),
+            tls_handshake_time: load_duration(&inner.tls_handshake_time),
+            time_to_first_byte: load_duration(&inner.time_to_first_byte),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn record_read(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        let inner = &self.inner;
+        if inner.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed) == 0 {
+            store_duration(&inner.time_to_first_byte, inner.started.elapsed());
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn record_write(&self, bytes: usize) {
+        self.inner
+            .bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Record that the connection was established now.
+    pub(crate) fn record_connect(&self) {
+        store_duration(&self.inner.connect_time, self.inner.started.elapsed());
+    }
+
+    pub(crate) fn record_tls_handshake(&self, duration: Duration) {
+        store_duration(&self.inner.tls_handshake_time, duration);
+    }
+}
+
+fn store_duration(value: &AtomicU64, duration: Duration) {
+    let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX - 1);
+    value.store(nanos + 1, Ordering::Relaxed);
+}
+
+fn load_duration(value: &AtomicU64) -> Option<Duration> {
+    match value.load(Ordering::Relaxed) {
+        0 => None,
+        nanos => Some(Duration::from_nanos(nanos - 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_metrics() {
+        let metrics = StreamMetrics::new();
+        assert_eq!(metrics.snapshot(), StreamMetricsSnapshot::default());
+
+        metrics.record_read(0);
+        assert_eq!(metrics.snapshot().time_to_first_byte, None);
+        metrics.record_connect();
+        metrics.record_tls_handshake(Duration::from_millis(5));
+        metrics.record_write(10);
+        metrics.record_read(3);
+        let first = metrics.snapshot().time_to_first_byte;
+        metrics.clone().record_read(4);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.bytes_read, 7);
+        assert_eq!(snapshot.bytes_written, 10);
+        assert!(snapshot.connect_time.is_some());
+        assert_eq!(snapshot.tls_handshake_time, Some(Duration::from_millis(5)));
+        // Only the first read sets the time to first byte.
+        assert!(first.is_some());
+        assert_eq!(snapshot.time_to_first_byte, first);
+    }
+}
@@ -1,7 +1,15 @@
+#[cfg(feature = "x509")]
+pub mod certificate;
+#[cfg(all(feature = "tokio", unix))]
+pub mod memory;
+pub mod metrics;
+pub mod proxy_protocol;
 pub mod resolver;
+pub mod session;
 pub mod stream;
 pub mod target;
 pub mod tls;
+pub mod verify;
 
 #[cfg(feature = "openssl")]
 pub mod openssl;
@@ -1,7 +1,7 @@
 use openssl::{
     ssl::{
         AlpnError, ClientHelloResponse, NameType, SniError, Ssl, SslAcceptor, SslContextBuilder,
-        SslMethod, SslOptions, SslRef, SslVerifyMode,
+        SslMethod, SslOptions, SslRef, SslSession, SslSessionCacheMode, SslVerifyMode,
     },
     x509::{verify::X509VerifyFlags, X509VerifyResult},
 };
@@ -14,12 +14,13 @@ use std::{
 };
 
 use crate::{
-    AsHandle, LocalAddress, PeekableStream, PeerCred, RemoteAddress, ResolvedTarget, SslError,
-    SslVersion, Stream, StreamMetadata, TlsCert, TlsClientCertVerify, TlsDriver, TlsHandshake,
-    TlsParameters, TlsServerCertVerify, TlsServerParameterProvider, TlsServerParameters, Transport,
+    AsHandle, LocalAddress, PeekableStream, PeerCred, RemoteAddress, ResolvedTarget, SpkiPin,
+    SslError, SslVersion, Stream, StreamMetadata, TlsCert, TlsClientCertVerify, TlsDriver,
+    TlsHandshake, TlsParameters, TlsServerCertVerify, TlsServerParameterProvider,
+    TlsServerParameters, TlsVerifyContext, Transport,
 };
 
-use super::tokio_stream::TokioStream;
+use super::{session::session_scope, tokio_stream::TokioStream};
 
 #[derive(Debug, Clone)]
 struct HandshakeData {
@@ -109,6 +110,9 @@ impl TlsDriver for OpensslDriver {
             alpn,
             sni_override,
             enable_keylog,
+            spki_pins,
+            verify_callback,
+            session_cache,
         } = params;
 
         // let mut ssl = SslConnector::builder(SslMethod::tls_client())?;
@@ -175,6 +179,62 @@ impl TlsDriver for OpensslDriver {
             }
         }
 
+        // Pins on the leaf certificate replace OpenSSL's chain verification,
+        // and the callback runs once the chain has been verified.
+        if !spki_pins.is_empty() || verify_callback.is_some() {
+            let ignore_errors =
+                !spki_pins.is_empty() || *server_cert_verify == TlsServerCertVerify::Insecure;
+            let spki_pins = spki_pins.clone();
+            let verify_callback = verify_callback.clone();
+            let server_name = match sni_override {
+                Some(hostname) => ServerName::try_from(hostname.to_string()).ok(),
+                None => name.as_ref().map(|name| name.to_owned()),
+            };
+            ssl.set_verify_callback(SslVerifyMode::PEER, move |preverify_ok, ctx| {
+                if !preverify_ok {
+                    return ignore_errors;
+                }
+                // OpenSSL signals success for each certificate in the chain,
+                // ending with the leaf.
+                if ctx.error_depth() != 0 {
+                    return true;
+                }
+                let cert_chain = ctx
+                    .chain()
+                    .map(|chain| {
+                        chain
+                            .iter()
+                            .filter_map(|cert| cert.to_der().ok())
+                            .map(CertificateDer::from)
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                if !spki_pins.is_empty() {
+                    let leaf = ctx
+                        .current_cert()
+                        .and_then(|cert| cert.to_der().ok())
+                        .map(CertificateDer::from);
+                    if !leaf.is_some_and(|leaf| SpkiPin::matches_leaf(&spki_pins, &leaf)) {
+                        ctx.set_error(X509VerifyResult::APPLICATION_VERIFICATION);
+                        return false;
+                    }
+                    // Clear any error retained from the ignored chain checks
+                    ctx.set_error(X509VerifyResult::OK);
+                }
+                if let Some(callback) = &verify_callback {
+                    let context = TlsVerifyContext {
+                        cert_chain: &cert_chain,
+                        server_name: server_name.as_ref(),
+                    };
+                    if callback.verify(&context).is_err() {
+                        ctx.set_error(X509VerifyResult::APPLICATION_VERIFICATION);
+                        return false;
+                    }
+                }
+                true
+            });
+        }
+
         // Load CRL
         if !crl.is_empty() {
             // The openssl crate doesn't yet have add_crl, so we need to use the raw FFI
@@ -226,9 +286,47 @@ impl TlsDriver for OpensslDriver {
             }
         }
 
+        // Share sessions with connections that verify the server the same
+        // way. Servers without a name or address (ie: Unix sockets) can't be
+        // told apart, so their sessions are not cached.
+        let server_name = match sni_override {
+            Some(hostname) => Some(hostname.to_string()),
+            None => name.as_ref().map(|name| name.to_str().into_owned()),
+        };
+        let session_key =
+            session_cache
+                .as_ref()
+                .zip(server_name)
+                .map(|(session_cache, server_name)| {
+                    (session_cache.clone(), session_scope(params), server_name)
+                });
+        if let Some((session_cache, scope, server_name)) = session_key.clone() {
+            // Keep sessions out of the context's internal cache so that they
+            // can be resumed by the next connection's context.
+            ssl.set_session_cache_mode(
+                SslSessionCacheMode::CLIENT | SslSessionCacheMode::NO_INTERNAL,
+            );
+            ssl.set_new_session_callback(move |_ssl, session| {
+                // OpenSSL marks the connection's session as not resumable if
+                // it is dropped without a shutdown, so store a copy instead.
+                let Ok(session) = session.to_der().and_then(|der| SslSession::from_der(&der))
+                else {
+                    return;
+                };
+                session_cache.openssl_insert(scope, &server_name, session);
+            });
+        }
+
         let mut ssl = openssl::ssl::Ssl::new(&ssl.build())?;
         ssl.set_connect_state();
 
+        if let Some((session_cache, scope, server_name)) = &session_key {
+            if let Some(session) = session_cache.openssl_session(*scope, server_name) {
+                // SAFETY: The session is not in any context's internal cache.
+                unsafe { ssl.set_session(&session)? };
+            }
+        }
+
         // Set hostname if it's not an IP address
         if let Some(hostname) = sni_override {
             ssl.set_hostname(hostname)?;
@@ -251,6 +349,8 @@ impl TlsDriver for OpensslDriver {
             server_certificate,
             // Handled elsewhere
             alpn: _alpn,
+            // Session tickets are only supported by rustls
+            session_tickets: _session_tickets,
         } = params;
 
         let mut ssl = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
@@ -303,28 +403,12 @@ impl TlsDriver for OpensslDriver {
             .map(|p| Cow::Owned(p.to_vec()));
 
         res.map_err(SslError::OpenSslError)?;
-        let cert = stream
-            .ssl()
-            .peer_certificate()
-            .map(|cert| cert.to_der())
-            .transpose()?;
-        let cert = cert.map(CertificateDer::from);
-        let version = match stream.ssl().version2() {
-            Some(openssl::ssl::SslVersion::TLS1) => Some(SslVersion::Tls1),
-            Some(openssl::ssl::SslVersion::TLS1_1) => Some(SslVersion::Tls1_1),
-            Some(openssl::ssl::SslVersion::TLS1_2) => Some(SslVersion::Tls1_2),
-            Some(openssl::ssl::SslVersion::TLS1_3) => Some(SslVersion::Tls1_3),
-            _ => None,
+        let mut handshake = TlsHandshake {
+            alpn,
+            ..Default::default()
         };
-        Ok((
-            TlsStream(stream),
-            TlsHandshake {
-                alpn,
-                sni: None,
-                cert,
-                version,
-            },
-        ))
+        connection_handshake(stream.ssl(), &mut handshake)?;
+        Ok((TlsStream(stream), handshake))
     }
 
     async fn upgrade_server<S: Stream>(
@@ -361,21 +445,7 @@ impl TlsDriver for OpensslDriver {
         res.map_err(SslError::OpenSslError)?;
 
         let mut handshake = std::mem::take(&mut handshake.lock().unwrap().handshake);
-        let cert = stream
-            .ssl()
-            .peer_certificate()
-            .and_then(|c| c.to_der().ok());
-        if let Some(cert) = cert {
-            handshake.cert = Some(CertificateDer::from(cert));
-        }
-        let version = match stream.ssl().version2() {
-            Some(openssl::ssl::SslVersion::TLS1) => Some(SslVersion::Tls1),
-            Some(openssl::ssl::SslVersion::TLS1_1) => Some(SslVersion::Tls1_1),
-            Some(openssl::ssl::SslVersion::TLS1_2) => Some(SslVersion::Tls1_2),
-            Some(openssl::ssl::SslVersion::TLS1_3) => Some(SslVersion::Tls1_3),
-            _ => None,
-        };
-        handshake.version = version;
+        connection_handshake(stream.ssl(), &mut handshake)?;
         Ok((TlsStream(stream), handshake))
     }
 
@@ -385,6 +455,45 @@ impl TlsDriver for OpensslDriver {
     }
 }
 
+/// Fill in the peer certificates, version and cipher suite from a completed
+/// handshake.
+fn connection_handshake(ssl: &SslRef, handshake: &mut TlsHandshake) -> Result<(), SslError> {
+    let cert = ssl.peer_certificate().map(|c| c.to_der()).transpose()?;
+    handshake.cert = cert.map(CertificateDer::from);
+
+    // On the client side, the peer chain includes the leaf certificate, but on
+    // the server side it does not.
+    handshake.cert_chain = handshake.cert.iter().cloned().collect();
+    if let Some(chain) = ssl.peer_cert_chain() {
+        let skip = if ssl.is_server() { 0 } else { 1 };
+        for cert in chain.iter().skip(skip) {
+            handshake
+                .cert_chain
+                .push(CertificateDer::from(cert.to_der()?));
+        }
+    }
+
+    handshake.resumed = ssl.session_reused();
+    handshake.version = match ssl.version2() {
+        Some(openssl::ssl::SslVersion::TLS1) => Some(SslVersion::Tls1),
+        Some(openssl::ssl::SslVersion::TLS1_1) => Some(SslVersion::Tls1_1),
+        Some(openssl::ssl::SslVersion::TLS1_2) => Some(SslVersion::Tls1_2),
+        Some(openssl::ssl::SslVersion::TLS1_3) => Some(SslVersion::Tls1_3),
+        _ => None,
+    };
+
+    if let Some(cipher) = ssl.current_cipher() {
+        // The IANA name is only available with OpenSSL 1.1.1 or newer
+        #[cfg(ossl111)]
+        let name = cipher.standard_name().unwrap_or_else(|| cipher.name());
+        #[cfg(not(ossl111))]
+        let name = cipher.name();
+        handshake.cipher_suite = Some(Cow::Borrowed(name));
+    }
+
+    Ok(())
+}
+
 fn ssl_select_next_proto<'b>(server: &[u8], client: &'b [u8]) -> Option<&'b [u8]> {
     let mut server_packet = server;
     while !server_packet.is_empty() {
@@ -0,0 +1,429 @@
+//! Support for the HAProxy PROXY protocol (v1 and v2), used by load balancers
+//! to pass the original client address to the server.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+
+/// The signature that starts every v2 header.
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+/// The maximum length of a v1 header, including the trailing CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// TLV type of the ALPN protocol negotiated by the load balancer.
+pub const PP2_TYPE_ALPN: u8 = 0x01;
+/// TLV type of the host name the client requested (usually the TLS SNI).
+pub const PP2_TYPE_AUTHORITY: u8 = 0x02;
+/// TLV type of the TLS information, containing nested TLVs.
+pub const PP2_TYPE_SSL: u8 = 0x20;
+
+/// Configuration for PROXY protocol headers on accepted connections.
+///
+/// Connections from trusted peers must start with a PROXY header, which is
+/// consumed before the connection is returned. Connections from other peers
+/// are returned as is, so that they cannot spoof their address.
+#[derive(Debug, Clone)]
+pub struct ProxyProtocolConfig {
+    /// The networks of the load balancers allowed to send PROXY headers.
+    pub trusted: Vec<IpCidr>,
+    /// Whether connections over Unix sockets must send a PROXY header.
+    pub trust_unix: bool,
+    /// The maximum duration to wait for the PROXY header.
+    pub header_timeout: std::time::Duration,
+}
+
+impl Default for ProxyProtocolConfig {
+    fn default() -> Self {
+        Self {
+            trusted: Vec::new(),
+            trust_unix: false,
+            header_timeout: std::time::Duration::from_secs(10),
+        }
+    }
+}
+
+impl ProxyProtocolConfig {
+    /// Create a configuration trusting the given networks.
+    pub fn new(trusted: impl IntoIterator<Item = IpCidr>) -> Self {
+        Self {
+            trusted: trusted.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether a connection from `peer` must start with a PROXY header.
+    pub(crate) fn is_trusted(&self, peer: &crate::ResolvedTarget) -> bool {
+        match peer.tcp() {
+            Some(addr) => {
+                let ip = addr.ip().to_canonical();
+                self.trusted.iter().any(|cidr| cidr.contains(&ip))
+            }
+            None => self.trust_unix,
+        }
+    }
+}
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// An invalid CIDR network.
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+#[display("Invalid CIDR network")]
+pub struct InvalidCidrError;
+
+impl IpCidr {
+    /// Create a network from an address and a prefix length. Host bits of
+    /// the address are ignored.
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, InvalidCidrError> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(InvalidCidrError);
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    /// Check if the network contains the given address.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = InvalidCidrError;
+
+    /// Parse a network such as `10.0.0.0/8`. A bare address is parsed as a
+    /// network containing only that address.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((addr, prefix)) => Self::new(
+                addr.parse().map_err(|_| InvalidCidrError)?,
+                prefix.parse().map_err(|_| InvalidCidrError)?,
+            ),
+            None => {
+                let addr: IpAddr = s.parse().map_err(|_| InvalidCidrError)?;
+                Self::new(addr, if addr.is_ipv4() { 32 } else { 128 })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// A TLV (type-length-value) field of a PROXY v2 header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyTlv {
+    /// The type of the field, e.g. [`PP2_TYPE_AUTHORITY`].
+    pub kind: u8,
+    /// The raw value of the field.
+    pub value: Vec<u8>,
+}
+
+/// The connection information received in a PROXY header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// The address of the client, if the load balancer provided one. This is
+    /// `None` for health checks from the load balancer itself and for
+    /// unsupported address families.
+    pub source: Option<SocketAddr>,
+    /// The address the client connected to, if provided.
+    pub destination: Option<SocketAddr>,
+    /// The TLV fields of a v2 header.
+    pub tlvs: Vec<ProxyTlv>,
+}
+
+impl ProxyHeader {
+    /// Get the value of the first TLV field of the given type.
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .map(|tlv| tlv.value.as_slice())
+    }
+
+    /// The host name the client requested from the load balancer, usually
+    /// the TLS SNI.
+    pub fn authority(&self) -> Option<&str> {
+        self.tlv(PP2_TYPE_AUTHORITY)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// The ALPN protocol negotiated by the load balancer.
+    pub fn alpn(&self) -> Option<&[u8]> {
+        self.tlv(PP2_TYPE_ALPN)
+    }
+
+    /// Parse a v1 (text) header, including the trailing CRLF.
+    fn parse_v1(line: &[u8]) -> Option<Self> {
+        let line = std::str::from_utf8(line.strip_suffix(b"\r\n")?).ok()?;
+        let mut parts = line.split(' ');
+        if parts.next()? != "PROXY" {
+            return None;
+        }
+        let family = parts.next()?;
+        if family == "UNKNOWN" {
+            return Some(Self::default());
+        }
+        let (source, destination, source_port, destination_port) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+        let (source, destination): (IpAddr, IpAddr) = match family {
+            "TCP4" => (
+                source.parse::<Ipv4Addr>().ok()?.into(),
+                destination.parse::<Ipv4Addr>().ok()?.into(),
+            ),
+            "TCP6" => (
+                source.parse::<Ipv6Addr>().ok()?.into(),
+                destination.parse::<Ipv6Addr>().ok()?.into(),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            source: Some(SocketAddr::new(source, source_port.parse().ok()?)),
+            destination: Some(SocketAddr::new(destination, destination_port.parse().ok()?)),
+            tlvs: Vec::new(),
+        })
+    }
+
+    /// Parse the body of a v2 (binary) header, following the 16-byte fixed
+    /// part with the given command and family bytes.
+    fn parse_v2(command: u8, family: u8, body: &[u8]) -> Option<Self> {
+        if command >> 4 != 2 {
+            return None;
+        }
+        let (source, destination, rest) = match (command & 0xf, family >> 4) {
+            // LOCAL command: the connection was made by the load balancer
+            // itself, the address block is ignored.
+            (0, _) => (None, None, None),
+            (1, 1) => {
+                let (addrs, rest) = body.split_at_checked(12)?;
+                let ip =
+                    |at: usize| Ipv4Addr::from(<[u8; 4]>::try_from(&addrs[at..at + 4]).unwrap());
+                let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+                (
+                    Some(SocketAddr::new(ip(0).into(), port(8))),
+                    Some(SocketAddr::new(ip(4).into(), port(10))),
+                    Some(rest),
+                )
+            }
+            (1, 2) => {
+                let (addrs, rest) = body.split_at_checked(36)?;
+                let ip =
+                    |at: usize| Ipv6Addr::from(<[u8; 16]>::try_from(&addrs[at..at + 16]).unwrap());
+                let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+                (
+                    Some(SocketAddr::new(ip(0).into(), port(32))),
+                    Some(SocketAddr::new(ip(16).into(), port(34))),
+                    Some(rest),
+                )
+            }
+            // Unix sockets and unspecified families carry no usable address.
+            (1, 0) => (None, None, None),
+            (1, 3) => (None, None, Some(body.get(216..)?)),
+            _ => return None,
+        };
+        let mut tlvs = Vec::new();
+        let mut rest = rest.unwrap_or_default();
+        while !rest.is_empty() {
+            let (head, tail) = rest.split_at_checked(3)?;
+            let len = u16::from_be_bytes([head[1], head[2]]) as usize;
+            let (value, tail) = tail.split_at_checked(len)?;
+            tlvs.push(ProxyTlv {
+                kind: head[0],
+                value: value.to_vec(),
+            });
+            rest = tail;
+        }
+        Some(Self {
+            source,
+            destination,
+            tlvs,
+        })
+    }
+}
+
+fn invalid_header() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "Invalid PROXY protocol header",
+    )
+}
+
+/// Read a v1 or v2 PROXY header from the start of the stream. Exactly the
+/// header is consumed, the rest of the stream is left untouched.
+#[cfg(feature = "tokio")]
+pub(crate) async fn read_header<S: tokio::io::AsyncRead + Unpin>(
+    stream: &mut S,
+) -> std::io::Result<ProxyHeader> {
+    use tokio::io::AsyncReadExt;
+
+    // Both "PROXY " and the v2 signature are at least six bytes long.
+    let mut prefix = [0; 16];
+    stream.read_exact(&mut prefix[..6]).await?;
+    if &prefix[..6] == b"PROXY " {
+        let mut line = prefix[..6].to_vec();
+        while !line.ends_with(b"\r\n") {
+            if line.len() >= V1_MAX_LENGTH {
+                return Err(invalid_header());
+            }
+            line.push(stream.read_u8().await?);
+        }
+        ProxyHeader::parse_v1(&line).ok_or_else(invalid_header)
+    } else if prefix[..6] == V2_SIGNATURE[..6] {
+        stream.read_exact(&mut prefix[6..]).await?;
+        if prefix[..12] != V2_SIGNATURE {
+            return Err(invalid_header());
+        }
+        let len = u16::from_be_bytes([prefix[14], prefix[15]]) as usize;
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await?;
+        ProxyHeader::parse_v2(prefix[12], prefix[13], &body).ok_or_else(invalid_header)
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Missing PROXY protocol header from trusted peer",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_header(command: u8, family: u8, body: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(command);
+        header.push(family);
+        header.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        header.extend_from_slice(body);
+        header
+    }
+
+    #[test]
+    fn test_cidr() {
+        let cidr: IpCidr = "10.1.0.0/16".parse().unwrap();
+        assert!(cidr.contains(&"10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(!cidr.contains(&"::1".parse().unwrap()));
+
+        let cidr: IpCidr = "fd00::/8".parse().unwrap();
+        assert!(cidr.contains(&"fd12::1".parse().unwrap()));
+        assert!(!cidr.contains(&"fe80::1".parse().unwrap()));
+
+        let cidr: IpCidr = "0.0.0.0/0".parse().unwrap();
+        assert!(cidr.contains(&"192.0.2.1".parse().unwrap()));
+
+        let cidr: IpCidr = "127.0.0.1".parse().unwrap();
+        assert_eq!(cidr.to_string(), "127.0.0.1/32");
+        assert!(cidr.contains(&"127.0.0.1".parse().unwrap()));
+        assert!(!cidr.contains(&"127.0.0.2".parse().unwrap()));
+
+        assert_eq!("10.0.0.0/33".parse::<IpCidr>(), Err(InvalidCidrError));
+        assert_eq!("localhost/8".parse::<IpCidr>(), Err(InvalidCidrError));
+    }
+
+    #[test]
+    fn test_trusted_mapped_ipv4() {
+        let config = ProxyProtocolConfig::new(["10.0.0.0/8".parse().unwrap()]);
+        let peer = crate::ResolvedTarget::SocketAddr("[::ffff:10.0.0.1]:1234".parse().unwrap());
+        assert!(config.is_trusted(&peer));
+        let peer = crate::ResolvedTarget::SocketAddr("11.0.0.1:1234".parse().unwrap());
+        assert!(!config.is_trusted(&peer));
+    }
+
+    #[tokio::test]
+    async fn test_read_v1() {
+        let mut data: &[u8] = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 5656\r\nhello";
+        let header = read_header(&mut data).await.unwrap();
+        assert_eq!(header.source, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(
+            header.destination,
+            Some("198.51.100.1:5656".parse().unwrap())
+        );
+        assert_eq!(data, b"hello");
+
+        let mut data: &[u8] = b"PROXY TCP6 2001:db8::1 2001:db8::2 1 2\r\n";
+        let header = read_header(&mut data).await.unwrap();
+        assert_eq!(header.source, Some("[2001:db8::1]:1".parse().unwrap()));
+
+        let mut data: &[u8] = b"PROXY UNKNOWN\r\nhello";
+        let header = read_header(&mut data).await.unwrap();
+        assert_eq!(header, ProxyHeader::default());
+        assert_eq!(data, b"hello");
+
+        let mut data: &[u8] = b"PROXY TCP4 192.0.2.1\r\n";
+        read_header(&mut data).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_read_v2() {
+        let mut body = vec![192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x16, 0x18];
+        body.extend_from_slice(&[PP2_TYPE_AUTHORITY, 0, 9]);
+        body.extend_from_slice(b"localhost");
+        body.extend_from_slice(&[PP2_TYPE_ALPN, 0, 6]);
+        body.extend_from_slice(b"edgedb");
+        let mut data = v2_header(0x21, 0x11, &body);
+        data.extend_from_slice(b"hello");
+
+        let mut stream = data.as_slice();
+        let header = read_header(&mut stream).await.unwrap();
+        assert_eq!(header.source, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(
+            header.destination,
+            Some("198.51.100.1:5656".parse().unwrap())
+        );
+        assert_eq!(header.authority(), Some("localhost"));
+        assert_eq!(header.alpn(), Some(&b"edgedb"[..]));
+        assert_eq!(stream, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_read_v2_local_and_ipv6() {
+        let data = v2_header(0x20, 0x00, &[]);
+        let header = read_header(&mut data.as_slice()).await.unwrap();
+        assert_eq!(header, ProxyHeader::default());
+
+        let mut body = vec![0; 36];
+        body[15] = 1;
+        body[31] = 2;
+        body[33] = 80;
+        body[35] = 81;
+        let data = v2_header(0x21, 0x21, &body);
+        let header = read_header(&mut data.as_slice()).await.unwrap();
+        assert_eq!(header.source, Some("[::1]:80".parse().unwrap()));
+        assert_eq!(header.destination, Some("[::2]:81".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_read_invalid() {
+        read_header(&mut &b"GET / HTTP/1.1\r\n"[..])
+            .await
+            .unwrap_err();
+        // Truncated TLV.
+        let data = v2_header(0x21, 0x11, &[0; 14]);
+        read_header(&mut data.as_slice()).await.unwrap_err();
+        // Unsupported version.
+        let data = v2_header(0x11, 0x11, &[0; 12]);
+        read_header(&mut data.as_slice()).await.unwrap_err();
+    }
+}
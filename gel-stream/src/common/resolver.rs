@@ -13,17 +13,21 @@ pub struct Resolver {
 
 #[cfg(feature = "tokio")]
 #[allow(unused)]
-async fn resolve_host_to_socket_addrs(host: String) -> std::io::Result<ResolvedTarget> {
-    let res = tokio::task::spawn_blocking(move || format!("{host}:0").to_socket_addrs())
+async fn resolve_host_to_socket_addrs(
+    host: String,
+    port: u16,
+) -> std::io::Result<Vec<ResolvedTarget>> {
+    let res = tokio::task::spawn_blocking(move || format!("{host}:{port}").to_socket_addrs())
         .await
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Interrupted, e.to_string()))??;
-    res.into_iter()
-        .next()
-        .ok_or(std::io::Error::new(
+    let addrs: Vec<_> = res.map(ResolvedTarget::SocketAddr).collect();
+    if addrs.is_empty() {
+        return Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             "No address found",
-        ))
-        .map(ResolvedTarget::SocketAddr)
+        ));
+    }
+    Ok(addrs)
 }
 
 impl Resolver {
@@ -31,23 +35,45 @@ impl Resolver {
     pub fn new() -> Result<Self, std::io::Error> {
         Ok(Self {
             #[cfg(feature = "hickory")]
-            resolver: hickory_resolver::Resolver::builder_tokio()?.build().into(),
+            resolver: {
+                let mut builder = hickory_resolver::Resolver::builder_tokio()?;
+                // Query both A and AAAA records so that all addresses are
+                // available to the connector.
+                builder.options_mut().ip_strategy =
+                    hickory_resolver::config::LookupIpStrategy::Ipv4AndIpv6;
+                builder.build().into()
+            },
         })
     }
 
+    /// Resolve a target name to all of its addresses, in the order returned
+    /// by the system or DNS resolver.
+    pub fn resolve_all(&self, target: &TargetName) -> ResolveResult<Vec<ResolvedTarget>> {
+        self.resolve_remote_all(target.maybe_resolved())
+    }
+
     pub(crate) fn resolve_remote(
         &self,
         host: &MaybeResolvedTarget,
     ) -> ResolveResult<ResolvedTarget> {
+        // A successful resolution always returns at least one address.
+        self.resolve_remote_all(host)
+            .map(|mut addrs| addrs.swap_remove(0))
+    }
+
+    pub(crate) fn resolve_remote_all(
+        &self,
+        host: &MaybeResolvedTarget,
+    ) -> ResolveResult<Vec<ResolvedTarget>> {
         match host {
             MaybeResolvedTarget::Resolved(resolved) => {
-                ResolveResult::new_sync(Ok(resolved.clone()))
+                ResolveResult::new_sync(Ok(vec![resolved.clone()]))
             }
             MaybeResolvedTarget::Unresolved(host, port, _) => {
                 if let Ok(ip) = IpAddr::from_str(host) {
-                    ResolveResult::new_sync(Ok(ResolvedTarget::SocketAddr(SocketAddr::from((
-                        ip, *port,
-                    )))))
+                    ResolveResult::new_sync(Ok(vec![ResolvedTarget::SocketAddr(SocketAddr::from(
+                        (ip, *port),
+                    ))]))
                 } else {
                     #[cfg(feature = "hickory")]
                     {
@@ -56,18 +82,26 @@ impl Resolver {
                         let port = *port;
                         ResolveResult::new_async(async move {
                             let f = resolver.lookup_ip(host);
-                            let Some(addr) = f.await?.iter().next() else {
+                            let addrs: Vec<_> = f
+                                .await?
+                                .iter()
+                                .map(|addr| ResolvedTarget::SocketAddr(SocketAddr::new(addr, port)))
+                                .collect();
+                            if addrs.is_empty() {
                                 return Err(std::io::Error::new(
                                     std::io::ErrorKind::NotFound,
                                     "No address found",
                                 ));
-                            };
-                            Ok(ResolvedTarget::SocketAddr(SocketAddr::new(addr, port)))
+                            }
+                            Ok(addrs)
                         })
                     }
                     #[cfg(all(feature = "tokio", not(feature = "hickory")))]
                     {
-                        ResolveResult::new_async(resolve_host_to_socket_addrs(host.to_string()))
+                        ResolveResult::new_async(resolve_host_to_socket_addrs(
+                            host.to_string(),
+                            *port,
+                        ))
                     }
                     #[cfg(not(any(feature = "tokio", feature = "hickory")))]
                     {
@@ -222,6 +256,18 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_resolve_all() {
+        let resolver = Resolver::new().unwrap();
+        let target = TargetName::new_tcp(("localhost", 8080));
+        let result = resolver.resolve_all(&target).await.unwrap();
+        assert!(result.contains(&ResolvedTarget::SocketAddr(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            8080
+        ))));
+        assert!(result.iter().all(|t| t.tcp().unwrap().port() == 8080));
+    }
+
     #[cfg(feature = "__manual_tests")]
     #[tokio::test]
     async fn test_resolve_real_domain() {
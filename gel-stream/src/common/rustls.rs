@@ -1,6 +1,6 @@
 use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
-use rustls::client::WebPkiServerVerifier;
-use rustls::server::{Acceptor, WebPkiClientVerifier};
+use rustls::client::{Resumption, WebPkiServerVerifier};
+use rustls::server::{Acceptor, ProducesTickets, WebPkiClientVerifier};
 use rustls::{
     ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, ServerConfig,
     SignatureScheme,
@@ -12,14 +12,19 @@ use rustls_platform_verifier::Verifier;
 use rustls_tokio_stream::{TlsStream, UnderlyingStream};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadBuf};
 
+use super::session::session_scope;
 use super::tokio_stream::TokioStream;
 use crate::{
     AsHandle, LocalAddress, PeerCred, RemoteAddress, ResolvedTarget, RewindStream, SslError,
     SslVersion, Stream, StreamMetadata, TlsClientCertVerify, TlsDriver, TlsHandshake,
     TlsServerParameterProvider, TlsServerParameters, Transport,
 };
-use crate::{TlsCert, TlsParameters, TlsServerCertVerify};
+use crate::{
+    SpkiPin, TlsCert, TlsParameters, TlsServerCertVerify, TlsTicketKeys, TlsVerifyCallback,
+    TlsVerifyContext,
+};
 use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
 use std::mem::MaybeUninit;
 use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
@@ -51,46 +56,82 @@ impl TlsDriver for RustlsDriver {
             alpn,
             enable_keylog,
             sni_override,
+            spki_pins,
+            verify_callback,
+            session_cache,
         } = params;
 
-        let verifier = make_verifier(server_cert_verify, root_cert, crl.clone())?;
-
-        let config = ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(verifier);
-
-        // Load client certificate and key if provided
-        let mut config = if let (Some(cert), Some(key)) = (cert, key) {
-            config
-                .with_client_auth_cert(vec![cert.clone()], key.clone_key())
-                .map_err(|_| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::InvalidInput,
-                        "Failed to set client auth cert",
-                    )
-                })?
+        let (name, enable_sni) = if let Some(sni_override) = sni_override {
+            (ServerName::try_from(sni_override.to_string())?, true)
+        } else if let Some(name) = name {
+            (name.to_owned(), true)
         } else {
-            config.with_no_client_auth()
+            (
+                ServerName::IpAddress(IpAddr::V4(Ipv4Addr::from_bits(0)).into()),
+                false,
+            )
         };
 
-        // Configure ALPN if provided
-        config.alpn_protocols = alpn.as_vec_vec();
+        let build = |resumption: Resumption| -> Result<ClientConfig, SslError> {
+            let mut verifier = make_verifier(server_cert_verify, root_cert, crl.clone())?;
+            if !spki_pins.is_empty() || verify_callback.is_some() {
+                verifier = Arc::new(PinningVerifier {
+                    verifier,
+                    spki_pins: spki_pins.clone(),
+                    verify_callback: verify_callback.clone(),
+                });
+            }
 
-        // Configure keylog if provided
-        if *enable_keylog {
-            config.key_log = Arc::new(rustls::KeyLogFile::new());
-        }
+            let config = ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(verifier);
+
+            // Load client certificate and key if provided
+            let mut config = if let (Some(cert), Some(key)) = (cert, key) {
+                config
+                    .with_client_auth_cert(vec![cert.clone()], key.clone_key())
+                    .map_err(|_| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            "Failed to set client auth cert",
+                        )
+                    })?
+            } else {
+                config.with_no_client_auth()
+            };
+
+            // Configure ALPN if provided
+            config.alpn_protocols = alpn.as_vec_vec();
+
+            // Configure keylog if provided
+            if *enable_keylog {
+                config.key_log = Arc::new(rustls::KeyLogFile::new());
+            }
 
-        let name = if let Some(sni_override) = sni_override {
-            ServerName::try_from(sni_override.to_string())?
-        } else if let Some(name) = name {
-            name.to_owned()
+            config.enable_sni = enable_sni;
+            config.resumption = resumption;
+            Ok(config)
+        };
+
+        // Share the configuration, and with it the sessions, with
+        // connections that verify the server the same way. Servers without a
+        // name or address (ie: Unix sockets) can't be told apart, so their
+        // sessions are not cached.
+        let config = if let (Some(session_cache), true) = (session_cache, enable_sni) {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            (
+                session_scope(params),
+                alpn.as_bytes(),
+                *enable_keylog,
+                enable_sni,
+            )
+                .hash(&mut hasher);
+            session_cache.rustls_config(hasher.finish(), |store| build(Resumption::store(store)))?
         } else {
-            config.enable_sni = false;
-            ServerName::IpAddress(IpAddr::V4(Ipv4Addr::from_bits(0)).into())
+            Arc::new(build(Resumption::default())?)
         };
 
-        Ok(ClientConnection::new(Arc::new(config), name)?)
+        Ok(ClientConnection::new(config, name)?)
     }
 
     fn init_server(params: &TlsServerParameters) -> Result<Self::ServerParams, SslError> {
@@ -124,6 +165,10 @@ impl TlsDriver for RustlsDriver {
 
         config.alpn_protocols = params.alpn.as_vec_vec();
 
+        if let Some(session_tickets) = &params.session_tickets {
+            config.ticketer = Arc::new(Ticketer(session_tickets.clone()));
+        }
+
         Ok(Arc::new(config))
     }
 
@@ -139,26 +184,12 @@ impl TlsDriver for RustlsDriver {
         let mut stream = TlsStream::new_client_side(stream, params, None);
         match stream.handshake().await {
             Ok(handshake) => {
-                let cert = stream
-                    .connection()
-                    .and_then(|c| c.peer_certificates())
-                    .and_then(|c| c.first().map(|cert| cert.to_owned()));
-                let version = stream.connection().and_then(|c| c.protocol_version());
-                Ok((
-                    stream,
-                    TlsHandshake {
-                        alpn: handshake.alpn.map(|alpn| Cow::Owned(alpn.to_vec())),
-                        sni: handshake.sni.and_then(|s| DnsName::try_from(s).ok()),
-                        cert,
-                        version: match version {
-                            Some(rustls::ProtocolVersion::TLSv1_0) => Some(SslVersion::Tls1),
-                            Some(rustls::ProtocolVersion::TLSv1_1) => Some(SslVersion::Tls1_1),
-                            Some(rustls::ProtocolVersion::TLSv1_2) => Some(SslVersion::Tls1_2),
-                            Some(rustls::ProtocolVersion::TLSv1_3) => Some(SslVersion::Tls1_3),
-                            _ => None,
-                        },
-                    },
-                ))
+                let handshake = TlsHandshake {
+                    alpn: handshake.alpn.map(|alpn| Cow::Owned(alpn.to_vec())),
+                    sni: handshake.sni.and_then(|s| DnsName::try_from(s).ok()),
+                    ..connection_handshake(stream.connection())
+                };
+                Ok((stream, handshake))
             }
             Err(e) => {
                 let kind = e.kind();
@@ -242,28 +273,14 @@ impl TlsDriver for RustlsDriver {
 
         match stream.handshake().await {
             Ok(handshake) => {
-                let cert = stream
-                    .connection()
-                    .and_then(|c| c.peer_certificates())
-                    .and_then(|c| c.first().map(|cert| cert.to_owned()));
-                let version = stream.connection().and_then(|c| c.protocol_version());
-                Ok((
-                    stream,
-                    TlsHandshake {
-                        alpn: handshake.alpn.map(|alpn| Cow::Owned(alpn.to_vec())),
-                        sni: handshake
-                            .sni
-                            .and_then(|s| DnsName::try_from(s.to_string()).ok()),
-                        cert,
-                        version: match version {
-                            Some(rustls::ProtocolVersion::TLSv1_0) => Some(SslVersion::Tls1),
-                            Some(rustls::ProtocolVersion::TLSv1_1) => Some(SslVersion::Tls1_1),
-                            Some(rustls::ProtocolVersion::TLSv1_2) => Some(SslVersion::Tls1_2),
-                            Some(rustls::ProtocolVersion::TLSv1_3) => Some(SslVersion::Tls1_3),
-                            _ => None,
-                        },
-                    },
-                ))
+                let handshake = TlsHandshake {
+                    alpn: handshake.alpn.map(|alpn| Cow::Owned(alpn.to_vec())),
+                    sni: handshake
+                        .sni
+                        .and_then(|s| DnsName::try_from(s.to_string()).ok()),
+                    ..connection_handshake(stream.connection())
+                };
+                Ok((stream, handshake))
             }
             Err(e) => {
                 let kind = e.kind();
@@ -285,6 +302,106 @@ impl TlsDriver for RustlsDriver {
     }
 }
 
+/// Encrypts session tickets with AES-256-GCM using the current
+/// [`TlsTicketKeys`] key. Tickets are laid out as the key name, the nonce and
+/// then the ciphertext.
+#[derive(Debug)]
+struct Ticketer(TlsTicketKeys);
+
+impl ProducesTickets for Ticketer {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn lifetime(&self) -> u32 {
+        self.0.lifetime().as_secs().try_into().unwrap_or(u32::MAX)
+    }
+
+    fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+        use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+
+        let key = self.0.encryption_key();
+        let aead = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key.cipher_key).ok()?);
+        let mut nonce = [0; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).ok()?;
+
+        let mut ticket =
+            Vec::with_capacity(key.name.len() + NONCE_LEN + plain.len() + AES_256_GCM.tag_len());
+        ticket.extend_from_slice(&key.name);
+        ticket.extend_from_slice(&nonce);
+        ticket.extend_from_slice(plain);
+        let mut ciphertext = ticket.split_off(key.name.len() + NONCE_LEN);
+        aead.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(key.name),
+            &mut ciphertext,
+        )
+        .ok()?;
+        ticket.append(&mut ciphertext);
+        Some(ticket)
+    }
+
+    fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
+        use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+
+        if cipher.len() < 16 + NONCE_LEN {
+            return None;
+        }
+        let (name, rest) = cipher.split_at(16);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let (key, _) = self.0.decryption_key(name)?;
+        let aead = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key.cipher_key).ok()?);
+        let mut plain = ciphertext.to_vec();
+        let len = aead
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).ok()?,
+                Aad::from(key.name),
+                &mut plain,
+            )
+            .ok()?
+            .len();
+        plain.truncate(len);
+        Some(plain)
+    }
+}
+
+/// Extract the peer certificates, version and cipher suite from a completed
+/// handshake.
+fn connection_handshake(connection: Option<&rustls::Connection>) -> TlsHandshake {
+    let Some(connection) = connection else {
+        return TlsHandshake::default();
+    };
+    let cert_chain = connection
+        .peer_certificates()
+        .map(|certs| certs.iter().map(|cert| cert.clone().into_owned()).collect())
+        .unwrap_or_default();
+    TlsHandshake {
+        cert: connection
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .map(|cert| cert.clone().into_owned()),
+        cert_chain,
+        version: match connection.protocol_version() {
+            Some(rustls::ProtocolVersion::TLSv1_0) => Some(SslVersion::Tls1),
+            Some(rustls::ProtocolVersion::TLSv1_1) => Some(SslVersion::Tls1_1),
+            Some(rustls::ProtocolVersion::TLSv1_2) => Some(SslVersion::Tls1_2),
+            Some(rustls::ProtocolVersion::TLSv1_3) => Some(SslVersion::Tls1_3),
+            _ => None,
+        },
+        resumed: connection.handshake_kind() == Some(rustls::HandshakeKind::Resumed),
+        cipher_suite: connection
+            .negotiated_cipher_suite()
+            .and_then(|suite| suite.suite().as_str())
+            .map(|name| match name.strip_prefix("TLS13_") {
+                // rustls prefixes TLS 1.3 suites to distinguish them, but the
+                // IANA names start with `TLS_`.
+                Some(name) => Cow::Owned(format!("TLS_{name}")),
+                None => Cow::Borrowed(name),
+            }),
+        ..Default::default()
+    }
+}
+
 fn make_roots(
     root_certs: &[CertificateDer<'static>],
     webpki: bool,
@@ -415,6 +532,110 @@ impl ServerCertVerifier for IgnoreHostnameVerifier {
     }
 }
 
+/// Checks the server's leaf certificate against public key pins (in place of
+/// chain verification), and then runs the user's verify callback.
+#[derive(Debug)]
+struct PinningVerifier {
+    verifier: Arc<dyn ServerCertVerifier>,
+    spki_pins: Vec<SpkiPin>,
+    verify_callback: Option<TlsVerifyCallback>,
+}
+
+impl PinningVerifier {
+    fn signature_algorithms() -> rustls::crypto::WebPkiSupportedAlgorithms {
+        rustls::crypto::ring::default_provider().signature_verification_algorithms
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.spki_pins.is_empty() {
+            self.verifier.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            )?;
+        } else if !SpkiPin::matches_leaf(&self.spki_pins, end_entity) {
+            return Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        if let Some(callback) = &self.verify_callback {
+            let cert_chain = std::iter::once(end_entity)
+                .chain(intermediates)
+                .cloned()
+                .collect::<Vec<_>>();
+            let context = TlsVerifyContext {
+                cert_chain: &cert_chain,
+                server_name: Some(server_name),
+            };
+            callback.verify(&context).map_err(|e| {
+                rustls::Error::InvalidCertificate(rustls::CertificateError::Other(
+                    rustls::OtherError(Arc::from(e)),
+                ))
+            })?;
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        if self.spki_pins.is_empty() {
+            self.verifier.verify_tls12_signature(message, cert, dss)
+        } else {
+            // The inner verifier may not check signatures at all (ie: when
+            // insecure), but a pinned key must prove possession.
+            rustls::crypto::verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &Self::signature_algorithms(),
+            )
+        }
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        if self.spki_pins.is_empty() {
+            self.verifier.verify_tls13_signature(message, cert, dss)
+        } else {
+            rustls::crypto::verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &Self::signature_algorithms(),
+            )
+        }
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        if self.spki_pins.is_empty() {
+            self.verifier.supported_verify_schemes()
+        } else {
+            Self::signature_algorithms().supported_schemes()
+        }
+    }
+}
+
 #[derive(Debug)]
 struct ChainingVerifier {
     verifier1: Arc<dyn ServerCertVerifier>,
@@ -690,6 +911,10 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.readable().await,
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.readable().await,
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => {
+                std::future::poll_fn(|cx| stream.poll_read_ready(cx)).await
+            }
         }
     }
     async fn writable(&self) -> std::io::Result<()> {
@@ -697,6 +922,10 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.writable().await,
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.writable().await,
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => {
+                std::future::poll_fn(|cx| stream.poll_write_ready(cx)).await
+            }
         }
     }
     fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
@@ -704,6 +933,8 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.poll_read_ready(cx),
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.poll_read_ready(cx),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.poll_read_ready(cx),
         }
     }
     fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
@@ -711,6 +942,8 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.poll_write_ready(cx),
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.poll_write_ready(cx),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.poll_write_ready(cx),
         }
     }
     fn try_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
@@ -718,6 +951,8 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.try_read(buf),
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.try_read(buf),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.try_read(buf),
         }
     }
     fn try_write(&self, buf: &[u8]) -> std::io::Result<usize> {
@@ -725,6 +960,8 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.try_write(buf),
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.try_write(buf),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.try_write(buf),
         }
     }
     fn shutdown(&self, how: std::net::Shutdown) -> std::io::Result<()> {
@@ -732,6 +969,11 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => stream.shutdown(how),
             #[cfg(unix)]
             TokioStream::Unix(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => {
+                stream.close(how);
+                Ok(())
+            }
         }
     }
 
@@ -744,6 +986,8 @@ impl UnderlyingStream for TokioStream {
             TokioStream::Tcp(stream) => UnderlyingStream::downcast(stream).map_err(Self::Tcp),
             #[cfg(unix)]
             TokioStream::Unix(stream) => UnderlyingStream::downcast(stream).map_err(Self::Unix),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => Err(Self::Memory(stream)),
         }
     }
 }
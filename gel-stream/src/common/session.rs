@@ -0,0 +1,384 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{TlsCert, TlsParameters};
+
+/// A cache of TLS sessions shared between client connections, so that
+/// reconnecting to a server resumes a previous session rather than
+/// performing a full handshake. Both TLS 1.3 tickets and TLS 1.2 session IDs
+/// and tickets are supported.
+///
+/// The server's certificate is not verified again when a session is
+/// resumed, so sessions are only offered by connections with the same
+/// verification settings as the connection that established them. Early data
+/// (0-RTT) is never sent, as it may be replayed.
+#[derive(Clone, derive_more::Debug)]
+#[debug("TlsSessionCache(capacity={})", self.inner.capacity)]
+pub struct TlsSessionCache {
+    inner: Arc<TlsSessionCacheInner>,
+}
+
+struct TlsSessionCacheInner {
+    capacity: usize,
+    #[cfg(feature = "rustls")]
+    rustls_store: Arc<rustls::client::ClientSessionMemoryCache>,
+    #[cfg(feature = "rustls")]
+    rustls: Mutex<RustlsConfigs>,
+    #[cfg(feature = "openssl")]
+    openssl: Mutex<OpensslSessions>,
+}
+
+impl PartialEq for TlsSessionCache {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for TlsSessionCache {}
+
+impl Default for TlsSessionCache {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl TlsSessionCache {
+    /// Create a cache holding sessions for up to `capacity` servers.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(TlsSessionCacheInner {
+                capacity,
+                #[cfg(feature = "rustls")]
+                rustls_store: Arc::new(rustls::client::ClientSessionMemoryCache::new(capacity)),
+                #[cfg(feature = "rustls")]
+                rustls: Default::default(),
+                #[cfg(feature = "openssl")]
+                openssl: Default::default(),
+            }),
+        }
+    }
+
+    /// The rustls client configuration for connections with the given key,
+    /// built with the shared session store on first use. rustls only resumes
+    /// sessions with the configuration's verifier and client certificate
+    /// resolver that established them, so the whole configuration is shared.
+    /// Configurations are evicted oldest first once `capacity` are cached.
+    #[cfg(feature = "rustls")]
+    pub(crate) fn rustls_config<E>(
+        &self,
+        key: u64,
+        build: impl FnOnce(
+            Arc<rustls::client::ClientSessionMemoryCache>,
+        ) -> Result<rustls::ClientConfig, E>,
+    ) -> Result<Arc<rustls::ClientConfig>, E> {
+        let mut configs = self.inner.rustls.lock().unwrap();
+        if let Some(config) = configs.configs.get(&key) {
+            return Ok(config.clone());
+        }
+        let config = Arc::new(build(self.inner.rustls_store.clone())?);
+        configs.configs.insert(key, config.clone());
+        configs.order.push_back(key);
+        while configs.order.len() > self.inner.capacity {
+            if let Some(oldest) = configs.order.pop_front() {
+                configs.configs.remove(&oldest);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Take a session to resume for the given server. TLS 1.3 sessions are
+    /// removed from the cache as their tickets should only be used once.
+    #[cfg(feature = "openssl")]
+    pub(crate) fn openssl_session(
+        &self,
+        scope: u64,
+        server_name: &str,
+    ) -> Option<openssl::ssl::SslSession> {
+        let mut sessions = self.inner.openssl.lock().unwrap();
+        let key = (scope, server_name.to_string());
+        let session = sessions.sessions.get(&key)?;
+        if session.protocol_version() == openssl::ssl::SslVersion::TLS1_3 {
+            sessions.order.retain(|k| *k != key);
+            sessions.sessions.remove(&key)
+        } else {
+            Some(session.clone())
+        }
+    }
+
+    /// Store a new session for the given server, evicting the oldest server's
+    /// session if the cache is full.
+    #[cfg(feature = "openssl")]
+    pub(crate) fn openssl_insert(
+        &self,
+        scope: u64,
+        server_name: &str,
+        session: openssl::ssl::SslSession,
+    ) {
+        let mut sessions = self.inner.openssl.lock().unwrap();
+        let key = (scope, server_name.to_string());
+        if sessions.sessions.insert(key.clone(), session).is_none() {
+            sessions.order.push_back(key);
+            while sessions.order.len() > self.inner.capacity {
+                if let Some(oldest) = sessions.order.pop_front() {
+                    sessions.sessions.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+#[derive(Default)]
+struct RustlsConfigs {
+    configs: std::collections::HashMap<u64, Arc<rustls::ClientConfig>>,
+    order: std::collections::VecDeque<u64>,
+}
+
+#[cfg(feature = "openssl")]
+#[derive(Default)]
+struct OpensslSessions {
+    sessions: std::collections::HashMap<(u64, String), openssl::ssl::SslSession>,
+    order: std::collections::VecDeque<(u64, String)>,
+}
+
+/// Fingerprint the settings used to verify the server, so that sessions are
+/// only resumed by connections that would have verified the server in the
+/// same way.
+pub(crate) fn session_scope(params: &TlsParameters) -> u64 {
+    let TlsParameters {
+        server_cert_verify,
+        cert,
+        key: _,
+        root_cert,
+        crl,
+        min_protocol_version: _,
+        max_protocol_version: _,
+        enable_keylog: _,
+        sni_override,
+        alpn: _,
+        spki_pins,
+        verify_callback,
+        session_cache: _,
+    } = params;
+
+    let mut hasher = DefaultHasher::new();
+    server_cert_verify.hash(&mut hasher);
+    cert.as_ref().map(|cert| cert.as_ref()).hash(&mut hasher);
+    std::mem::discriminant(root_cert).hash(&mut hasher);
+    match root_cert {
+        TlsCert::SystemPlus(certs) | TlsCert::WebpkiPlus(certs) | TlsCert::Custom(certs) => {
+            for cert in certs {
+                cert.as_ref().hash(&mut hasher);
+            }
+        }
+        TlsCert::System | TlsCert::Webpki => {}
+    }
+    for crl in crl {
+        crl.as_ref().hash(&mut hasher);
+    }
+    sni_override.hash(&mut hasher);
+    spki_pins.hash(&mut hasher);
+    verify_callback
+        .as_ref()
+        .map(|callback| callback.id())
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Keys used by a server to encrypt session tickets, allowing clients to
+/// resume sessions without the server storing them. Share one set of keys
+/// between the [`crate::TlsServerParameters`] whose sessions may be resumed by
+/// each other.
+///
+/// The key used to encrypt new tickets is rotated every `lifetime`. Tickets
+/// encrypted with the previous key are accepted for another `lifetime` and
+/// are replaced with tickets using the current key.
+///
+/// Session tickets are only issued by the rustls driver. The OpenSSL driver
+/// ignores these keys and does not resume sessions.
+#[derive(Clone, derive_more::Debug)]
+#[debug("TlsTicketKeys(lifetime={lifetime:?})")]
+pub struct TlsTicketKeys {
+    lifetime: Duration,
+    keys: Arc<Mutex<TicketKeys>>,
+}
+
+struct TicketKeys {
+    current: TicketKey,
+    previous: Option<TicketKey>,
+}
+
+/// A session ticket key: the name identifying it in tickets and the key
+/// tickets are encrypted with.
+#[derive(Clone)]
+pub(crate) struct TicketKey {
+    pub(crate) name: [u8; 16],
+    pub(crate) cipher_key: [u8; 32],
+    #[cfg(feature = "rustls")]
+    created: Instant,
+}
+
+impl TicketKey {
+    fn generate() -> Self {
+        let mut key = Self {
+            name: [0; 16],
+            cipher_key: [0; 32],
+            #[cfg(feature = "rustls")]
+            created: Instant::now(),
+        };
+        for buf in [&mut key.name[..], &mut key.cipher_key] {
+            getrandom::getrandom(buf).expect("Failed to generate a session ticket key");
+        }
+        key
+    }
+}
+
+impl TlsTicketKeys {
+    /// Create a new set of keys that are rotated every `lifetime`.
+    pub fn new(lifetime: Duration) -> Self {
+        Self {
+            lifetime,
+            keys: Arc::new(Mutex::new(TicketKeys {
+                current: TicketKey::generate(),
+                previous: None,
+            })),
+        }
+    }
+
+    /// How long a key is used to encrypt new tickets.
+    pub fn lifetime(&self) -> Duration {
+        self.lifetime
+    }
+
+    /// Rotate to a new key immediately. Tickets encrypted with the current
+    /// key remain valid until the next rotation.
+    pub fn rotate(&self) {
+        let mut keys = self.keys.lock().unwrap();
+        let current = std::mem::replace(&mut keys.current, TicketKey::generate());
+        keys.previous = Some(current);
+    }
+
+    #[cfg(feature = "rustls")]
+    fn keys(&self) -> std::sync::MutexGuard<'_, TicketKeys> {
+        let mut keys = self.keys.lock().unwrap();
+        if keys.current.created.elapsed() >= self.lifetime {
+            let current = std::mem::replace(&mut keys.current, TicketKey::generate());
+            keys.previous = Some(current);
+        }
+        keys
+    }
+
+    /// The key to encrypt new tickets with.
+    #[cfg(feature = "rustls")]
+    pub(crate) fn encryption_key(&self) -> TicketKey {
+        self.keys().current.clone()
+    }
+
+    /// The key to decrypt a ticket with the given key name, and whether the
+    /// ticket should be replaced because the key has been rotated.
+    #[cfg(feature = "rustls")]
+    pub(crate) fn decryption_key(&self, name: &[u8]) -> Option<(TicketKey, bool)> {
+        let keys = self.keys();
+        if keys.current.name == name {
+            return Some((keys.current.clone(), false));
+        }
+        let previous = keys.previous.as_ref()?;
+        if previous.name == name && previous.created.elapsed() < self.lifetime * 2 {
+            return Some((previous.clone(), true));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn test_ticket_key_rotation() {
+        let keys = TlsTicketKeys::new(Duration::from_secs(3600));
+        let first = keys.encryption_key();
+        assert_eq!(keys.encryption_key().name, first.name);
+        assert!(matches!(keys.decryption_key(&first.name), Some((_, false))));
+
+        keys.rotate();
+        let second = keys.encryption_key();
+        assert_ne!(second.name, first.name);
+        let (key, renew) = keys.decryption_key(&first.name).unwrap();
+        assert_eq!(key.cipher_key, first.cipher_key);
+        assert!(renew);
+
+        // Only the previous key is kept.
+        keys.rotate();
+        assert!(keys.decryption_key(&first.name).is_none());
+        assert!(matches!(keys.decryption_key(&second.name), Some((_, true))));
+        assert!(keys.decryption_key(&[0; 16]).is_none());
+    }
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn test_ticket_key_expiry() {
+        let keys = TlsTicketKeys::new(Duration::ZERO);
+        let first = keys.encryption_key();
+        assert_ne!(keys.encryption_key().name, first.name);
+        assert!(keys.decryption_key(&first.name).is_none());
+    }
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn test_rustls_config_eviction() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let cache = TlsSessionCache::new(2);
+        let mut stores = vec![];
+        let mut config = |key| {
+            cache
+                .rustls_config(key, |store| {
+                    stores.push(store);
+                    Ok::<_, rustls::Error>(
+                        rustls::ClientConfig::builder()
+                            .with_root_certificates(rustls::RootCertStore::empty())
+                            .with_no_client_auth(),
+                    )
+                })
+                .unwrap()
+        };
+        let first = config(1);
+        assert!(Arc::ptr_eq(&first, &config(1)));
+        config(2);
+        config(3);
+        // The oldest configuration was evicted and is built again.
+        assert!(!Arc::ptr_eq(&first, &config(1)));
+        // All configurations share one session store.
+        assert_eq!(stores.len(), 4);
+        assert!(stores.iter().all(|store| Arc::ptr_eq(store, &stores[0])));
+    }
+
+    #[test]
+    fn test_session_scope() {
+        let params = TlsParameters::default();
+        assert_eq!(
+            session_scope(&params),
+            session_scope(&TlsParameters::default())
+        );
+        assert_ne!(
+            session_scope(&params),
+            session_scope(&TlsParameters::insecure())
+        );
+        let custom = TlsParameters {
+            root_cert: TlsCert::Custom(vec![crate::test_keys::binary::CA_CERT.clone()]),
+            ..Default::default()
+        };
+        assert_ne!(session_scope(&params), session_scope(&custom));
+        // Settings that don't affect verification share sessions.
+        let alpn = TlsParameters {
+            alpn: crate::TlsAlpn::new_str(&["h2"]),
+            ..Default::default()
+        };
+        assert_eq!(session_scope(&params), session_scope(&alpn));
+    }
+}
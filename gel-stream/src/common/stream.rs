@@ -10,8 +10,9 @@ use std::{
 use std::{future::Future, num::NonZeroUsize, ops::Deref};
 
 use crate::{
-    LocalAddress, PeerCred, RemoteAddress, ResolvedTarget, Ssl, SslError, StreamMetadata,
-    TlsDriver, TlsHandshake, TlsServerParameterProvider, Transport, DEFAULT_PREVIEW_BUFFER_SIZE,
+    LocalAddress, PeerCred, ProxyHeader, RemoteAddress, ResolvedTarget, Ssl, SslError,
+    StreamMetadata, StreamMetrics, TlsDriver, TlsHandshake, TlsServerParameterProvider, Transport,
+    DEFAULT_PREVIEW_BUFFER_SIZE,
 };
 
 /// A trait for streams that can be converted to a handle or file descriptor.
@@ -101,6 +102,10 @@ impl StreamMetadata for Box<dyn Stream + Send> {
     fn transport(&self) -> Transport {
         self.as_ref().transport()
     }
+
+    fn proxy_header(&self) -> Option<&ProxyHeader> {
+        self.as_ref().proxy_header()
+    }
 }
 
 #[cfg(not(feature = "tokio"))]
@@ -355,16 +360,19 @@ impl Default for PreviewConfiguration {
 #[derive(Default, Debug)]
 struct UpgradableStreamOptions {
     ignore_missing_close_notify: bool,
+    handshake_timeout: Option<std::time::Duration>,
+    metrics: StreamMetrics,
+    #[cfg(feature = "server")]
+    admission: Option<Box<crate::server::AdmissionTicket>>,
 }
 
 #[allow(private_bounds)]
-#[cfg_attr(feature = "tokio", derive(derive_io::AsyncWrite))]
 #[derive(derive_more::Debug, derive_io::AsSocketDescriptor)]
 pub struct UpgradableStream<S: Stream, D: TlsDriver = Ssl> {
-    #[write]
     #[descriptor]
     inner: UpgradableStreamInner<S, D>,
     options: UpgradableStreamOptions,
+    proxy_header: Option<Box<ProxyHeader>>,
 }
 
 #[allow(private_bounds)]
@@ -374,6 +382,7 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
         UpgradableStream {
             inner: UpgradableStreamInner::BaseClient(base, config),
             options: Default::default(),
+            proxy_header: None,
         }
     }
 
@@ -382,6 +391,7 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
         UpgradableStream {
             inner: UpgradableStreamInner::BaseServer(base, config),
             options: Default::default(),
+            proxy_header: None,
         }
     }
 
@@ -393,10 +403,12 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
         UpgradableStream {
             inner: UpgradableStreamInner::BaseServerPreview(base, config),
             options: Default::default(),
+            proxy_header: None,
         }
     }
 
     /// Consume the `UpgradableStream` and return the underlying stream as a [`Box<dyn Stream>`].
+    #[allow(clippy::result_large_err)] // The stream is returned as-is on failure
     pub fn into_boxed(self) -> Result<Box<dyn Stream>, Self> {
         match self.inner {
             UpgradableStreamInner::BaseClient(base, _) => Ok(Box::new(base)),
@@ -418,8 +430,84 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
         self.options.ignore_missing_close_notify = true;
     }
 
+    /// The byte counters and timings for this connection.
+    pub fn metrics(&self) -> &StreamMetrics {
+        &self.options.metrics
+    }
+
+    /// Record this connection's metrics in `metrics`, which were created when
+    /// the connection was started.
+    pub(crate) fn set_metrics(&mut self, metrics: StreamMetrics) {
+        self.options.metrics = metrics;
+    }
+
+    pub(crate) fn set_proxy_header(&mut self, header: ProxyHeader) {
+        self.proxy_header = Some(Box::new(header));
+    }
+
+    /// Attach the connection's admission ticket, which also sets the TLS
+    /// handshake timeout.
+    #[cfg(feature = "server")]
+    pub(crate) fn set_admission(&mut self, ticket: crate::server::AdmissionTicket) {
+        self.options.handshake_timeout = ticket.tls_handshake_timeout();
+        self.options.admission = Some(Box::new(ticket));
+    }
+
+    /// The reason this connection was rejected by the acceptor's
+    /// [`crate::AdmissionControl`], if it was returned rather than closed.
+    /// The caller should send a rejection to the peer and close it.
+    #[cfg(feature = "server")]
+    pub fn admission_rejection(&self) -> Option<crate::AdmissionRejection> {
+        self.options
+            .admission
+            .as_ref()
+            .and_then(|ticket| ticket.rejection())
+    }
+
+    /// Perform the TLS handshake, limited by the handshake timeout.
+    async fn upgrade(
+        inner: UpgradableStreamInner<S, D>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(D::Stream, TlsHandshake), SslError> {
+        let upgrade = async move {
+            match inner {
+                UpgradableStreamInner::BaseClient(base, config) => {
+                    let Some(config) = config else {
+                        return Err(SslError::SslUnsupported);
+                    };
+                    D::upgrade_client(config, base).await
+                }
+                UpgradableStreamInner::BaseServer(base, config) => {
+                    let Some(config) = config else {
+                        return Err(SslError::SslUnsupported);
+                    };
+                    D::upgrade_server(config, base).await
+                }
+                UpgradableStreamInner::BaseServerPreview(base, config) => {
+                    let Some(config) = config else {
+                        return Err(SslError::SslUnsupported);
+                    };
+                    D::upgrade_server(config, base).await
+                }
+                _ => Err(SslError::SslAlreadyUpgraded),
+            }
+        };
+        #[cfg(feature = "tokio")]
+        if let Some(timeout) = timeout {
+            return match tokio::time::timeout(timeout, upgrade).await {
+                Ok(res) => res,
+                Err(_) => Err(SslError::SslIoError(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "TLS handshake timed out",
+                ))),
+            };
+        }
+        upgrade.await
+    }
+
     /// Uncleanly shut down the stream. This may cause errors on the peer side
     /// when using TLS.
+    #[allow(clippy::result_large_err)] // The stream is returned as-is on failure
     pub fn unclean_shutdown(self) -> Result<(), Self> {
         match self.inner {
             UpgradableStreamInner::BaseClient(..) => Ok(()),
@@ -430,6 +518,7 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
                     Err(Self {
                         inner: UpgradableStreamInner::Upgraded(e, cfg),
                         options: self.options,
+                        proxy_header: self.proxy_header,
                     })
                 } else {
                     Ok(())
@@ -447,6 +536,7 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
                             cfg,
                         ),
                         options: self.options,
+                        proxy_header: self.proxy_header,
                     })
                 } else {
                     Ok(())
@@ -458,32 +548,14 @@ impl<S: Stream, D: TlsDriver> UpgradableStream<S, D> {
 
 impl<S: Stream, D: TlsDriver> StreamUpgrade for UpgradableStream<S, D> {
     async fn secure_upgrade(self) -> Result<Self, SslError> {
-        let (upgraded, handshake) = match self.inner {
-            UpgradableStreamInner::BaseClient(base, config) => {
-                let Some(config) = config else {
-                    return Err(SslError::SslUnsupported);
-                };
-                D::upgrade_client(config, base).await?
-            }
-            UpgradableStreamInner::BaseServer(base, config) => {
-                let Some(config) = config else {
-                    return Err(SslError::SslUnsupported);
-                };
-                D::upgrade_server(config, base).await?
-            }
-            UpgradableStreamInner::BaseServerPreview(base, config) => {
-                let Some(config) = config else {
-                    return Err(SslError::SslUnsupported);
-                };
-                D::upgrade_server(config, base).await?
-            }
-            _ => {
-                return Err(SslError::SslAlreadyUpgraded);
-            }
-        };
+        let start = std::time::Instant::now();
+        let (upgraded, handshake) =
+            Self::upgrade(self.inner, self.options.handshake_timeout).await?;
+        self.options.metrics.record_tls_handshake(start.elapsed());
         Ok(Self {
             inner: UpgradableStreamInner::Upgraded(upgraded, handshake),
             options: self.options,
+            proxy_header: self.proxy_header,
         })
     }
 
@@ -491,29 +563,10 @@ impl<S: Stream, D: TlsDriver> StreamUpgrade for UpgradableStream<S, D> {
         self,
         options: PreviewConfiguration,
     ) -> Result<(Preview, Self), SslError> {
-        let (mut upgraded, handshake) = match self.inner {
-            UpgradableStreamInner::BaseClient(base, config) => {
-                let Some(config) = config else {
-                    return Err(SslError::SslUnsupported);
-                };
-                D::upgrade_client(config, base).await?
-            }
-            UpgradableStreamInner::BaseServer(base, config) => {
-                let Some(config) = config else {
-                    return Err(SslError::SslUnsupported);
-                };
-                D::upgrade_server(config, base).await?
-            }
-            UpgradableStreamInner::BaseServerPreview(base, config) => {
-                let Some(config) = config else {
-                    return Err(SslError::SslUnsupported);
-                };
-                D::upgrade_server(config, base).await?
-            }
-            _ => {
-                return Err(SslError::SslAlreadyUpgraded);
-            }
-        };
+        let start = std::time::Instant::now();
+        let (mut upgraded, handshake) =
+            Self::upgrade(self.inner, self.options.handshake_timeout).await?;
+        self.options.metrics.record_tls_handshake(start.elapsed());
         let mut buffer = smallvec::SmallVec::with_capacity(options.max_preview_bytes.get());
         buffer.resize(options.max_preview_bytes.get(), 0);
         #[cfg(feature = "tokio")]
@@ -527,6 +580,7 @@ impl<S: Stream, D: TlsDriver> StreamUpgrade for UpgradableStream<S, D> {
             Self {
                 inner: UpgradableStreamInner::UpgradedPreview(rewind, handshake),
                 options: self.options,
+                proxy_header: self.proxy_header,
             },
         ))
     }
@@ -547,9 +601,10 @@ impl<S: Stream, D: TlsDriver> tokio::io::AsyncRead for UpgradableStream<S, D> {
         cx: &mut std::task::Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> std::task::Poll<std::io::Result<()>> {
-        let ignore_missing_close_notify = self.options.ignore_missing_close_notify;
-        let inner = &mut self.get_mut().inner;
-        let res = match inner {
+        let this = self.get_mut();
+        let ignore_missing_close_notify = this.options.ignore_missing_close_notify;
+        let filled = buf.filled().len();
+        let res = match &mut this.inner {
             UpgradableStreamInner::BaseClient(base, _) => Pin::new(base).poll_read(cx, buf),
             UpgradableStreamInner::BaseServer(base, _) => Pin::new(base).poll_read(cx, buf),
             UpgradableStreamInner::BaseServerPreview(base, _) => Pin::new(base).poll_read(cx, buf),
@@ -563,10 +618,67 @@ impl<S: Stream, D: TlsDriver> tokio::io::AsyncRead for UpgradableStream<S, D> {
         {
             return std::task::Poll::Ready(Ok(()));
         }
+        if let std::task::Poll::Ready(Ok(())) = res {
+            this.options
+                .metrics
+                .record_read(buf.filled().len() - filled);
+        }
         res
     }
 }
 
+#[cfg(feature = "tokio")]
+impl<S: Stream, D: TlsDriver> tokio::io::AsyncWrite for UpgradableStream<S, D> {
+    #[inline(always)]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let std::task::Poll::Ready(Ok(n)) = res {
+            this.options.metrics.record_write(n);
+        }
+        res
+    }
+
+    #[inline(always)]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        if let std::task::Poll::Ready(Ok(n)) = res {
+            this.options.metrics.record_write(n);
+        }
+        res
+    }
+
+    #[inline(always)]
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    #[inline(always)]
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    #[inline(always)]
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
 impl<S: Stream, D: TlsDriver> LocalAddress for UpgradableStream<S, D> {
     fn local_address(&self) -> std::io::Result<ResolvedTarget> {
         self.inner
@@ -576,6 +688,9 @@ impl<S: Stream, D: TlsDriver> LocalAddress for UpgradableStream<S, D> {
 
 impl<S: Stream, D: TlsDriver> RemoteAddress for UpgradableStream<S, D> {
     fn remote_address(&self) -> std::io::Result<ResolvedTarget> {
+        if let Some(source) = self.proxy_header.as_ref().and_then(|h| h.source) {
+            return Ok(ResolvedTarget::SocketAddr(source));
+        }
         self.inner
             .with_inner_metadata(|inner| inner.remote_address())
     }
@@ -585,6 +700,10 @@ impl<S: Stream, D: TlsDriver> StreamMetadata for UpgradableStream<S, D> {
     fn transport(&self) -> Transport {
         self.inner.with_inner_metadata(|inner| inner.transport())
     }
+
+    fn proxy_header(&self) -> Option<&ProxyHeader> {
+        self.proxy_header.as_deref()
+    }
 }
 
 #[cfg_attr(feature = "tokio", derive(derive_io::AsyncRead, derive_io::AsyncWrite))]
@@ -748,6 +867,10 @@ impl<S: StreamMetadata> StreamMetadata for RewindStream<S> {
     fn transport(&self) -> Transport {
         self.inner.transport()
     }
+
+    fn proxy_header(&self) -> Option<&ProxyHeader> {
+        self.inner.proxy_header()
+    }
 }
 
 impl<S: PeekableStream> PeekableStream for RewindStream<S> {
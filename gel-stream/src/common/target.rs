@@ -428,7 +428,7 @@ impl MaybeResolvedTarget {
                 Some(ServerName::IpAddress(addr.ip().into()))
             }
             MaybeResolvedTarget::Unresolved(host, _, _) => {
-                Some(ServerName::DnsName(host.to_string().try_into().ok()?))
+                ServerName::try_from(host.to_string()).ok()
             }
             #[cfg(unix)]
             _ => None,
@@ -627,6 +627,13 @@ pub enum Transport {
 /// A trait for stream metadata.
 pub trait StreamMetadata: LocalAddress + RemoteAddress + PeerCred + Send {
     fn transport(&self) -> Transport;
+
+    /// The PROXY protocol header received from a trusted load balancer, if
+    /// any. When present, [`RemoteAddress::remote_address`] returns the
+    /// client address from the header.
+    fn proxy_header(&self) -> Option<&crate::ProxyHeader> {
+        None
+    }
 }
 
 pub(crate) trait TcpResolve {
@@ -106,7 +106,7 @@ impl TlsDriver for NullTlsDriver {
 /// Note that both EdgeDB/Gel and Postgres may alter certificate validation levels
 /// when custom root certificates are provided. This must be done in the
 /// `TlsParameters` struct by the caller.
-#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TlsServerCertVerify {
     /// Do not verify the server's certificate. Only confirm that the server is
     /// using TLS.
@@ -153,6 +153,17 @@ pub struct TlsParameters {
     pub enable_keylog: bool,
     pub sni_override: Option<Cow<'static, str>>,
     pub alpn: TlsAlpn,
+    /// Pins for the server's public key. When non-empty, the public key of
+    /// the server's leaf certificate must match one of the pins. A matching
+    /// certificate is trusted without consulting `root_cert` or checking the
+    /// hostname, and this applies even with [`TlsServerCertVerify::Insecure`].
+    /// Pinning an intermediate or root CA's key has no effect.
+    pub spki_pins: Vec<crate::SpkiPin>,
+    /// A callback invoked during the handshake after the built-in
+    /// verification (including pins) succeeds.
+    pub verify_callback: Option<crate::TlsVerifyCallback>,
+    /// A cache of sessions to resume, shared between connections.
+    pub session_cache: Option<crate::TlsSessionCache>,
 }
 
 impl TlsParameters {
@@ -259,6 +270,43 @@ impl TlsKey {
             cert: self.cert.clone(),
         }
     }
+
+    /// Check that the private key belongs to the certificate. Without a TLS
+    /// implementation to parse the key with, the check always succeeds.
+    #[cfg(feature = "pem")]
+    pub(crate) fn check_key_matches(&self) -> Result<(), std::io::Error> {
+        check_key_matches(self)
+    }
+}
+
+#[cfg(all(feature = "pem", feature = "rustls"))]
+fn check_key_matches(key: &TlsKey) -> Result<(), std::io::Error> {
+    ::rustls::sign::CertifiedKey::from_der(
+        vec![key.cert.clone()],
+        key.key.clone_key(),
+        &::rustls::crypto::ring::default_provider(),
+    )
+    .map(drop)
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(all(feature = "pem", feature = "openssl", not(feature = "rustls")))]
+fn check_key_matches(key: &TlsKey) -> Result<(), std::io::Error> {
+    let cert = ::openssl::x509::X509::from_der(&key.cert)?;
+    let private_key = ::openssl::pkey::PKey::private_key_from_der(key.key.secret_der())?;
+    if cert.public_key()?.public_eq(&private_key) {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Private key does not match the certificate",
+        ))
+    }
+}
+
+#[cfg(all(feature = "pem", not(any(feature = "rustls", feature = "openssl"))))]
+fn check_key_matches(_key: &TlsKey) -> Result<(), std::io::Error> {
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
@@ -318,6 +366,9 @@ pub struct TlsServerParameters {
     pub max_protocol_version: Option<SslVersion>,
     pub server_certificate: TlsKey,
     pub alpn: TlsAlpn,
+    /// Keys to issue session tickets with. Without them, sessions cannot be
+    /// resumed. Ignored by the OpenSSL driver, which does not resume sessions.
+    pub session_tickets: Option<crate::TlsTicketKeys>,
 }
 
 impl TlsServerParameters {
@@ -328,6 +379,7 @@ impl TlsServerParameters {
             max_protocol_version: None,
             server_certificate,
             alpn: TlsAlpn::default(),
+            session_tickets: None,
         }
     }
 }
@@ -429,10 +481,45 @@ pub struct TlsHandshake {
     pub alpn: Option<Cow<'static, [u8]>>,
     /// The SNI hostname if provided.
     pub sni: Option<DnsName<'static>>,
-    /// The client certificate, if any.
+    /// The peer's leaf certificate, if any.
     pub cert: Option<CertificateDer<'static>>,
     /// The negotiated TLS version.
     pub version: Option<SslVersion>,
+    pub(crate) cert_chain: Vec<CertificateDer<'static>>,
+    pub(crate) cipher_suite: Option<Cow<'static, str>>,
+    pub(crate) resumed: bool,
+}
+
+impl TlsHandshake {
+    /// The certificate chain presented by the peer, leaf first. Empty if the
+    /// peer did not present a certificate.
+    ///
+    /// This is the chain as sent by the peer, which is not necessarily the
+    /// path that was verified: with rustls, the peer may send extra or
+    /// out-of-order certificates, and the verified path may end at a trust
+    /// anchor that was never sent.
+    pub fn peer_cert_chain(&self) -> &[CertificateDer<'static>] {
+        &self.cert_chain
+    }
+
+    /// The IANA name of the negotiated cipher suite (ie:
+    /// `TLS_AES_256_GCM_SHA384`). Falls back to the implementation's own name
+    /// if the IANA name is not available.
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite.as_deref()
+    }
+
+    /// Whether a previous session was resumed rather than performing a full
+    /// handshake.
+    pub fn resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// Parse the peer's leaf certificate, if any.
+    #[cfg(feature = "x509")]
+    pub fn peer_certificate_info(&self) -> Option<Result<crate::CertificateInfo, std::io::Error>> {
+        self.cert.as_ref().map(crate::CertificateInfo::parse)
+    }
 }
 
 #[cfg(test)]
@@ -448,7 +535,8 @@ mod tests {
             format!("{params:?}"),
             "TlsParameters { server_cert_verify: VerifyFull, cert: None, key: None, \
             root_cert: System, crl: [], min_protocol_version: None, max_protocol_version: None, \
-            enable_keylog: false, sni_override: None, alpn: [] }"
+            enable_keylog: false, sni_override: None, alpn: [], spki_pins: [], \
+            verify_callback: None, session_cache: None }"
         );
         let params = TlsParameters {
             server_cert_verify: TlsServerCertVerify::Insecure,
@@ -463,13 +551,19 @@ mod tests {
             enable_keylog: false,
             sni_override: None,
             alpn: TlsAlpn::new_str(&["h2", "http/1.1"]),
+            spki_pins: vec![crate::SpkiPin::new([0; 32])],
+            verify_callback: Some(crate::TlsVerifyCallback::new(|_| Ok(()))),
+            session_cache: Some(crate::TlsSessionCache::new(16)),
         };
         assert_eq!(
             format!("{params:?}"),
             "TlsParameters { server_cert_verify: Insecure, cert: Some(...), key: Some(...), \
             root_cert: SystemPlus([1 cert(s)]), crl: [1 item(s)], min_protocol_version: None, \
             max_protocol_version: None, enable_keylog: false, sni_override: None, \
-            alpn: [b\"h2\", b\"http/1.1\"] }"
+            alpn: [b\"h2\", b\"http/1.1\"], \
+            spki_pins: [SpkiPin(sha256//AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=)], \
+            verify_callback: Some(TlsVerifyCallback(...)), \
+            session_cache: Some(TlsSessionCache(capacity=16)) }"
         );
     }
 
@@ -2,10 +2,15 @@
 
 use std::pin::Pin;
 use std::task::{ready, Context, Poll};
+
+use futures::StreamExt;
 use tokio::net::{TcpListener, TcpStream};
 #[cfg(unix)]
 use tokio::net::{UnixListener, UnixStream};
 
+#[cfg(unix)]
+use super::memory::{MemoryListener, MemoryStream};
+
 use crate::{AsHandle, PeekableStream, PeerCred, RemoteAddress, StreamMetadata, Transport};
 
 use super::target::{LocalAddress, ResolvedTarget};
@@ -105,6 +110,8 @@ pub(crate) enum TokioListenerStream {
     Tcp(TcpListener),
     #[cfg(unix)]
     Unix(UnixListener),
+    #[cfg(unix)]
+    Memory(MemoryListener),
 }
 
 impl LocalAddress for TokioListenerStream {
@@ -117,6 +124,8 @@ impl LocalAddress for TokioListenerStream {
             TokioListenerStream::Unix(listener) => listener
                 .local_addr()
                 .map(|addr| ResolvedTarget::UnixSocketAddr(addr.into())),
+            #[cfg(unix)]
+            TokioListenerStream::Memory(listener) => listener.local_address(),
         }
     }
 }
@@ -139,11 +148,15 @@ impl futures::Stream for TokioListenerStream {
                 let target = ResolvedTarget::UnixSocketAddr(addr.into());
                 Poll::Ready(Some(Ok((stream, target))))
             }
+            #[cfg(unix)]
+            TokioListenerStream::Memory(listener) => listener
+                .poll_next_unpin(cx)
+                .map_ok(|(stream, target)| (TokioStream::Memory(Box::new(stream)), target)),
         }
     }
 }
 
-/// Represents a connected Tokio stream, either TCP or Unix
+/// Represents a connected Tokio stream, either TCP, Unix or in-memory
 #[derive(
     derive_io::AsyncRead, derive_io::AsyncWrite, derive_io::AsSocketDescriptor, derive_more::Debug,
 )]
@@ -165,6 +178,15 @@ pub enum TokioStream {
         #[descriptor]
         UnixStream,
     ),
+    /// In-memory stream (only available on Unix systems)
+    #[cfg(unix)]
+    #[debug("{_0:?}")]
+    Memory(
+        #[read]
+        #[write]
+        #[descriptor]
+        Box<MemoryStream>,
+    ),
 }
 
 impl TokioStream {
@@ -189,6 +211,11 @@ impl TokioStream {
                 std::io::ErrorKind::Unsupported,
                 "Unix sockets do not support keepalive",
             )),
+            #[cfg(unix)]
+            TokioStream::Memory(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Memory streams do not support keepalive",
+            )),
         }
     }
 }
@@ -225,6 +252,17 @@ impl PeekableStream for TokioStream {
                     Err(e) => Poll::Ready(Err(e)),
                 };
             },
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => loop {
+                ready!(stream.poll_read_ready(cx))?;
+                break match stream.try_peek(buf.initialize_unfilled()) {
+                    Ok(n) => Poll::Ready(Ok(n)),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        continue;
+                    }
+                    Err(e) => Poll::Ready(Err(e)),
+                };
+            },
         }
     }
 }
@@ -235,6 +273,8 @@ impl LocalAddress for TokioStream {
             TokioStream::Tcp(stream) => <TcpStream as LocalAddress>::local_address(stream),
             #[cfg(unix)]
             TokioStream::Unix(stream) => <UnixStream as LocalAddress>::local_address(stream),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.local_address(),
         }
     }
 }
@@ -245,6 +285,8 @@ impl RemoteAddress for TokioStream {
             TokioStream::Tcp(stream) => <TcpStream as RemoteAddress>::remote_address(stream),
             #[cfg(unix)]
             TokioStream::Unix(stream) => <UnixStream as RemoteAddress>::remote_address(stream),
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.remote_address(),
         }
     }
 }
@@ -258,6 +300,7 @@ impl PeerCred for TokioStream {
                 std::io::ErrorKind::Unsupported,
                 "TCP sockets do not support peer credentials",
             )),
+            TokioStream::Memory(stream) => stream.peer_cred(),
         }
     }
 }
@@ -268,6 +311,8 @@ impl StreamMetadata for TokioStream {
             TokioStream::Tcp(_) => Transport::Tcp,
             #[cfg(unix)]
             TokioStream::Unix(_) => Transport::Unix,
+            #[cfg(unix)]
+            TokioStream::Memory(stream) => stream.transport(),
         }
     }
 }
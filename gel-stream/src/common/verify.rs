@@ -0,0 +1,192 @@
+use base64::Engine;
+use rustls_pki_types::{CertificateDer, ServerName};
+use std::{str::FromStr, sync::Arc};
+
+/// A SHA-256 digest of a certificate's DER-encoded `SubjectPublicKeyInfo`, as
+/// used for public key pinning.
+///
+/// Pins are displayed and parsed in the `sha256//<base64>` format used by
+/// curl's `--pinnedpubkey`. Parsing also accepts bare base64 and hex digests.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, derive_more::Debug)]
+#[debug("SpkiPin({self})")]
+pub struct SpkiPin([u8; 32]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+#[display("Invalid SPKI pin")]
+pub struct InvalidSpkiPinError;
+
+impl SpkiPin {
+    /// Create a pin from a raw SHA-256 digest.
+    pub fn new(digest: [u8; 32]) -> Self {
+        Self(digest)
+    }
+
+    /// Compute the pin of a DER-encoded certificate's public key.
+    #[cfg(any(feature = "x509", feature = "rustls", feature = "openssl"))]
+    pub fn from_certificate(cert: &CertificateDer) -> Result<Self, std::io::Error> {
+        use sha2::Digest;
+        use x509_parser::{certificate::X509Certificate, prelude::FromDer};
+
+        let (_, parsed) = X509Certificate::from_der(cert.as_ref()).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid certificate: {e}"),
+            )
+        })?;
+        Ok(Self(sha2::Sha256::digest(parsed.public_key().raw).into()))
+    }
+
+    /// The raw SHA-256 digest.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Check whether the peer's leaf certificate matches one of the pins.
+    ///
+    /// Only the leaf is checked: pinned connections skip chain verification,
+    /// so any other certificate the peer presents proves nothing.
+    #[cfg(any(feature = "rustls", feature = "openssl"))]
+    pub(crate) fn matches_leaf(pins: &[SpkiPin], leaf: &CertificateDer) -> bool {
+        Self::from_certificate(leaf).is_ok_and(|pin| pins.contains(&pin))
+    }
+}
+
+impl std::fmt::Display for SpkiPin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(self.0);
+        write!(f, "sha256//{encoded}")
+    }
+}
+
+impl FromStr for SpkiPin {
+    type Err = InvalidSpkiPinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix("sha256//").unwrap_or(s);
+        let hex = s.replace(':', "");
+        let digest = if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            (0..32)
+                .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| InvalidSpkiPinError)?
+        } else {
+            base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .map_err(|_| InvalidSpkiPinError)?
+        };
+        Ok(Self(digest.try_into().map_err(|_| InvalidSpkiPinError)?))
+    }
+}
+
+/// The peer certificate information passed to a [`TlsVerifyCallback`].
+#[derive(Debug)]
+pub struct TlsVerifyContext<'a> {
+    /// The certificate chain, leaf first.
+    pub cert_chain: &'a [CertificateDer<'a>],
+    /// The name used to verify the server's certificate, if any.
+    pub server_name: Option<&'a ServerName<'a>>,
+}
+
+/// The function signature of a [`TlsVerifyCallback`].
+pub type TlsVerifyFn = dyn Fn(&TlsVerifyContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    + Send
+    + Sync
+    + 'static;
+
+/// A callback invoked during the TLS handshake, after the built-in certificate
+/// verification has succeeded. Returning an error aborts the handshake.
+#[derive(Clone, derive_more::Debug)]
+#[debug("TlsVerifyCallback(...)")]
+pub struct TlsVerifyCallback(Arc<TlsVerifyFn>);
+
+impl TlsVerifyCallback {
+    pub fn new(
+        callback: impl Fn(&TlsVerifyContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    /// Identifies the callback, which is shared between its clones.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as *const () as usize
+    }
+
+    pub(crate) fn verify(
+        &self,
+        context: &TlsVerifyContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        (self.0)(context)
+    }
+}
+
+impl PartialEq for TlsVerifyCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TlsVerifyCallback {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_from_certificate() {
+        let pin = SpkiPin::from_certificate(&crate::test_keys::binary::SERVER_CERT).unwrap();
+        assert_eq!(
+            pin.to_string(),
+            "sha256//wvyj21luwK1bZZAbbUWkCvcEU31mniAsblx3ZJKXlFs="
+        );
+        let pin = SpkiPin::from_certificate(&crate::test_keys::binary::CA_CERT).unwrap();
+        assert_eq!(
+            pin,
+            "915bc301f0250c21e7355db6d1b8c8db056a4997d7598c10c601f7607456e4de"
+                .parse()
+                .unwrap()
+        );
+        SpkiPin::from_certificate(&CertificateDer::from(vec![0x30, 0x03, 1, 2])).unwrap_err();
+    }
+
+    #[test]
+    fn test_pin_parse() {
+        let pin: SpkiPin = "sha256//wvyj21luwK1bZZAbbUWkCvcEU31mniAsblx3ZJKXlFs="
+            .parse()
+            .unwrap();
+        assert_eq!(
+            pin,
+            "wvyj21luwK1bZZAbbUWkCvcEU31mniAsblx3ZJKXlFs="
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(
+            pin,
+            "C2:FC:A3:DB:59:6E:C0:AD:5B:65:90:1B:6D:45:A4:0A:\
+             F7:04:53:7D:66:9E:20:2C:6E:5C:77:64:92:97:94:5B"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!("sha256//AAAA".parse::<SpkiPin>(), Err(InvalidSpkiPinError));
+        assert_eq!("not a pin".parse::<SpkiPin>(), Err(InvalidSpkiPinError));
+    }
+
+    #[test]
+    fn test_pin_matches_leaf() {
+        let server = &crate::test_keys::binary::SERVER_CERT;
+        let ca = &crate::test_keys::binary::CA_CERT;
+        let server_pin = SpkiPin::from_certificate(server).unwrap();
+        let ca_pin = SpkiPin::from_certificate(ca).unwrap();
+        assert!(SpkiPin::matches_leaf(&[server_pin], server));
+        assert!(SpkiPin::matches_leaf(&[ca_pin, server_pin], server));
+        // A pinned CA does not vouch for the leaf
+        assert!(!SpkiPin::matches_leaf(&[ca_pin], server));
+        assert!(!SpkiPin::matches_leaf(&[], server));
+        assert!(!SpkiPin::matches_leaf(
+            &[server_pin],
+            &CertificateDer::from(vec![0x30, 0x03, 1, 2])
+        ));
+    }
+}
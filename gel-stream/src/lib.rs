@@ -15,17 +15,26 @@ mod client;
 mod server;
 
 #[cfg(feature = "client")]
-pub use client::Connector;
+pub use client::{Connector, Proxy, ProxyKind};
 
 #[cfg(feature = "server")]
-pub use server::Acceptor;
+pub use server::{AcceptRate, Acceptor, AdmissionControl, AdmissionRejection};
+#[cfg(all(feature = "server", feature = "pem"))]
+pub use server::{TlsServerCertificateReloader, TlsServerCertificates};
 
 mod common;
+#[cfg(feature = "x509")]
+pub use common::certificate::CertificateInfo;
+#[cfg(all(feature = "tokio", unix))]
+pub use common::memory::{MemoryListener, MemoryNetwork, MemoryStream, MemoryStreamConfig};
 #[cfg(feature = "openssl")]
 pub use common::openssl::OpensslDriver;
 #[cfg(feature = "rustls")]
 pub use common::rustls::RustlsDriver;
-pub use common::{resolver::*, stream::*, target::*, tls::*, BaseStream};
+pub use common::{
+    metrics::*, proxy_protocol::*, resolver::*, session::*, stream::*, target::*, tls::*,
+    verify::*, BaseStream,
+};
 pub use rustls_pki_types as pki_types;
 
 pub type RawStream = UpgradableStream<BaseStream>;
@@ -36,6 +45,10 @@ pub const DEFAULT_TCP_BACKLOG: u32 = 1024;
 pub const DEFAULT_TLS_BACKLOG: u32 = 128;
 /// The default preview buffer size for the server.
 pub const DEFAULT_PREVIEW_BUFFER_SIZE: u32 = 8;
+/// The default delay before the client starts a connection attempt to the
+/// next address of a host (as recommended by RFC 8305).
+pub const DEFAULT_CONNECTION_ATTEMPT_DELAY: std::time::Duration =
+    std::time::Duration::from_millis(250);
 
 #[derive(Debug, derive_more::Error, derive_more::Display, derive_more::From)]
 pub enum ConnectionError {
@@ -50,6 +63,10 @@ pub enum ConnectionError {
     /// SSL-related error.
     #[display("SSL error: {_0}")]
     SslError(#[from] SslError),
+
+    /// Connection attempts to all addresses of a host failed.
+    #[display("{_0}")]
+    AllAttemptsFailed(#[from] ConnectAttemptsError),
 }
 
 impl From<ConnectionError> for std::io::Error {
@@ -58,7 +75,50 @@ impl From<ConnectionError> for std::io::Error {
             ConnectionError::Io(e) => e,
             ConnectionError::Utf8Error(e) => std::io::Error::other(e),
             ConnectionError::SslError(e) => e.into(),
+            ConnectionError::AllAttemptsFailed(e) => std::io::Error::new(e.kind(), e),
+        }
+    }
+}
+
+/// The errors of each failed connection attempt when a host resolves to
+/// multiple addresses.
+#[derive(Debug, derive_more::Error)]
+pub struct ConnectAttemptsError {
+    #[error(not(source))]
+    attempts: Vec<(ResolvedTarget, std::io::Error)>,
+}
+
+impl ConnectAttemptsError {
+    pub(crate) fn new(attempts: Vec<(ResolvedTarget, std::io::Error)>) -> Self {
+        Self { attempts }
+    }
+
+    /// The address and error of each failed attempt, in the order the
+    /// attempts were started.
+    pub fn attempts(&self) -> &[(ResolvedTarget, std::io::Error)] {
+        &self.attempts
+    }
+
+    /// The kind of the first error, or `NotFound` if there were no attempts.
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.attempts
+            .first()
+            .map(|(_, e)| e.kind())
+            .unwrap_or(std::io::ErrorKind::NotFound)
+    }
+}
+
+impl std::fmt::Display for ConnectAttemptsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to connect to any address")?;
+        for (i, (target, err)) in self.attempts.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { "; " };
+            match target.tcp() {
+                Some(addr) => write!(f, "{sep}{addr}: {err}")?,
+                None => write!(f, "{sep}{target:?}: {err}")?,
+            }
         }
+        Ok(())
     }
 }
 
@@ -1,17 +1,23 @@
+use super::{Admission, AdmissionTicket};
 use crate::{
-    common::tokio_stream::TokioListenerStream, ConnectionError, LocalAddress, Preview,
-    PreviewConfiguration, ResolvedTarget, RewindStream, Ssl, StreamUpgrade, TlsDriver,
+    common::{proxy_protocol::read_header, tokio_stream::TokioListenerStream},
+    AdmissionControl, ConnectionError, LocalAddress, Preview, PreviewConfiguration, ProxyHeader,
+    ProxyProtocolConfig, ResolvedTarget, RewindStream, Ssl, StreamUpgrade, TlsDriver,
     TlsServerParameterProvider, UpgradableStream, DEFAULT_TLS_BACKLOG,
 };
 use futures::{stream::FuturesUnordered, StreamExt};
 use std::{
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{ready, Poll},
 };
 use std::{net::SocketAddr, path::Path};
 use tokio::io::AsyncReadExt;
 
+#[cfg(unix)]
+use crate::MemoryNetwork;
+
 type Connection<D = Ssl> = UpgradableStream<crate::BaseStream, D>;
 
 pub struct Acceptor<const PREVIEW: bool = false> {
@@ -21,7 +27,7 @@ pub struct Acceptor<const PREVIEW: bool = false> {
     options: StreamOptions<PREVIEW>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct StreamOptions<const PREVIEW: bool> {
     ignore_missing_tls_close_notify: bool,
     reuse_port: bool,
@@ -29,6 +35,10 @@ struct StreamOptions<const PREVIEW: bool> {
     preview_configuration: Option<PreviewConfiguration>,
     tcp_backlog: Option<u32>,
     tls_backlog: Option<u32>,
+    proxy_protocol: Option<Arc<ProxyProtocolConfig>>,
+    #[cfg(unix)]
+    memory_network: Option<MemoryNetwork>,
+    admission: Option<Arc<Admission>>,
 }
 
 impl<const PREVIEW: bool> Default for StreamOptions<PREVIEW> {
@@ -40,6 +50,10 @@ impl<const PREVIEW: bool> Default for StreamOptions<PREVIEW> {
             preview_configuration: None,
             tcp_backlog: None,
             tls_backlog: None,
+            proxy_protocol: None,
+            #[cfg(unix)]
+            memory_network: None,
+            admission: None,
         }
     }
 }
@@ -108,6 +122,68 @@ impl<const PREVIEW: bool> Acceptor<PREVIEW> {
             ..self
         }
     }
+
+    /// Expect a HAProxy PROXY protocol (v1 or v2) header from peers in the
+    /// trusted list.
+    ///
+    /// Connections from trusted peers must start with a PROXY header, which
+    /// is consumed before any TLS handshake or preview. The client address
+    /// it carries is returned from `remote_address`, and the full header is
+    /// available via [`crate::StreamMetadata::proxy_header`]. Connections
+    /// from other peers are accepted as-is and never parsed.
+    pub fn with_proxy_protocol(self, config: ProxyProtocolConfig) -> Self {
+        Self {
+            options: StreamOptions {
+                proxy_protocol: Some(Arc::new(config)),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Limit the connections that are accepted. Connections over the limits
+    /// are closed without being returned, or returned marked as rejected if
+    /// [`AdmissionControl::return_rejected`] is set.
+    pub fn with_admission_control(self, config: AdmissionControl) -> Self {
+        Self {
+            options: StreamOptions {
+                admission: Some(Arc::new(Admission::new(config))),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Listen on an in-memory network instead of the operating system's
+    /// sockets. The target address is registered with the network, and
+    /// socket options such as the backlog are ignored. Only available on
+    /// Unix.
+    #[cfg(unix)]
+    pub fn with_memory_network(self, network: &MemoryNetwork) -> Self {
+        Self {
+            options: StreamOptions {
+                memory_network: Some(network.clone()),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    async fn listen(&self) -> std::io::Result<TokioListenerStream> {
+        #[cfg(unix)]
+        if let Some(network) = &self.options.memory_network {
+            return Ok(TokioListenerStream::Memory(
+                network.listen(&self.resolved_target)?,
+            ));
+        }
+        self.resolved_target
+            .listen_raw(
+                self.options.tcp_backlog,
+                self.options.reuse_port,
+                self.options.reuse_addr,
+            )
+            .await
+    }
 }
 
 impl Acceptor<false> {
@@ -214,14 +290,7 @@ impl Acceptor<false> {
         impl ::futures::Stream<Item = Result<Connection, ConnectionError>> + LocalAddress,
         ConnectionError,
     > {
-        let stream = self
-            .resolved_target
-            .listen_raw(
-                self.options.tcp_backlog,
-                self.options.reuse_port,
-                self.options.reuse_addr,
-            )
-            .await?;
+        let stream = self.listen().await?;
         Ok(AcceptedStream::<Connection<Ssl>> {
             stream,
             should_upgrade: self.should_upgrade,
@@ -231,6 +300,8 @@ impl Acceptor<false> {
                 self.options.tls_backlog.unwrap_or(DEFAULT_TLS_BACKLOG) as _,
             ),
             preview_configuration: None,
+            proxy_protocol: self.options.proxy_protocol,
+            admission: self.options.admission,
             _phantom: None,
         })
     }
@@ -242,14 +313,7 @@ impl Acceptor<false> {
         impl ::futures::Stream<Item = Result<Connection<D>, ConnectionError>> + LocalAddress,
         ConnectionError,
     > {
-        let stream = self
-            .resolved_target
-            .listen_raw(
-                self.options.tcp_backlog,
-                self.options.reuse_port,
-                self.options.reuse_addr,
-            )
-            .await?;
+        let stream = self.listen().await?;
         Ok(AcceptedStream::<Connection<D>, D> {
             stream,
             ignore_missing_tls_close_notify: self.options.ignore_missing_tls_close_notify,
@@ -259,6 +323,8 @@ impl Acceptor<false> {
                 self.options.tls_backlog.unwrap_or(DEFAULT_TLS_BACKLOG) as _,
             ),
             preview_configuration: None,
+            proxy_protocol: self.options.proxy_protocol,
+            admission: self.options.admission,
             _phantom: None,
         })
     }
@@ -335,14 +401,7 @@ impl Acceptor<true> {
         impl ::futures::Stream<Item = Result<(Preview, Connection), ConnectionError>> + LocalAddress,
         ConnectionError,
     > {
-        let stream = self
-            .resolved_target
-            .listen_raw(
-                self.options.tcp_backlog,
-                self.options.reuse_port,
-                self.options.reuse_addr,
-            )
-            .await?;
+        let stream = self.listen().await?;
         Ok(AcceptedStream::<(Preview, Connection<Ssl>)> {
             stream,
             should_upgrade: self.should_upgrade,
@@ -350,6 +409,8 @@ impl Acceptor<true> {
             tls_provider: self.tls_provider,
             tls_backlog: TlsAcceptBacklog::new(self.options.tls_backlog.unwrap_or(128) as _),
             preview_configuration: self.options.preview_configuration,
+            proxy_protocol: self.options.proxy_protocol,
+            admission: self.options.admission,
             _phantom: None,
         })
     }
@@ -361,14 +422,7 @@ impl Acceptor<true> {
         impl ::futures::Stream<Item = Result<(Preview, Connection<D>), ConnectionError>> + LocalAddress,
         ConnectionError,
     > {
-        let stream = self
-            .resolved_target
-            .listen_raw(
-                self.options.tcp_backlog,
-                self.options.reuse_port,
-                self.options.reuse_addr,
-            )
-            .await?;
+        let stream = self.listen().await?;
         Ok(AcceptedStream::<(Preview, Connection<D>), D> {
             stream,
             should_upgrade: self.should_upgrade,
@@ -378,6 +432,8 @@ impl Acceptor<true> {
                 self.options.tls_backlog.unwrap_or(DEFAULT_TLS_BACKLOG) as _,
             ),
             preview_configuration: self.options.preview_configuration,
+            proxy_protocol: self.options.proxy_protocol,
+            admission: self.options.admission,
             _phantom: None,
         })
     }
@@ -401,10 +457,67 @@ struct AcceptedStream<S, D: TlsDriver = Ssl> {
     tls_provider: Option<TlsServerParameterProvider>,
     tls_backlog: TlsAcceptBacklog<S>,
     preview_configuration: Option<PreviewConfiguration>,
+    proxy_protocol: Option<Arc<ProxyProtocolConfig>>,
+    admission: Option<Arc<Admission>>,
     // Avoid using PhantomData because it fails to implement certain auto-traits
     _phantom: Option<&'static D>,
 }
 
+/// Read the PROXY protocol header if the peer is a trusted load balancer.
+async fn read_proxy_header(
+    config: Option<Arc<ProxyProtocolConfig>>,
+    stream: &mut crate::BaseStream,
+    peer: &ResolvedTarget,
+) -> Result<Option<ProxyHeader>, ConnectionError> {
+    let Some(config) = config.filter(|config| config.is_trusted(peer)) else {
+        return Ok(None);
+    };
+    let header = tokio::time::timeout(config.header_timeout, read_header(stream))
+        .await
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Timed out waiting for PROXY protocol header",
+            )
+        })??;
+    Ok(Some(header))
+}
+
+/// Admit a connection from `peer`. `None` means the connection was rejected
+/// and should be closed.
+fn admit(
+    admission: Option<&Arc<Admission>>,
+    peer: &ResolvedTarget,
+) -> Option<Option<AdmissionTicket>> {
+    let Some(admission) = admission else {
+        return Some(None);
+    };
+    let ticket = admission.admit(peer);
+    (!ticket.should_close()).then_some(Some(ticket))
+}
+
+/// Admit a connection from `peer` and then read its PROXY header, if any. The
+/// per-IP limit is checked again for the client address in the header.
+/// `None` means the connection was rejected and should be closed.
+async fn admit_and_read_proxy_header(
+    admission: Option<Arc<Admission>>,
+    config: Option<Arc<ProxyProtocolConfig>>,
+    stream: &mut crate::BaseStream,
+    peer: &ResolvedTarget,
+) -> Result<Option<(Option<AdmissionTicket>, Option<ProxyHeader>)>, ConnectionError> {
+    let Some(mut ticket) = admit(admission.as_ref(), peer) else {
+        return Ok(None);
+    };
+    let header = read_proxy_header(config, stream, peer).await?;
+    if let (Some(ticket), Some(source)) = (&mut ticket, header.as_ref().and_then(|h| h.source)) {
+        ticket.readmit(&ResolvedTarget::SocketAddr(source));
+        if ticket.should_close() {
+            return Ok(None);
+        }
+    }
+    Ok(Some((ticket, header)))
+}
+
 impl<S, D: TlsDriver> LocalAddress for AcceptedStream<S, D> {
     fn local_address(&self) -> std::io::Result<ResolvedTarget> {
         self.stream.local_address()
@@ -427,42 +540,74 @@ impl<D: TlsDriver> futures::Stream for AcceptedStream<Connection<D>, D> {
             stream
         };
 
-        // If we're not upgrading, we can just return the stream as is and skip
-        // the second-level backlog.
-        if !self.should_upgrade {
-            return self.as_mut().stream.poll_next_unpin(cx).map(|c| {
-                c.map(|c| Ok(c.map(|(c, _t)| make_stream(self.tls_provider.clone(), c))?))
-            });
-        }
-
-        // Fill the backlog to capacity as log as we have connections to accept.
-        while !self.tls_backlog.is_full() {
-            let Poll::Ready(r) = self.stream.poll_next_unpin(cx) else {
-                if self.tls_backlog.is_empty() {
-                    return Poll::Pending;
-                }
-                break;
-            };
-
-            let Some((stream, _t)) = r.transpose()? else {
-                if self.tls_backlog.is_empty() {
+        // If we're not upgrading or reading PROXY headers, we can just return
+        // the stream as is and skip the second-level backlog.
+        if !self.should_upgrade && self.proxy_protocol.is_none() {
+            loop {
+                let Some((c, peer)) = ready!(self.stream.poll_next_unpin(cx)).transpose()? else {
                     return Poll::Ready(None);
+                };
+                // Rejected connections are closed by dropping them
+                let Some(ticket) = admit(self.admission.as_ref(), &peer) else {
+                    continue;
+                };
+                let mut stream = make_stream(self.tls_provider.clone(), c);
+                if let Some(ticket) = ticket {
+                    stream.set_admission(ticket);
                 }
-                break;
-            };
-
-            let tls_provider = self.tls_provider.clone();
-            self.tls_backlog.push(async move {
-                let stream = make_stream(tls_provider, stream);
-                let stream = stream.secure_upgrade().await?;
-                Ok(stream)
-            })
+                return Poll::Ready(Some(Ok(stream)));
+            }
         }
 
-        // We've got at least one pending connection here
-        debug_assert!(!self.tls_backlog.is_empty());
-        let r = ready!(Pin::new(&mut self.tls_backlog).poll_next(cx))?;
-        Poll::Ready(Some(Ok(r)))
+        loop {
+            // Fill the backlog to capacity as log as we have connections to accept.
+            while !self.tls_backlog.is_full() {
+                let Poll::Ready(r) = self.stream.poll_next_unpin(cx) else {
+                    if self.tls_backlog.is_empty() {
+                        return Poll::Pending;
+                    }
+                    break;
+                };
+
+                let Some((mut stream, peer)) = r.transpose()? else {
+                    if self.tls_backlog.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    break;
+                };
+
+                let tls_provider = self.tls_provider.clone();
+                let proxy_protocol = self.proxy_protocol.clone();
+                let admission = self.admission.clone();
+                let should_upgrade = self.should_upgrade;
+                self.tls_backlog.push(async move {
+                    let Some((ticket, header)) =
+                        admit_and_read_proxy_header(admission, proxy_protocol, &mut stream, &peer)
+                            .await?
+                    else {
+                        return Ok(None);
+                    };
+                    let mut stream = make_stream(tls_provider, stream);
+                    if let Some(header) = header {
+                        stream.set_proxy_header(header);
+                    }
+                    if let Some(ticket) = ticket {
+                        stream.set_admission(ticket);
+                    }
+                    if should_upgrade {
+                        stream = stream.secure_upgrade().await?;
+                    }
+                    Ok(Some(stream))
+                })
+            }
+
+            // We've got at least one pending connection here
+            debug_assert!(!self.tls_backlog.is_empty());
+            // Rejected connections are closed, so accept another one instead
+            if let Some(r) = ready!(Pin::new(&mut self.tls_backlog).poll_next(cx))? {
+                return Poll::Ready(Some(Ok(r)));
+            }
+        }
     }
 }
 
@@ -472,47 +617,66 @@ impl<D: TlsDriver> futures::Stream for AcceptedStream<(Preview, Connection<D>),
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        // Fill the backlog to capacity as log as we have connections to accept.
-        while !self.tls_backlog.is_full() {
-            let Poll::Ready(r) = self.stream.poll_next_unpin(cx) else {
-                if self.tls_backlog.is_empty() {
-                    return Poll::Pending;
-                }
-                break;
-            };
-
-            let Some((mut stream, _t)) = r.transpose()? else {
-                if self.tls_backlog.is_empty() {
-                    return Poll::Ready(None);
-                }
-                break;
-            };
-
-            let tls_provider = self.tls_provider.clone();
-            let preview_configuration = self.preview_configuration.unwrap();
-            let ignore_missing_tls_close_notify = self.ignore_missing_tls_close_notify;
-            self.tls_backlog.push(async move {
-                let mut buf = smallvec::SmallVec::with_capacity(
-                    preview_configuration.max_preview_bytes.get(),
-                );
-                buf.resize(preview_configuration.max_preview_bytes.get(), 0);
-                stream.read_exact(&mut buf).await?;
-                let mut stream = RewindStream::new(stream);
-                stream.rewind(&buf);
-                let preview = Preview::new(buf);
-                let mut stream = UpgradableStream::<_, D>::new_server_preview(stream, tls_provider);
-                if ignore_missing_tls_close_notify {
-                    stream.ignore_missing_close_notify();
-                }
+        loop {
+            // Fill the backlog to capacity as log as we have connections to accept.
+            while !self.tls_backlog.is_full() {
+                let Poll::Ready(r) = self.stream.poll_next_unpin(cx) else {
+                    if self.tls_backlog.is_empty() {
+                        return Poll::Pending;
+                    }
+                    break;
+                };
+
+                let Some((mut stream, peer)) = r.transpose()? else {
+                    if self.tls_backlog.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    break;
+                };
+
+                let tls_provider = self.tls_provider.clone();
+                let preview_configuration = self.preview_configuration.unwrap();
+                let ignore_missing_tls_close_notify = self.ignore_missing_tls_close_notify;
+                let proxy_protocol = self.proxy_protocol.clone();
+                let admission = self.admission.clone();
+                self.tls_backlog.push(async move {
+                    let Some((ticket, header)) =
+                        admit_and_read_proxy_header(admission, proxy_protocol, &mut stream, &peer)
+                            .await?
+                    else {
+                        return Ok(None);
+                    };
+                    let mut buf = smallvec::SmallVec::with_capacity(
+                        preview_configuration.max_preview_bytes.get(),
+                    );
+                    buf.resize(preview_configuration.max_preview_bytes.get(), 0);
+                    stream.read_exact(&mut buf).await?;
+                    let mut stream = RewindStream::new(stream);
+                    stream.rewind(&buf);
+                    let preview = Preview::new(buf);
+                    let mut stream =
+                        UpgradableStream::<_, D>::new_server_preview(stream, tls_provider);
+                    if ignore_missing_tls_close_notify {
+                        stream.ignore_missing_close_notify();
+                    }
+                    if let Some(header) = header {
+                        stream.set_proxy_header(header);
+                    }
+                    if let Some(ticket) = ticket {
+                        stream.set_admission(ticket);
+                    }
+
+                    Ok(Some((preview, stream)))
+                })
+            }
 
-                Ok((preview, stream))
-            })
+            // We've got at least one pending connection here
+            debug_assert!(!self.tls_backlog.is_empty());
+            // Rejected connections are closed, so accept another one instead
+            if let Some(r) = ready!(Pin::new(&mut self.tls_backlog).poll_next(cx))? {
+                return Poll::Ready(Some(Ok(r)));
+            }
         }
-
-        // We've got at least one pending connection here
-        debug_assert!(!self.tls_backlog.is_empty());
-        let r = ready!(Pin::new(&mut self.tls_backlog).poll_next(cx))?;
-        Poll::Ready(Some(Ok(r)))
     }
 }
 
@@ -520,7 +684,7 @@ struct TlsAcceptBacklog<C> {
     capacity: usize,
     #[allow(clippy::type_complexity)]
     futures: FuturesUnordered<
-        Pin<Box<dyn Future<Output = Result<C, ConnectionError>> + Send + 'static>>,
+        Pin<Box<dyn Future<Output = Result<Option<C>, ConnectionError>> + Send + 'static>>,
     >,
 }
 
@@ -543,12 +707,17 @@ impl<C> TlsAcceptBacklog<C> {
     fn poll_next(
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
-    ) -> Poll<Result<C, ConnectionError>> {
+    ) -> Poll<Result<Option<C>, ConnectionError>> {
         debug_assert!(!self.is_empty());
         self.futures.poll_next_unpin(cx).map(|r| r.unwrap())
     }
 
-    fn push(&mut self, future: impl Future<Output = Result<C, ConnectionError>> + Send + 'static) {
+    /// Push a pending connection. The future resolves to `None` if the
+    /// connection was closed rather than accepted.
+    fn push(
+        &mut self,
+        future: impl Future<Output = Result<Option<C>, ConnectionError>> + Send + 'static,
+    ) {
         self.futures.push(Box::pin(future));
     }
 }
@@ -557,7 +726,8 @@ impl<C> TlsAcceptBacklog<C> {
 mod tests {
     use super::*;
     use crate::{
-        Connector, OpensslDriver, RustlsDriver, Target, TlsParameters, TlsServerParameters,
+        Connector, OpensslDriver, RemoteAddress, RustlsDriver, StreamMetadata, Target,
+        TlsParameters, TlsServerParameters, PP2_TYPE_AUTHORITY,
     };
     use std::net::*;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -618,4 +788,145 @@ mod tests {
     async fn test_acceptor_new_tcp_previewing_rustls() -> Result<(), ConnectionError> {
         test_acceptor_new_tcp_previewing::<RustlsDriver>().await
     }
+    fn localhost_proxy_protocol() -> ProxyProtocolConfig {
+        ProxyProtocolConfig::new(["127.0.0.0/8".parse().unwrap()])
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_proxy_protocol_v1() -> Result<(), ConnectionError> {
+        let acceptor = Acceptor::new_tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .with_proxy_protocol(localhost_proxy_protocol());
+        let mut conns = acceptor.bind().await?;
+        let addr = conns.local_address()?;
+        tokio::task::spawn(async move {
+            let mut conn = Connector::new_resolved(addr).connect().await?;
+            conn.write_all(b"PROXY TCP4 192.0.2.10 192.0.2.1 51234 5656\r\nHELLO")
+                .await?;
+            conn.shutdown().await
+        });
+
+        let mut conn = conns.next().await.unwrap()?;
+        assert_eq!(
+            conn.remote_address()?,
+            ResolvedTarget::SocketAddr("192.0.2.10:51234".parse().unwrap())
+        );
+        let header = conn.proxy_header().unwrap();
+        assert_eq!(header.destination, Some("192.0.2.1:5656".parse().unwrap()));
+        let mut string = String::new();
+        conn.read_to_string(&mut string).await?;
+        assert_eq!(string, "HELLO");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_proxy_protocol_untrusted() -> Result<(), ConnectionError> {
+        let acceptor = Acceptor::new_tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .with_proxy_protocol(ProxyProtocolConfig::new(["192.0.2.0/24".parse().unwrap()]));
+        let mut conns = acceptor.bind().await?;
+        let addr = conns.local_address()?;
+        tokio::task::spawn(async move {
+            let mut conn = Connector::new_resolved(addr).connect().await?;
+            conn.write_all(b"PROXY TCP4 192.0.2.10 192.0.2.1 51234 5656\r\n")
+                .await?;
+            conn.shutdown().await
+        });
+
+        let mut conn = conns.next().await.unwrap()?;
+        assert!(conn.proxy_header().is_none());
+        let ResolvedTarget::SocketAddr(remote) = conn.remote_address()? else {
+            panic!("expected a TCP peer");
+        };
+        assert!(remote.ip().is_loopback());
+        let mut string = String::new();
+        conn.read_to_string(&mut string).await?;
+        assert!(string.starts_with("PROXY TCP4"));
+        Ok(())
+    }
+
+    /// Per-IP limits apply to the client address in the PROXY header, and
+    /// rejected connections are closed rather than returned.
+    #[tokio::test]
+    async fn test_acceptor_proxy_protocol_admission() -> Result<(), ConnectionError> {
+        let acceptor = Acceptor::new_tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .with_proxy_protocol(localhost_proxy_protocol())
+            .with_admission_control(crate::AdmissionControl {
+                max_connections_per_ip: Some(1),
+                ..Default::default()
+            });
+        let mut conns = acceptor.bind().await?;
+        let addr = conns.local_address()?;
+        let connect = |source: &'static str| {
+            let addr = addr.clone();
+            async move {
+                let mut conn = Connector::new_resolved(addr).connect().await?;
+                conn.write_all(format!("PROXY TCP4 {source} 192.0.2.1 51234 5656\r\n").as_bytes())
+                    .await?;
+                Ok::<_, ConnectionError>(conn)
+            }
+        };
+
+        // Both connections come from the same load balancer.
+        let _client1 = connect("192.0.2.10").await?;
+        let server1 = conns.next().await.unwrap()?;
+        let _client2 = connect("192.0.2.11").await?;
+        let server2 = conns.next().await.unwrap()?;
+        assert_ne!(server1.remote_address()?, server2.remote_address()?);
+
+        let mut client3 = connect("192.0.2.10").await?;
+        let mut buf = [0; 1];
+        tokio::select! {
+            _ = conns.next() => panic!("rejected connection was returned"),
+            res = client3.read(&mut buf) => assert!(matches!(res, Ok(0) | Err(_))),
+        }
+
+        drop(server1);
+        let _client4 = connect("192.0.2.10").await?;
+        let server4 = conns.next().await.unwrap()?;
+        assert_eq!(server4.admission_rejection(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_proxy_protocol_v2_previewing() -> Result<(), ConnectionError> {
+        let acceptor = Acceptor::new_tcp_tls_previewing(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            PreviewConfiguration::default(),
+            TlsServerParameterProvider::new(TlsServerParameters::new_with_certificate(
+                crate::test_keys::SERVER_KEY.clone_key(),
+            )),
+        )
+        .with_proxy_protocol(localhost_proxy_protocol());
+        let mut conns = acceptor.bind().await?;
+        let addr = conns.local_address()?;
+        tokio::task::spawn(async move {
+            let mut header = b"\r\n\r\n\0\r\nQUIT\n\x21\x11".to_vec();
+            let authority = b"db.example.com";
+            header.extend_from_slice(&(12 + 3 + authority.len() as u16).to_be_bytes());
+            header.extend_from_slice(&[198, 51, 100, 7, 192, 0, 2, 1]);
+            header.extend_from_slice(&40000_u16.to_be_bytes());
+            header.extend_from_slice(&5656_u16.to_be_bytes());
+            header.push(PP2_TYPE_AUTHORITY);
+            header.extend_from_slice(&(authority.len() as u16).to_be_bytes());
+            header.extend_from_slice(authority);
+            header.extend_from_slice(b"HELLO WORLD");
+            let mut conn = Connector::new_resolved(addr).connect().await?;
+            conn.write_all(&header).await?;
+            conn.shutdown().await
+        });
+
+        let (preview, mut conn) = conns.next().await.unwrap()?;
+        assert_eq!(preview, b"HELLO WO");
+        assert_eq!(
+            conn.remote_address()?,
+            ResolvedTarget::SocketAddr("198.51.100.7:40000".parse().unwrap())
+        );
+        assert_eq!(
+            conn.proxy_header().unwrap().authority(),
+            Some("db.example.com")
+        );
+        let mut string = String::new();
+        conn.read_to_string(&mut string).await?;
+        assert_eq!(string, "HELLO WORLD");
+        Ok(())
+    }
 }
@@ -0,0 +1,383 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use crate::ResolvedTarget;
+
+/// Admission control for the connections accepted by an [`crate::Acceptor`].
+///
+/// Connections are admitted against the socket peer's address as soon as
+/// they are accepted, before any PROXY protocol header is read. Once a header
+/// is read, the per-IP limit is checked again for the client address it
+/// carries, which then takes over the connection's place. Admitted
+/// connections count against the limits until they are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct AdmissionControl {
+    /// The maximum number of concurrently open connections.
+    pub max_connections: Option<usize>,
+    /// The maximum number of concurrently open connections from a single
+    /// source IP. Unix socket connections are not limited.
+    pub max_connections_per_ip: Option<usize>,
+    /// The rate at which connections are admitted.
+    pub accept_rate: Option<AcceptRate>,
+    /// The maximum duration of the TLS handshake, whether it is performed by
+    /// the acceptor or later via [`crate::StreamUpgrade`].
+    pub tls_handshake_timeout: Option<Duration>,
+    /// Return rejected connections instead of closing them, so that the
+    /// caller can send a protocol-appropriate rejection. Rejected connections
+    /// are marked with [`crate::UpgradableStream::admission_rejection`].
+    /// Otherwise, rejected connections are closed without being returned.
+    pub return_rejected: bool,
+    /// The maximum number of rejected connections returned and not yet
+    /// dropped when `return_rejected` is set. Rejected connections over
+    /// this limit are closed without being returned, so that a flood of
+    /// connections cannot be handed over without bound.
+    pub max_returned_rejections: Option<usize>,
+}
+
+/// A token bucket limiting the rate at which connections are admitted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AcceptRate {
+    /// The number of connections admitted per second on average.
+    pub per_second: f64,
+    /// The number of connections that may be admitted in a burst.
+    pub burst: u32,
+}
+
+impl AcceptRate {
+    /// Admit `per_second` connections per second, with bursts of the same
+    /// size.
+    pub fn per_second(per_second: u32) -> Self {
+        Self {
+            per_second: per_second as f64,
+            burst: per_second,
+        }
+    }
+}
+
+/// The reason a connection was rejected by [`AdmissionControl`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+pub enum AdmissionRejection {
+    #[display("Too many connections")]
+    TooManyConnections,
+    #[display("Too many connections from {_0}")]
+    TooManyConnectionsFromIp(#[error(not(source))] IpAddr),
+    #[display("Connection rate limit exceeded")]
+    RateLimited,
+}
+
+#[derive(Debug)]
+pub(crate) struct Admission {
+    config: AdmissionControl,
+    state: Mutex<AdmissionState>,
+}
+
+#[derive(Debug)]
+struct AdmissionState {
+    open: usize,
+    per_ip: HashMap<IpAddr, usize>,
+    /// Rejected connections returned by the acceptor.
+    returned_rejections: usize,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Admission {
+    pub(crate) fn new(config: AdmissionControl) -> Self {
+        let tokens = config.accept_rate.map(|rate| rate.burst as f64);
+        Self {
+            config,
+            state: Mutex::new(AdmissionState {
+                open: 0,
+                per_ip: HashMap::new(),
+                returned_rejections: 0,
+                tokens: tokens.unwrap_or_default(),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Admit a connection from `peer`. The returned ticket holds the
+    /// connection's place until it is dropped. A rejected ticket holds a
+    /// place among the returned rejections instead, if it is to be returned.
+    pub(crate) fn admit(self: &Arc<Self>, peer: &ResolvedTarget) -> AdmissionTicket {
+        let ip = peer_ip(peer);
+        let mut state = self.state.lock().unwrap();
+
+        let rejection = if self
+            .config
+            .max_connections
+            .is_some_and(|max| state.open >= max)
+        {
+            Some(AdmissionRejection::TooManyConnections)
+        } else if let Some(ip) = ip.filter(|ip| self.over_ip_limit(&state, ip)) {
+            Some(AdmissionRejection::TooManyConnectionsFromIp(ip))
+        } else if let Some(rate) = self.config.accept_rate {
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * rate.per_second).min(rate.burst as f64);
+            state.refilled_at = now;
+            if state.tokens >= 1.0 {
+                state.tokens -= 1.0;
+                None
+            } else {
+                Some(AdmissionRejection::RateLimited)
+            }
+        } else {
+            None
+        };
+
+        let returned = if rejection.is_none() {
+            state.open += 1;
+            if let Some(ip) = ip {
+                *state.per_ip.entry(ip).or_default() += 1;
+            }
+            false
+        } else {
+            self.return_rejected(&mut state)
+        };
+
+        AdmissionTicket {
+            admission: self.clone(),
+            ip,
+            rejection,
+            returned,
+        }
+    }
+
+    /// Take a place among the returned rejections, returning `false` if the
+    /// rejected connection should be closed instead.
+    fn return_rejected(&self, state: &mut AdmissionState) -> bool {
+        if !self.config.return_rejected
+            || self
+                .config
+                .max_returned_rejections
+                .is_some_and(|max| state.returned_rejections >= max)
+        {
+            return false;
+        }
+        state.returned_rejections += 1;
+        true
+    }
+
+    fn over_ip_limit(&self, state: &AdmissionState, ip: &IpAddr) -> bool {
+        self.config
+            .max_connections_per_ip
+            .is_some_and(|max| state.per_ip.get(ip).copied().unwrap_or_default() >= max)
+    }
+}
+
+fn peer_ip(peer: &ResolvedTarget) -> Option<IpAddr> {
+    peer.tcp().map(|addr| addr.ip().to_canonical())
+}
+
+impl AdmissionState {
+    /// Release an admitted connection's place.
+    fn release(&mut self, ip: Option<IpAddr>) {
+        self.open -= 1;
+        if let Some(ip) = ip {
+            self.release_ip(ip);
+        }
+    }
+
+    fn release_ip(&mut self, ip: IpAddr) {
+        if let std::collections::hash_map::Entry::Occupied(mut entry) = self.per_ip.entry(ip) {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+    }
+}
+
+/// A connection's place in the [`AdmissionControl`] limits, released when
+/// the connection is dropped.
+#[derive(derive_more::Debug)]
+#[debug("AdmissionTicket({rejection:?})")]
+pub(crate) struct AdmissionTicket {
+    admission: Arc<Admission>,
+    ip: Option<IpAddr>,
+    rejection: Option<AdmissionRejection>,
+    /// Whether the rejected connection holds a place among the returned
+    /// rejections.
+    returned: bool,
+}
+
+impl AdmissionTicket {
+    pub(crate) fn rejection(&self) -> Option<AdmissionRejection> {
+        self.rejection
+    }
+
+    pub(crate) fn tls_handshake_timeout(&self) -> Option<Duration> {
+        self.admission.config.tls_handshake_timeout
+    }
+
+    /// Whether the acceptor should close the connection rather than return
+    /// it.
+    pub(crate) fn should_close(&self) -> bool {
+        self.rejection.is_some() && !self.returned
+    }
+
+    /// Move an admitted connection's per-IP place from the socket peer to
+    /// `source`, the client address from its PROXY header, rejecting it if
+    /// that address is over its limit.
+    pub(crate) fn readmit(&mut self, source: &ResolvedTarget) {
+        let ip = peer_ip(source);
+        if self.rejection.is_some() || ip == self.ip {
+            return;
+        }
+        let mut state = self.admission.state.lock().unwrap();
+        if let Some(ip) = ip.filter(|ip| self.admission.over_ip_limit(&state, ip)) {
+            state.release(self.ip);
+            self.rejection = Some(AdmissionRejection::TooManyConnectionsFromIp(ip));
+            self.returned = self.admission.return_rejected(&mut state);
+            return;
+        }
+        if let Some(ip) = self.ip {
+            state.release_ip(ip);
+        }
+        if let Some(ip) = ip {
+            *state.per_ip.entry(ip).or_default() += 1;
+        }
+        self.ip = ip;
+    }
+}
+
+impl Drop for AdmissionTicket {
+    fn drop(&mut self) {
+        let mut state = self.admission.state.lock().unwrap();
+        if self.rejection.is_none() {
+            state.release(self.ip);
+        } else if self.returned {
+            state.returned_rejections -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(ip: [u8; 4]) -> ResolvedTarget {
+        ResolvedTarget::SocketAddr((ip, 1234).into())
+    }
+
+    #[test]
+    fn test_connection_limits() {
+        let admission = Arc::new(Admission::new(AdmissionControl {
+            max_connections: Some(3),
+            max_connections_per_ip: Some(2),
+            ..Default::default()
+        }));
+        let a1 = admission.admit(&peer([10, 0, 0, 1]));
+        let a2 = admission.admit(&peer([10, 0, 0, 1]));
+        assert_eq!(a1.rejection(), None);
+        assert_eq!(a2.rejection(), None);
+        assert_eq!(
+            admission.admit(&peer([10, 0, 0, 1])).rejection(),
+            Some(AdmissionRejection::TooManyConnectionsFromIp(
+                [10, 0, 0, 1].into()
+            ))
+        );
+        let b1 = admission.admit(&peer([10, 0, 0, 2]));
+        assert_eq!(b1.rejection(), None);
+        assert_eq!(
+            admission.admit(&peer([10, 0, 0, 3])).rejection(),
+            Some(AdmissionRejection::TooManyConnections)
+        );
+
+        // Dropping an admitted connection frees its place.
+        drop(a1);
+        assert_eq!(admission.admit(&peer([10, 0, 0, 1])).rejection(), None);
+        drop((a2, b1));
+        let state = admission.state.lock().unwrap();
+        assert_eq!(state.open, 0);
+        assert!(state.per_ip.is_empty());
+    }
+
+    #[test]
+    fn test_readmit() {
+        let admission = Arc::new(Admission::new(AdmissionControl {
+            max_connections: Some(3),
+            max_connections_per_ip: Some(1),
+            ..Default::default()
+        }));
+        let balancer = peer([10, 0, 0, 100]);
+        let mut a = admission.admit(&balancer);
+        a.readmit(&peer([10, 0, 0, 1]));
+        assert_eq!(a.rejection(), None);
+
+        // The balancer's place was handed over, so it can connect again.
+        let mut b = admission.admit(&balancer);
+        assert_eq!(b.rejection(), None);
+        b.readmit(&peer([10, 0, 0, 1]));
+        assert_eq!(
+            b.rejection(),
+            Some(AdmissionRejection::TooManyConnectionsFromIp(
+                [10, 0, 0, 1].into()
+            ))
+        );
+        drop(b);
+
+        let c = admission.admit(&balancer);
+        assert_eq!(c.rejection(), None);
+        drop((a, c));
+        let state = admission.state.lock().unwrap();
+        assert_eq!(state.open, 0);
+        assert!(state.per_ip.is_empty());
+    }
+
+    #[test]
+    fn test_returned_rejections() {
+        let admission = Arc::new(Admission::new(AdmissionControl {
+            max_connections: Some(1),
+            return_rejected: true,
+            max_returned_rejections: Some(2),
+            ..Default::default()
+        }));
+        let a = admission.admit(&peer([10, 0, 0, 1]));
+        assert!(!a.should_close());
+        let r1 = admission.admit(&peer([10, 0, 0, 2]));
+        let r2 = admission.admit(&peer([10, 0, 0, 3]));
+        assert_eq!(r1.rejection(), Some(AdmissionRejection::TooManyConnections));
+        assert!(!r1.should_close());
+        assert!(!r2.should_close());
+
+        // Over the limit, rejected connections are closed.
+        let r3 = admission.admit(&peer([10, 0, 0, 4]));
+        assert_eq!(r3.rejection(), Some(AdmissionRejection::TooManyConnections));
+        assert!(r3.should_close());
+        drop((r1, r3));
+        assert!(!admission.admit(&peer([10, 0, 0, 5])).should_close());
+
+        drop((a, r2));
+        let state = admission.state.lock().unwrap();
+        assert_eq!(state.open, 0);
+        assert_eq!(state.returned_rejections, 0);
+    }
+
+    #[tokio::test]
+    async fn test_accept_rate() {
+        let admission = Arc::new(Admission::new(AdmissionControl {
+            accept_rate: Some(AcceptRate {
+                per_second: 20.0,
+                burst: 2,
+            }),
+            ..Default::default()
+        }));
+        let peer = peer([10, 0, 0, 1]);
+        assert_eq!(admission.admit(&peer).rejection(), None);
+        assert_eq!(admission.admit(&peer).rejection(), None);
+        assert_eq!(
+            admission.admit(&peer).rejection(),
+            Some(AdmissionRejection::RateLimited)
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(admission.admit(&peer).rejection(), None);
+    }
+}
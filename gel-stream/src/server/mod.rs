@@ -1,2 +1,9 @@
 mod acceptor;
+mod admission;
+#[cfg(feature = "pem")]
+mod reload;
 pub use acceptor::Acceptor;
+pub use admission::{AcceptRate, AdmissionControl, AdmissionRejection};
+pub(crate) use admission::{Admission, AdmissionTicket};
+#[cfg(feature = "pem")]
+pub use reload::{TlsServerCertificateReloader, TlsServerCertificates};
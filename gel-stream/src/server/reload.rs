@@ -0,0 +1,393 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::Duration,
+};
+
+use crate::{TlsKey, TlsServerParameterProvider, TlsServerParameters};
+
+type ParametersFn = dyn Fn(TlsKey) -> TlsServerParameters + Send + Sync + 'static;
+
+/// A set of PEM certificate and key files used to build a
+/// [`TlsServerParameterProvider`] that picks a certificate by SNI and can be
+/// reloaded when the files change.
+///
+/// ```no_run
+/// # use gel_stream::*;
+/// # async fn example() -> std::io::Result<()> {
+/// let certificates = TlsServerCertificates::new("server.cert.pem", "server.key.pem")
+///     .with_sni_certificate(["*.example.com"], "example.cert.pem", "example.key.pem")
+///     .load()?;
+/// certificates.watch(std::time::Duration::from_secs(30), |e| {
+///     eprintln!("Failed to reload certificates: {e}");
+/// });
+/// let acceptor = Acceptor::new_tcp_tls(
+///     "0.0.0.0:5656".parse().unwrap(),
+///     certificates.provider(),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(derive_more::Debug)]
+pub struct TlsServerCertificates {
+    sources: Vec<CertificateSource>,
+    #[debug(skip)]
+    parameters: Arc<ParametersFn>,
+}
+
+impl TlsServerCertificates {
+    /// Create a new certificate set with the default certificate, used when
+    /// the client does not send SNI or no other certificate matches.
+    pub fn new(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Self {
+        Self {
+            sources: vec![CertificateSource::new(vec![], cert_path, key_path)],
+            parameters: Arc::new(TlsServerParameters::new_with_certificate),
+        }
+    }
+
+    /// Add a certificate that is selected when the SNI matches one of the
+    /// given names. A name of the form `*.example.com` matches exactly one
+    /// label in place of the `*`. Exact names take precedence over wildcards.
+    pub fn with_sni_certificate(
+        mut self,
+        names: impl IntoIterator<Item = impl AsRef<str>>,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Self {
+        let names = names
+            .into_iter()
+            .map(|name| name.as_ref().to_ascii_lowercase())
+            .collect();
+        self.sources
+            .push(CertificateSource::new(names, cert_path, key_path));
+        self
+    }
+
+    /// Customize the [`TlsServerParameters`] built for each certificate (ie:
+    /// to set ALPN or client certificate verification). Defaults to
+    /// [`TlsServerParameters::new_with_certificate`].
+    pub fn with_parameters(
+        self,
+        parameters: impl Fn(TlsKey) -> TlsServerParameters + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            parameters: Arc::new(parameters),
+            ..self
+        }
+    }
+
+    /// Load all certificates from disk.
+    pub fn load(self) -> std::io::Result<TlsServerCertificateReloader> {
+        let reloader = TlsServerCertificateReloader {
+            inner: Arc::new(ReloaderInner {
+                sources: Mutex::new(self.sources),
+                parameters: self.parameters,
+                current: RwLock::new(Arc::new(CertificateMap::default())),
+            }),
+        };
+        reloader.reload()?;
+        Ok(reloader)
+    }
+}
+
+/// Loaded certificates from a [`TlsServerCertificates`] set.
+///
+/// Reloading swaps in the new [`TlsServerParameters`] atomically: connections
+/// that have already looked up their parameters are unaffected, and if any
+/// file fails to load, or a key does not match its certificate, the previous
+/// certificates stay in place.
+#[derive(Clone, derive_more::Debug)]
+#[debug("TlsServerCertificateReloader(...)")]
+pub struct TlsServerCertificateReloader {
+    inner: Arc<ReloaderInner>,
+}
+
+struct ReloaderInner {
+    sources: Mutex<Vec<CertificateSource>>,
+    parameters: Arc<ParametersFn>,
+    current: RwLock<Arc<CertificateMap>>,
+}
+
+impl TlsServerCertificateReloader {
+    /// Create a [`TlsServerParameterProvider`] that always uses the most
+    /// recently loaded certificates.
+    pub fn provider(&self) -> TlsServerParameterProvider {
+        let this = self.clone();
+        TlsServerParameterProvider::with_lookup(move |name, _stream| {
+            this.select(name.as_ref().map(|name| name.as_ref()))
+        })
+    }
+
+    /// Reload any certificate whose files changed since they were last
+    /// loaded. Returns `true` if the certificates were swapped.
+    ///
+    /// Changes are detected by comparing the contents of the files, so this
+    /// reads all of them and blocks on file I/O.
+    pub fn reload(&self) -> std::io::Result<bool> {
+        let mut sources = self.inner.sources.lock().unwrap();
+        let mut changed = Vec::new();
+        for (index, source) in sources.iter().enumerate() {
+            let (cert, key) = source.read()?;
+            let digest = file_digest(&cert, &key);
+            if source.loaded.as_ref().map(|(d, _)| d) != Some(&digest) {
+                let key = source.parse(&cert, &key)?;
+                changed.push((index, digest, Arc::new((self.inner.parameters)(key))));
+            }
+        }
+        if changed.is_empty() {
+            return Ok(false);
+        }
+
+        // Everything loaded successfully, so commit the new certificates.
+        for (index, digest, params) in changed {
+            sources[index].loaded = Some((digest, params));
+        }
+        let map = CertificateMap::new(&sources);
+        *self.inner.current.write().unwrap() = Arc::new(map);
+        Ok(true)
+    }
+
+    /// Spawn a task that polls the certificate files for changes at the given
+    /// interval. Each poll runs [`Self::reload`] on the blocking thread pool.
+    /// Errors of failed reloads are passed to `on_error`, and the reload is
+    /// retried on the next poll. The task exits when the last handle to this
+    /// reloader is dropped.
+    pub fn watch(
+        &self,
+        interval: Duration,
+        mut on_error: impl FnMut(std::io::Error) + Send + 'static,
+    ) -> tokio::task::JoinHandle<()> {
+        let weak = Arc::downgrade(&self.inner);
+        tokio::task::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(inner) = Weak::upgrade(&weak) else {
+                    break;
+                };
+                let reloader = TlsServerCertificateReloader { inner };
+                match tokio::task::spawn_blocking(move || reloader.reload()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => on_error(e),
+                    Err(e) => on_error(std::io::Error::other(e)),
+                }
+            }
+        })
+    }
+
+    fn select(&self, name: Option<&str>) -> Arc<TlsServerParameters> {
+        self.inner.current.read().unwrap().select(name)
+    }
+}
+
+#[derive(derive_more::Debug)]
+struct CertificateSource {
+    /// SNI names for this certificate. Empty for the default certificate.
+    names: Vec<String>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    #[debug(skip)]
+    loaded: Option<(FileDigest, Arc<TlsServerParameters>)>,
+}
+
+/// The SHA-256 digest of the certificate and key files' contents.
+type FileDigest = [u8; 32];
+
+fn file_digest(cert: &[u8], key: &[u8]) -> FileDigest {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    hasher.update((cert.len() as u64).to_be_bytes());
+    hasher.update(cert);
+    hasher.update(key);
+    hasher.finalize().into()
+}
+
+impl CertificateSource {
+    fn new(names: Vec<String>, cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Self {
+        Self {
+            names,
+            cert_path: cert_path.as_ref().to_owned(),
+            key_path: key_path.as_ref().to_owned(),
+            loaded: None,
+        }
+    }
+
+    fn read(&self) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+        Ok((
+            std::fs::read(&self.cert_path)?,
+            std::fs::read(&self.key_path)?,
+        ))
+    }
+
+    /// Parse the certificate and key, and check that they belong together.
+    fn parse(&self, cert: &[u8], key: &[u8]) -> std::io::Result<TlsKey> {
+        TlsKey::new_pem(key, cert)
+            .and_then(|key| key.check_key_matches().map(|()| key))
+            .map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "Failed to load certificate {:?} with key {:?}: {e}",
+                        self.cert_path, self.key_path
+                    ),
+                )
+            })
+    }
+}
+
+#[derive(Default)]
+struct CertificateMap {
+    default: Option<Arc<TlsServerParameters>>,
+    exact: HashMap<String, Arc<TlsServerParameters>>,
+    /// Wildcard certificates, keyed by the name without the leading `*.`.
+    wildcard: HashMap<String, Arc<TlsServerParameters>>,
+}
+
+impl CertificateMap {
+    fn new(sources: &[CertificateSource]) -> Self {
+        let mut map = Self::default();
+        for source in sources {
+            let Some((_, params)) = &source.loaded else {
+                continue;
+            };
+            if source.names.is_empty() {
+                map.default = Some(params.clone());
+            }
+            for name in &source.names {
+                if let Some(suffix) = name.strip_prefix("*.") {
+                    map.wildcard.insert(suffix.to_owned(), params.clone());
+                } else {
+                    map.exact.insert(name.clone(), params.clone());
+                }
+            }
+        }
+        map
+    }
+
+    fn select(&self, name: Option<&str>) -> Arc<TlsServerParameters> {
+        let params = name.and_then(|name| {
+            let name = name.to_ascii_lowercase();
+            self.exact.get(&name).or_else(|| {
+                let (_, suffix) = name.split_once('.')?;
+                self.wildcard.get(suffix)
+            })
+        });
+        params
+            .or(self.default.as_ref())
+            .expect("default certificate is always loaded")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_keys::raw;
+
+    fn write_pair(dir: &Path, name: &str, cert: &str, key: &str) -> (PathBuf, PathBuf) {
+        let cert_path = dir.join(format!("{name}.cert.pem"));
+        let key_path = dir.join(format!("{name}.key.pem"));
+        std::fs::write(&cert_path, cert).unwrap();
+        std::fs::write(&key_path, key).unwrap();
+        (cert_path, key_path)
+    }
+
+    fn is_alt(params: &TlsServerParameters) -> bool {
+        params.server_certificate.cert == *crate::test_keys::binary::SERVER_ALT_CERT
+    }
+
+    #[test]
+    fn test_sni_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_pair(dir.path(), "server", raw::SERVER_CERT, raw::SERVER_KEY);
+        let (alt_cert, alt_key) =
+            write_pair(dir.path(), "alt", raw::SERVER_ALT_CERT, raw::SERVER_ALT_KEY);
+        let reloader = TlsServerCertificates::new(cert, key)
+            .with_sni_certificate(["*.example.com", "Example.org"], alt_cert, alt_key)
+            .load()
+            .unwrap();
+
+        assert!(!is_alt(&reloader.select(None)));
+        assert!(!is_alt(&reloader.select(Some("localhost"))));
+        assert!(!is_alt(&reloader.select(Some("example.com"))));
+        assert!(!is_alt(&reloader.select(Some("a.b.example.com"))));
+        assert!(is_alt(&reloader.select(Some("db.example.com"))));
+        assert!(is_alt(&reloader.select(Some("DB.Example.com"))));
+        assert!(is_alt(&reloader.select(Some("example.org"))));
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_pair(dir.path(), "server", raw::SERVER_CERT, raw::SERVER_KEY);
+        let reloader = TlsServerCertificates::new(&cert, &key).load().unwrap();
+        let before = reloader.select(None);
+        assert!(!is_alt(&before));
+        assert!(!reloader.reload().unwrap());
+
+        // Rewrites are detected even if the size and modification time of the
+        // file didn't change.
+        std::fs::write(&key, "-".repeat(raw::SERVER_KEY.len())).unwrap();
+        reloader.reload().unwrap_err();
+        assert!(Arc::ptr_eq(&before, &reloader.select(None)));
+
+        // A half-written rotation fails and keeps the old certificate.
+        std::fs::write(&cert, raw::SERVER_ALT_CERT).unwrap();
+        std::fs::write(&key, "").unwrap();
+        reloader.reload().unwrap_err();
+        assert!(Arc::ptr_eq(&before, &reloader.select(None)));
+
+        // So does a new certificate with the old key.
+        std::fs::write(&key, raw::SERVER_KEY).unwrap();
+        let err = reloader.reload().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(Arc::ptr_eq(&before, &reloader.select(None)));
+
+        std::fs::write(&key, raw::SERVER_ALT_KEY).unwrap();
+        assert!(reloader.reload().unwrap());
+        assert!(is_alt(&reloader.select(None)));
+        // Parameters that were already handed out are unaffected.
+        assert!(!is_alt(&before));
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_pair(dir.path(), "server", raw::SERVER_CERT, raw::SERVER_KEY);
+        let reloader = TlsServerCertificates::new(&cert, &key).load().unwrap();
+        let (errors_tx, mut errors) = tokio::sync::mpsc::unbounded_channel();
+        let task = reloader.watch(Duration::from_millis(10), move |e| {
+            _ = errors_tx.send(e);
+        });
+
+        std::fs::write(&key, "").unwrap();
+        let err = tokio::time::timeout(Duration::from_secs(10), errors.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(err.to_string().contains("server.key.pem"), "{err}");
+
+        write_pair(
+            dir.path(),
+            "server",
+            raw::SERVER_ALT_CERT,
+            raw::SERVER_ALT_KEY,
+        );
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !is_alt(&reloader.select(None)) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        drop(reloader);
+        tokio::time::timeout(Duration::from_secs(10), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
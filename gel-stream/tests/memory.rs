@@ -0,0 +1,360 @@
+#![cfg(unix)]
+
+use futures::StreamExt;
+use gel_stream::*;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn tls_server_parameters() -> TlsServerParameterProvider {
+    TlsServerParameterProvider::new(TlsServerParameters::new_with_certificate(TlsKey::new(
+        gel_stream::test_keys::binary::SERVER_KEY.clone_key(),
+        gel_stream::test_keys::binary::SERVER_CERT.clone(),
+    )))
+}
+
+fn tls_parameters() -> TlsParameters {
+    TlsParameters {
+        root_cert: TlsCert::Custom(vec![gel_stream::test_keys::binary::CA_CERT.clone()]),
+        ..Default::default()
+    }
+}
+
+async fn bind_tls<S: TlsDriver>(
+    network: &MemoryNetwork,
+) -> Result<
+    (
+        u16,
+        impl futures::Stream<Item = Result<UpgradableStream<BaseStream, S>, ConnectionError>>,
+    ),
+    ConnectionError,
+> {
+    let acceptor = Acceptor::new_tcp_tls(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        tls_server_parameters(),
+    )
+    .with_memory_network(network)
+    .bind_explicit::<S>()
+    .await?;
+    let port = acceptor.local_address()?.tcp().unwrap().port();
+    Ok((port, acceptor))
+}
+
+async fn tls_round_trip<C: TlsDriver, S: TlsDriver>(
+    config: MemoryStreamConfig,
+) -> Result<(), ConnectionError> {
+    let network = MemoryNetwork::with_config(config);
+    let (port, mut acceptor) = bind_tls::<S>(&network).await?;
+
+    let accept_task = tokio::spawn(async move {
+        let mut connection = acceptor.next().await.unwrap()?;
+        let handshake = connection.handshake().unwrap();
+        assert_eq!(
+            handshake.sni.as_ref().map(|s| s.as_ref()),
+            Some("localhost")
+        );
+        assert_eq!(connection.transport(), Transport::Tcp);
+        let mut buf = [0; 16 * 1024];
+        connection.read_exact(&mut buf).await?;
+        assert!(buf.iter().all(|b| *b == 1));
+        connection.write_all(b"Hello, client!").await?;
+        connection.shutdown().await?;
+        Ok::<_, ConnectionError>(())
+    });
+
+    let mut connector =
+        Connector::<C>::new_explicit(Target::new_tcp_tls(("localhost", port), tls_parameters()))?;
+    connector.set_memory_network(Some(network));
+    let mut stm = connector.connect().await?;
+    assert!(stm.handshake().is_some());
+    stm.write_all(&[1; 16 * 1024]).await?;
+    let mut buf = String::new();
+    stm.read_to_string(&mut buf).await?;
+    assert_eq!(buf, "Hello, client!");
+
+    accept_task.await.unwrap()
+}
+
+async fn tls_reset<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
+    // Reset the connection in the middle of the handshake.
+    let network = MemoryNetwork::with_config(MemoryStreamConfig {
+        reset_after: Some(100),
+        ..Default::default()
+    });
+    let (port, mut acceptor) = bind_tls::<S>(&network).await?;
+    let accept_task = tokio::spawn(async move { acceptor.next().await.unwrap().map(|_| ()) });
+
+    let mut connector =
+        Connector::<C>::new_explicit(Target::new_tcp_tls(("localhost", port), tls_parameters()))?;
+    connector.set_memory_network(Some(network));
+    connector.connect().await.unwrap_err();
+    accept_task.await.unwrap().unwrap_err();
+    Ok(())
+}
+
+macro_rules! driver_test {
+    ($($name:ident => $body:expr;)*) => {
+        $(
+            mod $name {
+                use super::*;
+
+                #[tokio::test]
+                #[ntest::timeout(30_000)]
+                async fn rustls() -> Result<(), ConnectionError> {
+                    type C = RustlsDriver;
+                    type S = RustlsDriver;
+                    $body
+                }
+
+                #[tokio::test]
+                #[ntest::timeout(30_000)]
+                async fn openssl() -> Result<(), ConnectionError> {
+                    type C = OpensslDriver;
+                    type S = OpensslDriver;
+                    $body
+                }
+
+                #[tokio::test]
+                #[ntest::timeout(30_000)]
+                async fn rustls_client_openssl_server() -> Result<(), ConnectionError> {
+                    type C = RustlsDriver;
+                    type S = OpensslDriver;
+                    $body
+                }
+
+                #[tokio::test]
+                #[ntest::timeout(30_000)]
+                async fn openssl_client_rustls_server() -> Result<(), ConnectionError> {
+                    type C = OpensslDriver;
+                    type S = RustlsDriver;
+                    $body
+                }
+            }
+        )*
+    };
+}
+
+driver_test! {
+    test_memory_tls => tls_round_trip::<C, S>(MemoryStreamConfig::default()).await;
+    test_memory_tls_fragmented => tls_round_trip::<C, S>(MemoryStreamConfig {
+        latency: Duration::from_millis(1),
+        max_read_size: NonZeroUsize::new(7),
+        max_write_size: NonZeroUsize::new(13),
+        buffer_size: NonZeroUsize::new(1024).unwrap(),
+        ..Default::default()
+    })
+    .await;
+    test_memory_tls_reset => tls_reset::<C, S>().await;
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_memory_starttls() -> Result<(), ConnectionError> {
+    let network = MemoryNetwork::new();
+    let mut acceptor = Acceptor::new_starttls(
+        ResolvedTarget::try_from(std::path::PathBuf::from("/memory/gel.sock"))?,
+        tls_server_parameters(),
+    )
+    .with_memory_network(&network)
+    .bind()
+    .await?;
+
+    let accept_task = tokio::spawn(async move {
+        let mut connection = acceptor.next().await.unwrap()?;
+        assert_eq!(connection.transport(), Transport::Unix);
+        let mut buf = [0; 8];
+        connection.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"STARTTLS");
+        let mut connection = connection.secure_upgrade().await?;
+        let mut buf = String::new();
+        connection.read_to_string(&mut buf).await?;
+        assert_eq!(buf, "Hello, world!");
+        Ok::<_, ConnectionError>(())
+    });
+
+    let target = Target::new_starttls(
+        TargetName::new_unix_path("/memory/gel.sock")?,
+        TlsParameters {
+            server_cert_verify: TlsServerCertVerify::IgnoreHostname,
+            ..tls_parameters()
+        },
+    );
+    let mut connector = Connector::new(target)?;
+    connector.set_memory_network(Some(network.clone()));
+    let mut stm = connector.connect().await?;
+    stm.write_all(b"STARTTLS").await?;
+    let mut stm = stm.secure_upgrade().await?;
+    stm.write_all(b"Hello, world!").await?;
+    stm.shutdown().await?;
+    accept_task.await.unwrap()?;
+
+    // The path is not bound on the real filesystem.
+    assert!(!std::path::Path::new("/memory/gel.sock").exists());
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_memory_refused() -> Result<(), ConnectionError> {
+    let mut connector = Connector::new(Target::new_tcp(("localhost", 5656)))?;
+    connector.set_memory_network(Some(MemoryNetwork::new()));
+    let err = std::io::Error::from(connector.connect().await.unwrap_err());
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_memory_admission_control() -> Result<(), ConnectionError> {
+    let network = MemoryNetwork::new();
+    let mut acceptor = Acceptor::new_tcp(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))
+        .with_memory_network(&network)
+        .with_admission_control(AdmissionControl {
+            max_connections: Some(1),
+            ..Default::default()
+        })
+        .bind()
+        .await?;
+    let port = acceptor.local_address()?.tcp().unwrap().port();
+    let mut connector = Connector::new(Target::new_tcp(("localhost", port)))?;
+    connector.set_memory_network(Some(network));
+
+    let _client1 = connector.connect().await?;
+    let server1 = acceptor.next().await.unwrap()?;
+    assert_eq!(server1.admission_rejection(), None);
+
+    // The second connection is over the limit and is closed without being
+    // returned.
+    let mut client2 = connector.connect().await?;
+    let mut buf = [0; 1];
+    tokio::select! {
+        _ = acceptor.next() => panic!("rejected connection was returned"),
+        res = client2.read(&mut buf) => assert_eq!(res?, 0),
+    }
+
+    // Closing the first connection frees its place.
+    drop(server1);
+    let _client3 = connector.connect().await?;
+    let server3 = acceptor.next().await.unwrap()?;
+    assert_eq!(server3.admission_rejection(), None);
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_memory_admission_return_rejected() -> Result<(), ConnectionError> {
+    let network = MemoryNetwork::new();
+    let mut acceptor = Acceptor::new_tcp(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))
+        .with_memory_network(&network)
+        .with_admission_control(AdmissionControl {
+            accept_rate: Some(AcceptRate {
+                per_second: 0.001,
+                burst: 1,
+            }),
+            return_rejected: true,
+            ..Default::default()
+        })
+        .bind()
+        .await?;
+    let port = acceptor.local_address()?.tcp().unwrap().port();
+    let mut connector = Connector::new(Target::new_tcp(("localhost", port)))?;
+    connector.set_memory_network(Some(network));
+
+    let _client1 = connector.connect().await?;
+    let server1 = acceptor.next().await.unwrap()?;
+    assert_eq!(server1.admission_rejection(), None);
+
+    // The rejected connection is returned so that the server can reply.
+    let mut client2 = connector.connect().await?;
+    let mut server2 = acceptor.next().await.unwrap()?;
+    assert_eq!(
+        server2.admission_rejection(),
+        Some(AdmissionRejection::RateLimited)
+    );
+    server2.write_all(b"go away").await?;
+    server2.shutdown().await?;
+    let mut buf = String::new();
+    client2.read_to_string(&mut buf).await?;
+    assert_eq!(buf, "go away");
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_memory_tls_handshake_timeout() -> Result<(), ConnectionError> {
+    let network = MemoryNetwork::new();
+    let mut acceptor = Acceptor::new_tcp_tls(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        tls_server_parameters(),
+    )
+    .with_memory_network(&network)
+    .with_admission_control(AdmissionControl {
+        tls_handshake_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    })
+    .bind()
+    .await?;
+    let port = acceptor.local_address()?.tcp().unwrap().port();
+
+    // A client that connects but never starts the handshake.
+    let mut connector = Connector::new(Target::new_tcp(("localhost", port)))?;
+    connector.set_memory_network(Some(network));
+    let _client = connector.connect().await?;
+    let err = std::io::Error::from(acceptor.next().await.unwrap().unwrap_err());
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_memory_stream_metrics() -> Result<(), ConnectionError> {
+    let network = MemoryNetwork::with_config(MemoryStreamConfig {
+        latency: Duration::from_millis(10),
+        ..Default::default()
+    });
+    let mut acceptor = Acceptor::new_tcp_tls(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        tls_server_parameters(),
+    )
+    .with_memory_network(&network)
+    .bind()
+    .await?;
+    let port = acceptor.local_address()?.tcp().unwrap().port();
+
+    let accept_task = tokio::spawn(async move {
+        let mut connection = acceptor.next().await.unwrap()?;
+        let mut buf = [0; 5];
+        connection.read_exact(&mut buf).await?;
+        connection.write_all(b"Hello, client!").await?;
+        connection.shutdown().await?;
+        Ok::<_, ConnectionError>(connection.metrics().snapshot())
+    });
+
+    let mut connector = Connector::new(Target::new_tcp_tls(("localhost", port), tls_parameters()))?;
+    connector.set_memory_network(Some(network));
+    let mut stm = connector.connect().await?;
+    let metrics = stm.metrics().clone();
+    stm.write_all(b"Hello").await?;
+    let mut buf = String::new();
+    stm.read_to_string(&mut buf).await?;
+    drop(stm);
+
+    // The metrics outlive the stream.
+    let client = metrics.snapshot();
+    assert_eq!(client.bytes_written, 5);
+    assert_eq!(client.bytes_read, 14);
+    let connect_time = client.connect_time.unwrap();
+    let tls_handshake_time = client.tls_handshake_time.unwrap();
+    // The handshake takes at least one round trip.
+    assert!(tls_handshake_time >= Duration::from_millis(20));
+    assert!(client.time_to_first_byte.unwrap() >= connect_time + tls_handshake_time);
+
+    let server = accept_task.await.unwrap()?;
+    assert_eq!(server.bytes_read, 5);
+    assert_eq!(server.bytes_written, 14);
+    assert_eq!(server.connect_time, None);
+    assert!(server.tls_handshake_time.is_some());
+    assert!(server.time_to_first_byte.is_some());
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use futures::StreamExt;
+use gel_stream::*;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A minimal HTTP CONNECT proxy that serves a single connection.
+async fn spawn_http_proxy() -> (TargetName, tokio::task::JoinHandle<String>) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let task = tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(client.read_u8().await.unwrap());
+        }
+        let request = String::from_utf8(request).unwrap();
+        let authority = request.split(' ').nth(1).unwrap();
+        let mut upstream = TcpStream::connect(authority).await.unwrap();
+        client
+            .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+            .await
+            .unwrap();
+        _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+        request
+    });
+    (TargetName::new_tcp(addr), task)
+}
+
+/// A minimal SOCKS5 proxy without authentication that serves a single
+/// connection.
+async fn spawn_socks5_proxy() -> (TargetName, tokio::task::JoinHandle<String>) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let task = tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut greeting = [0; 3];
+        client.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [5, 1, 0]);
+        client.write_all(&[5, 0]).await.unwrap();
+        let mut request = [0; 4];
+        client.read_exact(&mut request).await.unwrap();
+        assert_eq!(request[..3], [5, 1, 0]);
+        let host = match request[3] {
+            1 => {
+                let mut ip = [0; 4];
+                client.read_exact(&mut ip).await.unwrap();
+                Ipv4Addr::from(ip).to_string()
+            }
+            3 => {
+                let mut host = vec![0; client.read_u8().await.unwrap() as usize];
+                client.read_exact(&mut host).await.unwrap();
+                String::from_utf8(host).unwrap()
+            }
+            other => panic!("unexpected address type {other}"),
+        };
+        let port = client.read_u16().await.unwrap();
+        let mut upstream = TcpStream::connect((host.as_str(), port)).await.unwrap();
+        client
+            .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+        _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+        format!("{host}:{port}")
+    });
+    (TargetName::new_tcp(addr), task)
+}
+
+async fn spawn_tls_server() -> (u16, tokio::task::JoinHandle<()>) {
+    let mut acceptor = Acceptor::new_tcp_tls(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        TlsServerParameterProvider::new(TlsServerParameters::new_with_certificate(TlsKey::new(
+            test_keys::binary::SERVER_KEY.clone_key(),
+            test_keys::binary::SERVER_CERT.clone(),
+        ))),
+    )
+    .bind()
+    .await
+    .unwrap();
+    let port = acceptor.local_address().unwrap().tcp().unwrap().port();
+    let task = tokio::spawn(async move {
+        let mut connection = acceptor.next().await.unwrap().unwrap();
+        let handshake = connection.handshake().expect("TLS handshake");
+        assert_eq!(
+            handshake.sni.as_ref().map(|s| s.as_ref()),
+            Some("localhost")
+        );
+        let mut buf = String::new();
+        connection.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "Hello, world!");
+        connection.shutdown().await.unwrap();
+    });
+    (port, task)
+}
+
+async fn connect_tls_through(proxy: Proxy, port: u16) -> Result<(), ConnectionError> {
+    let target = Target::new_tcp_tls(
+        ("localhost", port),
+        TlsParameters {
+            root_cert: TlsCert::Custom(vec![test_keys::binary::CA_CERT.clone()]),
+            ..Default::default()
+        },
+    );
+    let mut connector = Connector::new(target)?;
+    connector.set_proxy(Some(proxy));
+    let mut stm = connector.connect().await?;
+    assert!(stm.handshake().is_some());
+    stm.write_all(b"Hello, world!").await?;
+    stm.shutdown().await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_http_connect_tls() -> Result<(), ConnectionError> {
+    let (port, server) = spawn_tls_server().await;
+    let (proxy, proxy_task) = spawn_http_proxy().await;
+    connect_tls_through(Proxy::new_http(proxy), port).await?;
+    server.await.unwrap();
+    let request = proxy_task.await.unwrap();
+    assert!(
+        request.starts_with(&format!("CONNECT localhost:{port} HTTP/1.1\r\n")),
+        "{request}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_socks5_remote_dns_tls() -> Result<(), ConnectionError> {
+    let (port, server) = spawn_tls_server().await;
+    let (proxy, proxy_task) = spawn_socks5_proxy().await;
+    connect_tls_through(Proxy::new_socks5(proxy), port).await?;
+    server.await.unwrap();
+    assert_eq!(proxy_task.await.unwrap(), format!("localhost:{port}"));
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_socks5_local_dns() -> Result<(), ConnectionError> {
+    let mut acceptor = Acceptor::new_tcp(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0))
+        .bind()
+        .await?;
+    let port = acceptor.local_address()?.tcp().unwrap().port();
+    let server = tokio::spawn(async move {
+        let mut connection = acceptor.next().await.unwrap().unwrap();
+        connection.write_all(b"Hello, world!").await.unwrap();
+        connection.shutdown().await.unwrap();
+    });
+
+    let (proxy_target, proxy_task) = spawn_socks5_proxy().await;
+    let mut proxy = Proxy::new_socks5(proxy_target);
+    proxy.set_remote_dns(false);
+    let mut connector = Connector::new(Target::new_tcp(("localhost", port)))?;
+    connector.set_proxy(Some(proxy));
+    let mut stm = connector.connect().await?;
+    let mut buf = String::new();
+    stm.read_to_string(&mut buf).await?;
+    assert_eq!(buf, "Hello, world!");
+    drop(stm);
+
+    server.await.unwrap();
+    assert_eq!(proxy_task.await.unwrap(), format!("127.0.0.1:{port}"));
+    Ok(())
+}
@@ -4,6 +4,7 @@ use rustls_pki_types::DnsName;
 use std::borrow::Cow;
 use std::net::Ipv4Addr;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 fn load_client_test_cert() -> rustls_pki_types::CertificateDer<'static> {
@@ -52,6 +53,7 @@ fn tls_server_parameters(
         min_protocol_version: None,
         max_protocol_version: None,
         alpn,
+        session_tickets: None,
     })
 }
 
@@ -80,6 +82,37 @@ fn tls_server_parameters_lookup() -> TlsServerParameterProvider {
     })
 }
 
+/// Spawn a bare rustls server that presents an arbitrary certificate chain,
+/// which the drivers' own servers never do.
+fn spawn_chain_server(
+    chain: Vec<rustls_pki_types::CertificateDer<'static>>,
+    key: rustls_pki_types::PrivateKeyDer<'static>,
+) -> (
+    ResolvedTarget,
+    tokio::task::JoinHandle<Result<(), std::io::Error>>,
+) {
+    let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = ::rustls::ServerConfig::builder_with_provider(std::sync::Arc::new(
+        ::rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(chain, key)
+    .unwrap();
+    let task = tokio::task::spawn_blocking(move || {
+        use std::io::Read;
+        let (socket, _) = listener.accept()?;
+        let connection = ::rustls::ServerConnection::new(std::sync::Arc::new(config))
+            .map_err(std::io::Error::other)?;
+        let mut stream = ::rustls::StreamOwned::new(connection, socket);
+        stream.read_to_end(&mut vec![])?;
+        Ok(())
+    });
+    (ResolvedTarget::SocketAddr(addr), task)
+}
+
 async fn spawn_tls_server<S: TlsDriver>(
     expected_hostname: Option<&str>,
     server_alpn: TlsAlpn,
@@ -109,6 +142,8 @@ async fn spawn_tls_server<S: TlsDriver>(
             .handshake()
             .unwrap_or_else(|| panic!("handshake was not available on {connection:?}"));
         assert!(handshake.version.is_some());
+        let cipher_suite = handshake.cipher_suite().unwrap_or_default();
+        assert!(cipher_suite.starts_with("TLS_"), "{cipher_suite}");
         assert_eq!(
             handshake.alpn.as_ref().map(|b| b.as_ref().to_vec()),
             expected_alpn
@@ -125,6 +160,13 @@ async fn spawn_tls_server<S: TlsDriver>(
                 subject.to_ascii_lowercase().contains("ssl_user"),
                 "subject: {subject}"
             );
+            assert_eq!(handshake.peer_cert_chain().first(), handshake.cert.as_ref());
+            let info = handshake.peer_certificate_info().unwrap().unwrap();
+            assert_eq!(info.common_name.as_deref(), Some("ssl_user"));
+            assert_eq!(info.dns_names, vec!["localhost"]);
+        } else {
+            assert!(handshake.cert.is_none());
+            assert!(handshake.peer_cert_chain().is_empty());
         }
         let mut buf = String::new();
         connection.read_to_string(&mut buf).await.unwrap();
@@ -167,6 +209,73 @@ async fn spawn_tls_server_unclean<S: TlsDriver>() -> Result<
     Ok((addr, accept_task))
 }
 
+/// Connect three times with a shared session cache, the last time with
+/// different verification settings, and check which handshakes resumed a
+/// session on the server and client. The OpenSSL server does not issue
+/// session tickets, so no session is resumed.
+async fn session_resumption<C: TlsDriver, S: TlsDriver>(
+    max_protocol_version: Option<SslVersion>,
+) -> Result<(), ConnectionError> {
+    let mut acceptor = Acceptor::new_tcp_tls(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        TlsServerParameterProvider::new(TlsServerParameters {
+            session_tickets: Some(TlsTicketKeys::new(Duration::from_secs(60))),
+            ..TlsServerParameters::new_with_certificate(TlsKey::new(
+                load_test_key(),
+                load_test_cert(),
+            ))
+        }),
+    )
+    .bind_explicit::<S>()
+    .await?;
+    let addr = acceptor.local_address()?;
+
+    let accept_task = tokio::spawn(async move {
+        let mut resumed = vec![];
+        for _ in 0..3 {
+            let mut connection = acceptor.next().await.unwrap()?;
+            resumed.push(connection.handshake().unwrap().resumed());
+            connection.write_all(b"Hello, world!").await?;
+            connection.shutdown().await?;
+        }
+        Ok::<_, ConnectionError>(resumed)
+    });
+
+    let session_cache = TlsSessionCache::default();
+    let mut resumed = vec![];
+    for server_cert_verify in [
+        TlsServerCertVerify::IgnoreHostname,
+        TlsServerCertVerify::IgnoreHostname,
+        TlsServerCertVerify::Insecure,
+    ] {
+        let target = Target::new_resolved_tls(
+            addr.clone(),
+            TlsParameters {
+                server_cert_verify,
+                root_cert: TlsCert::Custom(vec![load_test_ca()]),
+                max_protocol_version,
+                session_cache: Some(session_cache.clone()),
+                ..Default::default()
+            },
+        );
+        let mut stm = Connector::<C>::new_explicit(target)?.connect().await?;
+        resumed.push(stm.handshake().unwrap().resumed());
+        // Reading the response also receives any TLS 1.3 session tickets.
+        let mut buf = String::new();
+        stm.read_to_string(&mut buf).await?;
+        assert_eq!(buf, "Hello, world!");
+    }
+
+    let expected = if S::DRIVER_NAME == "rustls" {
+        [false, true, false]
+    } else {
+        [false, false, false]
+    };
+    assert_eq!(resumed, expected);
+    assert_eq!(accept_task.await.unwrap()?, expected);
+    Ok(())
+}
+
 async fn spawn_tcp_server() -> Result<
     (
         ResolvedTarget,
@@ -255,6 +364,20 @@ macro_rules! tls_test (
 );
 
 tls_test! {
+    /// Reconnecting with a session cache resumes the TLS 1.3 session.
+    #[tokio::test]
+    #[ntest::timeout(30_000)]
+    async fn test_target_tcp_tls_session_resumption<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
+        session_resumption::<C, S>(None).await
+    }
+
+    /// Reconnecting with a session cache resumes the TLS 1.2 session.
+    #[tokio::test]
+    #[ntest::timeout(30_000)]
+    async fn test_target_tcp_tls_session_resumption_tls12<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
+        session_resumption::<C, S>(Some(SslVersion::Tls1_2)).await
+    }
+
     /// The certificate is not valid for 127.0.0.1, so the connection should fail.
     #[tokio::test]
     #[ntest::timeout(30_000)]
@@ -391,6 +514,11 @@ tls_test! {
                 },
             );
             let mut stm = Connector::<C>::new_explicit(target).unwrap().connect().await?;
+            let handshake = stm.handshake().unwrap();
+            assert_eq!(handshake.peer_cert_chain(), [load_test_cert()]);
+            assert!(handshake.cipher_suite().is_some());
+            let info = handshake.peer_certificate_info().unwrap()?;
+            assert_eq!(info.common_name.as_deref(), Some("localhost"));
             stm.write_all(b"Hello, world!").await?;
             stm.shutdown().await?;
             Ok::<_, ConnectionError>(())
@@ -758,6 +886,107 @@ tls_test! {
         Ok(())
     }
 
+    /// The CA is not trusted and the certificate is not valid for 127.0.0.1,
+    /// but the pinned key is trusted.
+    #[tokio::test]
+    #[ntest::timeout(30_000)]
+    async fn test_target_tcp_tls_spki_pin<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
+        let (addr, accept_task) =
+            spawn_tls_server::<S>(None, TlsAlpn::default(), None, TlsClientCertVerify::Ignore).await?;
+
+        let connect_task = tokio::spawn(async move {
+            let target = Target::new_resolved_tls(
+                addr, // Raw IP
+                TlsParameters {
+                    spki_pins: vec![SpkiPin::from_certificate(&load_test_cert())?],
+                    ..Default::default()
+                },
+            );
+            let mut stm = Connector::<C>::new_explicit(target).unwrap().connect().await?;
+            stm.write_all(b"Hello, world!").await?;
+            stm.shutdown().await?;
+            Ok::<_, std::io::Error>(())
+        });
+
+        accept_task.await.unwrap().unwrap();
+        connect_task.await.unwrap().unwrap();
+
+        Ok(())
+    }
+
+    /// The certificate is otherwise valid, but doesn't match the pin.
+    #[tokio::test]
+    #[ntest::timeout(30_000)]
+    async fn test_target_tcp_tls_spki_pin_mismatch<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
+        let (addr, accept_task) =
+            spawn_tls_server::<S>(Some("localhost"), TlsAlpn::default(), None, TlsClientCertVerify::Ignore).await?;
+
+        let connect_task = tokio::spawn(async move {
+            let target = Target::new_tcp_tls(
+                ("localhost", addr.tcp().unwrap().port()),
+                TlsParameters {
+                    root_cert: TlsCert::Custom(vec![load_test_ca()]),
+                    spki_pins: vec![SpkiPin::new([0; 32])],
+                    ..Default::default()
+                },
+            );
+            let stm = Connector::<C>::new_explicit(target).unwrap().connect().await;
+            assert!(matches!(&stm, Err(ConnectionError::SslError(_))), "{stm:?}");
+            Ok::<_, std::io::Error>(())
+        });
+
+        accept_task.await.unwrap().unwrap_err();
+        connect_task.await.unwrap().unwrap();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(30_000)]
+    async fn test_target_tcp_tls_verify_callback<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
+        for accept in [true, false] {
+            let (addr, accept_task) =
+                spawn_tls_server::<S>(Some("localhost"), TlsAlpn::default(), None, TlsClientCertVerify::Ignore).await?;
+
+            let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let callback_calls = calls.clone();
+            let verify_callback = TlsVerifyCallback::new(move |context| {
+                callback_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                assert_eq!(context.cert_chain.first(), Some(&load_test_cert()));
+                assert_eq!(
+                    context.server_name,
+                    Some(&rustls_pki_types::ServerName::try_from("localhost").unwrap())
+                );
+                if accept {
+                    Ok(())
+                } else {
+                    Err("rejected by callback".into())
+                }
+            });
+            let target = Target::new_tcp_tls(
+                ("localhost", addr.tcp().unwrap().port()),
+                TlsParameters {
+                    root_cert: TlsCert::Custom(vec![load_test_ca()]),
+                    verify_callback: Some(verify_callback),
+                    ..Default::default()
+                },
+            );
+            let stm = Connector::<C>::new_explicit(target).unwrap().connect().await;
+            if accept {
+                let mut stm = stm?;
+                stm.write_all(b"Hello, world!").await?;
+                stm.shutdown().await?;
+                accept_task.await.unwrap().unwrap();
+            } else {
+                assert!(matches!(&stm, Err(ConnectionError::SslError(_))), "{stm:?}");
+                accept_task.await.unwrap().unwrap_err();
+            }
+            assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[ntest::timeout(30_000)]
     async fn test_cert_selection<C: TlsDriver, S: TlsDriver>() -> Result<(), ConnectionError> {
@@ -957,3 +1186,42 @@ fn parse_cert<'a>(
     let (_, cert) = x509_parser::parse_x509_certificate(cert).unwrap();
     cert
 }
+
+/// A pinned CA key does not vouch for a leaf that the CA did not sign, even
+/// when the server also presents the CA's certificate.
+async fn spki_pin_attacker_chain<C: TlsDriver>() -> Result<(), ConnectionError> {
+    let (addr, accept_task) = spawn_chain_server(
+        vec![load_client_test_cert(), load_test_ca()],
+        load_client_test_key(),
+    );
+
+    let target = Target::new_resolved_tls(
+        addr,
+        TlsParameters {
+            spki_pins: vec![SpkiPin::from_certificate(&load_test_ca())?],
+            ..Default::default()
+        },
+    );
+    let stm = Connector::<C>::new_explicit(target)
+        .unwrap()
+        .connect()
+        .await;
+    assert!(matches!(&stm, Err(ConnectionError::SslError(_))), "{stm:?}");
+
+    accept_task.await.unwrap().unwrap_err();
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_target_tcp_tls_spki_pin_attacker_chain_rustls() -> Result<(), ConnectionError> {
+    spki_pin_attacker_chain::<RustlsDriver>().await
+}
+
+#[cfg(not(windows))]
+#[tokio::test]
+#[ntest::timeout(30_000)]
+async fn test_target_tcp_tls_spki_pin_attacker_chain_openssl() -> Result<(), ConnectionError> {
+    spki_pin_attacker_chain::<OpensslDriver>().await
+}
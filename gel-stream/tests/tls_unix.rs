@@ -19,6 +19,7 @@ fn tls_server_parameters(alpn: TlsAlpn) -> TlsServerParameterProvider {
         min_protocol_version: None,
         max_protocol_version: None,
         alpn,
+        session_tickets: None,
     })
 }
 
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::future::Future;
 use std::sync::Arc;
 
@@ -5,16 +6,17 @@ use gel_dsn::gel::Config;
 use gel_protocol::common::{Capabilities, Cardinality, IoFormat};
 use gel_protocol::model::Json;
 use gel_protocol::query_arg::QueryArgs;
+use gel_protocol::value::Value;
 use gel_protocol::QueryResult;
 use tokio::time::sleep;
 
 use crate::errors::InvalidArgumentError;
 use crate::errors::NoDataError;
-use crate::errors::{Error, ErrorKind, SHOULD_RETRY};
+use crate::errors::{Error, ErrorKind};
 use crate::options::{RetryOptions, TransactionOptions};
-use crate::raw::{Options, PoolState, Response};
-use crate::raw::{Pool, QueryCapabilities};
-use crate::state::{AliasesDelta, ConfigDelta, GlobalsDelta};
+use crate::raw::{Options, Pool, PoolState, Response};
+use crate::rows::Rows;
+use crate::state::{AliasesDelta, ConfigDelta, FromGlobals, GlobalsDelta};
 use crate::state::{AliasesModifier, ConfigModifier, Fn, GlobalsModifier};
 use crate::transaction;
 use crate::ResultVerbose;
@@ -69,6 +71,22 @@ impl Client {
     where
         A: QueryArgs,
         R: QueryResult,
+    {
+        self.query_rows_helper(query, arguments, io_format, cardinality)
+            .await?
+            .map(|rows| rows.decode_all())
+    }
+
+    /// Query with retry, without decoding the results.
+    async fn query_rows_helper<A>(
+        &self,
+        query: impl AsRef<str>,
+        arguments: &A,
+        io_format: IoFormat,
+        cardinality: Cardinality,
+    ) -> Result<Response<Rows>, Error>
+    where
+        A: QueryArgs,
     {
         let mut iteration = 0;
         loop {
@@ -78,7 +96,7 @@ impl Client {
             let state = &self.options.state;
             let caps = Capabilities::MODIFICATIONS | Capabilities::DDL;
             match conn
-                .query(
+                .query_rows(
                     query.as_ref(),
                     arguments,
                     state,
@@ -91,14 +109,7 @@ impl Client {
             {
                 Ok(resp) => return Ok(resp),
                 Err(e) => {
-                    let allow_retry = match e.get::<QueryCapabilities>() {
-                        // Error from a weird source, or just a bug
-                        // Let's keep on the safe side
-                        None => false,
-                        Some(QueryCapabilities::Unparsed) => true,
-                        Some(QueryCapabilities::Parsed(c)) => c.is_empty(),
-                    };
-                    if allow_retry && e.has_tag(SHOULD_RETRY) {
+                    if e.classify().is_retryable() {
                         let rule = self.options.retry.get_rule(&e);
                         iteration += 1;
                         if iteration < rule.attempts {
@@ -168,6 +179,32 @@ impl Client {
             .map(|r| r.data)
     }
 
+    /// Execute a query and return undecoded rows.
+    ///
+    /// Unlike [`query`](Client::query), the rows are not decoded upfront.
+    /// Use [`Rows::iter`] to decode them into types that borrow from the
+    /// received data, for example `&str` or structures deriving
+    /// [`Queryable`](macro@crate::Queryable) with a lifetime:
+    ///
+    /// ```rust,ignore
+    /// let rows = conn.query_rows("select User { name }", &()).await?;
+    /// for user in rows.iter::<User<'_>>()? {
+    ///     println!("{}", user?.name);
+    /// }
+    /// ```
+    pub async fn query_rows<A>(
+        &self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+    ) -> Result<Rows, Error>
+    where
+        A: QueryArgs,
+    {
+        Client::query_rows_helper(self, query, arguments, IoFormat::Binary, Cardinality::Many)
+            .await
+            .map(|r| r.data)
+    }
+
     /// Execute a query and return a single result
     ///
     /// You will usually have to specify the return type for the query:
@@ -351,14 +388,7 @@ impl Client {
             {
                 Ok(_) => return Ok(()),
                 Err(e) => {
-                    let allow_retry = match e.get::<QueryCapabilities>() {
-                        // Error from a weird source, or just a bug
-                        // Let's keep on the safe side
-                        None => false,
-                        Some(QueryCapabilities::Unparsed) => true,
-                        Some(QueryCapabilities::Parsed(c)) => c.is_empty(),
-                    };
-                    if allow_retry && e.has_tag(SHOULD_RETRY) {
+                    if e.classify().is_retryable() {
                         let rule = self.options.retry.get_rule(&e);
                         iteration += 1;
                         if iteration < rule.attempts {
@@ -629,6 +659,41 @@ impl Client {
         self.with_state(|s| s.with_config(Fn(f)))
     }
 
+    /// Returns the global variables set on the client read into a structure
+    ///
+    /// Most commonly used with `#[derive(FromGlobals)]`.
+    ///
+    /// This only reflects the state kept locally by the client: variables
+    /// set using [`with_globals`](Client::with_globals) and similar methods.
+    /// The server is not queried, so variables that are not set on the
+    /// client are read as `None` (or fail for non-optional fields) even if
+    /// the schema defines a default for them.
+    pub fn local_globals<T: FromGlobals>(&self) -> Result<T, Error> {
+        self.options.state.read_globals()
+    }
+
+    /// Returns the default module set on the client
+    ///
+    /// This only reflects the state kept locally by the client.
+    pub fn local_default_module(&self) -> &str {
+        self.options.state.default_module()
+    }
+
+    /// Returns the module aliases set on the client
+    ///
+    /// This only reflects the state kept locally by the client.
+    pub fn local_aliases(&self) -> &BTreeMap<String, String> {
+        self.options.state.aliases()
+    }
+
+    /// Returns the config settings set on the client
+    ///
+    /// This only reflects the state kept locally by the client, settings
+    /// that are configured on the server are not included.
+    pub fn local_config(&self) -> &BTreeMap<String, Value> {
+        self.options.state.config()
+    }
+
     /// Returns the client with the specified query tag.
     ///
     /// This method returns a "shallow copy" of the current client
@@ -102,6 +102,24 @@ pretty print if `source()` of the error is `gel_errors::Error` but not
 the top-level one. We leave those more complex cases as an excersize to the
 reader.
 
+Without miette, the same snippet can be rendered by
+[`display_diagnostic`](gel_errors::display::display_diagnostic), either as
+plain text or highlighted with ANSI colors. For log pipelines,
+[`display_json`](gel_errors::display::display_json) formats the kind,
+message, hint, details and position of the error as a JSON object:
+```rust,no_run
+# async fn do_something() -> Result<(), gel_tokio::Error> { unimplemented!() }
+use gel_errors::display::{display_diagnostic, display_json};
+
+# #[tokio::main]
+# async fn main() {
+if let Err(e) = do_something().await {
+    eprintln!("{}", display_diagnostic(&e, true));
+    log::error!("{}", display_json(&e));
+}
+# }
+```
+
 [miette]: https://crates.io/crates/miette
 [anyhow]: https://crates.io/crates/anyhow
 */
@@ -143,19 +161,23 @@ mod client;
 mod errors;
 mod options;
 mod query_executor;
+mod rows;
 mod sealed;
 pub mod state;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod transaction;
 pub mod tutorial;
 
 #[cfg(feature = "derive")]
-pub use gel_derive::{ConfigDelta, GlobalsDelta, Queryable};
+pub use gel_derive::{AliasesDelta, ConfigDelta, FromGlobals, GlobalsDelta, QueryArg, Queryable};
 
 pub use client::Client;
 pub use errors::Error;
 pub use options::{RetryCondition, RetryOptions, TransactionOptions};
 pub use query_executor::{QueryExecutor, ResultVerbose};
-pub use state::{ConfigDelta, GlobalsDelta};
+pub use rows::{RowIter, Rows};
+pub use state::{AliasesDelta, ConfigDelta, FromGlobals, GlobalsDelta};
 pub use transaction::{RetryingTransaction, Transaction};
 
 /// The ordered list of project filenames supported.
@@ -17,7 +17,7 @@ use gel_dsn::gel::{ClientSecurity, Config};
 use gel_auth::{handshake::{ClientAuthDrive, ClientAuthResponse}, AuthType, CredentialData};
 use gel_stream::{CommonError, ConnectionError, Connector, Target};
 use gel_protocol::client_message::{ClientHandshake, ClientMessage, SaslInitialResponse, SaslResponse};
-use gel_protocol::encoding::{Input, Output};
+use gel_protocol::encoding::Output;
 use gel_protocol::features::ProtocolVersion;
 use gel_protocol::value::Value;
 use gel_protocol::server_message::{
@@ -111,6 +111,11 @@ impl Connection {
     pub fn get_server_param<T: ServerParam>(&self) -> Option<&T::Value> {
         self.server_params.get::<T>()
     }
+    /// Bytes transferred and connect, TLS handshake and first byte timings
+    /// for the underlying stream.
+    pub fn stream_metrics(&self) -> gel_stream::StreamMetricsSnapshot {
+        self.stream.metrics().snapshot()
+    }
     #[cfg(feature = "unstable")]
     pub async fn ping_while<T, F>(&mut self, other: F) -> T
     where
@@ -680,6 +685,20 @@ async fn _wait_message(
     buf: &mut BytesMut,
     proto: &ProtocolVersion,
 ) -> Result<ServerMessage, Error> {
+    let frame = _read_frame(stream, buf).await?;
+    let result = crate::raw::messages::decode(proto, frame)
+        .map_err(ProtocolEncodingError::with_source)?;
+
+    log::debug!(target: "edgedb::incoming::frame",
+                "Frame Contents: {result:#?}");
+
+    Ok(result)
+}
+
+async fn _read_frame(
+    stream: &mut (impl AsyncRead + Unpin),
+    buf: &mut BytesMut,
+) -> Result<Bytes, Error> {
     while buf.len() < 5 {
         buf.reserve(5);
         if _read_buf(stream, buf).await.map_err(conn_err)? == 0 {
@@ -701,14 +720,7 @@ async fn _wait_message(
             ));
         }
     }
-    let frame = buf.split_to(frame_len).freeze();
-    let result = ServerMessage::decode(&mut Input::new(proto.clone(), frame))
-        .map_err(ProtocolEncodingError::with_source)?;
-
-    log::debug!(target: "edgedb::incoming::frame",
-                "Frame Contents: {result:#?}");
-
-    Ok(result)
+    Ok(buf.split_to(frame_len).freeze())
 }
 
 fn connect_sleep(retry: usize) -> Duration {
@@ -0,0 +1,189 @@
+//! Decoding of server messages on top of the zero-copy `gel-db-protocol`
+//! message views.
+//!
+//! Messages on the hot path of a query (`Data`, `CommandComplete`,
+//! `CommandDataDescription`, ...) are converted into [`ServerMessage`] by
+//! slicing the received frame, so no payload is copied. Other messages are
+//! rare and are decoded by [`ServerMessage::decode`].
+use std::collections::HashMap;
+
+use bytes::Bytes;
+
+use gel_protocol::common::{Capabilities, RawTypedesc, State};
+use gel_protocol::encoding::{Annotations, Input};
+use gel_protocol::errors::DecodeError;
+use gel_protocol::features::ProtocolVersion;
+use gel_protocol::model::Uuid;
+use gel_db_protocol::prelude::*;
+use gel_db_protocol::protocol::{self as proto, Annotation};
+use gel_protocol::server_message::{CommandComplete1, CommandDataDescription1, Data};
+use gel_protocol::server_message::{ParameterStatus, ReadyForCommand};
+use gel_protocol::server_message::{ServerMessage, StateDataDescription};
+
+/// Decodes a single frame.
+pub(crate) fn decode(proto: &ProtocolVersion, frame: Bytes) -> Result<ServerMessage, DecodeError> {
+    let typedesc = |id, data: &[u8]| RawTypedesc {
+        proto: proto.clone(),
+        id,
+        data: frame.slice_ref(data),
+    };
+    let message = match_message!(proto::Message::new(&frame), proto::EdgeDBBackend {
+        (proto::Data as msg) => {
+            ServerMessage::Data(Data {
+                data: msg
+                    .data()
+                    .into_iter()
+                    .map(|el| frame.slice_ref(el.data().into_bytes()))
+                    .collect(),
+            })
+        },
+        (proto::CommandComplete as msg) => {
+            let state_data = msg.state_data().into_bytes();
+            ServerMessage::CommandComplete1(CommandComplete1 {
+                annotations: annotations(msg.annotations()),
+                capabilities: Capabilities::from_bits_retain(msg.capabilities()),
+                status: msg.status().to_string_lossy().into_owned(),
+                state: if msg.state_typedesc_id() == Uuid::from_u128(0) {
+                    None
+                } else {
+                    Some(State {
+                        typedesc_id: msg.state_typedesc_id(),
+                        data: frame.slice_ref(state_data),
+                    })
+                },
+            })
+        },
+        (proto::CommandDataDescription as msg) => {
+            ServerMessage::CommandDataDescription1(CommandDataDescription1 {
+                annotations: annotations(msg.annotations()),
+                capabilities: Capabilities::from_bits_retain(msg.capabilities()),
+                result_cardinality: msg.result_cardinality().try_into()?,
+                input: typedesc(msg.input_typedesc_id(), msg.input_typedesc().into_bytes()),
+                output: typedesc(msg.output_typedesc_id(), msg.output_typedesc().into_bytes()),
+            })
+        },
+        (proto::StateDataDescription as msg) => {
+            ServerMessage::StateDataDescription(StateDataDescription {
+                typedesc: typedesc(msg.typedesc_id(), msg.typedesc().into_bytes()),
+            })
+        },
+        (proto::ReadyForCommand as msg) => {
+            ServerMessage::ReadyForCommand(ReadyForCommand {
+                annotations: annotations(msg.annotations()),
+                transaction_state: msg.transaction_state(),
+            })
+        },
+        (proto::ParameterStatus as msg) => {
+            ServerMessage::ParameterStatus(ParameterStatus {
+                proto: proto.clone(),
+                name: frame.slice_ref(msg.name().into_bytes()),
+                value: frame.slice_ref(msg.value().into_bytes()),
+            })
+        },
+        unknown => {
+            unknown?;
+            ServerMessage::decode(&mut Input::new(proto.clone(), frame.clone()))?
+        }
+    });
+    Ok(message)
+}
+
+fn annotations<'a>(items: impl IntoIterator<Item = Annotation<'a>>) -> Annotations {
+    items
+        .into_iter()
+        .map(|ann| {
+            (
+                ann.name().to_string_lossy().into_owned(),
+                ann.value().to_string_lossy().into_owned(),
+            )
+        })
+        .collect::<HashMap<_, _>>()
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::{Bytes, BytesMut};
+
+    use gel_protocol::common::{Capabilities, Cardinality, RawTypedesc, State};
+    use gel_protocol::encoding::{Input, Output};
+    use gel_protocol::features::ProtocolVersion;
+    use gel_protocol::model::Uuid;
+    use gel_protocol::server_message::*;
+
+    fn roundtrip(msg: ServerMessage) {
+        let proto = ProtocolVersion::current();
+        let mut buf = BytesMut::new();
+        msg.encode(&mut Output::new(&proto, &mut buf)).unwrap();
+        let frame = buf.freeze();
+        let old = ServerMessage::decode(&mut Input::new(proto.clone(), frame.clone())).unwrap();
+        let new = super::decode(&proto, frame).unwrap();
+        assert_eq!(new, old);
+        assert_eq!(new, msg);
+    }
+
+    #[test]
+    fn same_as_server_message() {
+        let proto = ProtocolVersion::current();
+        roundtrip(ServerMessage::Data(Data {
+            data: vec![Bytes::from_static(b"\x00\x01"), Bytes::new()],
+        }));
+        roundtrip(ServerMessage::CommandComplete1(CommandComplete1 {
+            annotations: Default::default(),
+            capabilities: Capabilities::MODIFICATIONS,
+            status: "INSERT".into(),
+            state: Some(State {
+                typedesc_id: Uuid::from_u128(0x1234),
+                data: Bytes::from_static(b"state"),
+            }),
+        }));
+        roundtrip(ServerMessage::CommandDataDescription1(
+            CommandDataDescription1 {
+                annotations: [("key".to_string(), "value".to_string())].into(),
+                capabilities: Capabilities::empty(),
+                result_cardinality: Cardinality::Many,
+                input: RawTypedesc {
+                    proto: proto.clone(),
+                    id: Uuid::from_u128(0xFF),
+                    data: Bytes::from_static(b"in"),
+                },
+                output: RawTypedesc {
+                    proto: proto.clone(),
+                    id: Uuid::from_u128(0x105),
+                    data: Bytes::from_static(b"out"),
+                },
+            },
+        ));
+        roundtrip(ServerMessage::ReadyForCommand(ReadyForCommand {
+            annotations: Default::default(),
+            transaction_state: TransactionState::InTransaction,
+        }));
+        roundtrip(ServerMessage::ParameterStatus(ParameterStatus {
+            proto,
+            name: Bytes::from_static(b"suggested_pool_concurrency"),
+            value: Bytes::from_static(b"10"),
+        }));
+        roundtrip(ServerMessage::ErrorResponse(ErrorResponse {
+            severity: ErrorSeverity::Error,
+            code: 0x04_00_00_00,
+            message: "error".into(),
+            attributes: Default::default(),
+        }));
+    }
+
+    #[test]
+    fn data_is_not_copied() {
+        let proto = ProtocolVersion::current();
+        let mut buf = BytesMut::new();
+        ServerMessage::Data(Data {
+            data: vec![Bytes::from_static(b"payload")],
+        })
+        .encode(&mut Output::new(&proto, &mut buf))
+        .unwrap();
+        let frame = buf.freeze();
+        let ServerMessage::Data(data) = super::decode(&proto, frame.clone()).unwrap() else {
+            panic!("data message expected");
+        };
+        let range = frame.as_ptr_range();
+        assert!(range.contains(&data.data[0].as_ptr()));
+    }
+}
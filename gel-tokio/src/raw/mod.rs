@@ -3,6 +3,7 @@
 mod connection;
 #[cfg(feature = "unstable")]
 mod dumps;
+mod messages;
 mod options;
 mod queries;
 mod response;
@@ -188,7 +189,7 @@ impl Drop for PoolConnection {
 }
 
 impl<T> Response<T> {
-    fn map<U, R>(self, f: impl FnOnce(T) -> Result<U, R>) -> Result<Response<U>, R> {
+    pub(crate) fn map<U, R>(self, f: impl FnOnce(T) -> Result<U, R>) -> Result<Response<U>, R> {
         #![allow(deprecated)]
         Ok(Response {
             status: self.status,
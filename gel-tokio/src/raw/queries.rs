@@ -3,7 +3,7 @@ use std::sync::Arc;
 use bytes::{Bytes, BytesMut};
 use tokio::time::Instant;
 
-use gel_errors::fields::QueryText;
+use gel_errors::fields::{QueryText, Request, RequestInfo};
 use gel_protocol::client_message::{ClientMessage, Parse};
 use gel_protocol::client_message::Execute1;
 use gel_protocol::common::CompilationOptions;
@@ -23,6 +23,7 @@ use crate::errors::{Error, ErrorKind};
 use crate::raw::connection::Mode;
 use crate::raw::{Connection, PoolConnection, QueryCapabilities};
 use crate::raw::{Description, Response, ResponseStream, State};
+use crate::rows::Rows;
 
 pub(crate) struct Guard;
 
@@ -381,8 +382,37 @@ impl Connection {
     where
         A: QueryArgs,
         R: QueryResult,
+    {
+        let response = self
+            .query_rows(
+                query,
+                arguments,
+                state,
+                annotations,
+                allow_capabilities,
+                io_format,
+                cardinality,
+            )
+            .await?;
+        response.map(|rows| rows.decode_all())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn query_rows<A>(
+        &mut self,
+        query: &str,
+        arguments: &A,
+        state: &dyn State,
+        annotations: &Arc<Annotations>,
+        allow_capabilities: Capabilities,
+        io_format: IoFormat,
+        cardinality: Cardinality,
+    ) -> Result<Response<Rows>, Error>
+    where
+        A: QueryArgs,
     {
         let mut caps = QueryCapabilities::Unparsed;
+        let mut sent = false;
         let result = async {
             let flags = CompilationOptions {
                 implicit_limit: None,
@@ -406,28 +436,26 @@ impl Connection {
                 return Err(e.set::<Description>(desc));
             }
 
+            sent = true;
             let response = self
                 ._execute(&flags, query, state, annotations, &desc, &arg_buf.freeze())
                 .await?;
             response.log_warnings();
 
             let out_desc = desc.output().map_err(ProtocolEncodingError::with_source)?;
-            match out_desc.root_pos() {
-                Some(root_pos) => {
-                    let ctx = out_desc.as_queryable_context();
-                    let mut state = R::prepare(&ctx, root_pos)?;
-                    response.map(|data| {
-                        data.into_iter()
-                            .flat_map(|chunk| chunk.data)
-                            .map(|chunk| R::decode(&mut state, &chunk))
-                            .collect::<Result<Vec<_>, _>>()
-                    })
-                }
-                None => Err(NoResultExpected::build()),
+            if out_desc.root_pos().is_none() {
+                return Err(NoResultExpected::build());
             }
+            response.map(|data| {
+                let data = data.into_iter().flat_map(|chunk| chunk.data).collect();
+                Ok::<_, Error>(Rows::new(out_desc, data))
+            })
         }
         .await;
-        result.map_err(|e| e.set::<QueryCapabilities>(caps))
+        result.map_err(|e| {
+            let request = request_info(&caps, sent);
+            e.set::<QueryCapabilities>(caps).set::<RequestInfo>(request)
+        })
     }
 
     pub async fn execute<A>(
@@ -442,6 +470,7 @@ impl Connection {
         A: QueryArgs,
     {
         let mut caps = QueryCapabilities::Unparsed;
+        let mut sent = false;
         let result: Result<_, Error> = async {
             let flags = CompilationOptions {
                 implicit_limit: None,
@@ -465,6 +494,7 @@ impl Connection {
                 return Err(e.set::<Description>(desc));
             }
 
+            sent = true;
             let response = self
                 ._execute(&flags, query, state, annotations, &desc, &arg_buf.freeze())
                 .await?;
@@ -472,7 +502,17 @@ impl Connection {
             response.map(|_| Ok::<_, Error>(()))
         }
         .await;
-        result.map_err(|e| e.set::<QueryCapabilities>(caps))
+        result.map_err(|e| {
+            let request = request_info(&caps, sent);
+            e.set::<QueryCapabilities>(caps).set::<RequestInfo>(request)
+        })
+    }
+}
+
+fn request_info(caps: &QueryCapabilities, sent: bool) -> Request {
+    Request {
+        sent,
+        read_only: matches!(caps, QueryCapabilities::Parsed(c) if c.is_empty()),
     }
 }
 
@@ -529,3 +569,72 @@ impl PoolConnection {
         self.inner.as_mut().expect("connection is not dropped")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use gel_errors::fields::RequestInfo;
+    use gel_errors::{ClientConnectionClosedError, ErrorKind, QueryError};
+    use gel_errors::{TransactionSerializationError, SHOULD_RETRY};
+    use gel_protocol::common::Capabilities;
+
+    use super::request_info;
+    use crate::raw::QueryCapabilities;
+
+    #[test]
+    fn retry_matrix() {
+        let capabilities = [
+            ("unparsed", None),
+            ("read-only", Some(Capabilities::empty())),
+            ("modifying", Some(Capabilities::MODIFICATIONS)),
+        ];
+        for sent in [false, true] {
+            for (name, caps) in capabilities {
+                let caps = match caps {
+                    None => QueryCapabilities::Unparsed,
+                    Some(caps) => QueryCapabilities::Parsed(caps),
+                };
+                let read_only = matches!(caps, QueryCapabilities::Parsed(c) if c.is_empty());
+                for error in [
+                    QueryError::build(),
+                    TransactionSerializationError::build(),
+                    ClientConnectionClosedError::build(),
+                ] {
+                    let should_retry = error.has_tag(SHOULD_RETRY);
+                    // reported by the server, so the query wasn't applied
+                    let from_server = error.is::<TransactionSerializationError>()
+                        || error.is::<QueryError>();
+                    let verdict = error
+                        .set::<RequestInfo>(request_info(&caps, sent))
+                        .classify();
+                    assert_eq!(
+                        verdict.is_retryable(),
+                        should_retry && (!sent || read_only || from_server),
+                        "{name} query, sent: {sent}, {verdict:?}",
+                    );
+                    assert_eq!(
+                        verdict.definitely_not_applied,
+                        !sent || from_server,
+                        "{name} query, sent: {sent}, {verdict:?}",
+                    );
+                    assert!(
+                        !(verdict.idempotency_required && verdict.definitely_not_applied),
+                        "{name} query, sent: {sent}, {verdict:?}",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_request_info() {
+        let verdict = TransactionSerializationError::build().classify();
+        assert!(verdict.transient);
+        assert!(verdict.definitely_not_applied);
+        assert!(verdict.is_retryable());
+
+        let verdict = ClientConnectionClosedError::build().classify();
+        assert!(verdict.transient);
+        assert!(verdict.idempotency_required);
+        assert!(!verdict.is_retryable());
+    }
+}
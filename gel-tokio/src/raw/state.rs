@@ -4,13 +4,17 @@ use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use arc_swap::ArcSwapOption;
+use bytes::Buf;
 use gel_protocol::client_message::State as EncodedState;
-use gel_protocol::descriptors::{RawTypedesc, StateBorrow};
-use gel_protocol::model::Uuid;
+use gel_protocol::codec::build_codec;
+use gel_protocol::descriptors::{Descriptor, RawTypedesc, StateBorrow};
+use gel_protocol::model::{BigInt, ConfigMemory, Decimal, Json, Uuid};
+use gel_protocol::model::{DateDuration, Datetime, Duration, RelativeDuration};
+use gel_protocol::model::{LocalDate, LocalDatetime, LocalTime};
 use gel_protocol::query_arg::QueryArg;
 use gel_protocol::value::Value;
 
-use crate::errors::{ClientError, Error, ErrorKind, ProtocolEncodingError};
+use crate::errors::{ClientError, DescriptorMismatch, Error, ErrorKind, ProtocolEncodingError};
 
 /// Unset a set of global or config variables
 ///
@@ -94,6 +98,16 @@ pub struct AliasesModifier<'a> {
     data: &'a mut BTreeMap<String, String>,
 }
 
+/// Utility object used to read globals
+///
+/// This object is passed to [`FromGlobals::from_globals`].
+#[derive(Debug)]
+pub struct GlobalsReader<'a> {
+    globals: &'a BTreeMap<String, Value>,
+    module: &'a str,
+    aliases: &'a BTreeMap<String, String>,
+}
+
 /// Trait that modifies global variables
 pub trait GlobalsDelta {
     /// Applies variables delta using specified modifier object
@@ -112,6 +126,26 @@ pub trait AliasesDelta {
     fn apply(self, man: &mut AliasesModifier);
 }
 
+/// Trait that reads global variables from the state
+///
+/// Most commonly implemented using `#[derive(FromGlobals)]`.
+pub trait FromGlobals: Sized {
+    /// Reads variables using specified reader object
+    fn from_globals(reader: &GlobalsReader<'_>) -> Result<Self, Error>;
+}
+
+/// Conversion of a value of a global variable into a Rust type
+///
+/// Use `Option<T>` for variables that might be unset.
+pub trait FromVariable: Sized {
+    /// Converts a value, returns `None` if the value has a different type
+    fn from_variable(value: &Value) -> Option<Self>;
+    /// Returns a value for a variable that is not set, if it's allowed
+    fn from_unset() -> Option<Self> {
+        None
+    }
+}
+
 pub trait SealedState {
     fn encode(&self, desc: &RawTypedesc) -> Result<EncodedState, Error>;
 }
@@ -121,6 +155,19 @@ pub trait SealedState {
 /// This trait is sealed.
 pub trait State: SealedState + Send + Sync {}
 
+/// Resolves the full name of a global variable
+fn global_name(module: &str, aliases: &BTreeMap<String, String>, key: &str) -> String {
+    if let Some(ns_off) = key.rfind("::") {
+        if let Some(alias) = aliases.get(&key[..ns_off]) {
+            format!("{alias}::{suffix}", suffix = &key[ns_off + 2..])
+        } else {
+            key.into()
+        }
+    } else {
+        format!("{module}::{key}")
+    }
+}
+
 impl GlobalsModifier<'_> {
     /// Set global variable to a value
     ///
@@ -151,19 +198,8 @@ impl GlobalsModifier<'_> {
     /// or call `to_value` manually before passing to `set`.
     pub fn set<T: QueryArg>(&mut self, key: &str, value: T) {
         let value = value.to_value().expect("global can be encoded");
-        if let Some(ns_off) = key.rfind("::") {
-            if let Some(alias) = self.aliases.get(&key[..ns_off]) {
-                self.globals.insert(
-                    format!("{alias}::{suffix}", suffix = &key[ns_off + 2..]),
-                    value,
-                );
-            } else {
-                self.globals.insert(key.into(), value);
-            }
-        } else {
-            self.globals
-                .insert(format!("{}::{}", self.module, key), value);
-        }
+        self.globals
+            .insert(global_name(self.module, self.aliases, key), value);
     }
     /// Unset the global variable
     ///
@@ -175,15 +211,93 @@ impl GlobalsModifier<'_> {
     ///
     /// Note: same namespacing rules like for `set` are applied here.
     pub fn unset(&mut self, key: &str) {
-        if let Some(ns_off) = key.rfind("::") {
-            if let Some(alias) = self.aliases.get(&key[..ns_off]) {
-                self.globals
-                    .remove(&format!("{alias}::{suffix}", suffix = &key[ns_off + 2..]));
-            } else {
-                self.globals.remove(key);
+        self.globals
+            .remove(&global_name(self.module, self.aliases, key));
+    }
+}
+
+impl GlobalsReader<'_> {
+    /// Get a value of the global variable
+    ///
+    /// Note: same namespacing rules like for [`GlobalsModifier::set`] are
+    /// applied here.
+    pub fn get<T: FromVariable>(&self, key: &str) -> Result<T, Error> {
+        let name = global_name(self.module, self.aliases, key);
+        match self.globals.get(&name) {
+            Some(value) => T::from_variable(value).ok_or_else(|| {
+                DescriptorMismatch::with_message(format!(
+                    "unexpected type {} of global `{name}`",
+                    value.kind(),
+                ))
+            }),
+            None => T::from_unset().ok_or_else(|| {
+                ClientError::with_message(format!("global `{name}` is not set"))
+            }),
+        }
+    }
+}
+
+macro_rules! from_variable {
+    ($($variant:ident => $ty:ty,)*) => {$(
+        impl FromVariable for $ty {
+            fn from_variable(value: &Value) -> Option<Self> {
+                match value {
+                    Value::$variant(value) => Some(value.clone()),
+                    _ => None,
+                }
             }
-        } else {
-            self.globals.remove(&format!("{}::{}", self.module, key));
+        }
+    )*};
+}
+
+from_variable! {
+    Str => String,
+    Bytes => bytes::Bytes,
+    Int16 => i16,
+    Int32 => i32,
+    Int64 => i64,
+    Float32 => f32,
+    Float64 => f64,
+    Bool => bool,
+    Uuid => Uuid,
+    BigInt => BigInt,
+    Decimal => Decimal,
+    ConfigMemory => ConfigMemory,
+    Datetime => Datetime,
+    LocalDatetime => LocalDatetime,
+    LocalDate => LocalDate,
+    LocalTime => LocalTime,
+    Duration => Duration,
+    RelativeDuration => RelativeDuration,
+    DateDuration => DateDuration,
+    Json => Json,
+}
+
+impl FromVariable for Value {
+    fn from_variable(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl<T: FromVariable> FromVariable for Option<T> {
+    fn from_variable(value: &Value) -> Option<Self> {
+        match value {
+            Value::Nothing => Some(None),
+            value => T::from_variable(value).map(Some),
+        }
+    }
+    fn from_unset() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: FromVariable> FromVariable for Vec<T> {
+    fn from_variable(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(items) | Value::Set(items) => {
+                items.iter().map(T::from_variable).collect()
+            }
+            _ => None,
         }
     }
 }
@@ -379,6 +493,101 @@ impl PoolState {
             cache: ArcSwapOption::new(None),
         }
     }
+    /// Default module, `default` if not set
+    pub fn default_module(&self) -> &str {
+        self.raw_state.common.module.as_deref().unwrap_or("default")
+    }
+    /// Module aliases
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.raw_state.common.aliases
+    }
+    /// Config settings set in this state
+    pub fn config(&self) -> &BTreeMap<String, Value> {
+        &self.raw_state.common.config
+    }
+    /// Global variables set in this state, by their full names
+    pub fn globals(&self) -> &BTreeMap<String, Value> {
+        &self.raw_state.globals
+    }
+    /// Reads global variables into a typed structure
+    pub fn read_globals<T: FromGlobals>(&self) -> Result<T, Error> {
+        T::from_globals(&GlobalsReader {
+            globals: &self.raw_state.globals,
+            module: self.default_module(),
+            aliases: &self.raw_state.common.aliases,
+        })
+    }
+    /// Decodes the state, previously encoded with [`PoolState::encode`]
+    ///
+    /// This is also the way to read the session state reported by the
+    /// server: decode [`Response::new_state`](crate::raw::Response) using
+    /// the [`Connection::state_descriptor`](crate::raw::Connection::state_descriptor).
+    pub fn decode(desc: &RawTypedesc, state: &EncodedState) -> Result<PoolState, Error> {
+        if state.typedesc_id != desc.id {
+            return Err(ClientError::with_message(
+                "state doesn't match state descriptor",
+            ));
+        }
+        let typedesc = desc.decode().map_err(ProtocolEncodingError::with_source)?;
+        let root = match typedesc.root() {
+            Some(Descriptor::InputShape(root)) => root,
+            _ => return Err(DescriptorMismatch::with_message("invalid state descriptor")),
+        };
+        let invalid = |name: &str| {
+            ProtocolEncodingError::with_message(format!("invalid `{name}` in state"))
+        };
+        let decode = |type_pos, data: &[u8]| {
+            build_codec(Some(type_pos), typedesc.descriptors())
+                .map_err(ProtocolEncodingError::with_source)?
+                .decode(data)
+                .map_err(ProtocolEncodingError::with_source)
+        };
+        let variables = |name: &str, value: Value| match value {
+            Value::SparseObject(vars) => Ok(vars
+                .pairs()
+                .map(|(key, value)| (key.to_owned(), value.cloned().unwrap_or(Value::Nothing)))
+                .collect::<BTreeMap<_, _>>()),
+            _ => Err(invalid(name)),
+        };
+        let mut common = CommonState {
+            module: None,
+            aliases: BTreeMap::new(),
+            config: BTreeMap::new(),
+        };
+        let mut globals = BTreeMap::new();
+        let mut buf = &state.data[..];
+        let count = read_u32(&mut buf).ok_or_else(|| invalid("state"))?;
+        for _ in 0..count {
+            let element = read_u32(&mut buf)
+                .and_then(|index| root.elements.get(index as usize))
+                .ok_or_else(|| invalid("state"))?;
+            let name = &element.name[..];
+            let Some(data) = read_slot(&mut buf).ok_or_else(|| invalid(name))? else {
+                continue;
+            };
+            match name {
+                "module" => match decode(element.type_pos, data)? {
+                    Value::Str(module) => common.module = Some(module),
+                    _ => return Err(invalid(name)),
+                },
+                // aliases are encoded by `serialize_state` as a sequence of
+                // length-prefixed pairs, rather than a regular array
+                "aliases" => {
+                    common.aliases = read_aliases(data).ok_or_else(|| invalid(name))?;
+                }
+                "config" => common.config = variables(name, decode(element.type_pos, data)?)?,
+                "globals" => globals = variables(name, decode(element.type_pos, data)?)?,
+                _ => {}
+            }
+        }
+        Ok(PoolState {
+            raw_state: RawState {
+                common: Arc::new(common),
+                globals,
+            },
+            cache: ArcSwapOption::new(Some(Arc::new(state.clone()))),
+        })
+    }
     pub fn encode(&self, desc: &RawTypedesc) -> Result<EncodedState, Error> {
         if let Some(cache) = &*self.cache.load() {
             if cache.typedesc_id == desc.id {
@@ -397,6 +606,44 @@ impl PoolState {
     }
 }
 
+fn read_u32(buf: &mut &[u8]) -> Option<u32> {
+    if buf.remaining() < 4 {
+        return None;
+    }
+    Some(buf.get_u32())
+}
+
+/// Reads a length-prefixed element, `Some(None)` is an empty value
+fn read_slot<'a>(buf: &mut &'a [u8]) -> Option<Option<&'a [u8]>> {
+    let len = read_u32(buf)? as i32;
+    if len < 0 {
+        return Some(None);
+    }
+    let len = len as usize;
+    if buf.remaining() < len {
+        return None;
+    }
+    let (data, rest) = buf.split_at(len);
+    *buf = rest;
+    Some(Some(data))
+}
+
+fn read_aliases(mut buf: &[u8]) -> Option<BTreeMap<String, String>> {
+    let mut aliases = BTreeMap::new();
+    let count = read_u32(&mut buf)?;
+    for _ in 0..count {
+        let mut pair = read_slot(&mut buf)??;
+        if read_u32(&mut pair)? != 2 {
+            return None;
+        }
+        let _reserved = read_u32(&mut pair)?;
+        let alias = std::str::from_utf8(read_slot(&mut pair)??).ok()?;
+        let module = std::str::from_utf8(read_slot(&mut pair)??).ok()?;
+        aliases.insert(alias.into(), module.into());
+    }
+    Some(aliases)
+}
+
 impl SealedState for &PoolState {
     fn encode(&self, desc: &RawTypedesc) -> Result<EncodedState, Error> {
         PoolState::encode(self, desc)
@@ -443,3 +690,103 @@ impl Default for PoolState {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use gel_protocol::descriptors::RawTypedesc;
+    use gel_protocol::features::ProtocolVersion;
+    use gel_protocol::model::Uuid;
+    use gel_protocol::value::Value;
+
+    use super::{FromGlobals, GlobalsReader, PoolState};
+    use crate::Error;
+
+    #[derive(Debug, PartialEq)]
+    struct Globals {
+        user_id: i64,
+        name: Option<String>,
+        missing: Option<i64>,
+    }
+
+    impl FromGlobals for Globals {
+        fn from_globals(reader: &GlobalsReader<'_>) -> Result<Self, Error> {
+            Ok(Globals {
+                user_id: reader.get("user_id")?,
+                name: reader.get("other::name")?,
+                missing: reader.get("missing")?,
+            })
+        }
+    }
+
+    /// Scalars `std::str` (0) and `std::int64` (1), the aliases tuple (2) and
+    /// array (3), input shapes of config (4), globals (5) and the state (6).
+    fn state_descriptor() -> RawTypedesc {
+        RawTypedesc {
+            proto: ProtocolVersion::current(),
+            id: Uuid::from_u128(0x1004),
+            data: Bytes::from_static(
+                b"\0\0\0 \x03\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\x01\0\0\0\x08std::str\0\0\0\
+                \0\0\0\x22\x03\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\x05\0\0\0\x0astd::int64\0\0\0\
+                \0\0\0\x37\x04\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x10\0\0\0\0\x19tuple<std::str, std::str>\0\0\0\0\x02\0\0\0\0\
+                \0\0\0@\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x10\x01\0\0\0 array<tuple<std::str, std::str>>\0\0\0\0\x02\0\x01\xff\xff\xff\xff\
+                \0\0\0\x35\x08\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x10\x02\0\x01\0\0\0\0o\0\0\0\x17allow_user_specified_id\0\x01\
+                \0\0\0_\x08\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x10\x03\0\x03\0\0\0\0o\0\0\0\x10default::user_id\0\x01\0\0\0\0o\0\0\0\x10default::missing\0\x01\0\0\0\0o\0\0\0\x0bother::name\0\0\
+                \0\0\0Y\x08\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x10\x04\0\x04\0\0\0\0o\0\0\0\x06module\0\0\0\0\0\0o\0\0\0\x07aliases\0\x03\0\0\0\0o\0\0\0\x06config\0\x04\0\0\0\0o\0\0\0\x07globals\0\x05",
+            ),
+        }
+    }
+
+    #[test]
+    fn decode_state() {
+        let desc = state_descriptor();
+        let state = PoolState::default()
+            .with_default_module(Some("app".into()))
+            .with_aliases([("o", "other")].into_iter().collect::<super::BTreeMap<_, _>>())
+            .with_config(super::Fn(|m: &mut super::ConfigModifier| {
+                m.set("allow_user_specified_id", 1i64)
+            }))
+            .with_default_module(None)
+            .with_globals(super::Fn(|m: &mut super::GlobalsModifier| {
+                m.set("user_id", 7i64);
+                m.set("o::name", "alice");
+            }));
+        let encoded = state.encode(&desc).unwrap();
+
+        let decoded = PoolState::decode(&desc, &encoded).unwrap();
+        assert_eq!(decoded.default_module(), "default");
+        assert_eq!(decoded.aliases().get("o").unwrap(), "other");
+        assert_eq!(
+            decoded.config().get("allow_user_specified_id"),
+            Some(&Value::Int64(1))
+        );
+        assert_eq!(decoded.globals(), state.globals());
+        assert_eq!(
+            decoded.read_globals::<Globals>().unwrap(),
+            Globals {
+                user_id: 7,
+                name: Some("alice".into()),
+                missing: None,
+            }
+        );
+    }
+
+    #[test]
+    fn read_errors() {
+        let state = PoolState::default();
+        let err = state.read_globals::<Globals>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ClientError: global `default::user_id` is not set"
+        );
+
+        let state = state.with_globals(super::Fn(|m: &mut super::GlobalsModifier| {
+            m.set("user_id", "seven")
+        }));
+        let err = state.read_globals::<Globals>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "DescriptorMismatch: unexpected type str of global `default::user_id`"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use std::slice;
+
+use bytes::Bytes;
+use gel_protocol::descriptors::Typedesc;
+use gel_protocol::queryable::{Decoder, Queryable};
+use gel_protocol::QueryResult;
+
+use crate::errors::{DescriptorMismatch, ProtocolEncodingError};
+use crate::errors::{Error, ErrorKind};
+
+/// Undecoded results of a query.
+///
+/// Rows are kept exactly as they were received from the server. This allows
+/// decoding values that borrow from the message buffers, such as `&str`,
+/// `&[u8]` or structures deriving [`Queryable`](macro@crate::Queryable) with
+/// a lifetime parameter, instead of allocating a copy of every field.
+///
+/// ```rust,no_run
+/// # async fn main_() -> Result<(), gel_tokio::Error> {
+/// #[derive(gel_tokio::Queryable)]
+/// struct Event<'a> {
+///     kind: &'a str,
+///     payload: &'a [u8],
+/// }
+///
+/// let client = gel_tokio::create_client().await?;
+/// let rows = client.query_rows("select Event { kind, payload }", &()).await?;
+/// let mut total = 0;
+/// for event in rows.iter::<Event>()? {
+///     let event = event?;
+///     if event.kind == "click" {
+///         total += event.payload.len();
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Rows {
+    desc: Typedesc,
+    data: Vec<Bytes>,
+}
+
+/// Iterator over rows decoded from [`Rows`].
+///
+/// Returned by [`Rows::iter`]. Decoded values can't outlive the [`Rows`]
+/// they were decoded from.
+pub struct RowIter<'a, T: Queryable<'a>> {
+    decoder: Decoder,
+    args: T::Args,
+    rows: slice::Iter<'a, Bytes>,
+}
+
+impl Rows {
+    pub(crate) fn new(desc: Typedesc, data: Vec<Bytes>) -> Rows {
+        Rows { desc, data }
+    }
+
+    /// Number of rows returned by the query.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the query returned no rows.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns an iterator decoding every row as `T`.
+    ///
+    /// The type descriptor is checked once, before the iteration starts.
+    pub fn iter<'a, T: Queryable<'a>>(&'a self) -> Result<RowIter<'a, T>, Error> {
+        let ctx = self.desc.as_queryable_context();
+        let root_pos = self
+            .desc
+            .root_pos()
+            .ok_or_else(|| DescriptorMismatch::with_message("query returns no data"))?;
+        let args = T::check_descriptor(&ctx, root_pos).map_err(DescriptorMismatch::with_source)?;
+        Ok(RowIter {
+            decoder: ctx.decoder(),
+            args,
+            rows: self.data.iter(),
+        })
+    }
+
+    pub(crate) fn decode_all<R: QueryResult>(&self) -> Result<Vec<R>, Error> {
+        let Some(root_pos) = self.desc.root_pos() else {
+            return Ok(Vec::new());
+        };
+        let ctx = self.desc.as_queryable_context();
+        let mut state = R::prepare(&ctx, root_pos)?;
+        self.data
+            .iter()
+            .map(|row| R::decode(&mut state, row))
+            .collect()
+    }
+}
+
+impl<'a, T: Queryable<'a>> Iterator for RowIter<'a, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        Some(T::decode(&self.decoder, &self.args, row).map_err(ProtocolEncodingError::with_source))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl<'a, T: Queryable<'a>> ExactSizeIterator for RowIter<'a, T> {}
+
+impl<'a, T: Queryable<'a>> fmt::Debug for RowIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowIter")
+            .field("remaining", &self.rows.len())
+            .finish()
+    }
+}
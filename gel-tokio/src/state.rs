@@ -5,3 +5,4 @@ pub use crate::raw::state::State;
 pub use crate::raw::state::{AliasesDelta, ConfigDelta, GlobalsDelta};
 pub use crate::raw::state::{AliasesModifier, ConfigModifier, GlobalsModifier};
 pub use crate::raw::state::{Fn, Unset};
+pub use crate::raw::state::{FromGlobals, FromVariable, GlobalsReader};
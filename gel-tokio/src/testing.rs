@@ -0,0 +1,818 @@
+/*!
+In-memory [`QueryExecutor`] for unit tests. Enable the `testing` feature to
+use it.
+
+Code that accepts any [`QueryExecutor`] can be tested against a
+[`MockExecutor`] instead of a database. Expected queries are registered
+with canned responses: rows given as [`Value`]s (or anything implementing
+[`QueryArg`], such as integers, strings and tuples) are decoded into the
+type requested by the code under test, just like rows received from the
+server.
+
+```rust
+use gel_errors::{ConstraintViolationError, ErrorKind};
+use gel_protocol::value::Value;
+use gel_tokio::testing::{Expectation, MockExecutor};
+use gel_tokio::{Error, QueryExecutor};
+
+async fn rename(db: impl QueryExecutor, id: i64, name: &str) -> Result<Option<String>, Error> {
+    db.query_single("update User filter .num = <int64>$0 set { name := <str>$1 }", &(id, name))
+        .await
+}
+
+# #[tokio::main(flavor = "current_thread")]
+# async fn main() {
+let mock = MockExecutor::new();
+mock.expect(
+    Expectation::regex(r"^update User")
+        .with_args(Value::Tuple(vec![Value::Int64(1), Value::Str("Alice".into())]))
+        .returning(["Alice"]),
+);
+mock.expect(
+    Expectation::regex(r"^update User")
+        .failing_with(|| ConstraintViolationError::with_message("name violates exclusivity")),
+);
+
+assert_eq!(rename(&mock, 1, "Alice").await.unwrap().as_deref(), Some("Alice"));
+let err = rename(&mock, 2, "Alice").await.unwrap_err();
+assert!(err.is::<ConstraintViolationError>());
+
+let calls = mock.calls();
+assert_eq!(calls.len(), 2);
+assert_eq!(calls[1].method, "query_single");
+assert_eq!(
+    calls[1].args,
+    Some(Value::Tuple(vec![Value::Int64(2), Value::Str("Alice".into())]))
+);
+# }
+```
+
+The types of the response are inferred from the rows. A field that is
+empty in all rows (and an empty set or array) is typed as `std::str`,
+use a query that doesn't return such fields if they are decoded into a
+different type.
+*/
+
+use std::fmt;
+use std::future::{self, Future};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bytes::BytesMut;
+use gel_protocol::codec::{self, NamedTupleShape, ObjectShape};
+use gel_protocol::descriptors::{self as desc, Descriptor, TypePos, Typedesc};
+use gel_protocol::features::ProtocolVersion;
+use gel_protocol::model::{Json, Uuid};
+use gel_protocol::query_arg::{QueryArg, QueryArgs};
+use gel_protocol::value::Value;
+use gel_protocol::QueryResult;
+use regex::Regex;
+
+use crate::errors::ResultCardinalityMismatchError;
+use crate::errors::{Error, ErrorKind};
+use crate::errors::{InterfaceError, NoDataError, ProtocolEncodingError};
+use crate::{QueryExecutor, ResultVerbose};
+
+/// Base of the ids of descriptors built for responses.
+const DESCRIPTOR_ID: u128 = 0x7e57_0000_0000;
+
+/// [`QueryExecutor`] that answers queries with canned responses.
+///
+/// Queries are matched against the expectations in the order they were
+/// registered, the first matching one is used. A query without a matching
+/// expectation fails with [`InterfaceError`]. Every call is recorded and can
+/// be inspected with [`calls`](MockExecutor::calls).
+#[derive(Default)]
+pub struct MockExecutor {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    expectations: Vec<Expectation>,
+    calls: Vec<Call>,
+}
+
+/// Query expected by a [`MockExecutor`] and the response to it.
+pub struct Expectation {
+    query: QueryMatcher,
+    args: Option<ArgsMatcher>,
+    response: Response,
+    times: Option<usize>,
+}
+
+enum QueryMatcher {
+    Text(String),
+    Regex(Regex),
+}
+
+type ArgsMatcher = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+
+enum Response {
+    Rows(Vec<Value>),
+    Error(Arc<dyn Fn() -> Error + Send + Sync>),
+}
+
+/// A call made to a [`MockExecutor`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Call {
+    /// Name of the [`QueryExecutor`] method, e.g. `"query_single"`.
+    pub method: &'static str,
+    /// Text of the query.
+    pub query: String,
+    /// Arguments of the query, see
+    /// [`QueryArgs::to_args_value`] (`None` if they can't be converted).
+    pub args: Option<Value>,
+}
+
+#[derive(Debug)]
+enum Type {
+    Unknown,
+    Scalar(Uuid),
+    Enum(Vec<String>),
+    Object(ObjectShape, Vec<Type>),
+    NamedTuple(NamedTupleShape, Vec<Type>),
+    Tuple(Vec<Type>),
+    Set(Box<Type>),
+    Array(Box<Type>),
+    Range(Box<Type>),
+}
+
+impl MockExecutor {
+    /// Creates an executor without expectations.
+    pub fn new() -> MockExecutor {
+        MockExecutor::default()
+    }
+
+    /// Registers an expected query.
+    pub fn expect(&self, expectation: Expectation) -> &MockExecutor {
+        self.lock().expectations.push(expectation);
+        self
+    }
+
+    /// Returns all calls made so far, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.lock().calls.clone()
+    }
+
+    /// Forgets recorded calls.
+    pub fn clear_calls(&self) {
+        self.lock().calls.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // a failed assertion in a matcher shouldn't break other tests
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn respond(
+        &self,
+        method: &'static str,
+        query: &str,
+        arguments: &impl QueryArgs,
+    ) -> Result<Vec<Value>, Error> {
+        let args = arguments.to_args_value().ok();
+        let mut state = self.lock();
+        state.calls.push(Call {
+            method,
+            query: query.into(),
+            args: args.clone(),
+        });
+        let expectation = state
+            .expectations
+            .iter_mut()
+            .find(|e| e.times != Some(0) && e.matches(query, args.as_ref()))
+            .ok_or_else(|| {
+                InterfaceError::with_message(format!(
+                    "unexpected query {query:?} with arguments {args:?}"
+                ))
+            })?;
+        if let Some(times) = &mut expectation.times {
+            *times -= 1;
+        }
+        match &expectation.response {
+            Response::Rows(rows) => Ok(rows.clone()),
+            Response::Error(make_error) => Err(make_error()),
+        }
+    }
+}
+
+impl fmt::Debug for MockExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("MockExecutor")
+            .field("expectations", &state.expectations)
+            .field("calls", &state.calls)
+            .finish()
+    }
+}
+
+impl Expectation {
+    /// Expects a query with exactly this text.
+    pub fn query(text: impl Into<String>) -> Expectation {
+        Expectation::new(QueryMatcher::Text(text.into()))
+    }
+
+    /// Expects a query matching a regular expression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the regular expression is invalid.
+    pub fn regex(pattern: &str) -> Expectation {
+        let regex = Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("invalid query pattern {pattern:?}: {e}"));
+        Expectation::new(QueryMatcher::Regex(regex))
+    }
+
+    fn new(query: QueryMatcher) -> Expectation {
+        Expectation {
+            query,
+            args: None,
+            response: Response::Rows(Vec::new()),
+            times: None,
+        }
+    }
+
+    /// Only matches if the arguments are equal to the value, see
+    /// [`QueryArgs::to_args_value`].
+    pub fn with_args(self, args: Value) -> Expectation {
+        self.with_args_matching(move |value| *value == args)
+    }
+
+    /// Only matches if the arguments satisfy the predicate.
+    pub fn with_args_matching(
+        mut self,
+        predicate: impl Fn(&Value) -> bool + Send + Sync + 'static,
+    ) -> Expectation {
+        self.args = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Responds with the rows.
+    ///
+    /// By default, a query returns no rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a row can't be converted into a [`Value`].
+    pub fn returning<T: QueryArg>(mut self, rows: impl IntoIterator<Item = T>) -> Expectation {
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.to_value()
+                    .unwrap_or_else(|e| panic!("invalid response row: {e:#}"))
+            })
+            .collect();
+        self.response = Response::Rows(rows);
+        self
+    }
+
+    /// Fails the query with an error created by the function.
+    pub fn failing_with(
+        mut self,
+        make_error: impl Fn() -> Error + Send + Sync + 'static,
+    ) -> Expectation {
+        self.response = Response::Error(Arc::new(make_error));
+        self
+    }
+
+    /// Only matches the first `n` queries, unlimited by default.
+    pub fn times(mut self, n: usize) -> Expectation {
+        self.times = Some(n);
+        self
+    }
+
+    fn matches(&self, query: &str, args: Option<&Value>) -> bool {
+        let query_matches = match &self.query {
+            QueryMatcher::Text(text) => text == query,
+            QueryMatcher::Regex(regex) => regex.is_match(query),
+        };
+        query_matches
+            && match (&self.args, args) {
+                (None, _) => true,
+                (Some(matcher), Some(args)) => matcher(args),
+                (Some(_), None) => false,
+            }
+    }
+}
+
+impl fmt::Debug for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut dbg = f.debug_struct("Expectation");
+        match &self.query {
+            QueryMatcher::Text(text) => dbg.field("query", text),
+            QueryMatcher::Regex(regex) => dbg.field("regex", &regex.as_str()),
+        };
+        match &self.response {
+            Response::Rows(rows) => dbg.field("rows", rows),
+            Response::Error(_) => dbg.field("error", &".."),
+        };
+        dbg.field("times", &self.times).finish()
+    }
+}
+
+impl Type {
+    fn of(value: &Value) -> Result<Type, Error> {
+        use gel_protocol::codec::*;
+
+        let scalar = |id| Ok(Type::Scalar(id));
+        match value {
+            Value::Nothing => Ok(Type::Unknown),
+            Value::Uuid(_) => scalar(STD_UUID),
+            Value::Str(_) => scalar(STD_STR),
+            Value::Bytes(_) => scalar(STD_BYTES),
+            Value::Int16(_) => scalar(STD_INT16),
+            Value::Int32(_) => scalar(STD_INT32),
+            Value::Int64(_) => scalar(STD_INT64),
+            Value::Float32(_) => scalar(STD_FLOAT32),
+            Value::Float64(_) => scalar(STD_FLOAT64),
+            Value::BigInt(_) => scalar(STD_BIGINT),
+            Value::ConfigMemory(_) => scalar(CFG_MEMORY),
+            Value::Decimal(_) => scalar(STD_DECIMAL),
+            Value::Bool(_) => scalar(STD_BOOL),
+            Value::Datetime(_) => scalar(STD_DATETIME),
+            Value::LocalDatetime(_) => scalar(CAL_LOCAL_DATETIME),
+            Value::LocalDate(_) => scalar(CAL_LOCAL_DATE),
+            Value::LocalTime(_) => scalar(CAL_LOCAL_TIME),
+            Value::Duration(_) => scalar(STD_DURATION),
+            Value::RelativeDuration(_) => scalar(CAL_RELATIVE_DURATION),
+            Value::DateDuration(_) => scalar(CAL_DATE_DURATION),
+            Value::Json(_) => scalar(STD_JSON),
+            Value::Vector(_) => scalar(PGVECTOR_VECTOR),
+            Value::PostGisGeometry(_) => scalar(POSTGIS_GEOMETRY),
+            Value::PostGisGeography(_) => scalar(POSTGIS_GEOGRAPHY),
+            Value::PostGisBox2d(_) => scalar(POSTGIS_BOX_2D),
+            Value::PostGisBox3d(_) => scalar(POSTGIS_BOX_3D),
+            Value::Enum(value) => Ok(Type::Enum(vec![value.to_string()])),
+            Value::Set(items) => Ok(Type::Set(Box::new(Type::common(items)?))),
+            Value::Array(items) => Ok(Type::Array(Box::new(Type::common(items)?))),
+            Value::Range(range) => {
+                let bounds = range.lower().into_iter().chain(range.upper());
+                let mut element = Type::Unknown;
+                for bound in bounds {
+                    element = element.merge(Type::of(bound)?)?;
+                }
+                Ok(Type::Range(Box::new(element)))
+            }
+            Value::Tuple(fields) => Ok(Type::Tuple(
+                fields.iter().map(Type::of).collect::<Result<_, _>>()?,
+            )),
+            Value::NamedTuple { shape, fields } => Ok(Type::NamedTuple(
+                shape.clone(),
+                fields.iter().map(Type::of).collect::<Result<_, _>>()?,
+            )),
+            Value::Object { shape, fields } => Ok(Type::Object(
+                shape.clone(),
+                fields
+                    .iter()
+                    .map(|f| f.as_ref().map_or(Ok(Type::Unknown), Type::of))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Value::SparseObject(_) | Value::SQLRow { .. } => Err(InterfaceError::with_message(
+                format!("{} can't be returned by a mock query", value.kind()),
+            )),
+        }
+    }
+
+    fn common(values: &[Value]) -> Result<Type, Error> {
+        values
+            .iter()
+            .try_fold(Type::Unknown, |ty, value| ty.merge(Type::of(value)?))
+    }
+
+    fn merge(self, other: Type) -> Result<Type, Error> {
+        use Type::*;
+
+        let merge_all = |a: Vec<Type>, b: Vec<Type>| {
+            a.into_iter()
+                .zip(b)
+                .map(|(a, b)| a.merge(b))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        match (self, other) {
+            (Unknown, ty) | (ty, Unknown) => Ok(ty),
+            (Scalar(a), Scalar(b)) if a == b => Ok(Scalar(a)),
+            (Enum(mut a), Enum(b)) => {
+                for member in b {
+                    if !a.contains(&member) {
+                        a.push(member);
+                    }
+                }
+                Ok(Enum(a))
+            }
+            (Set(a), Set(b)) => Ok(Set(Box::new(a.merge(*b)?))),
+            (Array(a), Array(b)) => Ok(Array(Box::new(a.merge(*b)?))),
+            (Range(a), Range(b)) => Ok(Range(Box::new(a.merge(*b)?))),
+            (Tuple(a), Tuple(b)) if a.len() == b.len() => Ok(Tuple(merge_all(a, b)?)),
+            (NamedTuple(shape, a), NamedTuple(other, b)) if shape == other => {
+                Ok(NamedTuple(shape, merge_all(a, b)?))
+            }
+            (Object(shape, a), Object(other, b)) if shape == other => {
+                Ok(Object(shape, merge_all(a, b)?))
+            }
+            (a, b) => Err(InterfaceError::with_message(format!(
+                "rows of a mock response have different types: {a:?} and {b:?}"
+            ))),
+        }
+    }
+}
+
+/// Builds descriptors for a type, elements first.
+#[derive(Default)]
+struct DescriptorBuilder {
+    descriptors: Vec<Descriptor>,
+}
+
+impl DescriptorBuilder {
+    fn next_id(&self) -> desc::DescriptorUuid {
+        Uuid::from_u128(DESCRIPTOR_ID + self.descriptors.len() as u128).into()
+    }
+
+    fn push(&mut self, descriptor: Descriptor) -> TypePos {
+        self.descriptors.push(descriptor);
+        TypePos(self.descriptors.len() as u16 - 1)
+    }
+
+    fn build(&mut self, ty: &Type) -> TypePos {
+        let descriptor = match ty {
+            Type::Unknown => return self.build(&Type::Scalar(codec::STD_STR)),
+            Type::Scalar(id) => {
+                let existing = self.descriptors.iter().position(|d| match d {
+                    Descriptor::BaseScalar(scalar) => *scalar.id == *id,
+                    _ => false,
+                });
+                if let Some(pos) = existing {
+                    return TypePos(pos as u16);
+                }
+                Descriptor::BaseScalar(desc::BaseScalarTypeDescriptor { id: (*id).into() })
+            }
+            Type::Enum(members) => Descriptor::Enumeration(desc::EnumerationTypeDescriptor {
+                id: self.next_id(),
+                members: members.clone(),
+                name: None,
+                schema_defined: None,
+                ancestors: Vec::new(),
+            }),
+            Type::Set(element) => {
+                let type_pos = self.build(element);
+                Descriptor::Set(desc::SetDescriptor {
+                    id: self.next_id(),
+                    type_pos,
+                })
+            }
+            Type::Array(element) => {
+                let type_pos = self.build(element);
+                Descriptor::Array(desc::ArrayTypeDescriptor {
+                    id: self.next_id(),
+                    type_pos,
+                    dimensions: vec![None],
+                    name: None,
+                    schema_defined: None,
+                    ancestors: Vec::new(),
+                })
+            }
+            Type::Range(element) => {
+                let type_pos = self.build(element);
+                Descriptor::Range(desc::RangeTypeDescriptor {
+                    id: self.next_id(),
+                    type_pos,
+                    name: None,
+                    schema_defined: None,
+                    ancestors: Vec::new(),
+                })
+            }
+            Type::Tuple(elements) => {
+                let element_types = elements.iter().map(|e| self.build(e)).collect();
+                Descriptor::Tuple(desc::TupleTypeDescriptor {
+                    id: self.next_id(),
+                    element_types,
+                    name: None,
+                    schema_defined: None,
+                    ancestors: Vec::new(),
+                })
+            }
+            Type::NamedTuple(shape, elements) => {
+                let elements = shape
+                    .elements
+                    .iter()
+                    .zip(elements)
+                    .map(|(el, ty)| desc::TupleElement {
+                        name: el.name.clone(),
+                        type_pos: self.build(ty),
+                    })
+                    .collect();
+                Descriptor::NamedTuple(desc::NamedTupleTypeDescriptor {
+                    id: self.next_id(),
+                    elements,
+                    name: None,
+                    schema_defined: None,
+                    ancestors: Vec::new(),
+                })
+            }
+            Type::Object(shape, elements) => {
+                let elements = shape
+                    .elements
+                    .iter()
+                    .zip(elements)
+                    .map(|(el, ty)| desc::ShapeElement {
+                        flag_implicit: el.flag_implicit,
+                        flag_link_property: el.flag_link_property,
+                        flag_link: el.flag_link,
+                        cardinality: el.cardinality,
+                        name: el.name.clone(),
+                        type_pos: self.build(ty),
+                        source_type_pos: None,
+                    })
+                    .collect();
+                Descriptor::ObjectShape(desc::ObjectShapeDescriptor {
+                    id: self.next_id(),
+                    ephemeral_free_shape: false,
+                    type_pos: None,
+                    elements,
+                })
+            }
+        };
+        self.push(descriptor)
+    }
+}
+
+/// Decodes rows as if they were received from the server.
+fn decode<R: QueryResult>(rows: &[Value]) -> Result<Vec<R>, Error> {
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut builder = DescriptorBuilder::default();
+    let root_pos = builder.build(&Type::common(rows)?);
+    let typedesc = Typedesc::new(
+        ProtocolVersion::current(),
+        builder.descriptors,
+        Some(root_pos),
+    )
+    .map_err(ProtocolEncodingError::with_source)?;
+    let codec = typedesc
+        .build_codec()
+        .map_err(ProtocolEncodingError::with_source)?;
+    let mut state = R::prepare(&typedesc.as_queryable_context(), root_pos)?;
+    rows.iter()
+        .map(|row| {
+            let mut buf = BytesMut::new();
+            codec
+                .encode(&mut buf, row)
+                .map_err(ProtocolEncodingError::with_source)?;
+            R::decode(&mut state, &buf.freeze())
+        })
+        .collect()
+}
+
+fn at_most_one<T>(mut rows: Vec<T>) -> Result<Option<T>, Error> {
+    if rows.len() > 1 {
+        return Err(ResultCardinalityMismatchError::with_message(
+            "the query returned more than one row",
+        ));
+    }
+    Ok(rows.pop())
+}
+
+fn required<T>(row: Option<T>) -> Result<T, Error> {
+    row.ok_or_else(|| NoDataError::with_message("query row returned zero results"))
+}
+
+fn to_json(rows: &[Value]) -> Json {
+    let rows = rows.iter().map(Value::to_json_lossless).collect();
+    Json::new_unchecked(serde_json::Value::Array(rows).to_string())
+}
+
+impl QueryExecutor for &MockExecutor {
+    fn query<R, A>(
+        self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+    ) -> impl Future<Output = Result<Vec<R>, Error>> + Send
+    where
+        A: QueryArgs,
+        R: QueryResult + Send,
+    {
+        let result = self
+            .respond("query", query.as_ref(), arguments)
+            .and_then(|rows| decode(&rows));
+        future::ready(result)
+    }
+
+    fn query_verbose<R, A>(
+        self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+    ) -> impl Future<Output = Result<ResultVerbose<Vec<R>>, Error>> + Send
+    where
+        A: QueryArgs,
+        R: QueryResult + Send,
+    {
+        let result = self
+            .respond("query_verbose", query.as_ref(), arguments)
+            .and_then(|rows| decode(&rows))
+            .map(|data| ResultVerbose {
+                data,
+                warnings: Vec::new(),
+            });
+        future::ready(result)
+    }
+
+    fn query_single<R, A>(
+        self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+    ) -> impl Future<Output = Result<Option<R>, Error>> + Send
+    where
+        A: QueryArgs,
+        R: QueryResult + Send,
+    {
+        let result = self
+            .respond("query_single", query.as_ref(), arguments)
+            .and_then(|rows| at_most_one(decode(&rows)?));
+        future::ready(result)
+    }
+
+    fn query_required_single<R, A>(
+        self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+    ) -> impl Future<Output = Result<R, Error>> + Send
+    where
+        A: QueryArgs,
+        R: QueryResult + Send,
+    {
+        let result = self
+            .respond("query_required_single", query.as_ref(), arguments)
+            .and_then(|rows| required(at_most_one(decode(&rows)?)?));
+        future::ready(result)
+    }
+
+    fn query_json(
+        self,
+        query: &str,
+        arguments: &impl QueryArgs,
+    ) -> impl Future<Output = Result<Json, Error>> + Send {
+        let result = self
+            .respond("query_json", query, arguments)
+            .map(|rows| to_json(&rows));
+        future::ready(result)
+    }
+
+    fn query_single_json(
+        self,
+        query: &str,
+        arguments: &impl QueryArgs,
+    ) -> impl Future<Output = Result<Option<Json>, Error>> + Send {
+        let result = self
+            .respond("query_single_json", query, arguments)
+            .and_then(at_most_one)
+            .map(|row| row.map(|row| Json::new_unchecked(row.to_json_lossless().to_string())));
+        future::ready(result)
+    }
+
+    fn query_required_single_json(
+        self,
+        query: &str,
+        arguments: &impl QueryArgs,
+    ) -> impl Future<Output = Result<Json, Error>> {
+        let result = self
+            .respond("query_required_single_json", query, arguments)
+            .and_then(at_most_one)
+            .and_then(required)
+            .map(|row| Json::new_unchecked(row.to_json_lossless().to_string()));
+        future::ready(result)
+    }
+
+    fn execute<A>(
+        self,
+        query: &str,
+        arguments: &A,
+    ) -> impl Future<Output = Result<(), Error>> + Send
+    where
+        A: QueryArgs,
+    {
+        let result = self.respond("execute", query, arguments).map(|_| ());
+        future::ready(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use gel_errors::TransactionConflictError;
+    use gel_protocol::codec::{ObjectShape, ShapeElement};
+    use gel_protocol::common::Cardinality;
+    use gel_protocol::value::Value;
+
+    use super::{Expectation, MockExecutor};
+    use crate::errors::ResultCardinalityMismatchError;
+    use crate::errors::{ErrorKind, InterfaceError, NoDataError};
+    use crate::QueryExecutor;
+
+    #[derive(Debug, PartialEq, gel_derive::Queryable)]
+    struct User {
+        name: String,
+        age: Option<i64>,
+        tags: Vec<String>,
+    }
+
+    fn user(name: &str, age: Option<i64>, tags: &[&str]) -> Value {
+        let element = |name: &str, cardinality| ShapeElement {
+            flag_implicit: false,
+            flag_link_property: false,
+            flag_link: false,
+            cardinality: Some(cardinality),
+            name: name.into(),
+        };
+        Value::Object {
+            shape: ObjectShape::new(vec![
+                element("name", Cardinality::One),
+                element("age", Cardinality::AtMostOne),
+                element("tags", Cardinality::Many),
+            ]),
+            fields: vec![
+                Some(Value::Str(name.into())),
+                age.map(Value::Int64),
+                Some(Value::Set(
+                    tags.iter().map(|t| Value::Str(t.to_string())).collect(),
+                )),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn objects() {
+        let mock = MockExecutor::new();
+        mock.expect(
+            Expectation::query("select User")
+                .returning([user("alice", None, &[]), user("bob", Some(42), &["admin"])]),
+        );
+        let users: Vec<User> = mock.query("select User", &()).await.unwrap();
+        assert_eq!(
+            users,
+            vec![
+                User {
+                    name: "alice".into(),
+                    age: None,
+                    tags: vec![],
+                },
+                User {
+                    name: "bob".into(),
+                    age: Some(42),
+                    tags: vec!["admin".into()],
+                },
+            ]
+        );
+        let json = mock.query_json("select User", &()).await.unwrap();
+        assert_eq!(
+            &*json,
+            r#"[{"age":null,"name":"alice","tags":[]},{"age":42,"name":"bob","tags":["admin"]}]"#
+        );
+        let err = mock
+            .query::<(String, i64), _>("select User", &())
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("expected tuple"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn cardinality() {
+        let mock = MockExecutor::new();
+        mock.expect(Expectation::query("select {1, 2}").returning([1i64, 2]));
+        mock.expect(Expectation::query("select <int64>{}"));
+        let err = mock
+            .query_single::<i64, _>("select {1, 2}", &())
+            .await
+            .unwrap_err();
+        assert!(err.is::<ResultCardinalityMismatchError>());
+        let empty = mock.query_single::<i64, _>("select <int64>{}", &()).await;
+        assert_eq!(empty.unwrap(), None);
+        let err = mock
+            .query_required_single::<i64, _>("select <int64>{}", &())
+            .await
+            .unwrap_err();
+        assert!(err.is::<NoDataError>());
+    }
+
+    #[tokio::test]
+    async fn retries() {
+        let mock = MockExecutor::new();
+        mock.expect(
+            Expectation::query("insert Log")
+                .times(1)
+                .failing_with(|| TransactionConflictError::with_message("conflict")),
+        );
+        mock.expect(
+            Expectation::query("insert Log")
+                .with_args_matching(|args| args == &Value::Tuple(vec![Value::Int64(1)])),
+        );
+        let err = mock.execute("insert Log", &(1i64,)).await.unwrap_err();
+        assert!(err.is::<TransactionConflictError>());
+        mock.execute("insert Log", &(1i64,)).await.unwrap();
+        let err = mock.execute("insert Log", &(2i64,)).await.unwrap_err();
+        assert!(err.is::<InterfaceError>());
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 3);
+        assert!(calls.iter().all(|c| c.method == "execute"));
+        assert_eq!(calls[2].args, Some(Value::Tuple(vec![Value::Int64(2)])));
+    }
+}
@@ -10,9 +10,10 @@ use gel_protocol::QueryResult;
 use tokio::sync::oneshot;
 use tokio::time::sleep;
 
-use crate::errors::{Error, ErrorKind, SHOULD_RETRY};
+use crate::errors::{Error, ErrorKind};
 use crate::errors::{NoDataError, ProtocolEncodingError};
 use crate::raw::{Options, Pool, PoolConnection, Response};
+use crate::rows::Rows;
 use crate::ResultVerbose;
 
 /// A representation of a transaction.
@@ -185,15 +186,12 @@ where
                 log::debug!("Rolling back transaction on error");
                 tran.rollback().await?;
 
-                let some_retry = outer.chain().find_map(|e| {
-                    e.downcast_ref::<Error>().and_then(|e| {
-                        if e.has_tag(SHOULD_RETRY) {
-                            Some(e)
-                        } else {
-                            None
-                        }
-                    })
-                });
+                // The transaction is rolled back, so queries in it are not
+                // applied regardless of their capabilities and whether they
+                // were sent. Only the kind of the error matters.
+                let some_retry = outer
+                    .chain()
+                    .find_map(|e| e.downcast_ref::<Error>().filter(|e| e.classify().transient));
 
                 if some_retry.is_none() {
                     return Err(outer);
@@ -272,12 +270,27 @@ impl Transaction {
     where
         A: QueryArgs,
         R: QueryResult,
+    {
+        self.query_rows_helper(query, arguments, io_format, cardinality)
+            .await?
+            .map(|rows| rows.decode_all())
+    }
+
+    async fn query_rows_helper<A>(
+        &mut self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+        io_format: IoFormat,
+        cardinality: Cardinality,
+    ) -> Result<Response<Rows>, Error>
+    where
+        A: QueryArgs,
     {
         self.ensure_started().await?;
 
         self.conn
             .inner()
-            .query(
+            .query_rows(
                 query.as_ref(),
                 arguments,
                 &self.options.state,
@@ -341,6 +354,22 @@ impl Transaction {
             .map(|Response { data, warnings, .. }| ResultVerbose { data, warnings })
     }
 
+    /// Execute a query and return undecoded rows.
+    ///
+    /// See [`Client::query_rows`](crate::Client::query_rows) for details.
+    pub async fn query_rows<A>(
+        &mut self,
+        query: impl AsRef<str> + Send,
+        arguments: &A,
+    ) -> Result<Rows, Error>
+    where
+        A: QueryArgs,
+    {
+        self.query_rows_helper(query, arguments, IoFormat::Binary, Cardinality::Many)
+            .await
+            .map(|r| r.data)
+    }
+
     /// Execute a query and return a single result
     ///
     /// The query must return exactly one element. If the query returns more
@@ -486,3 +486,45 @@ async fn props_in_wrong_order() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn borrowed_rows() -> anyhow::Result<()> {
+    let client = Client::new(&SERVER.config);
+    client.ensure_connected().await?;
+
+    #[derive(Debug, PartialEq, Queryable)]
+    struct Item<'a> {
+        name: &'a str,
+        tags: Vec<&'a str>,
+    }
+
+    let rows = client
+        .query_rows(
+            "select {
+                { name := 'a', tags := ['x', 'y'] },
+                { name := 'b', tags := <array<str>>[] },
+            }",
+            &(),
+        )
+        .await?;
+    assert_eq!(rows.len(), 2);
+    let items = rows.iter::<Item>()?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        items,
+        vec![
+            Item {
+                name: "a",
+                tags: vec!["x", "y"]
+            },
+            Item {
+                name: "b",
+                tags: vec![]
+            },
+        ]
+    );
+
+    let names = rows.iter::<(&str,)>();
+    assert!(names.is_err());
+
+    Ok(())
+}
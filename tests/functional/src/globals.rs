@@ -55,3 +55,40 @@ async fn global_struct() -> anyhow::Result<()> {
     assert_eq!(value, vec![678]);
     Ok(())
 }
+
+#[derive(gel_derive::FromGlobals, Debug, PartialEq)]
+struct CurrentGlobals {
+    str_val: String,
+    int_val: Option<i32>,
+}
+
+#[derive(gel_derive::AliasesDelta)]
+struct Aliases {
+    t: &'static str,
+}
+
+#[tokio::test]
+async fn global_read_back() -> anyhow::Result<()> {
+    let client = Client::new(&SERVER.config);
+    client.ensure_connected().await?;
+
+    let client = client
+        .with_aliases(&Aliases { t: "test" })
+        .with_globals_fn(|m| m.set("t::str_val", "aliased"));
+    assert_eq!(client.local_aliases().get("t").unwrap(), "test");
+    let value = client
+        .query::<String, _>("SELECT (global t::str_val)", &())
+        .await?;
+    assert_eq!(value, vec![String::from("aliased")]);
+
+    let client = client.with_default_module(Some("test"));
+    assert_eq!(client.local_default_module(), "test");
+    assert_eq!(
+        client.local_globals::<CurrentGlobals>()?,
+        CurrentGlobals {
+            str_val: "aliased".into(),
+            int_val: None,
+        }
+    );
+    Ok(())
+}